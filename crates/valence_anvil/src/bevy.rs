@@ -1,21 +1,54 @@
 use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::thread;
+use std::time::{Duration, Instant};
 
 use bevy_app::prelude::*;
 use bevy_ecs::prelude::*;
+use bevy_ecs::system::SystemParam;
 use flume::{Receiver, Sender};
-use valence_server::client::{Client, OldView, View};
+use valence_server::client::{Client, FlushPacketsSet, OldView, UpdateClientsSet, View};
 use valence_server::entity::{EntityLayerId, OldEntityLayerId};
+use valence_server::layer::chunk::UnloadedChunk;
 use valence_server::layer::UpdateLayersPreClientSet;
+use valence_server::nbt::Compound;
 use valence_server::protocol::anyhow;
 use valence_server::registry::BiomeRegistry;
-use valence_server::{ChunkLayer, ChunkPos};
+use valence_server::shutdown::{Save, SavePlugin};
+use valence_server::{ChunkLayer, ChunkPos, Despawned};
 
+use crate::entity::{spawn_entity, SavedEntityQuery};
+use crate::level_data::{
+    change_level_time, init_level_time_for_clients, tick_level_time, LevelData, LevelDataError,
+};
 use crate::parsing::{DimensionFolder, ParsedChunk};
 
-type WorkerResult = anyhow::Result<Option<ParsedChunk>>;
+/// A request sent to the anvil worker.
+enum WorkerRequest {
+    /// Load the chunk at the position.
+    Load(ChunkPos),
+    /// Write the chunk to the position. The minimum Y coordinate of the
+    /// dimension is included.
+    Save(ChunkPos, Box<UnloadedChunk>, i32),
+    /// Load the entities in the chunk at the position.
+    LoadEntities(ChunkPos),
+    /// Replace the entities in the chunk at the position.
+    SaveEntities(ChunkPos, Vec<Compound>),
+    /// Signal the sender once all previous requests have been handled.
+    Flush(Sender<()>),
+}
+
+/// The outcome of a request handled by the anvil worker.
+enum WorkerResult {
+    Load(ChunkPos, anyhow::Result<Option<ParsedChunk>>),
+    Save(ChunkPos, anyhow::Result<()>),
+    LoadEntities(ChunkPos, anyhow::Result<Vec<Compound>>),
+    SaveEntities(ChunkPos, anyhow::Result<()>),
+}
+
+/// The default value of [`AnvilLevel::autosave_interval`].
+pub const DEFAULT_AUTOSAVE_INTERVAL: Duration = Duration::from_secs(5 * 60);
 
 /// The order in which chunks should be processed by the anvil worker. Smaller
 /// values are sent first.
@@ -23,6 +56,8 @@ type Priority = u64;
 
 #[derive(Component, Debug)]
 pub struct AnvilLevel {
+    /// The directory of the level.
+    world_root: PathBuf,
     /// Chunk worker state to be moved to another thread.
     worker_state: Option<ChunkWorkerState>,
     /// The set of chunk positions that should not be loaded or unloaded by
@@ -30,33 +65,98 @@ pub struct AnvilLevel {
     ///
     /// This set is empty by default, but you can modify it at any time.
     pub ignored_chunks: HashSet<ChunkPos>,
+    /// How often chunks that have been [modified] are written to the region
+    /// files while they are still loaded. `None` disables autosaving. Modified
+    /// chunks are always saved when they are unloaded.
+    ///
+    /// This is [`DEFAULT_AUTOSAVE_INTERVAL`] by default.
+    ///
+    /// [modified]: valence_server::layer::chunk::LoadedChunk::is_modified
+    pub autosave_interval: Option<Duration>,
+    /// The time of the last autosave.
+    last_autosave: Instant,
+    /// Whether entities are loaded from the `entities` folder of the level
+    /// when a chunk is loaded. Loaded entities are spawned on the
+    /// [`EntityLayer`] of this entity with an [`AnvilEntity`] component.
+    ///
+    /// This is `true` by default.
+    ///
+    /// [`EntityLayer`]: valence_server::EntityLayer
+    /// [`AnvilEntity`]: crate::entity::AnvilEntity
+    pub load_entities: bool,
+    /// Whether entities with an [`AnvilEntity`] component are written back
+    /// to the `entities` folder of the level. Entities are saved along with
+    /// the chunks when they are unloaded, when the level is autosaved and in
+    /// the [`Save`] schedule. Only chunks whose entities were loaded from the
+    /// level are saved, so entities in the level are never overwritten
+    /// without having been loaded first.
+    ///
+    /// This is `false` by default.
+    ///
+    /// [`AnvilEntity`]: crate::entity::AnvilEntity
+    pub save_entities: bool,
+    /// Chunks whose entities have been loaded, along with whether the level
+    /// contains entities for the chunk.
+    entity_chunks: HashMap<ChunkPos, bool>,
+    /// Entities that could not be spawned, such as entities of unknown or
+    /// unsupported kinds. They are written back unchanged when the entities
+    /// of their chunk are saved.
+    unspawned_entities: HashMap<ChunkPos, Vec<Compound>>,
     /// Chunks that need to be loaded. Chunks with `None` priority have already
     /// been sent to the anvil thread.
     pending: HashMap<ChunkPos, Option<Priority>>,
     /// Sender for the chunk worker thread.
-    sender: Sender<ChunkPos>,
+    sender: Sender<WorkerRequest>,
     /// Receiver for the chunk worker thread.
-    receiver: Receiver<(ChunkPos, WorkerResult)>,
+    receiver: Receiver<WorkerResult>,
 }
 
 impl AnvilLevel {
     pub fn new<R: Into<PathBuf>>(world_root: R, biomes: &BiomeRegistry) -> Self {
+        let world_root = world_root.into();
         let (pending_sender, pending_receiver) = flume::unbounded();
-        let (finished_sender, finished_receiver) = flume::bounded(4096);
+        // Unbounded so that the worker never blocks while `save_all` waits for it.
+        let (finished_sender, finished_receiver) = flume::unbounded();
 
         Self {
             worker_state: Some(ChunkWorkerState {
-                dimension_folder: DimensionFolder::new(world_root, biomes),
+                dimension_folder: DimensionFolder::new(&world_root, biomes),
                 sender: finished_sender,
                 receiver: pending_receiver,
             }),
+            world_root,
             ignored_chunks: HashSet::new(),
+            autosave_interval: Some(DEFAULT_AUTOSAVE_INTERVAL),
+            last_autosave: Instant::now(),
+            load_entities: true,
+            save_entities: false,
+            entity_chunks: HashMap::new(),
+            unspawned_entities: HashMap::new(),
             pending: HashMap::new(),
             sender: pending_sender,
             receiver: finished_receiver,
         }
     }
 
+    /// The directory of the level passed to [`AnvilLevel::new`].
+    pub fn world_root(&self) -> &Path {
+        &self.world_root
+    }
+
+    /// Reads the `level.dat` file in the directory of the level. Returns
+    /// `Ok(None)` if the file does not exist.
+    ///
+    /// Only the overworld directory of a world contains `level.dat`. Use
+    /// [`LevelData::read`] for the other dimensions.
+    pub fn read_level_data(&self) -> Result<Option<LevelData>, LevelDataError> {
+        LevelData::read(self.world_root.join("level.dat"))
+    }
+
+    /// Writes `data` to the `level.dat` file in the directory of the level.
+    pub fn write_level_data(&self, data: &LevelData) -> Result<(), LevelDataError> {
+        data.write(self.world_root.join("level.dat"))
+    }
+
     /// Forces a chunk to be loaded at a specific position in this world. This
     /// will bypass [`AnvilLevel::ignored_chunks`].
     /// Note that the chunk will be unloaded next tick unless it has been added
@@ -77,6 +177,84 @@ impl AnvilLevel {
             }
         }
     }
+
+    /// Writes all [modified] chunks in the layer to the region files and
+    /// blocks until all pending saves have finished. The results of the saves
+    /// are reported with [`ChunkSaveEvent`]s afterwards.
+    ///
+    /// This is done automatically in the [`Save`] schedule, but it can be
+    /// useful to call it manually before shutting down in some other way.
+    /// Entities are not saved by this method.
+    ///
+    /// [modified]: valence_server::layer::chunk::LoadedChunk::is_modified
+    pub fn save_all(&mut self, layer: &mut ChunkLayer) {
+        // Start the worker if it hasn't been started yet.
+        if let Some(state) = self.worker_state.take() {
+            thread::spawn(move || anvil_worker(state));
+        }
+
+        self.queue_modified_chunks(layer);
+
+        let (done_sender, done_receiver) = flume::bounded(1);
+
+        if self.sender.send(WorkerRequest::Flush(done_sender)).is_ok() {
+            let _ = done_receiver.recv();
+        }
+    }
+
+    /// Sends all modified chunks in the layer to the anvil worker to be saved.
+    fn queue_modified_chunks(&mut self, layer: &mut ChunkLayer) {
+        let min_y = layer.min_y();
+
+        for (pos, chunk) in layer.chunks_mut() {
+            if chunk.is_modified() {
+                chunk.clear_modified();
+
+                let _ = self.sender.send(WorkerRequest::Save(
+                    pos,
+                    Box::new(chunk.to_unloaded()),
+                    min_y,
+                ));
+            }
+        }
+    }
+
+    /// Sends the entities of every chunk whose entities were loaded to the
+    /// anvil worker to be saved.
+    fn queue_entities(
+        &mut self,
+        layer: Entity,
+        entities: &Query<SavedEntityQuery, Without<Despawned>>,
+    ) {
+        let mut by_chunk = HashMap::<ChunkPos, Vec<Compound>>::new();
+
+        for entity in entities {
+            let pos = ChunkPos::from(entity.position());
+
+            if entity.layer() == layer && self.entity_chunks.contains_key(&pos) {
+                if let Some(nbt) = entity.to_nbt() {
+                    by_chunk.entry(pos).or_default().push(nbt);
+                }
+            }
+        }
+
+        for (pos, on_disk) in &mut self.entity_chunks {
+            let mut list = by_chunk.remove(pos).unwrap_or_default();
+
+            if let Some(unspawned) = self.unspawned_entities.get(pos) {
+                list.extend(unspawned.iter().cloned());
+            }
+
+            // Nothing to remove from the level.
+            if list.is_empty() && !*on_disk {
+                continue;
+            }
+
+            *on_disk = !list.is_empty();
+
+            let _ = self.sender.send(WorkerRequest::SaveEntities(*pos, list));
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -84,25 +262,47 @@ struct ChunkWorkerState {
     /// The world folder containing the region folder where chunks are loaded
     /// from.
     dimension_folder: DimensionFolder,
-    /// Sender of finished chunks.
-    sender: Sender<(ChunkPos, WorkerResult)>,
-    /// Receiver of pending chunks.
-    receiver: Receiver<ChunkPos>,
+    /// Sender of finished requests.
+    sender: Sender<WorkerResult>,
+    /// Receiver of pending requests.
+    receiver: Receiver<WorkerRequest>,
 }
 
 pub struct AnvilPlugin;
 
 impl Plugin for AnvilPlugin {
     fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<SavePlugin>() {
+            app.add_plugins(SavePlugin);
+        }
+
         app.add_event::<ChunkLoadEvent>()
             .add_event::<ChunkUnloadEvent>()
+            .add_event::<ChunkSaveEvent>()
+            .add_event::<EntitiesLoadEvent>()
+            .add_event::<EntitiesSaveEvent>()
             .add_systems(PreUpdate, remove_unviewed_chunks)
             .add_systems(
                 PostUpdate,
-                (init_anvil, update_client_views, send_recv_chunks)
+                (
+                    init_anvil,
+                    update_client_views,
+                    send_recv_chunks,
+                    autosave_chunks,
+                )
                     .chain()
                     .before(UpdateLayersPreClientSet),
-            );
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    (tick_level_time, change_level_time)
+                        .chain()
+                        .before(UpdateClientsSet),
+                    init_level_time_for_clients.before(FlushPacketsSet),
+                ),
+            )
+            .add_systems(Save, save_all);
     }
 }
 
@@ -114,26 +314,75 @@ fn init_anvil(mut query: Query<&mut AnvilLevel, (Added<AnvilLevel>, With<ChunkLa
     }
 }
 
-/// Removes all chunks no longer viewed by clients.
+/// Removes all chunks no longer viewed by clients. Modified chunks are sent to
+/// the anvil worker to be saved. Anvil entities in the removed chunks are
+/// despawned and saved.
 ///
 /// This needs to run in `PreUpdate` where the chunk viewer counts have been
 /// updated from the previous tick.
 fn remove_unviewed_chunks(
-    mut chunk_layers: Query<(Entity, &mut ChunkLayer, &AnvilLevel)>,
+    mut chunk_layers: Query<(Entity, &mut ChunkLayer, &mut AnvilLevel)>,
+    entities: Query<(Entity, SavedEntityQuery), Without<Despawned>>,
+    mut commands: Commands,
     mut unload_events: EventWriter<ChunkUnloadEvent>,
+    mut to_remove: Local<HashMap<ChunkPos, bool>>,
 ) {
-    for (entity, mut layer, anvil) in &mut chunk_layers {
-        layer.retain_chunks(|pos, chunk| {
-            if chunk.viewer_count_mut() > 0 || anvil.ignored_chunks.contains(&pos) {
-                true
-            } else {
-                unload_events.send(ChunkUnloadEvent {
-                    chunk_layer: entity,
-                    pos,
-                });
-                false
+    for (entity, mut layer, mut anvil) in &mut chunk_layers {
+        to_remove.extend(layer.chunks_mut().filter_map(|(pos, chunk)| {
+            (chunk.viewer_count_mut() == 0 && !anvil.ignored_chunks.contains(&pos))
+                .then(|| (pos, chunk.is_modified()))
+        }));
+
+        if to_remove.is_empty() {
+            continue;
+        }
+
+        let mut removed_entities = HashMap::<ChunkPos, Vec<Compound>>::new();
+
+        for (anvil_entity, saved) in &entities {
+            let pos = ChunkPos::from(saved.position());
+
+            if saved.layer() == entity && to_remove.contains_key(&pos) {
+                commands.entity(anvil_entity).insert(Despawned);
+
+                if let Some(nbt) = saved.to_nbt() {
+                    removed_entities.entry(pos).or_default().push(nbt);
+                }
+            }
+        }
+
+        let min_y = layer.min_y();
+
+        for (pos, modified) in to_remove.drain() {
+            let on_disk = anvil.entity_chunks.remove(&pos);
+            let unspawned = anvil.unspawned_entities.remove(&pos);
+
+            if anvil.save_entities {
+                if let Some(on_disk) = on_disk {
+                    let mut list = removed_entities.remove(&pos).unwrap_or_default();
+                    list.extend(unspawned.into_iter().flatten());
+
+                    if on_disk || !list.is_empty() {
+                        let _ = anvil.sender.send(WorkerRequest::SaveEntities(pos, list));
+                    }
+                }
+            }
+
+            let Some(chunk) = layer.remove_chunk(pos) else {
+                continue;
+            };
+
+            if modified {
+                let _ = anvil
+                    .sender
+                    .send(WorkerRequest::Save(pos, Box::new(chunk), min_y));
             }
-        });
+
+            unload_events.send(ChunkUnloadEvent {
+                chunk_layer: entity,
+                pos,
+            });
+        }
     }
 }
 
@@ -181,31 +430,13 @@ fn update_client_views(
 fn send_recv_chunks(
     mut layers: Query<(Entity, &mut ChunkLayer, &mut AnvilLevel)>,
     mut to_send: Local<Vec<(Priority, ChunkPos)>>,
-    mut load_events: EventWriter<ChunkLoadEvent>,
+    mut commands: Commands,
+    mut events: WorkerEvents,
 ) {
     for (entity, mut layer, anvil) in &mut layers {
         let anvil = anvil.into_inner();
 
-        // Insert the chunks that are finished loading into the chunk layer and send
-        // load events.
-        for (pos, res) in anvil.receiver.drain() {
-            anvil.pending.remove(&pos);
-
-            let status = match res {
-                Ok(Some(ParsedChunk { chunk, timestamp })) => {
-                    layer.insert_chunk(pos, chunk);
-                    ChunkLoadStatus::Success { timestamp }
-                }
-                Ok(None) => ChunkLoadStatus::Empty,
-                Err(e) => ChunkLoadStatus::Failed(e),
-            };
-
-            load_events.send(ChunkLoadEvent {
-                chunk_layer: entity,
-                pos,
-                status,
-            });
-        }
+        recv_worker_results(entity, &mut layer, anvil, &mut commands, &mut events);
 
         // Collect all the new chunks that need to be loaded this tick.
         for (pos, priority) in &mut anvil.pending {
@@ -219,19 +450,199 @@ fn send_recv_chunks(
 
         // Send the sorted chunks to be loaded.
         for (_, pos) in to_send.drain(..) {
-            let _ = anvil.sender.try_send(pos);
+            let _ = anvil.sender.try_send(WorkerRequest::Load(pos));
         }
     }
 }
 
+/// The events sent for finished anvil worker requests.
+#[derive(SystemParam)]
+struct WorkerEvents<'w> {
+    chunk_load: EventWriter<'w, ChunkLoadEvent>,
+    chunk_save: EventWriter<'w, ChunkSaveEvent>,
+    entities_load: EventWriter<'w, EntitiesLoadEvent>,
+    entities_save: EventWriter<'w, EntitiesSaveEvent>,
+}
+
+/// Inserts the chunks that are finished loading into the chunk layer, spawns
+/// the entities that are finished loading and sends events for all finished
+/// requests.
+fn recv_worker_results(
+    entity: Entity,
+    layer: &mut ChunkLayer,
+    anvil: &mut AnvilLevel,
+    commands: &mut Commands,
+    events: &mut WorkerEvents,
+) {
+    for res in anvil.receiver.drain() {
+        match res {
+            WorkerResult::Load(pos, res) => {
+                anvil.pending.remove(&pos);
+
+                let status = match res {
+                    Ok(Some(ParsedChunk { chunk, timestamp })) => {
+                        layer.insert_chunk(pos, chunk);
+
+                        // The chunk is identical to the one on disk, so there is nothing to save.
+                        if let Some(chunk) = layer.chunk_mut(pos) {
+                            chunk.clear_modified();
+                        }
+
+                        if anvil.load_entities {
+                            let _ = anvil.sender.send(WorkerRequest::LoadEntities(pos));
+                        }
+
+                        ChunkLoadStatus::Success { timestamp }
+                    }
+                    Ok(None) => ChunkLoadStatus::Empty,
+                    Err(e) => ChunkLoadStatus::Failed(e),
+                };
+
+                events.chunk_load.send(ChunkLoadEvent {
+                    chunk_layer: entity,
+                    pos,
+                    status,
+                });
+            }
+            WorkerResult::Save(pos, res) => {
+                events.chunk_save.send(ChunkSaveEvent {
+                    chunk_layer: entity,
+                    pos,
+                    status: match res {
+                        Ok(()) => ChunkSaveStatus::Success,
+                        Err(e) => ChunkSaveStatus::Failed(e),
+                    },
+                });
+            }
+            WorkerResult::LoadEntities(pos, res) => {
+                // The chunk was unloaded before its entities finished loading.
+                if layer.chunk(pos).is_none() {
+                    continue;
+                }
+
+                let status = match res {
+                    Ok(list) => {
+                        anvil.entity_chunks.insert(pos, !list.is_empty());
+
+                        let mut entities = vec![];
+                        let mut unspawned = vec![];
+
+                        for nbt in list {
+                            match spawn_entity(commands, entity, nbt) {
+                                Ok(e) => entities.push(e),
+                                Err(nbt) => unspawned.push(nbt),
+                            }
+                        }
+
+                        if !unspawned.is_empty() {
+                            anvil.unspawned_entities.insert(pos, unspawned);
+                        }
+
+                        EntitiesLoadStatus::Success { entities }
+                    }
+                    Err(e) => EntitiesLoadStatus::Failed(e),
+                };
+
+                events.entities_load.send(EntitiesLoadEvent {
+                    chunk_layer: entity,
+                    pos,
+                    status,
+                });
+            }
+            WorkerResult::SaveEntities(pos, res) => {
+                events.entities_save.send(EntitiesSaveEvent {
+                    chunk_layer: entity,
+                    pos,
+                    status: match res {
+                        Ok(()) => ChunkSaveStatus::Success,
+                        Err(e) => ChunkSaveStatus::Failed(e),
+                    },
+                });
+            }
+        }
+    }
+}
+
+/// Periodically sends the modified chunks and the entities of each level to
+/// the anvil worker.
+fn autosave_chunks(
+    mut layers: Query<(Entity, &mut ChunkLayer, &mut AnvilLevel)>,
+    entities: Query<SavedEntityQuery, Without<Despawned>>,
+) {
+    for (entity, mut layer, mut anvil) in &mut layers {
+        let Some(interval) = anvil.autosave_interval else {
+            continue;
+        };
+
+        if anvil.last_autosave.elapsed() >= interval {
+            anvil.last_autosave = Instant::now();
+            anvil.queue_modified_chunks(&mut layer);
+
+            if anvil.save_entities {
+                anvil.queue_entities(entity, &entities);
+            }
+        }
+    }
+}
+
+/// Saves all modified chunks and entities and waits for the anvil workers to
+/// finish when the server shuts down or the app exits.
+fn save_all(
+    mut layers: Query<(Entity, &mut ChunkLayer, &mut AnvilLevel)>,
+    entities: Query<SavedEntityQuery, Without<Despawned>>,
+    mut commands: Commands,
+    mut events: WorkerEvents,
+) {
+    for (entity, mut layer, mut anvil) in &mut layers {
+        if anvil.save_entities {
+            anvil.queue_entities(entity, &entities);
+        }
+
+        anvil.save_all(&mut layer);
+
+        recv_worker_results(entity, &mut layer, &mut anvil, &mut commands, &mut events);
+    }
+}
+
 fn anvil_worker(mut state: ChunkWorkerState) {
-    while let Ok(pos) = state.receiver.recv() {
-        let res = state
-            .dimension_folder
-            .get_chunk(pos)
-            .map_err(anyhow::Error::from);
+    while let Ok(req) = state.receiver.recv() {
+        let res = match req {
+            WorkerRequest::Load(pos) => WorkerResult::Load(
+                pos,
+                state
+                    .dimension_folder
+                    .get_chunk(pos)
+                    .map_err(anyhow::Error::from),
+            ),
+            WorkerRequest::Save(pos, chunk, min_y) => WorkerResult::Save(
+                pos,
+                state
+                    .dimension_folder
+                    .set_chunk(pos, &chunk, min_y)
+                    .map_err(anyhow::Error::from),
+            ),
+            WorkerRequest::LoadEntities(pos) => WorkerResult::LoadEntities(
+                pos,
+                state
+                    .dimension_folder
+                    .get_entities(pos)
+                    .map(Option::unwrap_or_default)
+                    .map_err(anyhow::Error::from),
+            ),
+            WorkerRequest::SaveEntities(pos, entities) => WorkerResult::SaveEntities(
+                pos,
+                state
+                    .dimension_folder
+                    .set_entities(pos, entities)
+                    .map_err(anyhow::Error::from),
+            ),
+            WorkerRequest::Flush(done) => {
+                let _ = done.send(());
+                continue;
+            }
+        };
 
-        let _ = state.sender.send((pos, res));
+        let _ = state.sender.send(res);
     }
 }
 
@@ -268,3 +679,55 @@ pub struct ChunkUnloadEvent {
     /// The position of the chunk that was unloaded.
     pub pos: ChunkPos,
 }
+
+/// An event sent by `valence_anvil` after an attempt to save a chunk is made.
+#[derive(Event, Debug)]
+pub struct ChunkSaveEvent {
+    /// The [`ChunkLayer`] the chunk was saved from.
+    pub chunk_layer: Entity,
+    /// The position of the chunk in the layer.
+    pub pos: ChunkPos,
+    pub status: ChunkSaveStatus,
+}
+
+#[derive(Debug)]
+pub enum ChunkSaveStatus {
+    /// The chunk was successfully written to its region file.
+    Success,
+    /// An attempt was made to save the chunk, but something went wrong.
+    Failed(anyhow::Error),
+}
+
+/// An event sent by `valence_anvil` after an attempt to load the entities of a
+/// chunk is made.
+#[derive(Event, Debug)]
+pub struct EntitiesLoadEvent {
+    /// The [`ChunkLayer`] where the chunk is located.
+    pub chunk_layer: Entity,
+    /// The position of the chunk in the layer.
+    pub pos: ChunkPos,
+    pub status: EntitiesLoadStatus,
+}
+
+#[derive(Debug)]
+pub enum EntitiesLoadStatus {
+    /// The entities of the chunk were successfully loaded.
+    Success {
+        /// The entities that were spawned. Entities that could not be spawned,
+        /// such as entities of unknown kinds, are not included.
+        entities: Vec<Entity>,
+    },
+    /// An attempt was made to load the entities, but something went wrong.
+    Failed(anyhow::Error),
+}
+
+/// An event sent by `valence_anvil` after an attempt to save the entities of a
+/// chunk is made.
+#[derive(Event, Debug)]
+pub struct EntitiesSaveEvent {
+    /// The [`ChunkLayer`] the entities were saved from.
+    pub chunk_layer: Entity,
+    /// The position of the chunk in the layer.
+    pub pos: ChunkPos,
+    pub status: ChunkSaveStatus,
+}
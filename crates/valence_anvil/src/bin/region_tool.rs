@@ -0,0 +1,127 @@
+//! Verifies, compacts and repairs the region files in a region folder.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use valence_anvil::maintenance::{CompactReport, RegionReport};
+use valence_anvil::{RegionError, RegionFolder};
+
+#[derive(Parser)]
+#[clap(author, version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check the header entries and chunks of every region file and report
+    /// problems.
+    Verify {
+        /// The folder containing the region files, such as `world/region`.
+        path: PathBuf,
+    },
+    /// Rewrite region files with their chunks stored contiguously. Region files
+    /// with broken chunks are skipped.
+    Compact {
+        /// The folder containing the region files, such as `world/region`.
+        path: PathBuf,
+    },
+    /// Remove broken chunks from region files and compact them.
+    Repair {
+        /// The folder containing the region files, such as `world/region`.
+        path: PathBuf,
+    },
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let (Command::Verify { path } | Command::Compact { path } | Command::Repair { path }) =
+        &cli.command;
+
+    let mut folder = RegionFolder::new(path);
+
+    let positions = match folder.region_positions() {
+        Ok(positions) => positions,
+        Err(e) => {
+            eprintln!("Failed to read `{}`: {e}", path.display());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut success = true;
+
+    for (region_x, region_z) in positions {
+        let res = match cli.command {
+            Command::Verify { .. } => folder.verify_region(region_x, region_z).map(|report| {
+                if let Some(report) = report {
+                    success &= report.is_ok();
+                    print_report(&report);
+                }
+            }),
+            Command::Compact { .. } => folder.compact_region(region_x, region_z).map(|report| {
+                if let Some(report) = report {
+                    success &= report.compacted;
+                    print_compact_report(&report);
+                }
+            }),
+            Command::Repair { .. } => folder.repair_region(region_x, region_z).map(|report| {
+                if let Some(report) = report {
+                    print_compact_report(&report);
+                }
+            }),
+        };
+
+        if let Err(e) = res {
+            success = false;
+            print_region_error(region_x, region_z, &e);
+        }
+    }
+
+    if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn print_report(report: &RegionReport) {
+    println!(
+        "r.{}.{}.mca: {} chunks, {} of {} sectors unused",
+        report.region_x,
+        report.region_z,
+        report.chunk_count,
+        report.unused_sectors(),
+        report.file_sectors
+    );
+
+    for error in &report.errors {
+        println!(
+            "  chunk ({}, {}): {}",
+            error.pos.0, error.pos.1, error.error
+        );
+    }
+}
+
+fn print_compact_report(report: &CompactReport) {
+    print_report(&report.report);
+
+    if report.compacted {
+        println!(
+            "  compacted from {} to {} bytes",
+            report.old_size, report.new_size
+        );
+
+        for (x, z) in &report.removed_chunks {
+            println!("  removed chunk ({x}, {z})");
+        }
+    } else {
+        println!("  skipped because of broken chunks, run `repair` to remove them");
+    }
+}
+
+fn print_region_error(region_x: i32, region_z: i32, error: &RegionError) {
+    eprintln!("r.{region_x}.{region_z}.mca: {error}");
+}
@@ -0,0 +1,757 @@
+//! Conversion between the entity NBT stored in the `entities` folder of a
+//! dimension and Valence entities.
+
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use bevy_ecs::prelude::*;
+use bevy_ecs::query::QueryData;
+use valence_server::entity::armor_stand::{
+    ArmorStandEntityBundle, ArmorStandFlags, TrackerBodyRotation, TrackerHeadRotation,
+    TrackerLeftArmRotation, TrackerLeftLegRotation, TrackerRightArmRotation,
+    TrackerRightLegRotation,
+};
+use valence_server::entity::glow_item_frame::GlowItemFrameEntityBundle;
+use valence_server::entity::item_frame::ItemFrameEntityBundle;
+use valence_server::entity::painting::PaintingEntityBundle;
+use valence_server::entity::tracked_data::TrackedData;
+use valence_server::entity::villager::VillagerEntityBundle;
+use valence_server::entity::{
+    entity, item_frame, painting, villager, EntityAnimations, EntityId, EntityKind, EntityLayerId,
+    EntityStatuses, EulerAngle, HeadYaw, Look, ObjectData, OldEntityLayerId, OldPosition, OnGround,
+    PaintingKind, Position, Velocity, VillagerData, VillagerKind, VillagerProfession,
+};
+use valence_server::math::{DVec3, Vec3};
+use valence_server::nbt::{compound, Compound, List, Value};
+use valence_server::uuid::Uuid;
+use valence_server::{Ident, ItemKind, ItemStack, Text, UniqueId};
+
+/// Marks an entity that is stored in the `entities` folder of the
+/// [`AnvilLevel`](crate::AnvilLevel) on its layer.
+///
+/// Entities loaded by an anvil level have this component. Entities with this
+/// component are despawned when the chunk they are in is unloaded, and are
+/// written to the level if [`AnvilLevel::save_entities`] is enabled. Add this
+/// component to entities you spawn yourself to have them saved as well.
+///
+/// Entities are converted to and from NBT in the same way as vanilla. Besides
+/// the data common to all entities (position, rotation, velocity, custom name
+/// and entity flags), the tracked data of armor stands, item frames, paintings
+/// and villagers is converted. Entities of other kinds are not spawned, and
+/// are kept in the level unchanged.
+///
+/// [`AnvilLevel::save_entities`]: crate::AnvilLevel::save_entities
+#[derive(Component, Clone, Default, Debug)]
+pub struct AnvilEntity {
+    /// The NBT of the entity that is not represented by components, such as
+    /// inventories or AI state. It is written back unchanged when the entity
+    /// is saved.
+    pub nbt: Compound,
+}
+
+/// The components of an entity that are saved by the anvil level.
+#[derive(QueryData)]
+pub(crate) struct SavedEntityQuery {
+    kind: &'static EntityKind,
+    uuid: &'static UniqueId,
+    layer: &'static EntityLayerId,
+    position: &'static Position,
+    look: &'static Look,
+    velocity: &'static Velocity,
+    on_ground: &'static OnGround,
+    object_data: &'static ObjectData,
+    anvil: &'static AnvilEntity,
+    flags: Option<&'static entity::Flags>,
+    custom_name: Option<&'static entity::CustomName>,
+    name_visible: Option<&'static entity::NameVisible>,
+    silent: Option<&'static entity::Silent>,
+    no_gravity: Option<&'static entity::NoGravity>,
+    armor_stand: Option<(
+        &'static ArmorStandFlags,
+        &'static TrackerHeadRotation,
+        &'static TrackerBodyRotation,
+        &'static TrackerLeftArmRotation,
+        &'static TrackerRightArmRotation,
+        &'static TrackerLeftLegRotation,
+        &'static TrackerRightLegRotation,
+    )>,
+    item_frame: Option<(
+        &'static item_frame::ItemStack,
+        &'static item_frame::Rotation,
+    )>,
+    painting_variant: Option<&'static painting::Variant>,
+    villager_data: Option<&'static villager::VillagerData>,
+}
+
+impl SavedEntityQueryItem<'_> {
+    /// Returns the layer the entity is on.
+    pub(crate) fn layer(&self) -> Entity {
+        self.layer.0
+    }
+
+    /// Returns the position of the entity.
+    pub(crate) fn position(&self) -> DVec3 {
+        self.position.0
+    }
+
+    /// Converts the entity into the NBT format used by vanilla. Returns `None`
+    /// if the entity kind has no identifier.
+    pub(crate) fn to_nbt(&self) -> Option<Compound> {
+        let mut nbt = self.anvil.nbt.clone();
+
+        nbt.insert("id", self.kind.ident()?.as_str());
+        nbt.insert("UUID", Value::IntArray(uuid_to_ints(self.uuid.0).to_vec()));
+        nbt.insert("Pos", List::Double(self.position.0.to_array().to_vec()));
+        nbt.insert(
+            "Motion",
+            List::Double((self.velocity.0 / 20.0).to_array().map(f64::from).to_vec()),
+        );
+        nbt.insert(
+            "Rotation",
+            List::Float(vec![self.look.yaw, self.look.pitch]),
+        );
+        nbt.insert("OnGround", self.on_ground.0);
+
+        match self.custom_name.and_then(|name| name.0.as_ref()) {
+            Some(name) => {
+                nbt.insert("CustomName", name.to_string());
+            }
+            None => {
+                nbt.remove("CustomName");
+            }
+        }
+
+        let flags = self.flags.cloned().unwrap_or_default();
+
+        nbt.insert(
+            "CustomNameVisible",
+            self.name_visible.is_some_and(|visible| visible.0),
+        );
+        nbt.insert("Silent", self.silent.is_some_and(|silent| silent.0));
+        nbt.insert(
+            "NoGravity",
+            self.no_gravity.is_some_and(|no_gravity| no_gravity.0),
+        );
+        nbt.insert("Glowing", flags.glowing());
+
+        match *self.kind {
+            EntityKind::ARMOR_STAND => {
+                nbt.insert("Invisible", flags.invisible());
+
+                if let Some((flags, head, body, left_arm, right_arm, left_leg, right_leg)) =
+                    self.armor_stand
+                {
+                    nbt.insert("Small", flags.small());
+                    nbt.insert("ShowArms", flags.show_arms());
+                    nbt.insert("NoBasePlate", flags.hide_base_plate());
+                    nbt.insert("Marker", flags.marker());
+
+                    let mut pose = Compound::new();
+
+                    for (name, angle) in [
+                        ("Head", head.0),
+                        ("Body", body.0),
+                        ("LeftArm", left_arm.0),
+                        ("RightArm", right_arm.0),
+                        ("LeftLeg", left_leg.0),
+                        ("RightLeg", right_leg.0),
+                    ] {
+                        pose.insert(name, List::Float(vec![angle.pitch, angle.yaw, angle.roll]));
+                    }
+
+                    nbt.insert("Pose", pose);
+                }
+            }
+            EntityKind::ITEM_FRAME | EntityKind::GLOW_ITEM_FRAME => {
+                nbt.insert("Invisible", flags.invisible());
+                nbt.insert("Facing", self.object_data.0 as i8);
+
+                if let Some((stack, rotation)) = self.item_frame {
+                    match item_stack_to_nbt(&stack.0) {
+                        Some(item) => {
+                            nbt.insert("Item", item);
+                        }
+                        None => {
+                            nbt.remove("Item");
+                        }
+                    }
+
+                    nbt.insert("ItemRotation", rotation.0 as i8);
+                }
+            }
+            EntityKind::PAINTING => {
+                let facing = match self.object_data.0 {
+                    3 => 0,
+                    4 => 1,
+                    5 => 3,
+                    _ => 2,
+                };
+
+                nbt.insert("facing", facing as i8);
+
+                if let Some(variant) = self.painting_variant {
+                    nbt.insert("variant", painting_kind_to_str(variant.0));
+                }
+            }
+            EntityKind::VILLAGER => {
+                if let Some(data) = self.villager_data {
+                    nbt.insert(
+                        "VillagerData",
+                        compound! {
+                            "type" => villager_kind_to_str(data.0.kind),
+                            "profession" => villager_profession_to_str(data.0.profession),
+                            "level" => data.0.level,
+                        },
+                    );
+                }
+            }
+            _ => {}
+        }
+
+        Some(nbt)
+    }
+}
+
+/// Spawns the entity described by the vanilla entity NBT on the given layer.
+/// The NBT is returned back if the kind of entity is missing, unknown or not
+/// supported by [`AnvilEntity`].
+pub(crate) fn spawn_entity(
+    commands: &mut Commands,
+    layer: Entity,
+    mut nbt: Compound,
+) -> Result<Entity, Compound> {
+    let Some(kind) = (match nbt.get("id") {
+        Some(Value::String(id)) => Ident::<Cow<str>>::new(id.as_str())
+            .ok()
+            .and_then(|id| EntityKind::from_ident(id.as_str_ident())),
+        _ => None,
+    }) else {
+        return Err(nbt);
+    };
+
+    if !matches!(
+        kind,
+        EntityKind::ARMOR_STAND
+            | EntityKind::ITEM_FRAME
+            | EntityKind::GLOW_ITEM_FRAME
+            | EntityKind::PAINTING
+            | EntityKind::VILLAGER
+    ) {
+        return Err(nbt);
+    }
+
+    nbt.remove("id");
+
+    let uuid = match nbt.remove("UUID") {
+        Some(Value::IntArray(ints)) if ints.len() == 4 => {
+            UniqueId(uuid_from_ints([ints[0], ints[1], ints[2], ints[3]]))
+        }
+        _ => UniqueId::default(),
+    };
+
+    let position = take_doubles::<3>(&mut nbt, "Pos")
+        .map(DVec3::from_array)
+        .unwrap_or_default();
+
+    let velocity = take_doubles::<3>(&mut nbt, "Motion")
+        .map(|motion| Vec3::from_array(motion.map(|v| v as f32)) * 20.0)
+        .unwrap_or_default();
+
+    let [yaw, pitch] = take_floats::<2>(&mut nbt, "Rotation").unwrap_or_default();
+
+    let on_ground = take_bool(&mut nbt, "OnGround");
+
+    let custom_name = match nbt.remove("CustomName") {
+        Some(Value::String(name)) => {
+            Some(Text::from_str(&name).unwrap_or_else(|_| Text::from(name)))
+        }
+        _ => None,
+    };
+
+    let mut flags = entity::Flags::default();
+    flags.set_glowing(take_bool(&mut nbt, "Glowing"));
+
+    let name_visible = take_bool(&mut nbt, "CustomNameVisible");
+    let silent = take_bool(&mut nbt, "Silent");
+    let no_gravity = take_bool(&mut nbt, "NoGravity");
+
+    let mut object_data = 0;
+
+    let mut commands = match kind {
+        EntityKind::ARMOR_STAND => {
+            flags.set_invisible(take_bool(&mut nbt, "Invisible"));
+
+            let mut armor_stand_flags = ArmorStandFlags::default();
+            armor_stand_flags.set_small(take_bool(&mut nbt, "Small"));
+            armor_stand_flags.set_show_arms(take_bool(&mut nbt, "ShowArms"));
+            armor_stand_flags.set_hide_base_plate(take_bool(&mut nbt, "NoBasePlate"));
+            armor_stand_flags.set_marker(take_bool(&mut nbt, "Marker"));
+
+            let mut bundle = ArmorStandEntityBundle {
+                armor_stand_armor_stand_flags: armor_stand_flags,
+                ..Default::default()
+            };
+
+            if let Some(Value::Compound(mut pose)) = nbt.remove("Pose") {
+                let mut take_angle = |name, angle: &mut EulerAngle| {
+                    if let Some([pitch, yaw, roll]) = take_floats::<3>(&mut pose, name) {
+                        *angle = EulerAngle { pitch, yaw, roll };
+                    }
+                };
+
+                take_angle("Head", &mut bundle.armor_stand_tracker_head_rotation.0);
+                take_angle("Body", &mut bundle.armor_stand_tracker_body_rotation.0);
+                take_angle(
+                    "LeftArm",
+                    &mut bundle.armor_stand_tracker_left_arm_rotation.0,
+                );
+                take_angle(
+                    "RightArm",
+                    &mut bundle.armor_stand_tracker_right_arm_rotation.0,
+                );
+                take_angle(
+                    "LeftLeg",
+                    &mut bundle.armor_stand_tracker_left_leg_rotation.0,
+                );
+                take_angle(
+                    "RightLeg",
+                    &mut bundle.armor_stand_tracker_right_leg_rotation.0,
+                );
+            }
+
+            commands.spawn(bundle)
+        }
+        EntityKind::ITEM_FRAME | EntityKind::GLOW_ITEM_FRAME => {
+            flags.set_invisible(take_bool(&mut nbt, "Invisible"));
+
+            if let Some(Value::Byte(facing)) = nbt.remove("Facing") {
+                object_data = i32::from(facing);
+            }
+
+            let stack = match nbt.remove("Item") {
+                Some(Value::Compound(item)) => item_stack_from_nbt(item),
+                _ => ItemStack::EMPTY,
+            };
+
+            let rotation = match nbt.remove("ItemRotation") {
+                Some(Value::Byte(rotation)) => i32::from(rotation),
+                _ => 0,
+            };
+
+            if kind == EntityKind::ITEM_FRAME {
+                commands.spawn(ItemFrameEntityBundle {
+                    item_frame_item_stack: item_frame::ItemStack(stack),
+                    item_frame_rotation: item_frame::Rotation(rotation),
+                    ..Default::default()
+                })
+            } else {
+                commands.spawn(GlowItemFrameEntityBundle {
+                    item_frame_item_stack: item_frame::ItemStack(stack),
+                    item_frame_rotation: item_frame::Rotation(rotation),
+                    ..Default::default()
+                })
+            }
+        }
+        EntityKind::PAINTING => {
+            // Paintings store a horizontal direction instead of a regular direction.
+            object_data = match nbt.remove("facing") {
+                Some(Value::Byte(0)) => 3,
+                Some(Value::Byte(1)) => 4,
+                Some(Value::Byte(3)) => 5,
+                _ => 2,
+            };
+
+            let variant = match nbt.remove("variant") {
+                Some(Value::String(variant)) => painting_kind_from_str(ident_path(&variant)),
+                _ => None,
+            };
+
+            commands.spawn(PaintingEntityBundle {
+                painting_variant: painting::Variant(variant.unwrap_or_default()),
+                ..Default::default()
+            })
+        }
+        EntityKind::VILLAGER => {
+            let mut data = VillagerData::default();
+
+            if let Some(Value::Compound(mut villager_data)) = nbt.remove("VillagerData") {
+                if let Some(Value::String(kind)) = villager_data.remove("type") {
+                    data.kind = villager_kind_from_str(ident_path(&kind)).unwrap_or_default();
+                }
+
+                if let Some(Value::String(profession)) = villager_data.remove("profession") {
+                    data.profession =
+                        villager_profession_from_str(ident_path(&profession)).unwrap_or_default();
+                }
+
+                if let Some(Value::Int(level)) = villager_data.remove("level") {
+                    data.level = level;
+                }
+            }
+
+            commands.spawn(VillagerEntityBundle {
+                villager_villager_data: villager::VillagerData(data),
+                ..Default::default()
+            })
+        }
+        _ => unreachable!("unsupported entity kinds are not spawned"),
+    };
+
+    commands.insert((
+        kind,
+        EntityId::default(),
+        uuid,
+        EntityLayerId(layer),
+        OldEntityLayerId::default(),
+        Position(position),
+        OldPosition::new(position),
+        Look { yaw, pitch },
+        HeadYaw(yaw),
+        OnGround(on_ground),
+        Velocity(velocity),
+        EntityStatuses::default(),
+        EntityAnimations::default(),
+        ObjectData(object_data),
+        TrackedData::default(),
+    ));
+
+    commands.insert((
+        flags,
+        entity::CustomName(custom_name),
+        entity::NameVisible(name_visible),
+        entity::Silent(silent),
+        entity::NoGravity(no_gravity),
+        AnvilEntity { nbt },
+    ));
+
+    Ok(commands.id())
+}
+
+pub(crate) fn item_stack_from_nbt(mut nbt: Compound) -> ItemStack {
+    let Some(Value::String(id)) = nbt.remove("id") else {
+        return ItemStack::EMPTY;
+    };
+
+    let Some(item) = ItemKind::from_str(ident_path(&id)) else {
+        return ItemStack::EMPTY;
+    };
+
+    let count = match nbt.remove("Count") {
+        Some(Value::Byte(count)) => count,
+        _ => 1,
+    };
+
+    let tag = match nbt.remove("tag") {
+        Some(Value::Compound(tag)) => Some(tag),
+        _ => None,
+    };
+
+    ItemStack::new(item, count, tag)
+}
+
+pub(crate) fn item_stack_to_nbt(stack: &ItemStack) -> Option<Compound> {
+    if stack.is_empty() {
+        return None;
+    }
+
+    let mut nbt = Compound::new();
+
+    nbt.insert("id", format!("minecraft:{}", stack.item.to_str()));
+    nbt.insert("Count", stack.count);
+
+    if let Some(tag) = &stack.nbt {
+        nbt.insert("tag", tag.clone());
+    }
+
+    Some(nbt)
+}
+
+fn take_bool(nbt: &mut Compound, key: &str) -> bool {
+    matches!(nbt.remove(key), Some(Value::Byte(b)) if b != 0)
+}
+
+fn take_doubles<const N: usize>(nbt: &mut Compound, key: &str) -> Option<[f64; N]> {
+    match nbt.remove(key) {
+        Some(Value::List(List::Double(values))) => values.try_into().ok(),
+        _ => None,
+    }
+}
+
+fn take_floats<const N: usize>(nbt: &mut Compound, key: &str) -> Option<[f32; N]> {
+    match nbt.remove(key) {
+        Some(Value::List(List::Float(values))) => values.try_into().ok(),
+        _ => None,
+    }
+}
+
+/// UUIDs are stored as four integers, starting with the most significant.
+fn uuid_from_ints(ints: [i32; 4]) -> Uuid {
+    let mut bytes = [0; 16];
+
+    for (chunk, int) in bytes.chunks_exact_mut(4).zip(ints) {
+        chunk.copy_from_slice(&int.to_be_bytes());
+    }
+
+    Uuid::from_bytes(bytes)
+}
+
+fn uuid_to_ints(uuid: Uuid) -> [i32; 4] {
+    let bytes = uuid.as_bytes();
+
+    [0, 1, 2, 3].map(|i| {
+        i32::from_be_bytes([
+            bytes[i * 4],
+            bytes[i * 4 + 1],
+            bytes[i * 4 + 2],
+            bytes[i * 4 + 3],
+        ])
+    })
+}
+
+/// Gets the path part of a resource identifier.
+fn ident_path(ident: &str) -> &str {
+    match ident.rsplit_once(':') {
+        Some((_, after)) => after,
+        None => ident,
+    }
+}
+
+const PAINTING_KINDS: [(PaintingKind, &str); 30] = [
+    (PaintingKind::Kebab, "kebab"),
+    (PaintingKind::Aztec, "aztec"),
+    (PaintingKind::Alban, "alban"),
+    (PaintingKind::Aztec2, "aztec2"),
+    (PaintingKind::Bomb, "bomb"),
+    (PaintingKind::Plant, "plant"),
+    (PaintingKind::Wasteland, "wasteland"),
+    (PaintingKind::Pool, "pool"),
+    (PaintingKind::Courbet, "courbet"),
+    (PaintingKind::Sea, "sea"),
+    (PaintingKind::Sunset, "sunset"),
+    (PaintingKind::Creebet, "creebet"),
+    (PaintingKind::Wanderer, "wanderer"),
+    (PaintingKind::Graham, "graham"),
+    (PaintingKind::Match, "match"),
+    (PaintingKind::Bust, "bust"),
+    (PaintingKind::Stage, "stage"),
+    (PaintingKind::Void, "void"),
+    (PaintingKind::SkullAndRoses, "skull_and_roses"),
+    (PaintingKind::Wither, "wither"),
+    (PaintingKind::Fighters, "fighters"),
+    (PaintingKind::Pointer, "pointer"),
+    (PaintingKind::Pigscene, "pigscene"),
+    (PaintingKind::BurningSkull, "burning_skull"),
+    (PaintingKind::Skeleton, "skeleton"),
+    (PaintingKind::Earth, "earth"),
+    (PaintingKind::Wind, "wind"),
+    (PaintingKind::Water, "water"),
+    (PaintingKind::Fire, "fire"),
+    (PaintingKind::DonkeyKong, "donkey_kong"),
+];
+
+fn painting_kind_from_str(name: &str) -> Option<PaintingKind> {
+    PAINTING_KINDS
+        .iter()
+        .find(|(_, n)| *n == name)
+        .map(|(kind, _)| *kind)
+}
+
+fn painting_kind_to_str(kind: PaintingKind) -> String {
+    let name = PAINTING_KINDS
+        .iter()
+        .find(|(k, _)| *k == kind)
+        .map_or("kebab", |(_, name)| name);
+
+    format!("minecraft:{name}")
+}
+
+const VILLAGER_KINDS: [(VillagerKind, &str); 7] = [
+    (VillagerKind::Desert, "desert"),
+    (VillagerKind::Jungle, "jungle"),
+    (VillagerKind::Plains, "plains"),
+    (VillagerKind::Savanna, "savanna"),
+    (VillagerKind::Snow, "snow"),
+    (VillagerKind::Swamp, "swamp"),
+    (VillagerKind::Taiga, "taiga"),
+];
+
+fn villager_kind_from_str(name: &str) -> Option<VillagerKind> {
+    VILLAGER_KINDS
+        .iter()
+        .find(|(_, n)| *n == name)
+        .map(|(kind, _)| *kind)
+}
+
+fn villager_kind_to_str(kind: VillagerKind) -> String {
+    let name = VILLAGER_KINDS
+        .iter()
+        .find(|(k, _)| *k == kind)
+        .map_or("plains", |(_, name)| name);
+
+    format!("minecraft:{name}")
+}
+
+const VILLAGER_PROFESSIONS: [(VillagerProfession, &str); 15] = [
+    (VillagerProfession::None, "none"),
+    (VillagerProfession::Armorer, "armorer"),
+    (VillagerProfession::Butcher, "butcher"),
+    (VillagerProfession::Cartographer, "cartographer"),
+    (VillagerProfession::Cleric, "cleric"),
+    (VillagerProfession::Farmer, "farmer"),
+    (VillagerProfession::Fisherman, "fisherman"),
+    (VillagerProfession::Fletcher, "fletcher"),
+    (VillagerProfession::Leatherworker, "leatherworker"),
+    (VillagerProfession::Librarian, "librarian"),
+    (VillagerProfession::Mason, "mason"),
+    (VillagerProfession::Nitwit, "nitwit"),
+    (VillagerProfession::Shepherd, "shepherd"),
+    (VillagerProfession::Toolsmith, "toolsmith"),
+    (VillagerProfession::Weaponsmith, "weaponsmith"),
+];
+
+fn villager_profession_from_str(name: &str) -> Option<VillagerProfession> {
+    VILLAGER_PROFESSIONS
+        .iter()
+        .find(|(_, n)| *n == name)
+        .map(|(profession, _)| *profession)
+}
+
+fn villager_profession_to_str(profession: VillagerProfession) -> String {
+    let name = VILLAGER_PROFESSIONS
+        .iter()
+        .find(|(p, _)| *p == profession)
+        .map_or("none", |(_, name)| name);
+
+    format!("minecraft:{name}")
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::world::CommandQueue;
+
+    use super::*;
+
+    fn round_trip(nbt: Compound) -> (World, Entity, Compound) {
+        let mut world = World::new();
+        let layer = world.spawn_empty().id();
+
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        let entity = spawn_entity(&mut commands, layer, nbt).unwrap();
+        queue.apply(&mut world);
+
+        let saved = world
+            .query::<SavedEntityQuery>()
+            .get(&world, entity)
+            .unwrap()
+            .to_nbt()
+            .unwrap();
+
+        (world, entity, saved)
+    }
+
+    #[test]
+    fn armor_stand_nbt_round_trip() {
+        let nbt = compound! {
+            "id" => "minecraft:armor_stand",
+            "UUID" => Value::IntArray(vec![1, -2, 3, -4]),
+            "Pos" => List::Double(vec![1.5, 64.0, -3.5]),
+            "Motion" => List::Double(vec![0.0, -0.5, 0.0]),
+            "Rotation" => List::Float(vec![90.0, 10.0]),
+            "OnGround" => true,
+            "CustomName" => r#"{"text":"Steve"}"#,
+            "CustomNameVisible" => true,
+            "Silent" => false,
+            "NoGravity" => true,
+            "Glowing" => false,
+            "Invisible" => true,
+            "Small" => true,
+            "ShowArms" => false,
+            "NoBasePlate" => true,
+            "Marker" => false,
+            "Pose" => compound! {
+                "Head" => List::Float(vec![1.0, 2.0, 3.0]),
+                "Body" => List::Float(vec![0.0, 0.0, 0.0]),
+                "LeftArm" => List::Float(vec![-10.0, 0.0, -10.0]),
+                "RightArm" => List::Float(vec![-15.0, 0.0, 10.0]),
+                "LeftLeg" => List::Float(vec![-1.0, 0.0, -1.0]),
+                "RightLeg" => List::Float(vec![1.0, 0.0, 1.0]),
+            },
+            "Fire" => -1_i16,
+        };
+
+        let (world, entity, saved) = round_trip(nbt.clone());
+
+        assert_eq!(
+            world.get::<EntityKind>(entity),
+            Some(&EntityKind::ARMOR_STAND)
+        );
+        assert_eq!(
+            world.get::<Velocity>(entity).unwrap().0,
+            Vec3::new(0.0, -10.0, 0.0)
+        );
+        assert!(world.get::<entity::Flags>(entity).unwrap().invisible());
+        assert_eq!(
+            world.get::<TrackerHeadRotation>(entity).unwrap().0,
+            EulerAngle {
+                pitch: 1.0,
+                yaw: 2.0,
+                roll: 3.0
+            }
+        );
+        // Unknown tags are kept.
+        assert_eq!(
+            world.get::<AnvilEntity>(entity).unwrap().nbt,
+            compound! { "Fire" => -1_i16 }
+        );
+
+        assert_eq!(saved, nbt);
+    }
+
+    #[test]
+    fn unknown_entity_is_not_spawned() {
+        let mut world = World::new();
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+
+        let nbt = compound! { "id" => "some_mod:thing", "Pos" => List::Double(vec![0.0; 3]) };
+
+        assert_eq!(
+            spawn_entity(&mut commands, Entity::PLACEHOLDER, nbt.clone()),
+            Err(nbt)
+        );
+
+        queue.apply(&mut world);
+        assert_eq!(world.entities().len(), 0);
+    }
+
+    #[test]
+    fn unsupported_entity_is_not_spawned() {
+        let mut world = World::new();
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+
+        let nbt = compound! {
+            "id" => "minecraft:zombie",
+            "Pos" => List::Double(vec![0.0; 3]),
+            "Health" => 20.0_f32,
+        };
+
+        assert_eq!(
+            spawn_entity(&mut commands, Entity::PLACEHOLDER, nbt.clone()),
+            Err(nbt)
+        );
+
+        queue.apply(&mut world);
+        assert_eq!(world.entities().len(), 0);
+    }
+
+    #[test]
+    fn uuid_ints_round_trip() {
+        let uuid = Uuid::from_u128(0x0123_4567_89ab_cdef_fedc_ba98_7654_3210);
+        let ints = uuid_to_ints(uuid);
+
+        assert_eq!(ints[0], 0x0123_4567);
+        assert_eq!(uuid_from_ints(ints), uuid);
+    }
+}
@@ -0,0 +1,564 @@
+//! Reading and writing the `level.dat` file of a world.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+
+use flate2::bufread::GzDecoder;
+use flate2::write::GzEncoder;
+use thiserror::Error;
+use valence_nbt::{Compound, Value};
+
+/// The world properties stored in the `Data` compound of `level.dat`.
+///
+/// Tags that are not represented by a field are kept in [`Self::other`] and
+/// written back unchanged, so reading and then writing a `level.dat` does not
+/// lose any data.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LevelData {
+    /// The data version of the game that last saved the world.
+    pub data_version: Option<i32>,
+    pub level_name: String,
+    /// The world seed, stored in `WorldGenSettings` since 1.16.
+    pub seed: i64,
+    /// The block position of the world spawn.
+    pub spawn_pos: [i32; 3],
+    /// The yaw angle of the world spawn in degrees.
+    pub spawn_angle: f32,
+    /// The number of ticks since the world was created.
+    pub time: i64,
+    /// The time of day in ticks. This keeps increasing past 24000 and is
+    /// reduced modulo 24000 by the client.
+    pub day_time: i64,
+    /// The numeric ID of the default game mode.
+    pub game_type: i32,
+    pub hardcore: bool,
+    pub difficulty: i8,
+    pub allow_commands: bool,
+    pub weather: LevelWeather,
+    pub world_border: LevelWorldBorder,
+    /// Game rules by name. Every game rule value is stored as a string.
+    pub game_rules: BTreeMap<String, String>,
+    /// The remaining tags of the `Data` compound.
+    pub other: Compound,
+}
+
+/// The weather state stored in `level.dat`.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub struct LevelWeather {
+    pub raining: bool,
+    /// Ticks until rain starts or stops.
+    pub rain_time: i32,
+    pub thundering: bool,
+    /// Ticks until thunder starts or stops.
+    pub thunder_time: i32,
+    /// Ticks of clear weather remaining, set by `/weather clear`.
+    pub clear_weather_time: i32,
+}
+
+/// The world border stored in `level.dat`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct LevelWorldBorder {
+    pub center_x: f64,
+    pub center_z: f64,
+    /// The current diameter of the border.
+    pub size: f64,
+    /// The diameter the border is moving towards.
+    pub size_lerp_target: f64,
+    /// Milliseconds until the border reaches [`Self::size_lerp_target`].
+    pub size_lerp_time: i64,
+    pub safe_zone: f64,
+    pub damage_per_block: f64,
+    pub warning_blocks: f64,
+    pub warning_time: f64,
+}
+
+impl Default for LevelWorldBorder {
+    fn default() -> Self {
+        Self {
+            center_x: 0.0,
+            center_z: 0.0,
+            size: 59_999_968.0,
+            size_lerp_target: 59_999_968.0,
+            size_lerp_time: 0,
+            safe_zone: 5.0,
+            damage_per_block: 0.2,
+            warning_blocks: 5.0,
+            warning_time: 15.0,
+        }
+    }
+}
+
+impl Default for LevelData {
+    fn default() -> Self {
+        Self {
+            data_version: None,
+            level_name: String::new(),
+            seed: 0,
+            spawn_pos: [0, 64, 0],
+            spawn_angle: 0.0,
+            time: 0,
+            day_time: 0,
+            game_type: 0,
+            hardcore: false,
+            difficulty: 2,
+            allow_commands: false,
+            weather: LevelWeather::default(),
+            world_border: LevelWorldBorder::default(),
+            game_rules: BTreeMap::new(),
+            other: Compound::new(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum LevelDataError {
+    #[error("an I/O error occurred: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to parse NBT: {0}")]
+    Nbt(#[from] valence_nbt::Error),
+    #[error("missing level data compound")]
+    MissingData,
+}
+
+impl LevelData {
+    /// Reads a gzip-compressed `level.dat` file.
+    ///
+    /// Returns `Ok(None)` if the file does not exist.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Option<Self>, LevelDataError> {
+        match read_gzip_nbt(path.as_ref())? {
+            Some(root) => Self::from_nbt(root).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Writes the level data to a gzip-compressed `level.dat` file,
+    /// overwriting the file if it exists.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), LevelDataError> {
+        write_gzip_nbt(path.as_ref(), &self.to_nbt())
+    }
+
+    /// Parses the level data from the root compound of `level.dat`. Missing
+    /// tags are replaced with their default values.
+    pub fn from_nbt(mut root: Compound) -> Result<Self, LevelDataError> {
+        let Some(Value::Compound(mut data)) = root.remove("Data") else {
+            return Err(LevelDataError::MissingData);
+        };
+
+        let default = Self::default();
+        let border = LevelWorldBorder::default();
+
+        let seed = match data.get_mut("WorldGenSettings") {
+            Some(Value::Compound(settings)) => take(settings, "seed", Value::as_i64),
+            _ => None,
+        }
+        .or_else(|| take(&mut data, "RandomSeed", Value::as_i64))
+        .unwrap_or(default.seed);
+
+        let game_rules = match data.remove("GameRules") {
+            Some(Value::Compound(rules)) => rules
+                .into_iter()
+                .filter_map(|(name, value)| match value {
+                    Value::String(value) => Some((name, value)),
+                    _ => None,
+                })
+                .collect(),
+            _ => BTreeMap::new(),
+        };
+
+        Ok(Self {
+            data_version: take(&mut data, "DataVersion", Value::as_i32),
+            level_name: match data.remove("LevelName") {
+                Some(Value::String(name)) => name,
+                _ => default.level_name,
+            },
+            seed,
+            spawn_pos: [
+                take(&mut data, "SpawnX", Value::as_i32).unwrap_or(default.spawn_pos[0]),
+                take(&mut data, "SpawnY", Value::as_i32).unwrap_or(default.spawn_pos[1]),
+                take(&mut data, "SpawnZ", Value::as_i32).unwrap_or(default.spawn_pos[2]),
+            ],
+            spawn_angle: take(&mut data, "SpawnAngle", Value::as_f32)
+                .unwrap_or(default.spawn_angle),
+            time: take(&mut data, "Time", Value::as_i64).unwrap_or(default.time),
+            day_time: take(&mut data, "DayTime", Value::as_i64).unwrap_or(default.day_time),
+            game_type: take(&mut data, "GameType", Value::as_i32).unwrap_or(default.game_type),
+            hardcore: take(&mut data, "hardcore", Value::as_bool).unwrap_or(default.hardcore),
+            difficulty: take(&mut data, "Difficulty", Value::as_i8).unwrap_or(default.difficulty),
+            allow_commands: take(&mut data, "allowCommands", Value::as_bool)
+                .unwrap_or(default.allow_commands),
+            weather: LevelWeather {
+                raining: take(&mut data, "raining", Value::as_bool).unwrap_or_default(),
+                rain_time: take(&mut data, "rainTime", Value::as_i32).unwrap_or_default(),
+                thundering: take(&mut data, "thundering", Value::as_bool).unwrap_or_default(),
+                thunder_time: take(&mut data, "thunderTime", Value::as_i32).unwrap_or_default(),
+                clear_weather_time: take(&mut data, "clearWeatherTime", Value::as_i32)
+                    .unwrap_or_default(),
+            },
+            world_border: LevelWorldBorder {
+                center_x: take(&mut data, "BorderCenterX", Value::as_f64)
+                    .unwrap_or(border.center_x),
+                center_z: take(&mut data, "BorderCenterZ", Value::as_f64)
+                    .unwrap_or(border.center_z),
+                size: take(&mut data, "BorderSize", Value::as_f64).unwrap_or(border.size),
+                size_lerp_target: take(&mut data, "BorderSizeLerpTarget", Value::as_f64)
+                    .unwrap_or(border.size_lerp_target),
+                size_lerp_time: take(&mut data, "BorderSizeLerpTime", Value::as_i64)
+                    .unwrap_or(border.size_lerp_time),
+                safe_zone: take(&mut data, "BorderSafeZone", Value::as_f64)
+                    .unwrap_or(border.safe_zone),
+                damage_per_block: take(&mut data, "BorderDamagePerBlock", Value::as_f64)
+                    .unwrap_or(border.damage_per_block),
+                warning_blocks: take(&mut data, "BorderWarningBlocks", Value::as_f64)
+                    .unwrap_or(border.warning_blocks),
+                warning_time: take(&mut data, "BorderWarningTime", Value::as_f64)
+                    .unwrap_or(border.warning_time),
+            },
+            game_rules,
+            other: data,
+        })
+    }
+
+    /// Converts the level data into the root compound of `level.dat`.
+    pub fn to_nbt(&self) -> Compound {
+        let mut data = self.other.clone();
+
+        if let Some(data_version) = self.data_version {
+            data.insert("DataVersion", data_version);
+        }
+
+        data.insert("LevelName", self.level_name.clone());
+
+        match data.get_mut("WorldGenSettings") {
+            Some(Value::Compound(settings)) => {
+                settings.insert("seed", self.seed);
+            }
+            _ => {
+                data.insert("RandomSeed", self.seed);
+            }
+        }
+
+        let [spawn_x, spawn_y, spawn_z] = self.spawn_pos;
+        data.insert("SpawnX", spawn_x);
+        data.insert("SpawnY", spawn_y);
+        data.insert("SpawnZ", spawn_z);
+        data.insert("SpawnAngle", self.spawn_angle);
+        data.insert("Time", self.time);
+        data.insert("DayTime", self.day_time);
+        data.insert("GameType", self.game_type);
+        data.insert("hardcore", self.hardcore);
+        data.insert("Difficulty", self.difficulty);
+        data.insert("allowCommands", self.allow_commands);
+
+        let weather = &self.weather;
+        data.insert("raining", weather.raining);
+        data.insert("rainTime", weather.rain_time);
+        data.insert("thundering", weather.thundering);
+        data.insert("thunderTime", weather.thunder_time);
+        data.insert("clearWeatherTime", weather.clear_weather_time);
+
+        let border = &self.world_border;
+        data.insert("BorderCenterX", border.center_x);
+        data.insert("BorderCenterZ", border.center_z);
+        data.insert("BorderSize", border.size);
+        data.insert("BorderSizeLerpTarget", border.size_lerp_target);
+        data.insert("BorderSizeLerpTime", border.size_lerp_time);
+        data.insert("BorderSafeZone", border.safe_zone);
+        data.insert("BorderDamagePerBlock", border.damage_per_block);
+        data.insert("BorderWarningBlocks", border.warning_blocks);
+        data.insert("BorderWarningTime", border.warning_time);
+
+        data.insert(
+            "GameRules",
+            self.game_rules
+                .iter()
+                .map(|(name, value)| (name.clone(), Value::String(value.clone())))
+                .collect::<Compound>(),
+        );
+
+        let mut root = Compound::new();
+        root.insert("Data", data);
+        root
+    }
+
+    /// Gets the value of a boolean game rule, or `None` if the game rule is
+    /// not set or is not a boolean.
+    pub fn bool_game_rule(&self, name: &str) -> Option<bool> {
+        self.game_rules.get(name)?.parse().ok()
+    }
+}
+
+/// Reads a gzip-compressed NBT file. Returns `Ok(None)` if the file does not
+/// exist.
+pub(crate) fn read_gzip_nbt(path: &Path) -> Result<Option<Compound>, LevelDataError> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut buf = vec![];
+    GzDecoder::new(BufReader::new(file)).read_to_end(&mut buf)?;
+
+    let (root, _) = valence_nbt::from_binary::<String>(&mut buf.as_slice())?;
+
+    Ok(Some(root))
+}
+
+/// Writes a gzip-compressed NBT file, overwriting the file if it exists.
+///
+/// The data is written to a temporary file first, which then replaces the old
+/// file, so that the old file is left intact if writing fails.
+pub(crate) fn write_gzip_nbt(path: &Path, root: &Compound) -> Result<(), LevelDataError> {
+    let tmp_path = path.with_extension("dat_new");
+
+    let mut encoder = GzEncoder::new(
+        BufWriter::new(File::create(&tmp_path)?),
+        flate2::Compression::default(),
+    );
+    valence_nbt::to_binary(root, &mut encoder, "")?;
+    encoder.finish()?.flush()?;
+
+    std::fs::rename(tmp_path, path)?;
+
+    Ok(())
+}
+
+/// Removes the value of `key` from `data` if it can be converted with `f`.
+fn take<T>(data: &mut Compound, key: &str, f: fn(&Value) -> Option<T>) -> Option<T> {
+    let value = f(data.get(key)?)?;
+    data.remove(key);
+    Some(value)
+}
+
+#[cfg(feature = "bevy_plugin")]
+mod bevy {
+    use bevy_ecs::prelude::*;
+    use bevy_ecs::system::EntityCommands;
+    use valence_server::client::{Client, VisibleChunkLayer};
+    use valence_server::protocol::packets::play::WorldTimeUpdateS2c;
+    use valence_server::protocol::WritePacket;
+    use valence_server::spawn::RespawnPosition;
+    use valence_server::{BlockPos, ChunkLayer};
+
+    use super::LevelData;
+
+    /// How often the time is sent to clients, in ticks. This matches vanilla.
+    const TIME_SYNC_INTERVAL: i64 = 20;
+
+    /// The time of a [`ChunkLayer`], which is sent to the clients viewing the
+    /// layer. Added by [`LevelData::apply`].
+    ///
+    /// The time advances by one every tick. Modifying this component sends
+    /// the new time to clients immediately.
+    #[derive(Component, Copy, Clone, PartialEq, Eq, Debug)]
+    pub struct LevelTime {
+        /// The number of ticks since the world was created.
+        pub world_age: i64,
+        /// The time of day in ticks.
+        pub time_of_day: i64,
+        /// Whether the time of day advances. This is the
+        /// `doDaylightCycle` game rule.
+        pub daylight_cycle: bool,
+    }
+
+    impl LevelTime {
+        fn packet(&self) -> WorldTimeUpdateS2c {
+            WorldTimeUpdateS2c {
+                world_age: self.world_age,
+                // A negative time of day stops the client from advancing it.
+                time_of_day: if self.daylight_cycle {
+                    self.time_of_day
+                } else {
+                    -self.time_of_day.max(1)
+                },
+            }
+        }
+    }
+
+    impl LevelData {
+        /// Inserts the components described by the level data into the
+        /// entity of a [`ChunkLayer`]. This includes the [`LevelTime`], the
+        /// `WeatherBundle` when the `weather` feature is enabled and the
+        /// `WorldBorderBundle` when the `world_border` feature is enabled.
+        ///
+        /// The spawn point is not a property of the layer, use
+        /// [`Self::respawn_position`] to get it for clients.
+        pub fn apply(&self, layer: &mut EntityCommands) {
+            layer.insert(LevelTime {
+                world_age: self.time,
+                time_of_day: self.day_time,
+                daylight_cycle: self.bool_game_rule("doDaylightCycle").unwrap_or(true),
+            });
+
+            #[cfg(feature = "weather")]
+            layer.insert(valence_weather::WeatherBundle {
+                rain: valence_weather::Rain(if self.weather.raining { 1.0 } else { 0.0 }),
+                thunder: valence_weather::Thunder(if self.weather.thundering { 1.0 } else { 0.0 }),
+            });
+
+            #[cfg(feature = "world_border")]
+            {
+                use valence_world_border::*;
+
+                let border = &self.world_border;
+
+                layer.insert(WorldBorderBundle {
+                    center: WorldBorderCenter {
+                        x: border.center_x,
+                        z: border.center_z,
+                    },
+                    lerp: WorldBorderLerp {
+                        current_diameter: border.size,
+                        target_diameter: border.size_lerp_target,
+                        // There are 50 milliseconds in a tick.
+                        remaining_ticks: (border.size_lerp_time / 50).max(0) as u64,
+                    },
+                    portal_teleport_boundary: WorldBorderPortalTpBoundary::default(),
+                    warn_time: WorldBorderWarnTime(border.warning_time as i32),
+                    warn_blocks: WorldBorderWarnBlocks(border.warning_blocks as i32),
+                });
+            }
+        }
+
+        /// The world spawn, which can be inserted into clients.
+        pub fn respawn_position(&self) -> RespawnPosition {
+            RespawnPosition {
+                pos: BlockPos::from(self.spawn_pos),
+                yaw: self.spawn_angle,
+            }
+        }
+    }
+
+    pub(crate) fn tick_level_time(mut layers: Query<(&mut ChunkLayer, &mut LevelTime)>) {
+        for (mut layer, mut time) in &mut layers {
+            // Avoid triggering change detection, which would send the time
+            // every tick.
+            let time = time.bypass_change_detection();
+
+            time.world_age += 1;
+
+            if time.daylight_cycle {
+                time.time_of_day += 1;
+            }
+
+            if time.world_age % TIME_SYNC_INTERVAL == 0 {
+                layer.write_packet(&time.packet());
+            }
+        }
+    }
+
+    pub(crate) fn change_level_time(
+        mut layers: Query<(&mut ChunkLayer, &LevelTime), Changed<LevelTime>>,
+    ) {
+        for (mut layer, time) in &mut layers {
+            layer.write_packet(&time.packet());
+        }
+    }
+
+    pub(crate) fn init_level_time_for_clients(
+        mut clients: Query<(&mut Client, &VisibleChunkLayer), Changed<VisibleChunkLayer>>,
+        layers: Query<&LevelTime>,
+    ) {
+        for (mut client, layer) in &mut clients {
+            if let Ok(time) = layers.get(layer.0) {
+                client.write_packet(&time.packet());
+            }
+        }
+    }
+}
+
+#[cfg(feature = "bevy_plugin")]
+pub use bevy::LevelTime;
+#[cfg(feature = "bevy_plugin")]
+pub(crate) use bevy::{change_level_time, init_level_time_for_clients, tick_level_time};
+
+#[cfg(test)]
+mod tests {
+    use valence_nbt::compound;
+
+    use super::*;
+
+    #[test]
+    fn level_data_round_trip() {
+        let root = compound! {
+            "Data" => compound! {
+                "DataVersion" => 3465,
+                "LevelName" => "Test World",
+                "SpawnX" => 12,
+                "SpawnY" => 70,
+                "SpawnZ" => -34,
+                "SpawnAngle" => 90.0_f32,
+                "Time" => 123_456_i64,
+                "DayTime" => 30_000_i64,
+                "raining" => true,
+                "rainTime" => 500,
+                "BorderSize" => 1000.0,
+                "BorderWarningBlocks" => 8.0,
+                "GameRules" => compound! {
+                    "doDaylightCycle" => "false",
+                    "randomTickSpeed" => "3",
+                },
+                "WorldGenSettings" => compound! {
+                    "seed" => -42_i64,
+                    "generate_features" => true,
+                },
+                "WanderingTraderSpawnDelay" => 24000,
+            }
+        };
+
+        let level = LevelData::from_nbt(root).unwrap();
+
+        assert_eq!(level.data_version, Some(3465));
+        assert_eq!(level.level_name, "Test World");
+        assert_eq!(level.seed, -42);
+        assert_eq!(level.spawn_pos, [12, 70, -34]);
+        assert_eq!(level.spawn_angle, 90.0);
+        assert_eq!(level.time, 123_456);
+        assert_eq!(level.day_time, 30_000);
+        assert!(level.weather.raining);
+        assert_eq!(level.weather.rain_time, 500);
+        assert!(!level.weather.thundering);
+        assert_eq!(level.world_border.size, 1000.0);
+        assert_eq!(level.world_border.warning_blocks, 8.0);
+        assert_eq!(level.world_border.warning_time, 15.0);
+        assert_eq!(level.bool_game_rule("doDaylightCycle"), Some(false));
+        assert_eq!(level.game_rules["randomTickSpeed"], "3");
+        assert_eq!(
+            level.other.get("WanderingTraderSpawnDelay"),
+            Some(&Value::Int(24000))
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("level.dat");
+
+        level.write(&path).unwrap();
+        let read = LevelData::read(&path).unwrap().unwrap();
+
+        assert_eq!(read, level);
+
+        // The seed is written back into the world generation settings.
+        let root = read.to_nbt();
+        let Some(Value::Compound(data)) = root.get("Data") else {
+            panic!("missing level data compound");
+        };
+        let Some(Value::Compound(settings)) = data.get("WorldGenSettings") else {
+            panic!("missing world generation settings");
+        };
+        assert_eq!(settings.get("seed"), Some(&Value::Long(-42)));
+        assert_eq!(settings.get("generate_features"), Some(&Value::Byte(1)));
+    }
+
+    #[test]
+    fn missing_level_data() {
+        assert!(LevelData::read("does/not/exist/level.dat")
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            LevelData::from_nbt(Compound::new()),
+            Err(LevelDataError::MissingData)
+        ));
+    }
+}
@@ -1,10 +1,12 @@
 #![doc = include_str!("../README.md")]
 
+use std::collections::HashMap;
 use std::fs::{DirEntry, File};
 use std::hash::Hash;
-use std::io::{Cursor, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
 use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 #[cfg(feature = "bevy_plugin")]
@@ -20,8 +22,15 @@ use valence_nbt::Compound;
 
 #[cfg(feature = "bevy_plugin")]
 mod bevy;
+#[cfg(feature = "bevy_plugin")]
+pub mod entity;
+pub mod level_data;
+mod lz4;
+pub mod maintenance;
 #[cfg(feature = "parsing")]
 pub mod parsing;
+#[cfg(feature = "playerdata")]
+pub mod playerdata;
 
 const LRU_CACHE_SIZE: NonZeroUsize = match NonZeroUsize::new(256) {
     Some(n) => n,
@@ -49,27 +58,68 @@ pub enum RegionError {
     TrailingNbtData,
     #[error("oversized chunk")]
     OversizedChunk,
+    #[error("chunk sectors extend past the end of the region file")]
+    SectorOutOfBounds,
+    #[error("chunk sectors overlap with the chunk at ({}, {})", other.0, other.1)]
+    OverlappingSectors {
+        /// The position of the other chunk.
+        other: (i32, i32),
+    },
+    #[error("chunk stream is truncated")]
+    TruncatedChunkStream,
+    #[error("unknown custom compression `{0}`")]
+    UnknownCustomCompression(String),
 }
 
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
-#[repr(u8)]
 #[non_exhaustive]
 pub enum Compression {
-    Gzip = 1,
+    Gzip,
     #[default]
-    Zlib = 2,
-    None = 3,
+    Zlib,
+    None,
+    /// The LZ4 block stream format written by Minecraft 1.20.5 and later.
+    Lz4,
+    /// A compression algorithm identified by name, which must be registered
+    /// with [`RegionFolder::register_custom_compression`]. The name is written
+    /// to the chunk stream, so that it can be read back by any region folder
+    /// that has the same algorithm registered.
+    Custom(&'static str),
 }
 
 impl Compression {
+    /// The compression scheme used for custom compression algorithms. The
+    /// name of the algorithm follows at the start of the chunk stream.
+    const CUSTOM_SCHEME: u8 = 127;
+
     fn from_u8(compression: u8) -> Option<Compression> {
         match compression {
             1 => Some(Compression::Gzip),
             2 => Some(Compression::Zlib),
             3 => Some(Compression::None),
+            4 => Some(Compression::Lz4),
             _ => None,
         }
     }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Compression::Gzip => 1,
+            Compression::Zlib => 2,
+            Compression::None => 3,
+            Compression::Lz4 => 4,
+            Compression::Custom(_) => Self::CUSTOM_SCHEME,
+        }
+    }
+}
+
+/// A compression algorithm for [`Compression::Custom`].
+pub trait CustomCompression: Send + Sync + std::fmt::Debug {
+    /// Appends the compressed `data` to `out`.
+    fn compress(&self, data: &[u8], out: &mut Vec<u8>) -> std::io::Result<()>;
+
+    /// Appends the decompressed `data` to `out`.
+    fn decompress(&self, data: &[u8], out: &mut Vec<u8>) -> std::io::Result<()>;
 }
 
 #[derive(Copy, Clone, Debug, Default)]
@@ -96,6 +146,8 @@ pub struct RegionFolder {
     region_root: PathBuf,
     /// Scratch buffer for (de)compression.
     compression_buf: Vec<u8>,
+    /// Compression algorithms for [`Compression::Custom`], by name.
+    custom_compressions: HashMap<String, Arc<dyn CustomCompression>>,
     /// Options to use for writing the chunk.
     pub write_options: WriteOptions,
 }
@@ -106,10 +158,22 @@ impl RegionFolder {
             regions: LruCache::new(LRU_CACHE_SIZE),
             region_root: region_root.into(),
             compression_buf: Vec::new(),
+            custom_compressions: HashMap::new(),
             write_options: WriteOptions::default(),
         }
     }
 
+    /// Registers a compression algorithm for [`Compression::Custom`] under the
+    /// given name. Chunks compressed with an unregistered algorithm cannot be
+    /// read or written.
+    pub fn register_custom_compression<N: Into<String>>(
+        &mut self,
+        name: N,
+        compression: Arc<dyn CustomCompression>,
+    ) {
+        self.custom_compressions.insert(name.into(), compression);
+    }
+
     fn region<'a>(
         regions: &'a mut LruCache<RegionPos, RegionEntry>,
         region_root: &Path,
@@ -170,7 +234,13 @@ impl RegionFolder {
             return Ok(None);
         };
 
-        region.get_chunk(pos_x, pos_z, &mut self.compression_buf, &self.region_root)
+        region.get_chunk(
+            pos_x,
+            pos_z,
+            &mut self.compression_buf,
+            &self.region_root,
+            &self.custom_compressions,
+        )
     }
 
     /// Deletes the chunk at the given chunk position, returning whether the
@@ -202,12 +272,22 @@ impl RegionFolder {
     where
         S: ToModifiedUtf8 + Hash + Ord,
     {
+        let compression = self.write_options.compression;
+        compress_chunk(
+            chunk,
+            compression,
+            &mut self.compression_buf,
+            &self.custom_compressions,
+        )?;
+
         let region_x = pos_x.div_euclid(32);
         let region_z = pos_z.div_euclid(32);
 
         let region = match Self::region(&mut self.regions, &self.region_root, region_x, region_z)? {
             Some(region) => region,
             None => {
+                std::fs::create_dir_all(&self.region_root)?;
+
                 let path = self
                     .region_root
                     .join(format!("r.{region_x}.{region_z}.mca"));
@@ -236,9 +316,9 @@ impl RegionFolder {
         region.set_chunk(
             pos_x,
             pos_z,
-            chunk,
+            compression.to_u8(),
+            &self.compression_buf,
             self.write_options,
-            &mut self.compression_buf,
             &self.region_root,
         )
     }
@@ -247,38 +327,6 @@ impl RegionFolder {
     pub fn all_chunk_positions(
         &mut self,
     ) -> Result<impl Iterator<Item = Result<(i32, i32), RegionError>> + '_, RegionError> {
-        fn extract_region_coordinates(
-            file: std::io::Result<DirEntry>,
-        ) -> Result<Option<(i32, i32)>, RegionError> {
-            let file = file?;
-
-            if !file.file_type()?.is_file() {
-                return Ok(None);
-            }
-
-            let file_name = file
-                .file_name()
-                .into_string()
-                .map_err(|_| RegionError::OsStringConv)?;
-
-            // read the file name as r.x.z.mca
-            let mut split = file_name.splitn(4, '.');
-            if split.next() != Some("r") {
-                return Ok(None);
-            }
-            let Some(Ok(x)) = split.next().map(str::parse) else {
-                return Ok(None);
-            };
-            let Some(Ok(z)) = split.next().map(str::parse) else {
-                return Ok(None);
-            };
-            if split.next() != Some("mca") {
-                return Ok(None);
-            }
-
-            Ok(Some((x, z)))
-        }
-
         fn region_chunks(
             this: &mut RegionFolder,
             pos: Result<(i32, i32), RegionError>,
@@ -302,11 +350,44 @@ impl RegionFolder {
         }
 
         Ok(std::fs::read_dir(&self.region_root)?
-            .filter_map(|file| extract_region_coordinates(file).transpose())
+            .filter_map(|file| region_file_position(file).transpose())
             .flat_map(|pos| region_chunks(self, pos)))
     }
 }
 
+/// Reads the position of a region from the name of a region file, which has
+/// the form `r.<x>.<z>.mca`. Returns `Ok(None)` if the entry is not a region
+/// file.
+fn region_file_position(file: std::io::Result<DirEntry>) -> Result<Option<RegionPos>, RegionError> {
+    let file = file?;
+
+    if !file.file_type()?.is_file() {
+        return Ok(None);
+    }
+
+    let file_name = file
+        .file_name()
+        .into_string()
+        .map_err(|_| RegionError::OsStringConv)?;
+
+    // read the file name as r.x.z.mca
+    let mut split = file_name.splitn(4, '.');
+    if split.next() != Some("r") {
+        return Ok(None);
+    }
+    let Some(Ok(x)) = split.next().map(str::parse) else {
+        return Ok(None);
+    };
+    let Some(Ok(z)) = split.next().map(str::parse) else {
+        return Ok(None);
+    };
+    if split.next() != Some("mca") {
+        return Ok(None);
+    }
+
+    Ok(Some((x, z)))
+}
+
 /// A chunk represented by the raw compound data.
 pub struct RawChunk<S = String> {
     pub data: Compound<S>,
@@ -417,6 +498,7 @@ impl Region {
         pos_z: i32,
         decompress_buf: &mut Vec<u8>,
         region_root: &Path,
+        custom_compressions: &HashMap<String, Arc<dyn CustomCompression>>,
     ) -> Result<Option<RawChunk<S>>, RegionError>
     where
         S: for<'a> FromModifiedUtf8<'a> + Hash + Ord,
@@ -443,7 +525,10 @@ impl Region {
         self.file
             .seek(SeekFrom::Start(sector_offset * SECTOR_SIZE as u64))?;
 
-        let exact_chunk_size = self.file.read_u32::<BigEndian>()? as usize;
+        let exact_chunk_size = self
+            .file
+            .read_u32::<BigEndian>()
+            .map_err(truncated_stream)? as usize;
         if exact_chunk_size == 0 {
             return Err(RegionError::MissingChunkStream);
         }
@@ -453,7 +538,7 @@ impl Region {
             return Err(RegionError::InvalidChunkSize);
         }
 
-        let mut compression = self.file.read_u8()?;
+        let mut compression = self.file.read_u8().map_err(truncated_stream)?;
 
         let data_buf = if Self::is_external_stream_chunk(compression) {
             compression = Self::external_chunk_version(compression);
@@ -465,30 +550,56 @@ impl Region {
         } else {
             // the size includes the version of the stream, but we have already read that
             let mut data_buf = vec![0; exact_chunk_size - 1].into_boxed_slice();
-            self.file.read_exact(&mut data_buf)?;
+            self.file
+                .read_exact(&mut data_buf)
+                .map_err(truncated_stream)?;
             data_buf
         };
 
-        let r = data_buf.as_ref();
+        let mut r = data_buf.as_ref();
 
         decompress_buf.clear();
 
         // What compression does the chunk use?
-        let mut nbt_slice = match Compression::from_u8(compression) {
-            Some(Compression::Gzip) => {
-                let mut z = GzDecoder::new(r);
-                z.read_to_end(decompress_buf)?;
-                decompress_buf.as_slice()
+        let mut nbt_slice = if compression == Compression::CUSTOM_SCHEME {
+            // The name of the algorithm is stored before the compressed data.
+            let name_len = r.read_u16::<BigEndian>().map_err(truncated_stream)? as usize;
+            if r.len() < name_len {
+                return Err(RegionError::TruncatedChunkStream);
             }
-            Some(Compression::Zlib) => {
-                let mut z = ZlibDecoder::new(r);
-                z.read_to_end(decompress_buf)?;
-                decompress_buf.as_slice()
+
+            let (name, r) = r.split_at(name_len);
+            let name = String::from_utf8_lossy(name);
+
+            let Some(custom) = custom_compressions.get(name.as_ref()) else {
+                return Err(RegionError::UnknownCustomCompression(name.into_owned()));
+            };
+
+            custom.decompress(r, decompress_buf)?;
+            decompress_buf.as_slice()
+        } else {
+            match Compression::from_u8(compression) {
+                Some(Compression::Gzip) => {
+                    let mut z = GzDecoder::new(r);
+                    z.read_to_end(decompress_buf)?;
+                    decompress_buf.as_slice()
+                }
+                Some(Compression::Zlib) => {
+                    let mut z = ZlibDecoder::new(r);
+                    z.read_to_end(decompress_buf)?;
+                    decompress_buf.as_slice()
+                }
+                // Uncompressed
+                Some(Compression::None) => r,
+                Some(Compression::Lz4) => {
+                    lz4::decompress(r, decompress_buf)?;
+                    decompress_buf.as_slice()
+                }
+                // Unknown
+                Some(Compression::Custom(_)) | None => {
+                    return Err(RegionError::InvalidCompressionScheme(compression))
+                }
             }
-            // Uncompressed
-            Some(Compression::None) => r,
-            // Unknown
-            None => return Err(RegionError::InvalidCompressionScheme(compression)),
         };
 
         let (data, _) = valence_nbt::from_binary(&mut nbt_slice)?;
@@ -535,39 +646,20 @@ impl Region {
         Ok(true)
     }
 
-    fn set_chunk<S>(
+    /// Writes the compressed data of a chunk, replacing the old chunk at the
+    /// position.
+    fn set_chunk(
         &mut self,
         pos_x: i32,
         pos_z: i32,
-        chunk: &Compound<S>,
+        compression: u8,
+        compress_buf: &[u8],
         options: WriteOptions,
-        compress_buf: &mut Vec<u8>,
         region_root: &Path,
-    ) -> Result<(), RegionError>
-    where
-        S: ToModifiedUtf8 + Hash + Ord,
-    {
+    ) -> Result<(), RegionError> {
         // erase the chunk from allocated chunks (not from disk)
         self.delete_chunk(pos_x, pos_z, false, region_root)?;
 
-        // write the chunk into NBT and compress it according to the compression method
-        compress_buf.clear();
-        let mut compress_cursor = Cursor::new(compress_buf);
-        match options.compression {
-            Compression::Gzip => valence_nbt::to_binary(
-                chunk,
-                GzEncoder::new(&mut compress_cursor, flate2::Compression::default()),
-                "",
-            )?,
-            Compression::Zlib => valence_nbt::to_binary(
-                chunk,
-                ZlibEncoder::new(&mut compress_cursor, flate2::Compression::default()),
-                "",
-            )?,
-            Compression::None => valence_nbt::to_binary(chunk, &mut compress_cursor, "")?,
-        }
-        let compress_buf = compress_cursor.into_inner();
-
         // additional 5 bytes for exact chunk size + compression type
         let num_sectors_needed = (compress_buf.len() + 5).div_ceil(SECTOR_SIZE);
         let (start_sector, num_sectors) = if num_sectors_needed >= 256 {
@@ -577,7 +669,7 @@ impl Region {
 
             // write oversized chunk to external file
             File::create(Self::external_chunk_file(pos_x, pos_z, region_root))?
-                .write_all(&*compress_buf)?;
+                .write_all(compress_buf)?;
 
             let start_sector = self.allocate_sectors(1);
             self.file
@@ -587,7 +679,7 @@ impl Region {
             // (the rest of the chunk is external)
             self.file.write_u32::<BigEndian>(1)?;
             // write the compression, with the marker which says our chunk is oversized
-            self.file.write_u8((options.compression as u8) | 0x80)?;
+            self.file.write_u8(compression | 0x80)?;
 
             (start_sector, 1)
         } else {
@@ -603,9 +695,9 @@ impl Region {
             self.file
                 .write_u32::<BigEndian>((compress_buf.len() + 1) as u32)?;
             // write the compression
-            self.file.write_u8(options.compression as u8)?;
+            self.file.write_u8(compression)?;
             // write the data
-            self.file.write_all(&*compress_buf)?;
+            self.file.write_all(compress_buf)?;
 
             (start_sector, num_sectors_needed)
         };
@@ -741,3 +833,129 @@ impl Region {
 }
 
 const SECTOR_SIZE: usize = 4096;
+
+/// Writes the chunk into NBT and compresses it into `buf` according to the
+/// compression method.
+fn compress_chunk<S>(
+    chunk: &Compound<S>,
+    compression: Compression,
+    buf: &mut Vec<u8>,
+    custom_compressions: &HashMap<String, Arc<dyn CustomCompression>>,
+) -> Result<(), RegionError>
+where
+    S: ToModifiedUtf8 + Hash + Ord,
+{
+    buf.clear();
+
+    match compression {
+        Compression::Gzip => valence_nbt::to_binary(
+            chunk,
+            GzEncoder::new(buf, flate2::Compression::default()),
+            "",
+        )?,
+        Compression::Zlib => valence_nbt::to_binary(
+            chunk,
+            ZlibEncoder::new(buf, flate2::Compression::default()),
+            "",
+        )?,
+        Compression::None => valence_nbt::to_binary(chunk, buf, "")?,
+        Compression::Lz4 => {
+            let mut nbt = vec![];
+            valence_nbt::to_binary(chunk, &mut nbt, "")?;
+            lz4::compress(&nbt, buf);
+        }
+        Compression::Custom(name) => {
+            let Some(custom) = custom_compressions.get(name) else {
+                return Err(RegionError::UnknownCustomCompression(name.into()));
+            };
+
+            let mut nbt = vec![];
+            valence_nbt::to_binary(chunk, &mut nbt, "")?;
+
+            buf.write_u16::<BigEndian>(name.len() as u16)?;
+            buf.extend_from_slice(name.as_bytes());
+            custom.compress(&nbt, buf)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts an I/O error from reading a chunk stream, reporting an unexpected
+/// end of file as a truncated stream.
+fn truncated_stream(e: std::io::Error) -> RegionError {
+    if e.kind() == ErrorKind::UnexpectedEof {
+        RegionError::TruncatedChunkStream
+    } else {
+        e.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use valence_nbt::{compound, List};
+
+    use super::*;
+
+    /// Stores the data reversed, to check that the custom algorithm is used.
+    #[derive(Debug)]
+    struct Reverse;
+
+    impl CustomCompression for Reverse {
+        fn compress(&self, data: &[u8], out: &mut Vec<u8>) -> std::io::Result<()> {
+            out.extend(data.iter().rev());
+            Ok(())
+        }
+
+        fn decompress(&self, data: &[u8], out: &mut Vec<u8>) -> std::io::Result<()> {
+            out.extend(data.iter().rev());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn compression_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut folder = RegionFolder::new(dir.path());
+        folder.register_custom_compression("valence:reverse", Arc::new(Reverse));
+
+        let chunk = compound! {
+            "Status" => "minecraft:full",
+            "data" => List::Long((0..2000).collect()),
+        };
+
+        let compressions = [
+            Compression::Gzip,
+            Compression::Zlib,
+            Compression::None,
+            Compression::Lz4,
+            Compression::Custom("valence:reverse"),
+        ];
+
+        for (x, compression) in compressions.into_iter().enumerate() {
+            folder.write_options.compression = compression;
+            folder.set_chunk(x as i32, 0, &chunk).unwrap();
+        }
+
+        // Chunks are read with the compression they were written with.
+        folder.write_options.compression = Compression::Zlib;
+
+        for x in 0..compressions.len() {
+            let raw = folder.get_chunk(x as i32, 0).unwrap().unwrap();
+            assert_eq!(raw.data, chunk);
+        }
+
+        // The custom algorithm is needed to read the chunk.
+        let mut folder = RegionFolder::new(dir.path());
+        assert!(matches!(
+            folder.get_chunk::<String>(4, 0),
+            Err(RegionError::UnknownCustomCompression(name)) if name == "valence:reverse"
+        ));
+
+        folder.write_options.compression = Compression::Custom("valence:missing");
+        assert!(matches!(
+            folder.set_chunk(5, 0, &chunk),
+            Err(RegionError::UnknownCustomCompression(_))
+        ));
+    }
+}
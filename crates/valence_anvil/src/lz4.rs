@@ -0,0 +1,201 @@
+//! The LZ4 block stream format of lz4-java's `LZ4BlockOutputStream`, which is
+//! used by Minecraft for compression scheme 4.
+//!
+//! The stream is a sequence of blocks, each starting with a header containing
+//! the compressed and decompressed length of the block and a checksum of the
+//! decompressed data. The stream ends with an empty block.
+
+use std::io::{Error, ErrorKind, Result};
+
+use twox_hash::XxHash32;
+
+const MAGIC: &[u8; 8] = b"LZ4Block";
+const HEADER_LEN: usize = MAGIC.len() + 13;
+
+const METHOD_RAW: u8 = 0x10;
+const METHOD_LZ4: u8 = 0x20;
+
+/// The maximum size of the decompressed data in a block written by
+/// `LZ4BlockOutputStream` by default.
+const BLOCK_SIZE: usize = 1 << 16;
+/// `log2(BLOCK_SIZE) - 10`, stored in the low bits of the block token.
+const COMPRESSION_LEVEL: u8 = 6;
+
+const CHECKSUM_SEED: u32 = 0x9747_b28c;
+
+fn checksum(data: &[u8]) -> u32 {
+    XxHash32::oneshot(CHECKSUM_SEED, data) & 0x0fff_ffff
+}
+
+fn invalid_data(msg: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid LZ4 block stream: {msg}"),
+    )
+}
+
+/// Appends the compressed stream of `data` to `out`.
+pub(crate) fn compress(data: &[u8], out: &mut Vec<u8>) {
+    let mut compressed = vec![0; lz4_flex::block::get_maximum_output_size(BLOCK_SIZE)];
+
+    for block in data.chunks(BLOCK_SIZE) {
+        let compressed_len = lz4_flex::block::compress_into(block, &mut compressed)
+            .expect("output buffer should fit the compressed block");
+
+        // Blocks that do not get smaller are stored uncompressed.
+        let (method, stored) = if compressed_len < block.len() {
+            (METHOD_LZ4, &compressed[..compressed_len])
+        } else {
+            (METHOD_RAW, block)
+        };
+
+        write_header(out, method, stored.len(), block.len(), checksum(block));
+        out.extend_from_slice(stored);
+    }
+
+    write_header(out, METHOD_RAW, 0, 0, 0);
+}
+
+fn write_header(out: &mut Vec<u8>, method: u8, compressed_len: usize, len: usize, checksum: u32) {
+    out.extend_from_slice(MAGIC);
+    out.push(method | COMPRESSION_LEVEL);
+    out.extend_from_slice(&(compressed_len as u32).to_le_bytes());
+    out.extend_from_slice(&(len as u32).to_le_bytes());
+    out.extend_from_slice(&checksum.to_le_bytes());
+}
+
+/// Appends the decompressed data of the stream to `out`.
+pub(crate) fn decompress(mut data: &[u8], out: &mut Vec<u8>) -> Result<()> {
+    loop {
+        if data.len() < HEADER_LEN {
+            return Err(invalid_data("truncated block header"));
+        }
+
+        let (header, rest) = data.split_at(HEADER_LEN);
+
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err(invalid_data("missing magic"));
+        }
+
+        let token = header[MAGIC.len()];
+        let int = |i: usize| {
+            let start = MAGIC.len() + 1 + i * 4;
+            u32::from_le_bytes(header[start..start + 4].try_into().unwrap())
+        };
+
+        let compressed_len = int(0) as usize;
+        let len = int(1) as usize;
+        let expected_checksum = int(2);
+
+        // The end of the stream.
+        if len == 0 && compressed_len == 0 {
+            return Ok(());
+        }
+
+        // Checked before the output is allocated, so that a corrupt length cannot
+        // allocate gigabytes. The block size is at most 32 MiB.
+        let block_size = 1 << (10 + (token & 0x0f));
+
+        if len > block_size {
+            return Err(invalid_data("block larger than the block size"));
+        }
+
+        if rest.len() < compressed_len {
+            return Err(invalid_data("truncated block"));
+        }
+
+        let (block, rest) = rest.split_at(compressed_len);
+        let start = out.len();
+
+        match token & 0xf0 {
+            METHOD_RAW => {
+                if compressed_len != len {
+                    return Err(invalid_data("raw block length mismatch"));
+                }
+
+                out.extend_from_slice(block);
+            }
+            METHOD_LZ4 => {
+                out.resize(start + len, 0);
+
+                let written = lz4_flex::block::decompress_into(block, &mut out[start..])
+                    .map_err(|e| invalid_data(&e.to_string()))?;
+
+                if written != len {
+                    return Err(invalid_data("decompressed block length mismatch"));
+                }
+            }
+            _ => return Err(invalid_data("unknown compression method")),
+        }
+
+        if checksum(&out[start..]) != expected_checksum {
+            return Err(invalid_data("checksum mismatch"));
+        }
+
+        data = rest;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lz4_round_trip() {
+        // Compressible data spanning multiple blocks, followed by data that does not
+        // compress.
+        let mut data: Vec<u8> = (0..BLOCK_SIZE * 2 + 100).map(|i| (i % 7) as u8).collect();
+        data.extend((0..1000_u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8));
+
+        let mut compressed = vec![];
+        compress(&data, &mut compressed);
+        assert!(compressed.len() < data.len());
+
+        let mut decompressed = vec![];
+        decompress(&compressed, &mut decompressed).unwrap();
+        assert_eq!(decompressed, data);
+
+        // Corrupting the data is caught by the checksum.
+        let last = compressed.len() - HEADER_LEN - 1;
+        compressed[last] ^= 1;
+        assert!(decompress(&compressed, &mut vec![]).is_err());
+    }
+
+    /// A stream laid out like the output of lz4-java's `LZ4BlockOutputStream`
+    /// for data that does not compress.
+    #[test]
+    fn lz4_java_stream() {
+        let mut stream = vec![];
+        stream.extend_from_slice(b"LZ4Block");
+        // A raw block containing "hello".
+        stream.push(0x16);
+        stream.extend_from_slice(&5_u32.to_le_bytes());
+        stream.extend_from_slice(&5_u32.to_le_bytes());
+        stream.extend_from_slice(&checksum(b"hello").to_le_bytes());
+        stream.extend_from_slice(b"hello");
+        stream.extend_from_slice(b"LZ4Block");
+        stream.push(0x16);
+        stream.extend_from_slice(&[0; 12]);
+
+        let mut out = vec![];
+        decompress(&stream, &mut out).unwrap();
+        assert_eq!(out, b"hello");
+    }
+
+    #[test]
+    fn lz4_oversized_block() {
+        // A compressed block claiming to decompress to 4 GiB, in a stream with a
+        // block size of 64 KiB.
+        let mut stream = vec![];
+        stream.extend_from_slice(b"LZ4Block");
+        stream.push(METHOD_LZ4 | COMPRESSION_LEVEL);
+        stream.extend_from_slice(&1_u32.to_le_bytes());
+        stream.extend_from_slice(&u32::MAX.to_le_bytes());
+        stream.extend_from_slice(&0_u32.to_le_bytes());
+        stream.push(0);
+
+        let mut out = vec![];
+        assert!(decompress(&stream, &mut out).is_err());
+        assert_eq!(out.capacity(), 0);
+    }
+}
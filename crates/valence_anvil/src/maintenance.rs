@@ -0,0 +1,551 @@
+//! Verification, compaction and repair of region files.
+//!
+//! Region files only grow as chunks are written to them, since chunks that
+//! outgrow their sectors are moved to free space elsewhere in the file.
+//! [`RegionFolder::compact_region`] rewrites a region file with its chunks
+//! stored contiguously, and [`RegionFolder::verify_region`] reports chunks
+//! whose header entries or data are broken.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::{
+    region_file_position, CustomCompression, Location, Region, RegionError, RegionFolder,
+    SECTOR_SIZE,
+};
+
+/// The result of verifying a region file.
+#[derive(Debug)]
+pub struct RegionReport {
+    /// The X position of the region.
+    pub region_x: i32,
+    /// The Z position of the region.
+    pub region_z: i32,
+    /// The number of chunks in the header of the region file.
+    pub chunk_count: usize,
+    /// The problems found with the chunks in the region, in the order of the
+    /// header entries. A chunk can have more than one problem.
+    pub errors: Vec<ChunkError>,
+    /// The size of the region file in sectors, including the header.
+    pub file_sectors: u64,
+    /// The number of sectors used by the header and the chunks in the region.
+    pub used_sectors: u64,
+}
+
+impl RegionReport {
+    /// Returns `true` if no problems were found.
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// The number of sectors in the file that are not used by any chunk, which
+    /// are reclaimed by compaction.
+    pub fn unused_sectors(&self) -> u64 {
+        self.file_sectors.saturating_sub(self.used_sectors)
+    }
+
+    /// Returns the positions of the chunks that cannot be read. Chunks that
+    /// only overlap with other chunks are not included, since they can still
+    /// be read and compaction separates them.
+    pub fn broken_chunks(&self) -> Vec<(i32, i32)> {
+        let mut chunks: Vec<_> = self
+            .errors
+            .iter()
+            .filter(|e| !matches!(e.error, RegionError::OverlappingSectors { .. }))
+            .map(|e| e.pos)
+            .collect();
+
+        chunks.dedup();
+        chunks
+    }
+}
+
+/// A problem with a chunk in a region file.
+#[derive(Debug)]
+pub struct ChunkError {
+    /// The position of the chunk.
+    pub pos: (i32, i32),
+    pub error: RegionError,
+}
+
+/// The result of compacting a region file.
+#[derive(Debug)]
+pub struct CompactReport {
+    /// The verification report of the region before it was compacted.
+    pub report: RegionReport,
+    /// Whether the region file was rewritten. Compaction is skipped when the
+    /// region has broken chunks that are not being removed.
+    pub compacted: bool,
+    /// The positions of the broken chunks that were removed from the region.
+    pub removed_chunks: Vec<(i32, i32)>,
+    /// The size of the region file in bytes before compaction.
+    pub old_size: u64,
+    /// The size of the region file in bytes after compaction.
+    pub new_size: u64,
+}
+
+impl RegionFolder {
+    /// Returns the positions of all region files in the folder.
+    pub fn region_positions(&self) -> Result<Vec<(i32, i32)>, RegionError> {
+        std::fs::read_dir(&self.region_root)?
+            .filter_map(|file| region_file_position(file).transpose())
+            .collect()
+    }
+
+    /// Checks every header entry of a region file and reads every chunk in it.
+    ///
+    /// Returns `Ok(None)` if the region file does not exist. Problems with
+    /// individual chunks are listed in the report, while `Err(_)` is only
+    /// returned if the region file itself cannot be read.
+    pub fn verify_region(
+        &mut self,
+        region_x: i32,
+        region_z: i32,
+    ) -> Result<Option<RegionReport>, RegionError> {
+        let Some(region) = Self::region(&mut self.regions, &self.region_root, region_x, region_z)?
+        else {
+            return Ok(None);
+        };
+
+        region
+            .verify(
+                region_x,
+                region_z,
+                &mut self.compression_buf,
+                &self.region_root,
+                &self.custom_compressions,
+            )
+            .map(Some)
+    }
+
+    /// Rewrites a region file with its chunks stored contiguously, in the
+    /// order of the header entries, and truncates the unused space.
+    ///
+    /// The region is verified first. If it has [broken chunks], the file is
+    /// left unchanged; use [`RegionFolder::repair_region`] to remove them.
+    /// Returns `Ok(None)` if the region file does not exist.
+    ///
+    /// [broken chunks]: RegionReport::broken_chunks
+    pub fn compact_region(
+        &mut self,
+        region_x: i32,
+        region_z: i32,
+    ) -> Result<Option<CompactReport>, RegionError> {
+        self.rewrite_region(region_x, region_z, false)
+    }
+
+    /// Removes the [broken chunks] of a region file and compacts it like
+    /// [`RegionFolder::compact_region`]. Returns `Ok(None)` if the region file
+    /// does not exist.
+    ///
+    /// [broken chunks]: RegionReport::broken_chunks
+    pub fn repair_region(
+        &mut self,
+        region_x: i32,
+        region_z: i32,
+    ) -> Result<Option<CompactReport>, RegionError> {
+        self.rewrite_region(region_x, region_z, true)
+    }
+
+    fn rewrite_region(
+        &mut self,
+        region_x: i32,
+        region_z: i32,
+        remove_broken: bool,
+    ) -> Result<Option<CompactReport>, RegionError> {
+        let Some(region) = Self::region(&mut self.regions, &self.region_root, region_x, region_z)?
+        else {
+            return Ok(None);
+        };
+
+        let report = region.verify(
+            region_x,
+            region_z,
+            &mut self.compression_buf,
+            &self.region_root,
+            &self.custom_compressions,
+        )?;
+
+        let old_size = region.file.metadata()?.len();
+        let broken = report.broken_chunks();
+
+        if !broken.is_empty() && !remove_broken {
+            return Ok(Some(CompactReport {
+                report,
+                compacted: false,
+                removed_chunks: vec![],
+                old_size,
+                new_size: old_size,
+            }));
+        }
+
+        let path = self
+            .region_root
+            .join(format!("r.{region_x}.{region_z}.mca"));
+        let tmp_path = path.with_extension("mca_new");
+
+        let new_size =
+            region.write_compacted(File::create(&tmp_path)?, region_x, region_z, &broken)?;
+
+        for &(pos_x, pos_z) in &broken {
+            Region::delete_external_chunk_file(pos_x, pos_z, &self.region_root)?;
+        }
+
+        // Close the old file before replacing it. The region is opened again the next
+        // time it is accessed.
+        self.regions.pop(&(region_x, region_z));
+
+        std::fs::rename(tmp_path, path)?;
+
+        Ok(Some(CompactReport {
+            report,
+            compacted: true,
+            removed_chunks: broken,
+            old_size,
+            new_size,
+        }))
+    }
+}
+
+impl Region {
+    fn verify(
+        &mut self,
+        region_x: i32,
+        region_z: i32,
+        decompress_buf: &mut Vec<u8>,
+        region_root: &Path,
+        custom_compressions: &HashMap<String, Arc<dyn CustomCompression>>,
+    ) -> Result<RegionReport, RegionError> {
+        let file_sectors = self.file.metadata()?.len().div_ceil(SECTOR_SIZE as u64);
+
+        // The chunk index that owns each sector of the file.
+        let mut owners = vec![None; file_sectors as usize];
+
+        let mut report = RegionReport {
+            region_x,
+            region_z,
+            chunk_count: 0,
+            errors: vec![],
+            file_sectors,
+            used_sectors: 2.min(file_sectors),
+        };
+
+        for chunk_idx in 0..self.locations.len() {
+            let location = self.locations[chunk_idx];
+
+            if location.is_none() {
+                continue;
+            }
+
+            report.chunk_count += 1;
+
+            let pos = chunk_pos(region_x, region_z, chunk_idx);
+            let (sector_offset, sector_count) = location.offset_and_count();
+
+            let header_error = if sector_offset < 2 {
+                Some(RegionError::InvalidChunkSectorOffset)
+            } else if sector_count == 0 {
+                Some(RegionError::InvalidChunkSize)
+            } else if sector_offset + sector_count as u64 > file_sectors {
+                Some(RegionError::SectorOutOfBounds)
+            } else {
+                None
+            };
+
+            if let Some(error) = header_error {
+                report.errors.push(ChunkError { pos, error });
+                continue;
+            }
+
+            let start = sector_offset as usize;
+            let mut overlapping = vec![];
+
+            for owner in &mut owners[start..start + sector_count] {
+                match *owner {
+                    Some(other) => {
+                        if !overlapping.contains(&other) {
+                            overlapping.push(other);
+                        }
+                    }
+                    None => {
+                        *owner = Some(chunk_idx);
+                        report.used_sectors += 1;
+                    }
+                }
+            }
+
+            for other in overlapping {
+                report.errors.push(ChunkError {
+                    pos,
+                    error: RegionError::OverlappingSectors {
+                        other: chunk_pos(region_x, region_z, other),
+                    },
+                });
+            }
+
+            if let Err(error) = self.get_chunk::<String>(
+                pos.0,
+                pos.1,
+                decompress_buf,
+                region_root,
+                custom_compressions,
+            ) {
+                report.errors.push(ChunkError { pos, error });
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Writes the region to `file` with every chunk stream copied to the
+    /// sectors directly after the previous one. The chunks at the positions in
+    /// `removed` are left out. Returns the size of the new file in bytes.
+    ///
+    /// All the chunks that are not removed must have valid header entries.
+    fn write_compacted(
+        &mut self,
+        file: File,
+        region_x: i32,
+        region_z: i32,
+        removed: &[(i32, i32)],
+    ) -> Result<u64, RegionError> {
+        let mut out = BufWriter::new(file);
+        let mut locations = [Location::new(); 1024];
+        let mut timestamps = [0; 1024];
+
+        out.write_all(&[0; SECTOR_SIZE * 2])?;
+
+        let mut next_sector = 2;
+        let mut stream = vec![];
+
+        for chunk_idx in 0..self.locations.len() {
+            let location = self.locations[chunk_idx];
+
+            if location.is_none() || removed.contains(&chunk_pos(region_x, region_z, chunk_idx)) {
+                continue;
+            }
+
+            let (sector_offset, _) = location.offset_and_count();
+
+            self.file
+                .seek(SeekFrom::Start(sector_offset * SECTOR_SIZE as u64))?;
+
+            let exact_chunk_size = self.file.read_u32::<BigEndian>()? as usize;
+
+            stream.clear();
+            stream.resize(exact_chunk_size, 0);
+            self.file.read_exact(&mut stream)?;
+
+            let sector_count = (exact_chunk_size + 4).div_ceil(SECTOR_SIZE);
+
+            out.write_u32::<BigEndian>(exact_chunk_size as u32)?;
+            out.write_all(&stream)?;
+            out.write_all(&[0; SECTOR_SIZE][..sector_count * SECTOR_SIZE - exact_chunk_size - 4])?;
+
+            locations[chunk_idx] = Location::new()
+                .with_offset(next_sector)
+                .with_count(sector_count as u8);
+            timestamps[chunk_idx] = self.timestamps[chunk_idx];
+
+            next_sector += sector_count as u32;
+        }
+
+        out.seek(SeekFrom::Start(0))?;
+
+        for location in locations {
+            out.write_u32::<BigEndian>(location.0)?;
+        }
+
+        for timestamp in timestamps {
+            out.write_u32::<BigEndian>(timestamp)?;
+        }
+
+        out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+
+        Ok(u64::from(next_sector) * SECTOR_SIZE as u64)
+    }
+}
+
+/// The position of the chunk at an index of the region header.
+fn chunk_pos(region_x: i32, region_z: i32, chunk_idx: usize) -> (i32, i32) {
+    (
+        region_x * 32 + (chunk_idx % 32) as i32,
+        region_z * 32 + (chunk_idx / 32) as i32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::OpenOptions;
+
+    use valence_nbt::{compound, Compound, Value};
+
+    use super::*;
+    use crate::Compression;
+
+    /// A chunk whose uncompressed data takes up `sectors` sectors.
+    fn chunk(sectors: usize) -> Compound {
+        compound! {
+            "data" => Value::ByteArray(vec![1; sectors * SECTOR_SIZE - 100]),
+        }
+    }
+
+    fn region_folder(root: &Path) -> RegionFolder {
+        let mut folder = RegionFolder::new(root);
+        folder.write_options.compression = Compression::None;
+        folder
+    }
+
+    fn write_header_entry(root: &Path, chunk_idx: u64, location: u32) {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(root.join("r.0.0.mca"))
+            .unwrap();
+
+        file.seek(SeekFrom::Start(chunk_idx * 4)).unwrap();
+        file.write_u32::<BigEndian>(location).unwrap();
+    }
+
+    #[test]
+    fn compact_region_reclaims_space() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut folder = region_folder(dir.path());
+
+        folder.set_chunk(0, 0, &chunk(1)).unwrap();
+        folder.set_chunk(1, 0, &chunk(1)).unwrap();
+        // The chunk no longer fits in its sector and is moved to the end of the file.
+        folder.set_chunk(0, 0, &chunk(3)).unwrap();
+
+        let report = folder.verify_region(0, 0).unwrap().unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.chunk_count, 2);
+        assert_eq!(report.unused_sectors(), 1);
+
+        let compacted = folder.compact_region(0, 0).unwrap().unwrap();
+        assert!(compacted.compacted);
+        assert_eq!(compacted.old_size - compacted.new_size, SECTOR_SIZE as u64);
+        assert_eq!(
+            std::fs::metadata(dir.path().join("r.0.0.mca"))
+                .unwrap()
+                .len(),
+            compacted.new_size
+        );
+
+        let report = folder.verify_region(0, 0).unwrap().unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.unused_sectors(), 0);
+
+        assert_eq!(folder.get_chunk(0, 0).unwrap().unwrap().data, chunk(3));
+        assert_eq!(folder.get_chunk(1, 0).unwrap().unwrap().data, chunk(1));
+        assert!(folder.region_positions().unwrap() == [(0, 0)]);
+    }
+
+    #[test]
+    fn verify_and_repair_broken_region() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut folder = region_folder(dir.path());
+
+        for x in 0..4 {
+            folder.set_chunk(x, 0, &chunk(1)).unwrap();
+        }
+
+        drop(folder);
+
+        // Chunk 1 points into the header, chunk 2 shares the sector of chunk 0 and
+        // chunk 3 points past the end of the file.
+        write_header_entry(
+            dir.path(),
+            1,
+            Location::new().with_offset(1).with_count(1).0,
+        );
+        write_header_entry(
+            dir.path(),
+            2,
+            Location::new().with_offset(2).with_count(1).0,
+        );
+        write_header_entry(
+            dir.path(),
+            3,
+            Location::new().with_offset(50).with_count(1).0,
+        );
+
+        let mut folder = region_folder(dir.path());
+        let report = folder.verify_region(0, 0).unwrap().unwrap();
+
+        assert_eq!(report.chunk_count, 4);
+        assert!(matches!(
+            report.errors[0],
+            ChunkError {
+                pos: (1, 0),
+                error: RegionError::InvalidChunkSectorOffset
+            }
+        ));
+        assert!(matches!(
+            report.errors[1],
+            ChunkError {
+                pos: (2, 0),
+                error: RegionError::OverlappingSectors { other: (0, 0) }
+            }
+        ));
+        assert!(matches!(
+            report.errors[2],
+            ChunkError {
+                pos: (3, 0),
+                error: RegionError::SectorOutOfBounds
+            }
+        ));
+        assert_eq!(report.broken_chunks(), [(1, 0), (3, 0)]);
+
+        // Broken chunks are not removed by compaction.
+        let compacted = folder.compact_region(0, 0).unwrap().unwrap();
+        assert!(!compacted.compacted);
+
+        let repaired = folder.repair_region(0, 0).unwrap().unwrap();
+        assert!(repaired.compacted);
+        assert_eq!(repaired.removed_chunks, [(1, 0), (3, 0)]);
+
+        let report = folder.verify_region(0, 0).unwrap().unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.chunk_count, 2);
+        assert_eq!(folder.get_chunk(2, 0).unwrap().unwrap().data, chunk(1));
+    }
+
+    #[test]
+    fn truncated_chunk_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut folder = region_folder(dir.path());
+
+        folder.set_chunk(0, 0, &chunk(2)).unwrap();
+        drop(folder);
+
+        // Cut off the end of the chunk stream in its last sector.
+        OpenOptions::new()
+            .write(true)
+            .open(dir.path().join("r.0.0.mca"))
+            .unwrap()
+            .set_len(SECTOR_SIZE as u64 * 3 + 100)
+            .unwrap();
+
+        let mut folder = region_folder(dir.path());
+
+        assert!(matches!(
+            folder.get_chunk::<String>(0, 0),
+            Err(RegionError::TruncatedChunkStream)
+        ));
+
+        let report = folder.verify_region(0, 0).unwrap().unwrap();
+
+        assert!(matches!(
+            report.errors[..],
+            [ChunkError {
+                pos: (0, 0),
+                error: RegionError::TruncatedChunkStream,
+            }]
+        ));
+    }
+}
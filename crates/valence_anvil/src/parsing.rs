@@ -1,39 +1,59 @@
 use std::borrow::Cow;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use thiserror::Error;
-use valence_server::block::{PropName, PropValue};
-use valence_server::layer::chunk::{Chunk, UnloadedChunk};
-use valence_server::nbt::{Compound, List, Value};
+use valence_server::block::{BlockState, PropName, PropValue};
+use valence_server::layer::chunk::{Chunk, LightArray, UnloadedChunk};
+use valence_server::nbt::{compound, Compound, List, Value};
 use valence_server::protocol::BlockKind;
 use valence_server::registry::biome::BiomeId;
 use valence_server::registry::BiomeRegistry;
 use valence_server::{ChunkPos, Ident};
 
-use crate::{RegionError, RegionFolder};
+use crate::{CustomCompression, RegionError, RegionFolder};
 
 #[derive(Debug)]
 pub struct DimensionFolder {
     region: RegionFolder,
+    /// The region folder containing the entities of each chunk.
+    entities: RegionFolder,
     /// Mapping of biome names to their biome ID.
     biome_to_id: BTreeMap<Ident<String>, BiomeId>,
+    /// Mapping of biome IDs to their biome name.
+    id_to_biome: BTreeMap<BiomeId, Ident<String>>,
 }
 
 impl DimensionFolder {
     pub fn new<R: Into<PathBuf>>(dimension_root: R, biomes: &BiomeRegistry) -> Self {
-        let mut region_root = dimension_root.into();
-        region_root.push("region");
+        let dimension_root = dimension_root.into();
 
         Self {
-            region: RegionFolder::new(region_root),
+            region: RegionFolder::new(dimension_root.join("region")),
+            entities: RegionFolder::new(dimension_root.join("entities")),
             biome_to_id: biomes
                 .iter()
                 .map(|(id, name, _)| (name.to_string_ident(), id))
                 .collect(),
+            id_to_biome: biome_names(biomes),
         }
     }
 
+    /// Registers a custom compression algorithm for both the chunks and the
+    /// entities of the dimension. See
+    /// [`RegionFolder::register_custom_compression`].
+    pub fn register_custom_compression<N: Into<String>>(
+        &mut self,
+        name: N,
+        compression: Arc<dyn CustomCompression>,
+    ) {
+        let name = name.into();
+        self.region
+            .register_custom_compression(name.clone(), compression.clone());
+        self.entities.register_custom_compression(name, compression);
+    }
+
     /// Gets the parsed chunk at the given chunk position.
     ///
     /// Returns `Ok(Some(chunk))` if the chunk exists and no errors occurred
@@ -50,6 +70,61 @@ impl DimensionFolder {
             timestamp: raw_chunk.timestamp,
         }))
     }
+
+    /// Writes the chunk to the given chunk position, overwriting the old chunk
+    /// if it exists. `min_y` is the minimum Y coordinate of the dimension the
+    /// chunk belongs to.
+    pub fn set_chunk(
+        &mut self,
+        pos: ChunkPos,
+        chunk: &UnloadedChunk,
+        min_y: i32,
+    ) -> Result<(), RegionError> {
+        let nbt = encode_chunk(chunk, pos, min_y, &self.id_to_biome);
+        self.region.set_chunk(pos.x, pos.z, &nbt)
+    }
+
+    /// Gets the NBT of the entities in the chunk at the given chunk position.
+    /// Since Minecraft 1.17, entities are stored separately from chunks in the
+    /// `entities` folder of the dimension.
+    ///
+    /// Returns `Ok(None)` if no entities are stored for the chunk.
+    pub fn get_entities(
+        &mut self,
+        pos: ChunkPos,
+    ) -> Result<Option<Vec<Compound>>, ParseChunkError> {
+        let Some(mut raw_chunk) = self.entities.get_chunk(pos.x, pos.z)? else {
+            return Ok(None);
+        };
+
+        match raw_chunk.data.remove("Entities") {
+            Some(Value::List(List::Compound(entities))) => Ok(Some(entities)),
+            Some(Value::List(List::End)) => Ok(Some(vec![])),
+            _ => Err(ParseChunkError::MissingEntities),
+        }
+    }
+
+    /// Writes the NBT of the entities in the chunk at the given chunk
+    /// position, overwriting the old entities if they exist. The entity chunk
+    /// is deleted if `entities` is empty.
+    pub fn set_entities(
+        &mut self,
+        pos: ChunkPos,
+        entities: Vec<Compound>,
+    ) -> Result<(), RegionError> {
+        if entities.is_empty() {
+            self.entities.delete_chunk(pos.x, pos.z)?;
+            return Ok(());
+        }
+
+        let nbt = compound! {
+            "DataVersion" => DATA_VERSION,
+            "Position" => Value::IntArray(vec![pos.x, pos.z]),
+            "Entities" => List::Compound(entities),
+        };
+
+        self.entities.set_chunk(pos.x, pos.z, &nbt)
+    }
 }
 
 /// A chunk parsed to show block information, biome information etc.
@@ -113,6 +188,8 @@ pub enum ParseChunkError {
     InvalidBlockEntityName(String),
     #[error("invalid block entity position")]
     InvalidBlockEntityPosition,
+    #[error("missing entities")]
+    MissingEntities,
 }
 
 fn parse_chunk(
@@ -314,6 +391,14 @@ fn parse_chunk(
                 }
             }
         }
+
+        if let Some(Value::ByteArray(light)) = section.get("SkyLight") {
+            chunk.set_sky_light(sect_y, parse_light(light));
+        }
+
+        if let Some(Value::ByteArray(light)) = section.get("BlockLight") {
+            chunk.set_block_light(sect_y, parse_light(light));
+        }
     }
 
     let Some(Value::List(block_entities)) = nbt.remove("block_entities") else {
@@ -363,9 +448,301 @@ fn parse_chunk(
     Ok(chunk)
 }
 
+/// Converts a chunk into the NBT format used by vanilla 1.20.1 region files.
+/// This is the inverse of the parsing done by [`DimensionFolder::get_chunk`].
+///
+/// `pos` is the position of the chunk and `min_y` is the minimum Y coordinate
+/// of the dimension the chunk belongs to. Biomes missing from `biomes` are
+/// written as `minecraft:plains`.
+///
+/// Light is included if every section of the chunk has both sky light and
+/// block light. Otherwise, vanilla recomputes the light of the chunk when it
+/// is loaded.
+pub fn chunk_to_nbt(
+    chunk: &UnloadedChunk,
+    pos: ChunkPos,
+    min_y: i32,
+    biomes: &BiomeRegistry,
+) -> Compound {
+    encode_chunk(chunk, pos, min_y, &biome_names(biomes))
+}
+
+fn biome_names(biomes: &BiomeRegistry) -> BTreeMap<BiomeId, Ident<String>> {
+    biomes
+        .iter()
+        .map(|(id, name, _)| (id, name.to_string_ident()))
+        .collect()
+}
+
+fn encode_chunk(
+    chunk: &UnloadedChunk,
+    pos: ChunkPos,
+    min_y: i32,
+    biome_names: &BTreeMap<BiomeId, Ident<String>>,
+) -> Compound {
+    let min_sect_y = min_y.div_euclid(16);
+
+    let mut sections = vec![];
+    let mut block_palette = vec![];
+    let mut block_idxs = HashMap::new();
+    let mut biome_palette = vec![];
+    let mut biome_idxs = HashMap::new();
+    let mut blocks = vec![0_usize; BLOCKS_PER_SECTION];
+    let mut biomes = vec![0_usize; BIOMES_PER_SECTION];
+    let mut has_light = chunk.height() > 0;
+
+    for sect_y in 0..chunk.height() / 16 {
+        block_palette.clear();
+        block_idxs.clear();
+
+        for (i, idx) in blocks.iter_mut().enumerate() {
+            let i = i as u32;
+            let state = chunk.block_state(i % 16, sect_y * 16 + i / (16 * 16), i / 16 % 16);
+
+            *idx = *block_idxs.entry(state).or_insert_with(|| {
+                block_palette.push(state);
+                block_palette.len() - 1
+            });
+        }
+
+        let mut block_states = Compound::new();
+
+        block_states.insert(
+            "palette",
+            List::Compound(block_palette.iter().map(|&b| block_to_nbt(b)).collect()),
+        );
+
+        if block_palette.len() > 1 {
+            let bits_per_idx = bit_width(block_palette.len() - 1).max(4);
+            block_states.insert("data", pack_indices(&blocks, bits_per_idx));
+        }
+
+        biome_palette.clear();
+        biome_idxs.clear();
+
+        for (i, idx) in biomes.iter_mut().enumerate() {
+            let i = i as u32;
+            let biome = chunk.biome(i % 4, sect_y * 4 + i / (4 * 4), i / 4 % 4);
+
+            *idx = *biome_idxs.entry(biome).or_insert_with(|| {
+                biome_palette.push(biome);
+                biome_palette.len() - 1
+            });
+        }
+
+        let mut biomes_nbt = Compound::new();
+
+        biomes_nbt.insert(
+            "palette",
+            List::String(
+                biome_palette
+                    .iter()
+                    .map(|id| match biome_names.get(id) {
+                        Some(name) => name.to_string(),
+                        None => "minecraft:plains".into(),
+                    })
+                    .collect(),
+            ),
+        );
+
+        if biome_palette.len() > 1 {
+            let bits_per_idx = bit_width(biome_palette.len() - 1);
+            biomes_nbt.insert("data", pack_indices(&biomes, bits_per_idx));
+        }
+
+        let mut section = Compound::new();
+
+        section.insert("Y", (min_sect_y + sect_y as i32) as i8);
+        section.insert("block_states", block_states);
+        section.insert("biomes", biomes_nbt);
+
+        match chunk.sky_light(sect_y) {
+            Some(light) => {
+                section.insert("SkyLight", light_to_nbt(light));
+            }
+            None => has_light = false,
+        }
+
+        match chunk.block_light(sect_y) {
+            Some(light) => {
+                section.insert("BlockLight", light_to_nbt(light));
+            }
+            None => has_light = false,
+        }
+
+        sections.push(section);
+    }
+
+    let block_entities = chunk
+        .block_entities()
+        .filter_map(|([x, y, z], nbt)| {
+            let mut comp = nbt.clone();
+
+            // The ID is not kept by `parse_chunk` since it's implied by the block.
+            if !comp.contains_key("id") {
+                let kind = chunk.block_state(x, y, z).block_entity_kind()?;
+                comp.insert("id", kind.ident().as_str());
+            }
+
+            comp.insert("x", pos.x * 16 + x as i32);
+            comp.insert("y", min_y + y as i32);
+            comp.insert("z", pos.z * 16 + z as i32);
+            comp.insert("keepPacked", false);
+
+            Some(comp)
+        })
+        .collect();
+
+    let mut nbt = Compound::new();
+
+    nbt.insert("DataVersion", DATA_VERSION);
+    nbt.insert("xPos", pos.x);
+    nbt.insert("yPos", min_sect_y);
+    nbt.insert("zPos", pos.z);
+    nbt.insert("Status", "minecraft:full");
+    nbt.insert("LastUpdate", 0_i64);
+    nbt.insert("InhabitedTime", 0_i64);
+    nbt.insert("isLightOn", has_light);
+    nbt.insert("sections", List::Compound(sections));
+    nbt.insert("block_entities", List::Compound(block_entities));
+    nbt.insert("Heightmaps", heightmaps_to_nbt(chunk));
+    nbt.insert("block_ticks", List::End);
+    nbt.insert("fluid_ticks", List::End);
+    nbt.insert(
+        "structures",
+        compound! {
+            "References" => Compound::new(),
+            "starts" => Compound::new(),
+        },
+    );
+
+    nbt
+}
+
+/// Converts a [`LightArray`] into the byte array stored in sections.
+fn light_to_nbt(light: &LightArray) -> Value {
+    Value::ByteArray(light.to_bytes().iter().map(|&b| b as i8).collect())
+}
+
+/// Converts a block state into an entry of a block palette.
+fn block_to_nbt(state: BlockState) -> Compound {
+    let kind = state.to_kind();
+
+    let mut nbt = Compound::new();
+    nbt.insert("Name", format!("minecraft:{}", kind.to_str()));
+
+    let props: Compound = kind
+        .props()
+        .iter()
+        .filter_map(|&name| {
+            let value = state.get(name)?;
+            Some((
+                name.to_str().to_owned(),
+                Value::String(value.to_str().to_owned()),
+            ))
+        })
+        .collect();
+
+    if !props.is_empty() {
+        nbt.insert("Properties", props);
+    }
+
+    nbt
+}
+
+/// Computes the heightmaps stored in vanilla chunks. Each entry is the
+/// number of blocks from the bottom of the chunk to the first block above
+/// the highest block matching the heightmap in the column.
+fn heightmaps_to_nbt(chunk: &UnloadedChunk) -> Compound {
+    const WORLD_SURFACE: usize = 0;
+    const OCEAN_FLOOR: usize = 1;
+    const MOTION_BLOCKING: usize = 2;
+    const MOTION_BLOCKING_NO_LEAVES: usize = 3;
+
+    let mut heights = [[0_usize; 16 * 16]; 4];
+
+    for z in 0..16 {
+        for x in 0..16 {
+            let column = (x + z * 16) as usize;
+            let mut remaining = heights.len();
+
+            for y in (0..chunk.height()).rev() {
+                let state = chunk.block_state(x, y, z);
+
+                let blocks_motion = state.blocks_motion();
+                let motion_blocking = blocks_motion
+                    || state.is_liquid()
+                    || state.get(PropName::Waterlogged) == Some(PropValue::True);
+
+                let matches = [
+                    !state.is_air(),
+                    blocks_motion,
+                    motion_blocking,
+                    motion_blocking && !state.to_kind().to_str().ends_with("_leaves"),
+                ];
+
+                for (heightmap, matches) in heights.iter_mut().zip(matches) {
+                    if matches && heightmap[column] == 0 {
+                        heightmap[column] = y as usize + 1;
+                        remaining -= 1;
+                    }
+                }
+
+                if remaining == 0 {
+                    break;
+                }
+            }
+        }
+    }
+
+    let bits_per_entry = bit_width(chunk.height() as usize).max(1);
+
+    let mut nbt = Compound::new();
+
+    for (name, idx) in [
+        ("WORLD_SURFACE", WORLD_SURFACE),
+        ("OCEAN_FLOOR", OCEAN_FLOOR),
+        ("MOTION_BLOCKING", MOTION_BLOCKING),
+        ("MOTION_BLOCKING_NO_LEAVES", MOTION_BLOCKING_NO_LEAVES),
+    ] {
+        nbt.insert(name, pack_indices(&heights[idx], bits_per_entry));
+    }
+
+    nbt
+}
+
+/// Packs integers into a long array with `bits_per_idx` bits per integer. As
+/// in vanilla, integers do not span across multiple longs.
+fn pack_indices(idxs: &[usize], bits_per_idx: usize) -> Value {
+    let idxs_per_long = 64 / bits_per_idx;
+
+    Value::LongArray(
+        idxs.chunks(idxs_per_long)
+            .map(|chunk| {
+                let mut long = 0_u64;
+
+                for (j, &idx) in chunk.iter().enumerate() {
+                    long |= (idx as u64) << (bits_per_idx * j);
+                }
+
+                long as i64
+            })
+            .collect(),
+    )
+}
+
+/// The data version of chunks written by Minecraft 1.20.1.
+const DATA_VERSION: i32 = 3465;
+
 const BLOCKS_PER_SECTION: usize = 16 * 16 * 16;
 const BIOMES_PER_SECTION: usize = 4 * 4 * 4;
 
+/// Converts a light array of a section into a [`LightArray`]. Arrays with an
+/// unexpected length are ignored.
+fn parse_light(light: &[i8]) -> Option<LightArray> {
+    LightArray::from_bytes(&light.iter().map(|&b| b as u8).collect::<Vec<_>>())
+}
+
 /// Gets the path part of a resource identifier.
 fn ident_path(ident: &str) -> &str {
     match ident.rsplit_once(':') {
@@ -378,3 +755,442 @@ fn ident_path(ident: &str) -> &str {
 const fn bit_width(n: usize) -> usize {
     (usize::BITS - n.leading_zeros()) as usize
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use valence_server::ident;
+    use valence_server::registry::biome::Biome;
+
+    use super::*;
+
+    fn biome_registry() -> BiomeRegistry {
+        let mut biomes = BiomeRegistry::default();
+
+        for name in [ident!("plains"), ident!("desert"), ident!("swamp")] {
+            biomes.insert(name, Biome::default());
+        }
+
+        biomes
+    }
+
+    #[track_caller]
+    fn assert_chunks_eq(a: &UnloadedChunk, b: &UnloadedChunk) {
+        assert_eq!(a.height(), b.height());
+
+        for y in 0..a.height() {
+            for z in 0..16 {
+                for x in 0..16 {
+                    assert_eq!(a.block_state(x, y, z), b.block_state(x, y, z));
+                    assert_eq!(a.block_entity(x, y, z), b.block_entity(x, y, z));
+                }
+            }
+        }
+
+        for y in 0..a.height() / 4 {
+            for z in 0..4 {
+                for x in 0..4 {
+                    assert_eq!(a.biome(x, y, z), b.biome(x, y, z));
+                }
+            }
+        }
+
+        for sect_y in 0..a.height() / 16 {
+            assert_eq!(a.sky_light(sect_y), b.sky_light(sect_y));
+            assert_eq!(a.block_light(sect_y), b.block_light(sect_y));
+        }
+    }
+
+    /// Unpacks a heightmap written by [`heightmaps_to_nbt`].
+    fn unpack_heightmap(nbt: &Compound, name: &str, bits_per_entry: usize) -> Vec<u64> {
+        let Some(Value::LongArray(longs)) = nbt.get(name) else {
+            panic!("missing heightmap {name}");
+        };
+
+        let entries_per_long = 64 / bits_per_entry;
+
+        longs
+            .iter()
+            .flat_map(|&long| {
+                (0..entries_per_long).map(move |j| {
+                    (long as u64 >> (bits_per_entry * j)) & ((1 << bits_per_entry) - 1)
+                })
+            })
+            .take(16 * 16)
+            .collect()
+    }
+
+    #[test]
+    fn chunk_nbt_round_trip() {
+        let biomes = biome_registry();
+        let desert = biomes.index_of(ident!("desert")).unwrap();
+        let swamp = biomes.index_of(ident!("swamp")).unwrap();
+
+        let mut chunk = UnloadedChunk::with_height(384);
+
+        // Enough distinct states to require more than the minimum palette bits.
+        for i in 0..300 {
+            let state = BlockState::from_raw(i as u16 * 17).unwrap();
+            chunk.set_block_state(i % 16, (i * 7) % 384, i / 16 % 16, state);
+        }
+
+        chunk.fill_block_state_section(2, BlockState::STONE);
+        chunk.set_block_state(
+            3,
+            100,
+            4,
+            BlockState::OAK_STAIRS
+                .set(PropName::Facing, PropValue::East)
+                .set(PropName::Waterlogged, PropValue::True),
+        );
+
+        chunk.set_block_state(1, 2, 3, BlockState::CHEST);
+        chunk.set_block_entity(1, 2, 3, Some(compound! { "Lock" => "secret" }));
+
+        chunk.fill_biome_section(5, swamp);
+        chunk.set_biome(1, 5, 2, desert);
+
+        let nbt = chunk_to_nbt(&chunk, ChunkPos::new(-3, 5), -64, &biomes);
+
+        assert_eq!(nbt.get("DataVersion"), Some(&Value::Int(DATA_VERSION)));
+        assert_eq!(
+            nbt.get("Status"),
+            Some(&Value::String("minecraft:full".into()))
+        );
+        assert_eq!(nbt.get("xPos"), Some(&Value::Int(-3)));
+        assert_eq!(nbt.get("yPos"), Some(&Value::Int(-4)));
+        assert_eq!(nbt.get("zPos"), Some(&Value::Int(5)));
+        // The chunk has no light.
+        assert_eq!(nbt.get("isLightOn"), Some(&Value::Byte(0)));
+
+        let Some(Value::List(List::Compound(block_entities))) = nbt.get("block_entities") else {
+            panic!("missing block entities");
+        };
+
+        assert_eq!(block_entities.len(), 1);
+        assert_eq!(
+            block_entities[0].get("id"),
+            Some(&Value::String("minecraft:chest".into()))
+        );
+        assert_eq!(block_entities[0].get("x"), Some(&Value::Int(-47)));
+        assert_eq!(block_entities[0].get("y"), Some(&Value::Int(-62)));
+        assert_eq!(block_entities[0].get("z"), Some(&Value::Int(83)));
+
+        let parsed = parse_chunk(
+            nbt,
+            &biome_names(&biomes)
+                .into_iter()
+                .map(|(id, name)| (name, id))
+                .collect(),
+        )
+        .unwrap();
+
+        assert_chunks_eq(&chunk, &parsed);
+    }
+
+    #[test]
+    fn chunk_nbt_light() {
+        let biomes = biome_registry();
+
+        let mut chunk = UnloadedChunk::with_height(32);
+        let mut light = LightArray::uniform(15);
+        light.set(1, 2, 3, 4);
+
+        chunk.set_sky_light(0, Some(light.clone()));
+        chunk.set_sky_light(1, Some(LightArray::uniform(15)));
+        chunk.set_block_light(0, Some(LightArray::uniform(0)));
+        chunk.set_block_light(1, Some(light));
+
+        let nbt = chunk_to_nbt(&chunk, ChunkPos::new(0, 0), 0, &biomes);
+        assert_eq!(nbt.get("isLightOn"), Some(&Value::Byte(1)));
+
+        let parsed = parse_chunk(nbt, &BTreeMap::new()).unwrap();
+        assert_chunks_eq(&chunk, &parsed);
+    }
+
+    #[test]
+    fn chunk_nbt_heightmaps() {
+        let biomes = biome_registry();
+
+        let mut chunk = UnloadedChunk::with_height(384);
+
+        chunk.set_block_state(0, 10, 0, BlockState::STONE);
+        chunk.set_block_state(0, 20, 0, BlockState::OAK_LEAVES);
+        chunk.set_block_state(0, 30, 0, BlockState::TORCH);
+        chunk.set_block_state(5, 40, 0, BlockState::WATER);
+
+        let nbt = chunk_to_nbt(&chunk, ChunkPos::new(0, 0), -64, &biomes);
+
+        let Some(Value::Compound(heightmaps)) = nbt.get("Heightmaps") else {
+            panic!("missing heightmaps");
+        };
+
+        // 385 possible values need 9 bits.
+        let world_surface = unpack_heightmap(heightmaps, "WORLD_SURFACE", 9);
+        let ocean_floor = unpack_heightmap(heightmaps, "OCEAN_FLOOR", 9);
+        let motion_blocking = unpack_heightmap(heightmaps, "MOTION_BLOCKING", 9);
+        let no_leaves = unpack_heightmap(heightmaps, "MOTION_BLOCKING_NO_LEAVES", 9);
+
+        assert_eq!(world_surface[0], 31);
+        assert_eq!(ocean_floor[0], 21);
+        assert_eq!(motion_blocking[0], 21);
+        assert_eq!(no_leaves[0], 11);
+
+        assert_eq!(world_surface[5], 41);
+        assert_eq!(ocean_floor[5], 0);
+        assert_eq!(motion_blocking[5], 41);
+        assert_eq!(no_leaves[5], 41);
+
+        assert_eq!(world_surface[1], 0);
+    }
+
+    const WOOL_COLORS: [&str; 16] = [
+        "white",
+        "orange",
+        "magenta",
+        "light_blue",
+        "yellow",
+        "lime",
+        "pink",
+        "gray",
+        "light_gray",
+        "cyan",
+        "purple",
+        "blue",
+        "brown",
+        "green",
+        "red",
+        "black",
+    ];
+
+    /// Packs palette indices the way vanilla does, independently of
+    /// [`pack_indices`].
+    fn vanilla_packed(len: usize, bits: usize, idx: impl Fn(usize) -> usize) -> Value {
+        let per_long = 64 / bits;
+
+        Value::LongArray(
+            (0..len.div_ceil(per_long))
+                .map(|l| {
+                    (0..per_long)
+                        .map(|j| l * per_long + j)
+                        .filter(|&i| i < len)
+                        .fold(0_u64, |long, i| {
+                            long | (idx(i) as u64) << (bits * (i % per_long))
+                        }) as i64
+                })
+                .collect(),
+        )
+    }
+
+    fn block(name: &str) -> Compound {
+        compound! { "Name" => format!("minecraft:{name}") }
+    }
+
+    /// A chunk at (2, -3) with four sections starting at Y -64, laid out like
+    /// the chunks in the region files of vanilla 1.20.1.
+    fn vanilla_chunk() -> Compound {
+        // Blocks indexed by Y, then Z, then X.
+        let xyz = |i: usize| (i % 16, i / 256, i / 16 % 16);
+
+        let bottom = compound! {
+            "Y" => -4_i8,
+            "block_states" => compound! {
+                "palette" => List::Compound(vec![
+                    block("bedrock"),
+                    block("stone"),
+                    compound! {
+                        "Name" => "minecraft:deepslate",
+                        "Properties" => compound! { "axis" => "y" },
+                    },
+                    compound! {
+                        "Name" => "minecraft:chest",
+                        "Properties" => compound! {
+                            "facing" => "north",
+                            "type" => "single",
+                            "waterlogged" => "false",
+                        },
+                    },
+                ]),
+                "data" => vanilla_packed(4096, 4, |i| match xyz(i) {
+                    (_, 0, _) => 0,
+                    (1, 9, 2) => 3,
+                    (_, y, _) if y < 8 => 1,
+                    _ => 2,
+                }),
+            },
+            "biomes" => compound! {
+                "palette" => List::String(vec!["minecraft:plains".into(), "minecraft:desert".into()]),
+                "data" => vanilla_packed(64, 1, |i| usize::from(i % 4 < 2)),
+            },
+        };
+
+        let mut palette: Vec<_> = WOOL_COLORS
+            .iter()
+            .map(|color| block(&format!("{color}_wool")))
+            .collect();
+        palette.push(block("glass"));
+
+        let sky_light: Vec<i8> = (0..2048).map(|i| (i % 251) as i8).collect();
+
+        let wool = compound! {
+            "Y" => -3_i8,
+            "block_states" => compound! {
+                "palette" => List::Compound(palette),
+                "data" => vanilla_packed(4096, 5, |i| {
+                    let (x, _, z) = xyz(i);
+                    (x + z) % 17
+                }),
+            },
+            "biomes" => compound! {
+                "palette" => List::String(vec!["minecraft:swamp".into()]),
+            },
+            "SkyLight" => sky_light,
+            "BlockLight" => vec![0_i8; 2048],
+        };
+
+        let air = |y: i8| {
+            compound! {
+                "Y" => y,
+                "block_states" => compound! {
+                    "palette" => List::Compound(vec![block("air")]),
+                },
+                "biomes" => compound! {
+                    "palette" => List::String(vec!["minecraft:plains".into()]),
+                },
+            }
+        };
+
+        compound! {
+            "DataVersion" => DATA_VERSION,
+            "Status" => "minecraft:full",
+            "xPos" => 2,
+            "yPos" => -4,
+            "zPos" => -3,
+            "LastUpdate" => 1234_i64,
+            "InhabitedTime" => 0_i64,
+            "isLightOn" => 0_i8,
+            "sections" => List::Compound(vec![bottom, wool, air(-2), air(-1)]),
+            "block_entities" => List::Compound(vec![compound! {
+                "id" => "minecraft:chest",
+                "x" => 33,
+                "y" => -55,
+                "z" => -46,
+                "keepPacked" => 0_i8,
+                "Items" => List::Compound(vec![compound! {
+                    "Slot" => 0_i8,
+                    "id" => "minecraft:diamond",
+                    "Count" => 3_i8,
+                }]),
+            }]),
+            "Heightmaps" => Compound::new(),
+            "structures" => compound! {
+                "References" => Compound::new(),
+                "starts" => Compound::new(),
+            },
+            "block_ticks" => List::End,
+            "fluid_ticks" => List::End,
+            "PostProcessing" => List::End,
+        }
+    }
+
+    #[test]
+    fn chunk_nbt_round_trip_vanilla_fixture() {
+        let biomes = biome_registry();
+        let biome_map = biome_names(&biomes)
+            .into_iter()
+            .map(|(id, name)| (name, id))
+            .collect();
+        let plains = biomes.index_of(ident!("plains")).unwrap();
+        let desert = biomes.index_of(ident!("desert")).unwrap();
+        let swamp = biomes.index_of(ident!("swamp")).unwrap();
+
+        let chunk = parse_chunk(vanilla_chunk(), &biome_map).unwrap();
+
+        assert_eq!(chunk.height(), 64);
+        assert_eq!(chunk.block_state(4, 0, 4), BlockState::BEDROCK);
+        assert_eq!(chunk.block_state(4, 7, 4), BlockState::STONE);
+        assert_eq!(chunk.block_state(4, 8, 4), BlockState::DEEPSLATE);
+        assert_eq!(
+            chunk.block_state(1, 9, 2),
+            BlockState::CHEST.set(PropName::Facing, PropValue::North)
+        );
+        assert_eq!(
+            chunk.block_entity(1, 9, 2),
+            Some(&compound! {
+                "Items" => List::Compound(vec![compound! {
+                    "Slot" => 0_i8,
+                    "id" => "minecraft:diamond",
+                    "Count" => 3_i8,
+                }]),
+            })
+        );
+        assert_eq!(chunk.block_state(15, 20, 1), BlockState::GLASS);
+        assert_eq!(chunk.block_state(3, 31, 5), BlockState::LIGHT_GRAY_WOOL);
+        assert_eq!(chunk.block_state(0, 32, 0), BlockState::AIR);
+        assert_eq!(chunk.biome(0, 0, 0), desert);
+        assert_eq!(chunk.biome(3, 3, 3), plains);
+        assert_eq!(chunk.biome(3, 4, 3), swamp);
+        assert_eq!(
+            chunk.sky_light(1).map(|light| light.to_bytes().to_vec()),
+            Some((0..2048).map(|i| (i % 251) as u8).collect())
+        );
+
+        let nbt = chunk_to_nbt(&chunk, ChunkPos::new(2, -3), -64, &biomes);
+
+        // Block entities are written back at their position in the world.
+        let Some(Value::List(List::Compound(block_entities))) = nbt.get("block_entities") else {
+            panic!("missing block entities");
+        };
+        assert_eq!(block_entities[0].get("x"), Some(&Value::Int(33)));
+        assert_eq!(block_entities[0].get("y"), Some(&Value::Int(-55)));
+        assert_eq!(block_entities[0].get("z"), Some(&Value::Int(-46)));
+
+        let reparsed = parse_chunk(nbt, &biome_map).unwrap();
+        assert_chunks_eq(&chunk, &reparsed);
+    }
+
+    /// Round trips the chunks of the world used by the anvil benchmark. The
+    /// world is the `sp_world_1.19.2` asset of `valence-test-data`, extracted
+    /// into the asset cache.
+    #[test]
+    #[ignore = "requires the benchmark world in `.asset_cache`"]
+    fn chunk_nbt_round_trip_bench_world() {
+        let world_dir =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../../.asset_cache/1.19.2 benchmark world");
+
+        assert!(
+            world_dir.exists(),
+            "`{}` does not exist",
+            world_dir.display()
+        );
+
+        let biomes = biome_registry();
+        let biome_map = biome_names(&biomes)
+            .into_iter()
+            .map(|(id, name)| (name, id))
+            .collect();
+
+        let mut region = RegionFolder::new(world_dir.join("region"));
+
+        for z in -5..5 {
+            for x in -5..5 {
+                let raw = region
+                    .get_chunk(x, z)
+                    .unwrap()
+                    .expect("missing chunk at position");
+
+                let min_y = match raw.data.get("yPos") {
+                    Some(Value::Int(y)) => y * 16,
+                    _ => -64,
+                };
+
+                let chunk = parse_chunk(raw.data, &biome_map).unwrap();
+                let nbt = chunk_to_nbt(&chunk, ChunkPos::new(x, z), min_y, &biomes);
+                let reparsed = parse_chunk(nbt, &biome_map).unwrap();
+
+                assert_chunks_eq(&chunk, &reparsed);
+            }
+        }
+    }
+}
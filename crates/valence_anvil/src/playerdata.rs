@@ -0,0 +1,736 @@
+//! Loading and saving players from the `playerdata` folder of a world.
+//!
+//! Add [`PlayerDataPlugin`] to the app and insert a [`PlayerDataFolder`]
+//! resource. When a client joins, the `playerdata/<uuid>.dat` file of the
+//! client is read and applied to the components of the client. The player is
+//! written back when the client disconnects, periodically while the client is
+//! connected and in the [`Save`] schedule.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use bevy_app::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_ecs::query::QueryData;
+use valence_inventory::player_inventory::PlayerInventory;
+use valence_inventory::{init_new_client_inventories, HeldItem, Inventory, InventoryKind};
+use valence_server::client::{Client, ClientMarker, FlushPacketsSet, SpawnClientsSet};
+use valence_server::entity::active_status_effects::{ActiveStatusEffect, ActiveStatusEffects};
+use valence_server::entity::living::Health;
+use valence_server::entity::player::{Food, Saturation};
+use valence_server::entity::{Look, Position};
+use valence_server::experience::Experience;
+use valence_server::nbt::{Compound, List, Value};
+use valence_server::protocol::anyhow;
+use valence_server::protocol::status_effects::StatusEffect;
+use valence_server::shutdown::{Save, SavePlugin};
+use valence_server::uuid::Uuid;
+use valence_server::{GameMode, UniqueId};
+
+use crate::entity::{item_stack_from_nbt, item_stack_to_nbt};
+use crate::level_data::{read_gzip_nbt, write_gzip_nbt, LevelDataError};
+use crate::ChunkSaveStatus;
+
+/// The default value of [`PlayerDataFolder::autosave_interval`].
+pub const DEFAULT_PLAYER_AUTOSAVE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+pub struct PlayerDataPlugin;
+
+impl Plugin for PlayerDataPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<SavePlugin>() {
+            app.add_plugins(SavePlugin);
+        }
+
+        app.add_event::<PlayerDataLoadEvent>()
+            .add_event::<PlayerDataSaveEvent>()
+            .add_systems(
+                PreUpdate,
+                load_player_data
+                    .after(SpawnClientsSet)
+                    .after(init_new_client_inventories)
+                    .run_if(resource_exists::<PlayerDataFolder>),
+            )
+            .add_systems(
+                PostUpdate,
+                (save_disconnected_players, autosave_players)
+                    .after(FlushPacketsSet)
+                    .run_if(resource_exists::<PlayerDataFolder>),
+            )
+            .add_systems(
+                Save,
+                save_all_players.run_if(resource_exists::<PlayerDataFolder>),
+            );
+    }
+}
+
+/// The `playerdata` folder of a world. Players are only loaded and saved while
+/// this resource exists.
+#[derive(Resource, Debug)]
+pub struct PlayerDataFolder {
+    /// The path to the `playerdata` folder.
+    root: PathBuf,
+    /// How often the players of connected clients are written to the folder.
+    /// `None` disables autosaving. Players are always saved when their
+    /// client disconnects.
+    ///
+    /// This is [`DEFAULT_PLAYER_AUTOSAVE_INTERVAL`] by default.
+    pub autosave_interval: Option<Duration>,
+    /// The time of the last autosave.
+    last_autosave: Instant,
+}
+
+impl PlayerDataFolder {
+    /// Creates the resource for the `playerdata` folder in the directory of a
+    /// world. The folder is created when the first player is saved.
+    pub fn new<R: AsRef<Path>>(world_root: R) -> Self {
+        Self {
+            root: world_root.as_ref().join("playerdata"),
+            autosave_interval: Some(DEFAULT_PLAYER_AUTOSAVE_INTERVAL),
+            last_autosave: Instant::now(),
+        }
+    }
+
+    /// The path to the `playerdata` folder.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Reads the NBT of the player with the given UUID. Returns `Ok(None)` if
+    /// the player has no file in the folder.
+    pub fn read(&self, uuid: Uuid) -> Result<Option<Compound>, LevelDataError> {
+        read_gzip_nbt(&self.path(uuid))
+    }
+
+    /// Writes the NBT of the player with the given UUID, replacing the
+    /// previous file of the player.
+    pub fn write(&self, uuid: Uuid, nbt: &Compound) -> Result<(), LevelDataError> {
+        std::fs::create_dir_all(&self.root)?;
+        write_gzip_nbt(&self.path(uuid), nbt)
+    }
+
+    fn path(&self, uuid: Uuid) -> PathBuf {
+        self.root.join(format!("{}.dat", uuid.hyphenated()))
+    }
+}
+
+/// The player data of a client, inserted when the client joins. Only clients
+/// with this component are saved.
+///
+/// The NBT is the contents of the player file at the time the client joined,
+/// and is updated from the components of the client whenever the player is
+/// saved. Tags that are not used by Valence, such as the recipe book and the
+/// dimension of the player, are written back unchanged.
+#[derive(Component, Clone, Default, Debug)]
+pub struct PlayerData {
+    pub nbt: Compound,
+    /// Whether the player had no file when the client joined.
+    new: bool,
+}
+
+impl PlayerData {
+    /// Returns `true` if the player had no file in the `playerdata` folder
+    /// when the client joined. The components of the client are left as they
+    /// were in this case.
+    pub fn is_new(&self) -> bool {
+        self.new
+    }
+}
+
+/// The ender chest of a player, loaded from the `EnderItems` of the player.
+#[derive(Component, Debug)]
+pub struct EnderChest(pub Inventory);
+
+impl Default for EnderChest {
+    fn default() -> Self {
+        Self(Inventory::with_title(
+            InventoryKind::Generic9x3,
+            "Ender Chest",
+        ))
+    }
+}
+
+/// The components of a client that are stored in the player file.
+#[derive(QueryData)]
+#[query_data(mutable)]
+struct PlayerQuery {
+    position: &'static mut Position,
+    look: &'static mut Look,
+    game_mode: &'static mut GameMode,
+    health: &'static mut Health,
+    food: &'static mut Food,
+    saturation: &'static mut Saturation,
+    experience: &'static mut Experience,
+    held_item: &'static mut HeldItem,
+    inventory: &'static mut Inventory,
+    status_effects: &'static mut ActiveStatusEffects,
+}
+
+impl PlayerQueryItem<'_> {
+    /// Applies the vanilla player NBT to the components. Tags that are
+    /// missing or invalid leave their components unchanged.
+    fn apply_nbt(&mut self, nbt: &Compound, ender_chest: &mut EnderChest) {
+        if let Some([x, y, z]) = get_doubles(nbt, "Pos") {
+            self.position.set([x, y, z]);
+        }
+
+        if let Some([yaw, pitch]) = get_floats(nbt, "Rotation") {
+            self.look.yaw = yaw;
+            self.look.pitch = pitch;
+        }
+
+        if let Some(game_mode) = nbt
+            .get("playerGameType")
+            .and_then(Value::as_i32)
+            .and_then(game_mode_from_i32)
+        {
+            *self.game_mode = game_mode;
+        }
+
+        if let Some(health) = nbt.get("Health").and_then(Value::as_f32) {
+            self.health.0 = health;
+        }
+
+        if let Some(food) = nbt.get("foodLevel").and_then(Value::as_i32) {
+            self.food.0 = food;
+        }
+
+        if let Some(saturation) = nbt.get("foodSaturationLevel").and_then(Value::as_f32) {
+            self.saturation.0 = saturation;
+        }
+
+        if let Some(level) = nbt.get("XpLevel").and_then(Value::as_i32) {
+            self.experience.level = level;
+        }
+
+        if let Some(progress) = nbt.get("XpP").and_then(Value::as_f32) {
+            self.experience.progress = progress;
+        }
+
+        if let Some(total) = nbt.get("XpTotal").and_then(Value::as_i32) {
+            self.experience.total = total;
+        }
+
+        if let Some(idx) = nbt.get("SelectedItemSlot").and_then(Value::as_i32) {
+            if let Ok(idx @ 0..=8) = u8::try_from(idx) {
+                self.held_item.set_hotbar_idx(idx);
+            }
+        }
+
+        if let Some(Value::List(List::Compound(items))) = nbt.get("Inventory") {
+            for item in items {
+                let Some(slot) = item
+                    .get("Slot")
+                    .and_then(Value::as_i8)
+                    .and_then(inventory_slot_from_nbt)
+                else {
+                    continue;
+                };
+
+                self.inventory
+                    .set_slot(slot, item_stack_from_nbt(item.clone()));
+            }
+        }
+
+        if let Some(Value::List(List::Compound(items))) = nbt.get("EnderItems") {
+            for item in items {
+                let Some(slot) = item.get("Slot").and_then(Value::as_i8) else {
+                    continue;
+                };
+
+                if let Ok(slot) = u16::try_from(slot) {
+                    if slot < ender_chest.0.slot_count() {
+                        ender_chest
+                            .0
+                            .set_slot(slot, item_stack_from_nbt(item.clone()));
+                    }
+                }
+            }
+        }
+
+        if let Some(Value::List(List::Compound(effects))) = nbt.get("ActiveEffects") {
+            for effect in effects {
+                if let Some(effect) = status_effect_from_nbt(effect) {
+                    self.status_effects.apply(effect);
+                }
+            }
+        }
+    }
+
+    /// Writes the components to the vanilla player NBT, replacing the tags
+    /// that were there before.
+    fn write_nbt(&self, nbt: &mut Compound, ender_chest: Option<&EnderChest>) {
+        nbt.insert("Pos", List::Double(self.position.0.to_array().to_vec()));
+        nbt.insert(
+            "Rotation",
+            List::Float(vec![self.look.yaw, self.look.pitch]),
+        );
+        nbt.insert("playerGameType", *self.game_mode as i32);
+        nbt.insert("Health", self.health.0);
+        nbt.insert("foodLevel", self.food.0);
+        nbt.insert("foodSaturationLevel", self.saturation.0);
+        nbt.insert("XpLevel", self.experience.level);
+        nbt.insert("XpP", self.experience.progress);
+        nbt.insert("XpTotal", self.experience.total);
+        nbt.insert("SelectedItemSlot", i32::from(self.held_item.hotbar_idx()));
+
+        let mut items = vec![];
+
+        // Vanilla writes the main inventory first, followed by the armor and the
+        // offhand.
+        for nbt_slot in (0..=35)
+            .chain(NBT_SLOT_FEET..=NBT_SLOT_HEAD)
+            .chain([NBT_SLOT_OFFHAND])
+        {
+            let Some(slot) = inventory_slot_from_nbt(nbt_slot) else {
+                continue;
+            };
+
+            if let Some(mut item) = item_stack_to_nbt(self.inventory.slot(slot)) {
+                item.insert("Slot", nbt_slot);
+                items.push(item);
+            }
+        }
+
+        nbt.insert("Inventory", List::Compound(items));
+
+        if let Some(ender_chest) = ender_chest {
+            let mut items = vec![];
+
+            for slot in 0..ender_chest.0.slot_count() {
+                if let Some(mut item) = item_stack_to_nbt(ender_chest.0.slot(slot)) {
+                    item.insert("Slot", slot as i8);
+                    items.push(item);
+                }
+            }
+
+            nbt.insert("EnderItems", List::Compound(items));
+        }
+
+        nbt.insert(
+            "ActiveEffects",
+            List::Compound(
+                self.status_effects
+                    .get_current_effects()
+                    .into_iter()
+                    .map(status_effect_to_nbt)
+                    .collect(),
+            ),
+        );
+    }
+}
+
+/// An event sent by `valence_anvil` after an attempt to load the player data
+/// of a client is made.
+#[derive(Event, Debug)]
+pub struct PlayerDataLoadEvent {
+    /// The client entity.
+    pub client: Entity,
+    pub status: PlayerDataLoadStatus,
+}
+
+#[derive(Debug)]
+pub enum PlayerDataLoadStatus {
+    /// The player file was read and applied to the client.
+    Success,
+    /// The player has no file in the `playerdata` folder. The components of
+    /// the client were left unchanged.
+    Empty,
+    /// An attempt was made to load the player file, but something went wrong.
+    /// The client has no [`PlayerData`] component and will not be saved, so
+    /// the file is not overwritten.
+    Failed(anyhow::Error),
+}
+
+/// An event sent by `valence_anvil` after an attempt to save the player data
+/// of a client is made.
+#[derive(Event, Debug)]
+pub struct PlayerDataSaveEvent {
+    /// The client entity. The entity may have been despawned by the time the
+    /// event is read.
+    pub client: Entity,
+    /// The UUID of the player that was saved.
+    pub uuid: Uuid,
+    pub status: ChunkSaveStatus,
+}
+
+fn load_player_data(
+    mut clients: Query<(Entity, &UniqueId, PlayerQuery), Added<Client>>,
+    folder: Res<PlayerDataFolder>,
+    mut commands: Commands,
+    mut events: EventWriter<PlayerDataLoadEvent>,
+) {
+    for (entity, uuid, mut player) in &mut clients {
+        let status = match folder.read(uuid.0) {
+            Ok(Some(nbt)) => {
+                let mut ender_chest = EnderChest::default();
+                player.apply_nbt(&nbt, &mut ender_chest);
+
+                commands
+                    .entity(entity)
+                    .insert((ender_chest, PlayerData { nbt, new: false }));
+
+                PlayerDataLoadStatus::Success
+            }
+            Ok(None) => {
+                commands.entity(entity).insert((
+                    EnderChest::default(),
+                    PlayerData {
+                        nbt: Compound::new(),
+                        new: true,
+                    },
+                ));
+
+                PlayerDataLoadStatus::Empty
+            }
+            Err(e) => PlayerDataLoadStatus::Failed(e.into()),
+        };
+
+        events.send(PlayerDataLoadEvent {
+            client: entity,
+            status,
+        });
+    }
+}
+
+/// Saves a player and sends the [`PlayerDataSaveEvent`].
+fn save_player(
+    folder: &PlayerDataFolder,
+    entity: Entity,
+    uuid: Uuid,
+    data: &mut PlayerData,
+    player: &PlayerQueryItem,
+    ender_chest: Option<&EnderChest>,
+    events: &mut EventWriter<PlayerDataSaveEvent>,
+) {
+    player.write_nbt(&mut data.nbt, ender_chest);
+
+    events.send(PlayerDataSaveEvent {
+        client: entity,
+        uuid,
+        status: match folder.write(uuid, &data.nbt) {
+            Ok(()) => ChunkSaveStatus::Success,
+            Err(e) => ChunkSaveStatus::Failed(e.into()),
+        },
+    });
+}
+
+/// Saves the players of clients that have disconnected. The [`PlayerData`] is
+/// removed afterwards so that each player is only saved once.
+fn save_disconnected_players(
+    mut clients: Query<
+        (
+            Entity,
+            &UniqueId,
+            &mut PlayerData,
+            PlayerQuery,
+            Option<&EnderChest>,
+        ),
+        (With<ClientMarker>, Without<Client>),
+    >,
+    folder: Res<PlayerDataFolder>,
+    mut commands: Commands,
+    mut events: EventWriter<PlayerDataSaveEvent>,
+) {
+    for (entity, uuid, mut data, player, ender_chest) in &mut clients {
+        save_player(
+            &folder,
+            entity,
+            uuid.0,
+            &mut data,
+            &player,
+            ender_chest,
+            &mut events,
+        );
+
+        commands.entity(entity).remove::<PlayerData>();
+    }
+}
+
+/// Periodically saves the players of connected clients.
+fn autosave_players(
+    mut clients: Query<(
+        Entity,
+        &UniqueId,
+        &mut PlayerData,
+        PlayerQuery,
+        Option<&EnderChest>,
+    )>,
+    mut folder: ResMut<PlayerDataFolder>,
+    mut events: EventWriter<PlayerDataSaveEvent>,
+) {
+    let Some(interval) = folder.autosave_interval else {
+        return;
+    };
+
+    if folder.last_autosave.elapsed() < interval {
+        return;
+    }
+
+    folder.last_autosave = Instant::now();
+
+    for (entity, uuid, mut data, player, ender_chest) in &mut clients {
+        save_player(
+            &folder,
+            entity,
+            uuid.0,
+            &mut data,
+            &player,
+            ender_chest,
+            &mut events,
+        );
+    }
+}
+
+/// Saves all players when the server shuts down or the app exits.
+fn save_all_players(
+    mut clients: Query<(
+        Entity,
+        &UniqueId,
+        &mut PlayerData,
+        PlayerQuery,
+        Option<&EnderChest>,
+    )>,
+    folder: Res<PlayerDataFolder>,
+    mut events: EventWriter<PlayerDataSaveEvent>,
+) {
+    for (entity, uuid, mut data, player, ender_chest) in &mut clients {
+        save_player(
+            &folder,
+            entity,
+            uuid.0,
+            &mut data,
+            &player,
+            ender_chest,
+            &mut events,
+        );
+    }
+}
+
+fn get_doubles<const N: usize>(nbt: &Compound, key: &str) -> Option<[f64; N]> {
+    match nbt.get(key) {
+        Some(Value::List(List::Double(values))) => values.as_slice().try_into().ok(),
+        _ => None,
+    }
+}
+
+fn get_floats<const N: usize>(nbt: &Compound, key: &str) -> Option<[f32; N]> {
+    match nbt.get(key) {
+        Some(Value::List(List::Float(values))) => values.as_slice().try_into().ok(),
+        _ => None,
+    }
+}
+
+fn game_mode_from_i32(game_mode: i32) -> Option<GameMode> {
+    match game_mode {
+        0 => Some(GameMode::Survival),
+        1 => Some(GameMode::Creative),
+        2 => Some(GameMode::Adventure),
+        3 => Some(GameMode::Spectator),
+        _ => None,
+    }
+}
+
+/// Vanilla slot numbers of the offhand and the feet, legs, chest and head
+/// armor slots in the player file.
+const NBT_SLOT_OFFHAND: i8 = -106;
+const NBT_SLOT_FEET: i8 = 100;
+const NBT_SLOT_HEAD: i8 = 103;
+
+/// Converts a slot number in the `Inventory` of a player file to a slot of
+/// the player [`Inventory`]. In the file, the hotbar is stored in slots 0-8
+/// followed by the rest of the main inventory.
+fn inventory_slot_from_nbt(slot: i8) -> Option<u16> {
+    match slot {
+        0..=8 => Some(PlayerInventory::hotbar_to_slot(slot as u8)),
+        9..=35 => Some(slot as u16),
+        NBT_SLOT_FEET..=NBT_SLOT_HEAD => {
+            Some(PlayerInventory::SLOT_FEET - (slot - NBT_SLOT_FEET) as u16)
+        }
+        NBT_SLOT_OFFHAND => Some(PlayerInventory::SLOT_OFFHAND),
+        _ => None,
+    }
+}
+
+fn status_effect_from_nbt(nbt: &Compound) -> Option<ActiveStatusEffect> {
+    let id = nbt.get("Id").and_then(Value::as_i32)?;
+    let effect = StatusEffect::from_raw(u16::try_from(id).ok()?)?;
+
+    let flag = |key, default| nbt.get(key).and_then(Value::as_bool).unwrap_or(default);
+
+    let mut active = ActiveStatusEffect::from_effect(effect)
+        .with_amplifier(nbt.get("Amplifier").and_then(Value::as_i8).unwrap_or(0) as u8)
+        .with_ambient(flag("Ambient", false))
+        .with_show_particles(flag("ShowParticles", true))
+        .with_show_icon(flag("ShowIcon", true));
+
+    active = match nbt.get("Duration").and_then(Value::as_i32) {
+        Some(-1) => active.with_infinite(),
+        Some(duration) => active.with_duration(duration),
+        None => active.with_duration(0),
+    };
+
+    Some(active)
+}
+
+fn status_effect_to_nbt(effect: &ActiveStatusEffect) -> Compound {
+    let mut nbt = Compound::new();
+
+    nbt.insert("Id", effect.status_effect().to_raw() as i8);
+    nbt.insert("Amplifier", effect.amplifier() as i8);
+    nbt.insert("Duration", effect.remaining_duration().unwrap_or(-1));
+    nbt.insert("Ambient", effect.ambient());
+    nbt.insert("ShowParticles", effect.show_particles());
+    nbt.insert("ShowIcon", effect.show_icon());
+
+    nbt
+}
+
+#[cfg(test)]
+mod tests {
+    use valence_server::nbt::compound;
+    use valence_server::{ItemKind, ItemStack};
+
+    use super::*;
+
+    #[test]
+    fn inventory_slots() {
+        assert_eq!(inventory_slot_from_nbt(0), Some(36));
+        assert_eq!(inventory_slot_from_nbt(9), Some(9));
+        assert_eq!(
+            inventory_slot_from_nbt(100),
+            Some(PlayerInventory::SLOT_FEET)
+        );
+        assert_eq!(
+            inventory_slot_from_nbt(103),
+            Some(PlayerInventory::SLOT_HEAD)
+        );
+        assert_eq!(
+            inventory_slot_from_nbt(-106),
+            Some(PlayerInventory::SLOT_OFFHAND)
+        );
+        assert_eq!(inventory_slot_from_nbt(36), None);
+    }
+
+    #[test]
+    fn player_nbt_round_trip() {
+        let nbt = compound! {
+            "Pos" => List::Double(vec![1.5, 64.0, -3.5]),
+            "Rotation" => List::Float(vec![90.0, 10.0]),
+            "playerGameType" => 1,
+            "Health" => 12.5_f32,
+            "foodLevel" => 15,
+            "foodSaturationLevel" => 2.0_f32,
+            "XpLevel" => 7,
+            "XpP" => 0.25_f32,
+            "XpTotal" => 100,
+            "SelectedItemSlot" => 4,
+            "Inventory" => List::Compound(vec![
+                compound! { "Slot" => 4_i8, "id" => "minecraft:diamond", "Count" => 3_i8 },
+                compound! { "Slot" => 103_i8, "id" => "minecraft:iron_helmet", "Count" => 1_i8 },
+            ]),
+            "EnderItems" => List::Compound(vec![
+                compound! { "Slot" => 26_i8, "id" => "minecraft:stone", "Count" => 64_i8 },
+            ]),
+            "ActiveEffects" => List::Compound(vec![
+                compound! {
+                    "Id" => 1_i8,
+                    "Amplifier" => 2_i8,
+                    "Duration" => -1,
+                    "Ambient" => false,
+                    "ShowParticles" => true,
+                    "ShowIcon" => true,
+                },
+            ]),
+            "recipeBook" => Compound::new(),
+        };
+
+        let mut world = World::new();
+        let entity = world
+            .spawn((
+                Position::default(),
+                Look::default(),
+                GameMode::default(),
+                Health(20.0),
+                Food::default(),
+                Saturation::default(),
+                Experience::default(),
+                HeldItem::default(),
+                Inventory::new(InventoryKind::Player),
+                ActiveStatusEffects::default(),
+            ))
+            .id();
+
+        let mut ender_chest = EnderChest::default();
+
+        let mut query = world.query::<PlayerQuery>();
+        query
+            .get_mut(&mut world, entity)
+            .unwrap()
+            .apply_nbt(&nbt, &mut ender_chest);
+
+        world
+            .get_mut::<ActiveStatusEffects>(entity)
+            .unwrap()
+            .apply_changes();
+
+        let player = query.get_mut(&mut world, entity).unwrap();
+
+        assert_eq!(player.position.0.to_array(), [1.5, 64.0, -3.5]);
+        assert_eq!(*player.game_mode, GameMode::Creative);
+        assert_eq!(player.health.0, 12.5);
+        assert_eq!(player.experience.level, 7);
+        assert_eq!(player.held_item.slot(), 40);
+        assert_eq!(
+            player.inventory.slot(40),
+            &ItemStack::new(ItemKind::Diamond, 3, None)
+        );
+        assert_eq!(
+            player.inventory.slot(PlayerInventory::SLOT_HEAD).item,
+            ItemKind::IronHelmet
+        );
+        assert_eq!(ender_chest.0.slot(26).count, 64);
+        assert!(player.status_effects.has_effect(StatusEffect::Speed));
+
+        let mut saved = nbt.clone();
+        player.write_nbt(&mut saved, Some(&ender_chest));
+
+        assert_eq!(saved, nbt);
+    }
+
+    #[test]
+    fn players_saved_on_exit_without_shutdown_plugin() {
+        let dir = tempfile::tempdir().unwrap();
+        let uuid = Uuid::from_u128(1);
+
+        let mut app = App::new();
+        app.add_plugins(PlayerDataPlugin)
+            .insert_resource(PlayerDataFolder::new(dir.path()));
+
+        app.world_mut().spawn((
+            UniqueId(uuid),
+            PlayerData::default(),
+            Position::default(),
+            Look::default(),
+            GameMode::default(),
+            Health(20.0),
+            Food::default(),
+            Saturation::default(),
+            Experience::default(),
+            HeldItem::default(),
+            Inventory::new(InventoryKind::Player),
+            ActiveStatusEffects::default(),
+        ));
+
+        app.update();
+
+        let folder = app.world().resource::<PlayerDataFolder>();
+        assert_eq!(folder.read(uuid).unwrap(), None);
+
+        app.world_mut().send_event(AppExit::Success);
+        app.update();
+
+        let folder = app.world().resource::<PlayerDataFolder>();
+        assert!(folder.read(uuid).unwrap().is_some());
+    }
+}
@@ -299,6 +299,8 @@ fn build_entities() -> anyhow::Result<TokenStream> {
     let mut entity_kind_consts = TokenStream::new();
     let mut entity_kind_fmt_args = TokenStream::new();
     let mut translation_key_arms = TokenStream::new();
+    let mut entity_kind_from_ident_arms = TokenStream::new();
+    let mut entity_kind_to_ident_arms = TokenStream::new();
     let mut modules = TokenStream::new();
     let mut systems = TokenStream::new();
     let mut system_names = vec![];
@@ -347,6 +349,16 @@ fn build_entities() -> anyhow::Result<TokenStream> {
                 EntityKind::#stripped_shouty_entity_name_ident => #translation_key_expr,
             }]);
 
+            let entity_type_ident = format!("minecraft:{entity_type}");
+
+            entity_kind_from_ident_arms.extend([quote! {
+                #entity_type_ident => Some(EntityKind::#stripped_shouty_entity_name_ident),
+            }]);
+
+            entity_kind_to_ident_arms.extend([quote! {
+                EntityKind::#stripped_shouty_entity_name_ident => Some(valence_protocol::ident!(#entity_type_ident)),
+            }]);
+
             // Create bundle type.
             let mut bundle_fields = TokenStream::new();
             let mut bundle_init_fields = TokenStream::new();
@@ -698,6 +710,22 @@ fn build_entities() -> anyhow::Result<TokenStream> {
                     _ => None,
                 }
             }
+
+            #[doc = "Gets the entity kind from its resource identifier, such as `minecraft:armor_stand`."]
+            pub fn from_ident(ident: valence_protocol::Ident<&str>) -> Option<Self> {
+                match ident.as_str() {
+                    #entity_kind_from_ident_arms
+                    _ => None,
+                }
+            }
+
+            #[doc = "Gets the resource identifier of this entity kind, such as `minecraft:armor_stand`."]
+            pub fn ident(self) -> Option<valence_protocol::Ident<&'static str>> {
+                match self {
+                    #entity_kind_to_ident_arms
+                    _ => None,
+                }
+            }
         }
 
         impl std::fmt::Debug for EntityKind {
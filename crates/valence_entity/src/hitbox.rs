@@ -56,7 +56,8 @@ impl Plugin for HitboxPlugin {
                     update_slime_hitbox,
                     update_painting_hitbox,
                     update_shulker_hitbox,
-                ),
+                )
+                    .in_set(HitboxShapeUpdateSet),
             )
             .configure_sets(PostUpdate, HitboxComponentsAddSet)
             .add_systems(
@@ -17,6 +17,11 @@ struct TopLevel {
 struct Block {
     id: u16,
     item_id: u16,
+    hardness: f32,
+    requires_tool: bool,
+    tool: Option<String>,
+    tool_tier: u8,
+    sword_efficient: bool,
     wall_variant_id: Option<u16>,
     translation_key: String,
     name: String,
@@ -400,6 +405,68 @@ pub(crate) fn build() -> anyhow::Result<TokenStream> {
         })
         .collect::<TokenStream>();
 
+    let block_kind_to_hardness_arms = blocks
+        .iter()
+        .map(|block| {
+            let name = ident(block.name.to_pascal_case());
+            let hardness = block.hardness;
+
+            quote! {
+                Self::#name => #hardness,
+            }
+        })
+        .collect::<TokenStream>();
+
+    let block_kind_requires_tool_arms = blocks
+        .iter()
+        .filter(|block| block.requires_tool)
+        .map(|block| {
+            let name = ident(block.name.to_pascal_case());
+
+            quote! {
+                Self::#name => true,
+            }
+        })
+        .collect::<TokenStream>();
+
+    let block_kind_to_tool_arms = blocks
+        .iter()
+        .filter_map(|block| block.tool.as_ref().map(|tool| (block, tool)))
+        .map(|(block, tool)| {
+            let name = ident(block.name.to_pascal_case());
+            let tool = crate::item::tool_kind(tool)?;
+
+            Ok(quote! {
+                Self::#name => Some(ToolKind::#tool),
+            })
+        })
+        .collect::<anyhow::Result<TokenStream>>()?;
+
+    let block_kind_to_tool_tier_arms = blocks
+        .iter()
+        .filter(|block| block.tool_tier != 0)
+        .map(|block| {
+            let name = ident(block.name.to_pascal_case());
+            let tier = block.tool_tier;
+
+            quote! {
+                Self::#name => #tier,
+            }
+        })
+        .collect::<TokenStream>();
+
+    let block_kind_sword_efficient_arms = blocks
+        .iter()
+        .filter(|block| block.sword_efficient)
+        .map(|block| {
+            let name = ident(block.name.to_pascal_case());
+
+            quote! {
+                Self::#name => true,
+            }
+        })
+        .collect::<TokenStream>();
+
     let block_kind_from_item_kind_arms = blocks
         .iter()
         .filter(|block| block.item_id != 0)
@@ -771,6 +838,51 @@ pub(crate) fn build() -> anyhow::Result<TokenStream> {
                 }
             }
 
+            #[doc = "Returns how long the block kind takes to break. Unbreakable blocks such as"]
+            #[doc = "bedrock have a hardness of `-1.0`."]
+            pub const fn hardness(self) -> f32 {
+                match self {
+                    #block_kind_to_hardness_arms
+                }
+            }
+
+            #[doc = "Returns if the block kind only drops items when broken with the right tool."]
+            #[doc = ""]
+            #[doc = "See [`BlockKind::tool`] and [`BlockKind::tool_tier`]."]
+            pub const fn requires_tool(self) -> bool {
+                match self {
+                    #block_kind_requires_tool_arms
+                    _ => false,
+                }
+            }
+
+            #[doc = "Returns the kind of tool that breaks this block kind faster."]
+            #[doc = ""]
+            #[doc = "If no tool breaks this block kind faster, `None` is returned."]
+            pub const fn tool(self) -> Option<ToolKind> {
+                match self {
+                    #block_kind_to_tool_arms
+                    _ => None,
+                }
+            }
+
+            #[doc = "Returns the minimum [tier](crate::item::Tool::tier) of tool that breaks this"]
+            #[doc = "block kind faster and lets it drop items."]
+            pub const fn tool_tier(self) -> u8 {
+                match self {
+                    #block_kind_to_tool_tier_arms
+                    _ => 0,
+                }
+            }
+
+            #[doc = "Returns if swords break this block kind faster."]
+            pub const fn sword_efficient(self) -> bool {
+                match self {
+                    #block_kind_sword_efficient_arms
+                    _ => false,
+                }
+            }
+
             #[doc = "Converts a block kind to its corresponding item kind."]
             #[doc = ""]
             #[doc = "[`ItemKind::Air`] is used to indicate the absence of an item."]
@@ -15,6 +15,7 @@ struct Item {
     enchantability: u8,
     fireproof: bool,
     food: Option<FoodComponent>,
+    tool: Option<Tool>,
 }
 
 #[derive(Deserialize, Clone, Debug)]
@@ -27,6 +28,24 @@ struct FoodComponent {
     // TODO: effects
 }
 
+#[derive(Deserialize, Clone, Debug)]
+struct Tool {
+    kind: String,
+    tier: u8,
+    speed: f32,
+}
+
+/// Returns the name of the `ToolKind` variant for a tool kind in the
+/// extracted data.
+pub(crate) fn tool_kind(kind: &str) -> anyhow::Result<proc_macro2::Ident> {
+    match kind {
+        "pickaxe" | "axe" | "shovel" | "hoe" | "sword" | "shears" => {
+            Ok(ident(kind.to_pascal_case()))
+        }
+        _ => anyhow::bail!("unknown tool kind \"{kind}\""),
+    }
+}
+
 pub(crate) fn build() -> anyhow::Result<TokenStream> {
     rerun_if_changed(["extracted/items.json"]);
 
@@ -172,6 +191,25 @@ pub(crate) fn build() -> anyhow::Result<TokenStream> {
         })
         .collect::<TokenStream>();
 
+    let item_kind_to_tool_arms = items
+        .iter()
+        .filter_map(|item| item.tool.as_ref().map(|tool| (item, tool)))
+        .map(|(item, tool)| {
+            let name = ident(item.name.to_pascal_case());
+            let kind = tool_kind(&tool.kind)?;
+            let tier = tool.tier;
+            let speed = tool.speed;
+
+            Ok(quote! {
+                Self::#name => Some(Tool {
+                    kind: ToolKind::#kind,
+                    tier: #tier,
+                    speed: #speed,
+                }),
+            })
+        })
+        .collect::<anyhow::Result<TokenStream>>()?;
+
     Ok(quote! {
         #[doc = "Represents an item from the game"]
         #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
@@ -193,6 +231,30 @@ pub(crate) fn build() -> anyhow::Result<TokenStream> {
             pub snack: bool,
         }
 
+        #[doc = "The kind of a tool."]
+        #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+        pub enum ToolKind {
+            Pickaxe,
+            Axe,
+            Shovel,
+            Hoe,
+            Sword,
+            Shears,
+        }
+
+        #[doc = "Contains information about how an item breaks blocks."]
+        #[doc = ""]
+        #[doc = "Only tools have a tool component."]
+        #[derive(Clone, Copy, PartialEq, Debug)]
+        pub struct Tool {
+            pub kind: ToolKind,
+            #[doc = "The tier of the tool's material, from `0` for wood and gold to `4` for"]
+            #[doc = "netherite."]
+            pub tier: u8,
+            #[doc = "How many times faster than a hand the tool breaks blocks it is made for."]
+            pub speed: f32,
+        }
+
         impl ItemKind {
             #[doc = "Constructs a item kind from a raw item ID."]
             #[doc = ""]
@@ -282,6 +344,16 @@ pub(crate) fn build() -> anyhow::Result<TokenStream> {
                 }
             }
 
+            #[doc = "Returns the tool component of the item kind."]
+            #[doc = ""]
+            #[doc = "If the item kind isn't a tool, `None` is returned."]
+            pub const fn tool(self) -> Option<Tool> {
+                match self {
+                    #item_kind_to_tool_arms
+                    _ => None
+                }
+            }
+
             /*
             #[doc = "Constructs an item kind from a block kind."]
             #[doc = ""]
@@ -7,6 +7,7 @@ mod item;
 mod packet_id;
 mod sound;
 mod status_effects;
+mod translation;
 
 pub fn main() -> anyhow::Result<()> {
     write_generated_file(attributes::build()?, "attributes.rs")?;
@@ -16,6 +17,7 @@ pub fn main() -> anyhow::Result<()> {
     write_generated_file(packet_id::build()?, "packet_id.rs")?;
     write_generated_file(chunk_view::build(), "chunk_view.rs")?;
     write_generated_file(status_effects::build()?, "status_effects.rs")?;
+    write_generated_file(translation::build()?, "translation.rs")?;
 
     Ok(())
 }
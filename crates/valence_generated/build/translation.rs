@@ -0,0 +1,334 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Context};
+use proc_macro2::{Literal, TokenStream};
+use quote::quote;
+use serde::Deserialize;
+use valence_build_utils::{ident, rerun_if_changed};
+
+/// The registries of the Minecraft versions clients can be translated to.
+/// Every file lists the registries by name in ID order, along with the
+/// entries to send in place of native entries the version does not have.
+const VERSIONS: &[(&str, &str)] = &[(
+    "extracted/versions/1.19.4.json",
+    include_str!("../extracted/versions/1.19.4.json"),
+)];
+
+#[derive(Deserialize)]
+struct NativeBlocks {
+    block_entity_types: Vec<Named>,
+    blocks: Vec<NativeBlock>,
+}
+
+#[derive(Deserialize)]
+struct NativeBlock {
+    name: String,
+    properties: Vec<Property>,
+    states: Vec<NativeState>,
+}
+
+#[derive(Deserialize)]
+struct NativeState {
+    id: u16,
+}
+
+#[derive(Deserialize)]
+struct Named {
+    id: u16,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct VersionData {
+    protocol_version: i32,
+    minecraft_version: String,
+    blocks: Vec<VersionBlock>,
+    block_entity_types: Vec<String>,
+    items: Vec<String>,
+    sounds: Vec<String>,
+    particle_types: Vec<String>,
+    fallbacks: Fallbacks,
+}
+
+/// Blocks of other versions, whose states are numbered consecutively in the
+/// order of the blocks. Like for native blocks, the last property varies the
+/// fastest.
+#[derive(Deserialize)]
+struct VersionBlock {
+    name: String,
+    default_state_id: u16,
+    properties: Vec<Property>,
+}
+
+#[derive(Deserialize)]
+struct Property {
+    name: String,
+    values: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Fallbacks {
+    blocks: HashMap<String, String>,
+    block_entity_types: HashMap<String, String>,
+    items: HashMap<String, String>,
+    sounds: HashMap<String, String>,
+    particle_types: HashMap<String, String>,
+}
+
+pub(crate) fn build() -> anyhow::Result<TokenStream> {
+    rerun_if_changed([
+        "extracted/blocks.json",
+        "extracted/items.json",
+        "extracted/sounds.json",
+        "extracted/particles.json",
+        "extracted/versions",
+    ]);
+
+    let native_blocks: NativeBlocks =
+        serde_json::from_str(include_str!("../extracted/blocks.json"))?;
+    let native_items: Vec<Named> = serde_json::from_str(include_str!("../extracted/items.json"))?;
+    let native_sounds: Vec<Named> = serde_json::from_str(include_str!("../extracted/sounds.json"))?;
+    let native_particles: Vec<Named> =
+        serde_json::from_str(include_str!("../extracted/particles.json"))?;
+
+    let native_block_names = native_blocks
+        .blocks
+        .iter()
+        .map(|b| b.name.clone())
+        .collect::<Vec<_>>();
+
+    let mut modules = TokenStream::new();
+
+    for (path, json) in VERSIONS {
+        let version: VersionData =
+            serde_json::from_str(json).with_context(|| format!("parsing `{path}`"))?;
+
+        let block_names = version
+            .blocks
+            .iter()
+            .map(|b| b.name.clone())
+            .collect::<Vec<_>>();
+
+        let blocks = map_registry(&native_block_names, &block_names, &version.fallbacks.blocks)
+            .context("mapping blocks")?;
+        let blocks_to_native = invert_registry(&native_block_names, &block_names, &blocks)
+            .context("mapping blocks")?;
+        let block_states =
+            map_block_states(&native_blocks.blocks, &version).context("mapping block states")?;
+        let block_entity_kinds = map_registry(
+            &names(&native_blocks.block_entity_types),
+            &version.block_entity_types,
+            &version.fallbacks.block_entity_types,
+        )
+        .context("mapping block entity types")?;
+        let native_item_names = names(&native_items);
+        let items = map_registry(&native_item_names, &version.items, &version.fallbacks.items)
+            .context("mapping items")?;
+        let items_to_native =
+            invert_registry(&native_item_names, &version.items, &items).context("mapping items")?;
+        let sounds = map_registry(
+            &names(&native_sounds),
+            &version.sounds,
+            &version.fallbacks.sounds,
+        )
+        .context("mapping sounds")?;
+        let particles = map_registry(
+            &names(&native_particles),
+            &version.particle_types,
+            &version.fallbacks.particle_types,
+        )
+        .context("mapping particle types")?;
+
+        let module_name = ident(format!("v{}", version.minecraft_version.replace('.', "_")));
+        let module_doc = format!(
+            "ID tables for clients on Minecraft {}.",
+            version.minecraft_version
+        );
+        let protocol_version = Literal::i32_unsuffixed(version.protocol_version);
+        let minecraft_version = &version.minecraft_version;
+
+        let block_states = table(&block_states);
+        let blocks = table(&blocks);
+        let blocks_to_native = table(&blocks_to_native);
+        let block_entity_kinds = table(&block_entity_kinds);
+        let items = table(&items);
+        let items_to_native = table(&items_to_native);
+        let sounds = table(&sounds);
+        let particles = table(&particles);
+
+        modules.extend(quote! {
+            #[doc = #module_doc]
+            #[doc = ""]
+            #[doc = "The tables are indexed by native ID and contain the client's ID, \
+                     unless noted otherwise. Native entries missing in the client's \
+                     version are mapped to a similar entry."]
+            pub mod #module_name {
+                pub const PROTOCOL_VERSION: i32 = #protocol_version;
+
+                pub const MINECRAFT_VERSION: &str = #minecraft_version;
+
+                pub const BLOCK_STATES: &[u16] = #block_states;
+
+                pub const BLOCKS: &[u16] = #blocks;
+
+                #[doc = "Native block IDs indexed by the client's block ID."]
+                pub const BLOCKS_TO_NATIVE: &[u16] = #blocks_to_native;
+
+                pub const BLOCK_ENTITY_KINDS: &[u16] = #block_entity_kinds;
+
+                pub const ITEMS: &[u16] = #items;
+
+                #[doc = "Native item IDs indexed by the client's item ID."]
+                pub const ITEMS_TO_NATIVE: &[u16] = #items_to_native;
+
+                pub const SOUNDS: &[u16] = #sounds;
+
+                pub const PARTICLES: &[u16] = #particles;
+            }
+        });
+    }
+
+    Ok(modules)
+}
+
+fn names(entries: &[Named]) -> Vec<String> {
+    let mut entries = entries.iter().collect::<Vec<_>>();
+    entries.sort_by_key(|e| e.id);
+    entries.into_iter().map(|e| e.name.clone()).collect()
+}
+
+fn table(ids: &[u16]) -> TokenStream {
+    let ids = ids.iter().map(|&id| Literal::u16_unsuffixed(id));
+    quote!(&[#(#ids,)*])
+}
+
+/// Maps every native entry to the client entry with the same name, or the
+/// fallback for it.
+fn map_registry(
+    native: &[String],
+    client: &[String],
+    fallbacks: &HashMap<String, String>,
+) -> anyhow::Result<Vec<u16>> {
+    let client_ids = client
+        .iter()
+        .enumerate()
+        .map(|(id, name)| (name.as_str(), id as u16))
+        .collect::<HashMap<_, _>>();
+
+    native
+        .iter()
+        .map(|name| {
+            let target = fallbacks.get(name).unwrap_or(name);
+            client_ids
+                .get(target.as_str())
+                .copied()
+                .with_context(|| format!("`{name}` has no equivalent or fallback"))
+        })
+        .collect()
+}
+
+/// Maps every client entry back to the native entry with the same name. Client
+/// entries that were renamed are mapped to the first native entry using them
+/// as fallback.
+fn invert_registry(
+    native: &[String],
+    client: &[String],
+    to_client: &[u16],
+) -> anyhow::Result<Vec<u16>> {
+    let native_ids = native
+        .iter()
+        .enumerate()
+        .map(|(id, name)| (name.as_str(), id as u16))
+        .collect::<HashMap<_, _>>();
+
+    client
+        .iter()
+        .enumerate()
+        .map(|(client_id, name)| {
+            native_ids
+                .get(name.as_str())
+                .copied()
+                .or_else(|| {
+                    to_client
+                        .iter()
+                        .position(|&id| usize::from(id) == client_id)
+                        .map(|id| id as u16)
+                })
+                .with_context(|| format!("`{name}` has no native equivalent"))
+        })
+        .collect()
+}
+
+/// Maps every native block state to the state of the equivalent client block,
+/// or its fallback, with the same property values. Properties the native state
+/// does not have take the value of the client block's default state.
+fn map_block_states(native: &[NativeBlock], version: &VersionData) -> anyhow::Result<Vec<u16>> {
+    let mut client_blocks = HashMap::new();
+    let mut first_state_id = 0_u16;
+
+    for block in &version.blocks {
+        client_blocks.insert(block.name.as_str(), (block, first_state_id));
+        first_state_id += state_count(&block.properties);
+    }
+
+    let mut states = vec![];
+
+    for block in native {
+        let target = version
+            .fallbacks
+            .blocks
+            .get(&block.name)
+            .unwrap_or(&block.name);
+        let Some(&(client, client_first_id)) = client_blocks.get(target.as_str()) else {
+            bail!("`{}` has no equivalent or fallback", block.name);
+        };
+
+        let client_default = state_values(
+            &client.properties,
+            client.default_state_id - client_first_id,
+        );
+
+        let first_id = block.states.iter().map(|s| s.id).min().unwrap_or(0);
+
+        for state in &block.states {
+            let values = state_values(&block.properties, state.id - first_id);
+
+            let mut offset = 0;
+            for (prop, default) in client.properties.iter().zip(&client_default) {
+                let value = block
+                    .properties
+                    .iter()
+                    .position(|p| p.name == prop.name)
+                    .and_then(|i| {
+                        let value = &block.properties[i].values[values[i]];
+                        prop.values.iter().position(|v| v == value)
+                    })
+                    .unwrap_or(*default);
+
+                offset = offset * prop.values.len() as u16 + value as u16;
+            }
+
+            states.push(client_first_id + offset);
+        }
+    }
+
+    Ok(states)
+}
+
+fn state_count(properties: &[Property]) -> u16 {
+    properties.iter().map(|p| p.values.len() as u16).product()
+}
+
+/// Returns the indices of the property values of the state at `offset` from the
+/// first state of a block.
+fn state_values(properties: &[Property], mut offset: u16) -> Vec<usize> {
+    let mut values = vec![0; properties.len()];
+
+    for (prop, value) in properties.iter().zip(&mut values).rev() {
+        let len = prop.values.len() as u16;
+        *value = usize::from(offset % len);
+        offset /= len;
+    }
+
+    values
+}
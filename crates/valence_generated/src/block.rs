@@ -6,7 +6,7 @@ use std::iter::FusedIterator;
 
 use valence_ident::{ident, Ident};
 
-use crate::item::ItemKind;
+use crate::item::{ItemKind, ToolKind};
 
 include!(concat!(env!("OUT_DIR"), "/block.rs"));
 
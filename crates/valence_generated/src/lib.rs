@@ -26,3 +26,9 @@ pub mod chunk_view {
 pub mod status_effects {
     include!(concat!(env!("OUT_DIR"), "/status_effects.rs"));
 }
+
+/// Contains tables mapping native registry IDs to the IDs of other Minecraft
+/// versions.
+pub mod translation {
+    include!(concat!(env!("OUT_DIR"), "/translation.rs"));
+}
@@ -0,0 +1,565 @@
+//! Validating how long clients take to break blocks.
+//!
+//! [`BlockBreakingPlugin`] is not part of the default plugins. Once added, it
+//! keeps track of the block every client is breaking and how far along it is,
+//! using the same calculation as vanilla. The progress is shown to other
+//! players with [`BlockBreakingProgressS2c`] packets.
+//!
+//! When a client finishes breaking a block, a [`BreakBlockEvent`] is sent. If
+//! the client finished too fast, a [`BreakBlockRejectedEvent`] is sent instead
+//! and the block is sent to the client again. Game code should break blocks in
+//! response to [`BreakBlockEvent`]s instead of
+//! [`DiggingEvent`](valence_server::action::DiggingEvent)s when this plugin is
+//! added.
+//!
+//! See [`break_progress`] for the calculation.
+
+use bevy_app::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_ecs::query::QueryData;
+use valence_server::action::{DiggingEvent, DiggingState};
+use valence_server::block::{BlockKind, PropName, PropValue};
+use valence_server::client::{Client, SpawnClientsSet, VisibleChunkLayer};
+use valence_server::entity::active_status_effects::ActiveStatusEffects;
+use valence_server::entity::{entity, EntityId, EntityLayerId, OnGround, Pose, Position};
+use valence_server::event_loop::EventLoopUpdate;
+use valence_server::item::ToolKind;
+use valence_server::math::DVec3;
+use valence_server::nbt::{List, Value};
+use valence_server::protocol::packets::play::{BlockBreakingProgressS2c, BlockUpdateS2c};
+use valence_server::protocol::status_effects::StatusEffect;
+use valence_server::protocol::{VarInt, WritePacket};
+use valence_server::{
+    BlockPos, BlockState, ChunkLayer, EntityLayer, GameMode, ItemKind, ItemStack, Layer,
+};
+
+use crate::player_inventory::PlayerInventory;
+use crate::{HeldItem, Inventory};
+
+pub struct BlockBreakingPlugin;
+
+impl Plugin for BlockBreakingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BlockBreakingSettings>()
+            .add_event::<BreakBlockEvent>()
+            .add_event::<BreakBlockRejectedEvent>()
+            .add_systems(
+                PreUpdate,
+                (init_block_breaking_states, tick_block_breaking)
+                    .chain()
+                    .after(SpawnClientsSet),
+            )
+            .add_systems(EventLoopUpdate, handle_digging);
+    }
+}
+
+/// Global settings for block breaking.
+#[derive(Resource, Clone, PartialEq, Debug)]
+pub struct BlockBreakingSettings {
+    /// The fraction of a block that must have been broken when a client
+    /// finishes breaking it. Like in vanilla, this allows for some latency.
+    pub min_progress: f32,
+}
+
+impl Default for BlockBreakingSettings {
+    fn default() -> Self {
+        Self { min_progress: 0.7 }
+    }
+}
+
+/// Sent when a client breaks a block, either instantly or after breaking it
+/// for long enough.
+#[derive(Event, Copy, Clone, PartialEq, Debug)]
+pub struct BreakBlockEvent {
+    pub client: Entity,
+    pub position: BlockPos,
+    /// The block that was broken.
+    pub block: BlockState,
+}
+
+/// Sent when a client finishes breaking a block faster than possible.
+#[derive(Event, Copy, Clone, PartialEq, Debug)]
+pub struct BreakBlockRejectedEvent {
+    pub client: Entity,
+    pub position: BlockPos,
+    /// The fraction of the block the client had broken.
+    pub progress: f32,
+}
+
+/// [`Component`] with the block a client is breaking. It is added to every
+/// client.
+#[derive(Component, Clone, PartialEq, Default, Debug)]
+pub struct BlockBreakingState {
+    target: Option<BlockPos>,
+    progress: f32,
+    /// The destroy stage last shown to other players.
+    stage: Option<u8>,
+}
+
+impl BlockBreakingState {
+    /// Returns the position of the block the client is breaking.
+    pub fn target(&self) -> Option<BlockPos> {
+        self.target
+    }
+
+    /// Returns the fraction of the target block the client has broken.
+    pub fn progress(&self) -> f32 {
+        self.progress
+    }
+}
+
+/// The conditions a block is broken in, apart from the tool.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct BreakingConditions {
+    /// The amplifier of the haste or conduit power effect.
+    pub haste: Option<u8>,
+    /// The amplifier of the mining fatigue effect.
+    pub mining_fatigue: Option<u8>,
+    pub on_ground: bool,
+    /// Whether the eyes of the player are in water.
+    pub underwater: bool,
+    /// Whether the helmet of the player has the aqua affinity enchantment.
+    pub aqua_affinity: bool,
+}
+
+impl Default for BreakingConditions {
+    fn default() -> Self {
+        Self {
+            haste: None,
+            mining_fatigue: None,
+            on_ground: true,
+            underwater: false,
+            aqua_affinity: false,
+        }
+    }
+}
+
+/// Returns the fraction of `block` that is broken every tick when it is broken
+/// with `tool`. Blocks are broken instantly if this is at least `1.0`, and
+/// cannot be broken if this is `0.0`.
+///
+/// ```
+/// # use valence_inventory::block_breaking::*;
+/// # use valence_server::{BlockState, ItemKind, ItemStack};
+/// let conditions = BreakingConditions::default();
+/// let pickaxe = ItemStack::new(ItemKind::IronPickaxe, 1, None);
+///
+/// // An iron pickaxe breaks 2/15 of a stone block every tick.
+/// let progress = break_progress(BlockState::STONE, &pickaxe, conditions);
+/// assert!((progress - 2.0 / 15.0).abs() < 1e-6);
+///
+/// // Torches break instantly, even by hand.
+/// assert!(break_progress(BlockState::TORCH, &ItemStack::EMPTY, conditions) >= 1.0);
+///
+/// // Bedrock cannot be broken.
+/// assert_eq!(break_progress(BlockState::BEDROCK, &pickaxe, conditions), 0.0);
+/// ```
+pub fn break_progress(block: BlockState, tool: &ItemStack, conditions: BreakingConditions) -> f32 {
+    let kind = block.to_kind();
+    let hardness = kind.hardness();
+
+    if hardness < 0.0 {
+        return 0.0;
+    }
+
+    if hardness == 0.0 {
+        return 1.0;
+    }
+
+    let divisor = if can_harvest(kind, tool.item) {
+        30.0
+    } else {
+        100.0
+    };
+
+    mining_speed(kind, tool, conditions) / hardness / divisor
+}
+
+/// Returns the number of ticks it takes to break `block` with `tool`, or
+/// `None` if it cannot be broken. Blocks that break instantly take `0` ticks.
+///
+/// See [`break_progress`].
+/// ```
+/// # use valence_inventory::block_breaking::*;
+/// # use valence_server::{BlockState, ItemKind, ItemStack};
+/// let conditions = BreakingConditions::default();
+/// let pickaxe = ItemStack::new(ItemKind::IronPickaxe, 1, None);
+///
+/// // Stone breaks in 8 ticks with an iron pickaxe, and in 150 ticks by hand.
+/// assert_eq!(break_ticks(BlockState::STONE, &pickaxe, conditions), Some(8));
+/// assert_eq!(
+///     break_ticks(BlockState::STONE, &ItemStack::EMPTY, conditions),
+///     Some(150)
+/// );
+/// assert_eq!(
+///     break_ticks(BlockState::TORCH, &ItemStack::EMPTY, conditions),
+///     Some(0)
+/// );
+/// assert_eq!(break_ticks(BlockState::BEDROCK, &pickaxe, conditions), None);
+/// ```
+pub fn break_ticks(
+    block: BlockState,
+    tool: &ItemStack,
+    conditions: BreakingConditions,
+) -> Option<u32> {
+    let progress = break_progress(block, tool, conditions);
+
+    if progress <= 0.0 {
+        None
+    } else if progress >= 1.0 {
+        Some(0)
+    } else {
+        let ticks = (1.0 / progress).ceil();
+
+        // Correct for rounding errors in the division.
+        if (ticks - 1.0) * progress >= 1.0 {
+            Some(ticks as u32 - 1)
+        } else {
+            Some(ticks as u32)
+        }
+    }
+}
+
+/// Returns whether `block` drops items when it is broken with `tool`.
+pub fn can_harvest(block: BlockKind, tool: ItemKind) -> bool {
+    if !block.requires_tool() {
+        return true;
+    }
+
+    let Some(tool) = tool.tool() else {
+        return false;
+    };
+
+    match tool.kind {
+        ToolKind::Sword => block == BlockKind::Cobweb,
+        ToolKind::Shears => matches!(
+            block,
+            BlockKind::Cobweb | BlockKind::RedstoneWire | BlockKind::Tripwire
+        ),
+        kind => block.tool() == Some(kind) && tool.tier >= block.tool_tier(),
+    }
+}
+
+fn mining_speed(block: BlockKind, tool: &ItemStack, conditions: BreakingConditions) -> f32 {
+    let mut speed = tool_speed(block, tool.item);
+
+    if speed > 1.0 {
+        let efficiency = enchantment_level(tool, "minecraft:efficiency");
+
+        if efficiency > 0 {
+            speed += (efficiency * efficiency + 1) as f32;
+        }
+    }
+
+    if let Some(amplifier) = conditions.haste {
+        speed *= 1.0 + (f32::from(amplifier) + 1.0) * 0.2;
+    }
+
+    if let Some(amplifier) = conditions.mining_fatigue {
+        speed *= match amplifier {
+            0 => 0.3,
+            1 => 0.09,
+            2 => 0.0027,
+            _ => 8.1e-4,
+        };
+    }
+
+    if conditions.underwater && !conditions.aqua_affinity {
+        speed /= 5.0;
+    }
+
+    if !conditions.on_ground {
+        speed /= 5.0;
+    }
+
+    speed
+}
+
+/// Returns how many times faster than a hand `tool` breaks `block`.
+fn tool_speed(block: BlockKind, tool: ItemKind) -> f32 {
+    let Some(tool) = tool.tool() else {
+        return 1.0;
+    };
+
+    match tool.kind {
+        ToolKind::Sword if block == BlockKind::Cobweb => 15.0,
+        ToolKind::Sword if block.sword_efficient() => 1.5,
+        ToolKind::Shears => match block {
+            BlockKind::Cobweb
+            | BlockKind::OakLeaves
+            | BlockKind::SpruceLeaves
+            | BlockKind::BirchLeaves
+            | BlockKind::JungleLeaves
+            | BlockKind::AcaciaLeaves
+            | BlockKind::CherryLeaves
+            | BlockKind::DarkOakLeaves
+            | BlockKind::MangroveLeaves
+            | BlockKind::AzaleaLeaves
+            | BlockKind::FloweringAzaleaLeaves => 15.0,
+            BlockKind::WhiteWool
+            | BlockKind::OrangeWool
+            | BlockKind::MagentaWool
+            | BlockKind::LightBlueWool
+            | BlockKind::YellowWool
+            | BlockKind::LimeWool
+            | BlockKind::PinkWool
+            | BlockKind::GrayWool
+            | BlockKind::LightGrayWool
+            | BlockKind::CyanWool
+            | BlockKind::PurpleWool
+            | BlockKind::BlueWool
+            | BlockKind::BrownWool
+            | BlockKind::GreenWool
+            | BlockKind::RedWool
+            | BlockKind::BlackWool => 5.0,
+            BlockKind::Vine | BlockKind::GlowLichen => 2.0,
+            _ => 1.0,
+        },
+        kind if block.tool() == Some(kind) => tool.speed,
+        _ => 1.0,
+    }
+}
+
+/// Returns the level of the enchantment with the given ID on `stack`, or `0`
+/// if it is not enchanted with it.
+fn enchantment_level(stack: &ItemStack, id: &str) -> i32 {
+    let Some(Value::List(List::Compound(enchantments))) =
+        stack.nbt.as_ref().and_then(|nbt| nbt.get("Enchantments"))
+    else {
+        return 0;
+    };
+
+    enchantments
+        .iter()
+        .find(|enchantment| matches!(enchantment.get("id"), Some(Value::String(s)) if s == id))
+        .and_then(|enchantment| match enchantment.get("lvl")? {
+            Value::Byte(lvl) => Some(i32::from(*lvl)),
+            Value::Short(lvl) => Some(i32::from(*lvl)),
+            Value::Int(lvl) => Some(*lvl),
+            _ => None,
+        })
+        .unwrap_or(0)
+}
+
+/// Returns whether the block is water or contains water.
+fn is_water(block: BlockState) -> bool {
+    matches!(
+        block.to_kind(),
+        BlockKind::Water
+            | BlockKind::BubbleColumn
+            | BlockKind::Kelp
+            | BlockKind::KelpPlant
+            | BlockKind::Seagrass
+            | BlockKind::TallSeagrass
+    ) || block.get(PropName::Waterlogged) == Some(PropValue::True)
+}
+
+#[derive(QueryData)]
+#[query_data(mutable)]
+struct BreakerQuery {
+    entity: Entity,
+    client: &'static mut Client,
+    state: &'static mut BlockBreakingState,
+    inventory: &'static Inventory,
+    held_item: &'static HeldItem,
+    game_mode: &'static GameMode,
+    pos: &'static Position,
+    on_ground: &'static OnGround,
+    pose: &'static entity::Pose,
+    status_effects: Option<&'static ActiveStatusEffects>,
+    id: &'static EntityId,
+    entity_layer: &'static EntityLayerId,
+    chunk_layer: &'static VisibleChunkLayer,
+}
+
+impl BreakerQueryItem<'_> {
+    fn conditions(&self, layer: &ChunkLayer) -> BreakingConditions {
+        let effect = |effect| {
+            self.status_effects
+                .and_then(|effects| effects.get_current_effect(effect))
+                .map(|effect| effect.amplifier())
+        };
+
+        let eye_height = match self.pose.0 {
+            Pose::Sneaking => 1.27,
+            Pose::Swimming | Pose::FallFlying | Pose::SpinAttack => 0.4,
+            _ => 1.62,
+        };
+
+        let eyes = BlockPos::from(self.pos.0 + DVec3::new(0.0, eye_height, 0.0));
+
+        let helmet = self.inventory.slot(PlayerInventory::SLOT_HEAD);
+
+        BreakingConditions {
+            haste: effect(StatusEffect::Haste).max(effect(StatusEffect::ConduitPower)),
+            mining_fatigue: effect(StatusEffect::MiningFatigue),
+            on_ground: self.on_ground.0,
+            underwater: layer.block(eyes).is_some_and(|b| is_water(b.state)),
+            aqua_affinity: enchantment_level(helmet, "minecraft:aqua_affinity") > 0,
+        }
+    }
+
+    fn break_progress(&self, layer: &ChunkLayer, block: BlockState) -> f32 {
+        let tool = self.inventory.slot(self.held_item.slot());
+
+        break_progress(block, tool, self.conditions(layer))
+    }
+
+    /// Shows the destroy stage of the target block to other players, or hides
+    /// it if `stage` is `None`.
+    fn show_stage(&mut self, layers: &mut Query<&mut EntityLayer>, stage: Option<u8>) {
+        if self.state.stage == stage {
+            return;
+        }
+
+        self.state.stage = stage;
+
+        let (Some(target), Ok(mut layer)) =
+            (self.state.target, layers.get_mut(self.entity_layer.0))
+        else {
+            return;
+        };
+
+        layer
+            .view_except_writer(target, self.entity)
+            .write_packet(&BlockBreakingProgressS2c {
+                entity_id: VarInt(self.id.get()),
+                position: target,
+                // Values outside of 0-9 remove the animation.
+                destroy_stage: stage.unwrap_or(u8::MAX),
+            });
+    }
+
+    fn stop_breaking(&mut self, layers: &mut Query<&mut EntityLayer>) {
+        self.show_stage(layers, None);
+        self.state.target = None;
+        self.state.progress = 0.0;
+    }
+}
+
+fn init_block_breaking_states(
+    clients: Query<Entity, (Added<Client>, Without<BlockBreakingState>)>,
+    mut commands: Commands,
+) {
+    for entity in &clients {
+        commands
+            .entity(entity)
+            .insert(BlockBreakingState::default());
+    }
+}
+
+fn tick_block_breaking(
+    mut clients: Query<BreakerQuery>,
+    chunk_layers: Query<&ChunkLayer>,
+    mut entity_layers: Query<&mut EntityLayer>,
+) {
+    for mut client in &mut clients {
+        let Some(target) = client.state.target else {
+            continue;
+        };
+
+        let Ok(layer) = chunk_layers.get(client.chunk_layer.0) else {
+            continue;
+        };
+
+        let block = layer.block(target).map_or(BlockState::AIR, |b| b.state);
+
+        if block.is_air() {
+            client.stop_breaking(&mut entity_layers);
+            continue;
+        }
+
+        client.state.progress += client.break_progress(layer, block);
+
+        let stage = (client.state.progress * 10.0).min(9.0) as u8;
+        client.show_stage(&mut entity_layers, Some(stage));
+    }
+}
+
+fn handle_digging(
+    mut events: EventReader<DiggingEvent>,
+    mut clients: Query<BreakerQuery>,
+    chunk_layers: Query<&ChunkLayer>,
+    mut entity_layers: Query<&mut EntityLayer>,
+    settings: Res<BlockBreakingSettings>,
+    mut break_events: EventWriter<BreakBlockEvent>,
+    mut rejected_events: EventWriter<BreakBlockRejectedEvent>,
+) {
+    for event in events.read() {
+        let Ok(mut client) = clients.get_mut(event.client) else {
+            continue;
+        };
+
+        if *client.game_mode == GameMode::Spectator {
+            continue;
+        }
+
+        let Ok(layer) = chunk_layers.get(client.chunk_layer.0) else {
+            continue;
+        };
+
+        let block = layer
+            .block(event.position)
+            .map_or(BlockState::AIR, |b| b.state);
+
+        match event.state {
+            DiggingState::Start => {
+                client.stop_breaking(&mut entity_layers);
+
+                if block.is_air() {
+                    continue;
+                }
+
+                let progress = client.break_progress(layer, block);
+
+                if *client.game_mode == GameMode::Creative || progress >= 1.0 {
+                    break_events.send(BreakBlockEvent {
+                        client: event.client,
+                        position: event.position,
+                        block,
+                    });
+                } else if progress > 0.0 {
+                    client.state.target = Some(event.position);
+                }
+            }
+            DiggingState::Abort => {
+                if client.state.target == Some(event.position) {
+                    client.stop_breaking(&mut entity_layers);
+                }
+            }
+            DiggingState::Stop => {
+                let progress = if client.state.target == Some(event.position) {
+                    // The tick the client finished on counts too.
+                    client.state.progress + client.break_progress(layer, block)
+                } else {
+                    0.0
+                };
+
+                client.stop_breaking(&mut entity_layers);
+
+                if block.is_air() {
+                    continue;
+                }
+
+                if progress >= settings.min_progress {
+                    break_events.send(BreakBlockEvent {
+                        client: event.client,
+                        position: event.position,
+                        block,
+                    });
+                } else {
+                    client.client.write_packet(&BlockUpdateS2c {
+                        position: event.position,
+                        block_id: block,
+                    });
+
+                    rejected_events.send(BreakBlockRejectedEvent {
+                        client: event.client,
+                        position: event.position,
+                        progress,
+                    });
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,448 @@
+//! Dropping, merging and picking up item entities.
+//!
+//! [`ItemEntityPlugin`] is not part of the default plugins. Once added, every
+//! [`DropItemStackEvent`] spawns an item entity in front of the client that
+//! dropped the stack. Item entities merge with nearby item entities holding
+//! the same item, are picked up by players walking over them once their
+//! pickup delay has passed, and despawn once they reach the configured age.
+//!
+//! Item entities spawned by game code are picked up as well. Insert an
+//! [`ItemEntityState`] when spawning them to give them a pickup delay.
+//!
+//! Item entities only fall to the ground when the
+//! [`PhysicsPlugin`](valence_server::physics::PhysicsPlugin) is added too.
+
+use std::collections::HashMap;
+
+use bevy_app::prelude::*;
+use bevy_ecs::entity::EntityHashSet;
+use bevy_ecs::prelude::*;
+use valence_server::client::{Client, FlushPacketsSet};
+use valence_server::entity::hitbox::HitboxShape;
+use valence_server::entity::item::{ItemEntity, ItemEntityBundle, Stack};
+use valence_server::entity::{EntityId, EntityLayerId, Look, Position, Velocity};
+use valence_server::layer::UpdateLayersPreClientSet;
+use valence_server::math::{Aabb, DVec3, Vec3};
+use valence_server::protocol::packets::play::ItemPickupAnimationS2c;
+use valence_server::protocol::{VarInt, WritePacket};
+use valence_server::{ChunkPos, Despawned, EntityLayer, GameMode, ItemStack, Layer};
+
+use crate::player_inventory::PlayerInventory;
+use crate::{update_player_inventories, DropItemStackEvent, Inventory};
+
+pub struct ItemEntityPlugin;
+
+/// The system set item entities are dropped, merged, picked up and despawned
+/// in, in [`PostUpdate`].
+#[derive(SystemSet, Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ItemEntitySet;
+
+impl Plugin for ItemEntityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ItemEntitySettings>()
+            .configure_sets(
+                PostUpdate,
+                ItemEntitySet
+                    .before(UpdateLayersPreClientSet)
+                    .before(update_player_inventories)
+                    .before(FlushPacketsSet),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    init_item_entities,
+                    drop_items,
+                    merge_items,
+                    pick_up_items,
+                    age_items,
+                )
+                    .chain()
+                    .in_set(ItemEntitySet),
+            );
+    }
+}
+
+/// Global settings for item entities.
+#[derive(Resource, Clone, PartialEq, Debug)]
+pub struct ItemEntitySettings {
+    /// The number of ticks before an item dropped by a client can be picked
+    /// up.
+    pub pickup_delay: u32,
+    /// The age in ticks item entities are despawned at, or `None` to never
+    /// despawn them.
+    pub despawn_age: Option<u32>,
+    /// How far apart the hitboxes of two item entities can be horizontally
+    /// for them to merge, or `None` to never merge them.
+    pub merge_distance: Option<f64>,
+}
+
+impl Default for ItemEntitySettings {
+    fn default() -> Self {
+        Self {
+            pickup_delay: 40,
+            despawn_age: Some(6000),
+            merge_distance: Some(0.5),
+        }
+    }
+}
+
+/// [`Component`] with the age and pickup delay of an item entity. It is added
+/// to item entities without one when they are spawned.
+#[derive(Component, Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub struct ItemEntityState {
+    /// The number of ticks the item entity has existed for.
+    pub age: u32,
+    /// The number of ticks left before the item entity can be picked up.
+    pub pickup_delay: u32,
+}
+
+/// The hitbox of item entities relative to their position.
+const ITEM_HITBOX: Aabb = Aabb::new_unchecked(
+    DVec3::new(-0.125, 0.0, -0.125),
+    DVec3::new(0.125, 0.25, 0.125),
+);
+
+/// The hitbox of a standing player relative to their position.
+const PLAYER_HITBOX: Aabb =
+    Aabb::new_unchecked(DVec3::new(-0.3, 0.0, -0.3), DVec3::new(0.3, 1.8, 0.3));
+
+/// How far the hitbox of a player is grown to pick up items, like in vanilla.
+const PICKUP_REACH: DVec3 = DVec3::new(1.0, 0.5, 1.0);
+
+/// How high above the feet of a player dropped items are spawned.
+const DROP_HEIGHT: f64 = 1.32;
+
+/// The speed dropped items are thrown with, in blocks per tick.
+const DROP_SPEED: f32 = 0.3;
+
+#[allow(clippy::type_complexity)]
+fn init_item_entities(
+    items: Query<Entity, (Added<ItemEntity>, Without<ItemEntityState>)>,
+    mut commands: Commands,
+) {
+    for entity in &items {
+        commands.entity(entity).insert(ItemEntityState::default());
+    }
+}
+
+fn drop_items(
+    mut events: EventReader<DropItemStackEvent>,
+    clients: Query<(&Position, &Look, &EntityLayerId)>,
+    settings: Res<ItemEntitySettings>,
+    mut commands: Commands,
+) {
+    for event in events.read() {
+        let Ok((pos, look, layer)) = clients.get(event.client) else {
+            continue;
+        };
+
+        if event.stack.is_empty() {
+            continue;
+        }
+
+        // Blocks per tick, converted to meters per second.
+        let velocity = (look.vec() * DROP_SPEED + Vec3::new(0.0, 0.1, 0.0)) * 20.0;
+
+        commands.spawn((
+            ItemEntityBundle {
+                item_stack: Stack(event.stack.clone()),
+                layer: *layer,
+                position: Position(pos.0 + DVec3::new(0.0, DROP_HEIGHT, 0.0)),
+                velocity: Velocity(velocity),
+                ..Default::default()
+            },
+            ItemEntityState {
+                age: 0,
+                pickup_delay: settings.pickup_delay,
+            },
+        ));
+    }
+}
+
+/// Item entities grouped by their layer and the chunk they are in. Unlike the
+/// index of an [`EntityLayer`], this includes items spawned this tick.
+#[derive(Default)]
+struct ItemIndex(HashMap<(Entity, ChunkPos), Vec<Entity>>);
+
+impl ItemIndex {
+    fn new<'a, I>(items: I) -> Self
+    where
+        I: IntoIterator<Item = (Entity, &'a Position, &'a EntityLayerId)>,
+    {
+        let mut index = Self::default();
+
+        for (entity, pos, layer) in items {
+            index
+                .0
+                .entry((layer.0, ChunkPos::from(pos.0)))
+                .or_default()
+                .push(entity);
+        }
+
+        index
+    }
+
+    /// Returns the items in the chunks an item intersecting `aabb` can be in.
+    fn near(&self, layer: Entity, aabb: Aabb) -> Vec<Entity> {
+        let min = ChunkPos::from(aabb.min() - ITEM_HITBOX.max());
+        let max = ChunkPos::from(aabb.max() - ITEM_HITBOX.min());
+
+        (min.z..=max.z)
+            .flat_map(|z| (min.x..=max.x).map(move |x| ChunkPos::new(x, z)))
+            .filter_map(|pos| self.0.get(&(layer, pos)))
+            .flatten()
+            .copied()
+            .collect()
+    }
+}
+
+/// Returns whether the given stacks hold the same item and can be combined.
+fn stackable(a: &ItemStack, b: &ItemStack) -> bool {
+    a.item == b.item && a.nbt == b.nbt
+}
+
+#[allow(clippy::type_complexity)]
+fn merge_items(
+    mut items: Query<
+        (
+            Entity,
+            &mut Stack,
+            &mut ItemEntityState,
+            Ref<Position>,
+            &EntityLayerId,
+        ),
+        Without<Despawned>,
+    >,
+    settings: Res<ItemEntitySettings>,
+    mut commands: Commands,
+) {
+    let Some(distance) = settings.merge_distance else {
+        return;
+    };
+
+    let reach = DVec3::new(distance, 0.0, distance);
+
+    // Like in vanilla, items only look for other items when they move and
+    // every two seconds.
+    let candidates: Vec<_> = items
+        .iter()
+        .filter(|(_, _, state, pos, _)| pos.is_changed() || state.age % 40 == 0)
+        .map(|(entity, _, _, pos, layer)| (entity, pos.0, layer.0))
+        .collect();
+
+    let index = ItemIndex::new(
+        items
+            .iter()
+            .map(|(entity, _, _, pos, layer)| (entity, pos.into_inner(), layer)),
+    );
+
+    let mut merged = EntityHashSet::default();
+
+    for (entity, pos, layer_id) in candidates {
+        if merged.contains(&entity) {
+            continue;
+        }
+
+        let aabb = ITEM_HITBOX + pos;
+        let area = Aabb::new_unchecked(aabb.min() - reach, aabb.max() + reach);
+
+        for other in index.near(layer_id, area) {
+            if other == entity || merged.contains(&other) {
+                continue;
+            }
+
+            let Ok([a, b]) = items.get_many_mut([entity, other]) else {
+                continue;
+            };
+
+            let (_, mut a_stack, mut a_state, _, _) = a;
+            let (_, mut b_stack, mut b_state, b_pos, _) = b;
+
+            if !area.intersects(ITEM_HITBOX + b_pos.0) {
+                continue;
+            }
+
+            let count = i16::from(a_stack.count) + i16::from(b_stack.count);
+
+            if !stackable(&a_stack, &b_stack) || count > i16::from(a_stack.item.max_stack()) {
+                continue;
+            }
+
+            // The larger stack absorbs the smaller one.
+            let absorbed = if a_stack.count >= b_stack.count {
+                a_stack.count = count as i8;
+                a_state.age = a_state.age.min(b_state.age);
+                a_state.pickup_delay = a_state.pickup_delay.max(b_state.pickup_delay);
+                other
+            } else {
+                b_stack.count = count as i8;
+                b_state.age = b_state.age.min(a_state.age);
+                b_state.pickup_delay = b_state.pickup_delay.max(a_state.pickup_delay);
+                entity
+            };
+
+            merged.insert(absorbed);
+            commands.entity(absorbed).insert(Despawned);
+
+            if absorbed == entity {
+                break;
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn pick_up_items(
+    mut clients: Query<
+        (
+            Entity,
+            &mut Client,
+            &mut Inventory,
+            &Position,
+            &EntityId,
+            &EntityLayerId,
+            &GameMode,
+            Option<&HitboxShape>,
+        ),
+        Without<Despawned>,
+    >,
+    mut items: Query<
+        (
+            Entity,
+            &mut Stack,
+            &ItemEntityState,
+            &Position,
+            &EntityId,
+            &EntityLayerId,
+        ),
+        Without<Despawned>,
+    >,
+    mut layers: Query<&mut EntityLayer>,
+    mut commands: Commands,
+) {
+    let index = ItemIndex::new(
+        items
+            .iter()
+            .map(|(entity, _, _, pos, _, layer)| (entity, pos, layer)),
+    );
+
+    let mut collected = EntityHashSet::default();
+
+    for (client_entity, mut client, mut inventory, pos, client_id, layer_id, game_mode, shape) in
+        &mut clients
+    {
+        if *game_mode == GameMode::Spectator || inventory.readonly {
+            continue;
+        }
+
+        let Ok(mut layer) = layers.get_mut(layer_id.0) else {
+            continue;
+        };
+
+        // The hitbox shape is not known yet on the tick clients spawn.
+        let hitbox = shape
+            .map(HitboxShape::get)
+            .filter(|&shape| shape != Aabb::ZERO)
+            .unwrap_or(PLAYER_HITBOX);
+
+        let aabb = hitbox + pos.0;
+        let area = Aabb::new_unchecked(aabb.min() - PICKUP_REACH, aabb.max() + PICKUP_REACH);
+
+        for item in index.near(layer_id.0, area) {
+            if collected.contains(&item) {
+                continue;
+            }
+
+            let Ok((_, mut stack, state, item_pos, item_id, _)) = items.get_mut(item) else {
+                continue;
+            };
+
+            if state.pickup_delay > 0 || !area.intersects(ITEM_HITBOX + item_pos.0) {
+                continue;
+            }
+
+            let count = insert_into_player_inventory(&mut inventory, &stack.0);
+
+            if count == 0 {
+                continue;
+            }
+
+            let mut packet = ItemPickupAnimationS2c {
+                collected_entity_id: VarInt(item_id.get()),
+                collector_entity_id: VarInt(client_id.get()),
+                pickup_item_count: VarInt(count.into()),
+            };
+
+            layer
+                .view_except_writer(item_pos.0, client_entity)
+                .write_packet(&packet);
+
+            // Clients see themselves with the reserved entity ID 0.
+            packet.collector_entity_id = VarInt(0);
+            client.write_packet(&packet);
+
+            if count == stack.0.count {
+                collected.insert(item);
+                commands.entity(item).insert(Despawned);
+            } else {
+                stack.0.count -= count;
+            }
+        }
+    }
+}
+
+/// Inserts as much of `stack` as fits into the hotbar and main slots of a
+/// player inventory, and returns how many items were inserted. Like in
+/// vanilla, stacks of the same item are filled before empty slots are used.
+fn insert_into_player_inventory(inventory: &mut Inventory, stack: &ItemStack) -> i8 {
+    let max_stack = stack.item.max_stack();
+    let slots = PlayerInventory::SLOTS_HOTBAR.chain(9..*PlayerInventory::SLOTS_HOTBAR.start());
+
+    let mut remaining = stack.count;
+
+    for slot in slots.clone() {
+        if remaining == 0 {
+            break;
+        }
+
+        let existing = inventory.slot(slot);
+
+        if !existing.is_empty() && stackable(existing, stack) && existing.count < max_stack {
+            let count = remaining.min(max_stack - existing.count);
+            let total = existing.count + count;
+
+            inventory.set_slot_amount(slot, total);
+            remaining -= count;
+        }
+    }
+
+    for slot in slots {
+        if remaining == 0 {
+            break;
+        }
+
+        if inventory.slot(slot).is_empty() {
+            let count = remaining.min(max_stack);
+
+            inventory.set_slot(slot, stack.clone().with_count(count));
+            remaining -= count;
+        }
+    }
+
+    stack.count - remaining
+}
+
+fn age_items(
+    mut items: Query<(Entity, &mut ItemEntityState), Without<Despawned>>,
+    settings: Res<ItemEntitySettings>,
+    mut commands: Commands,
+) {
+    for (entity, mut state) in &mut items {
+        state.age += 1;
+        state.pickup_delay = state.pickup_delay.saturating_sub(1);
+
+        if settings.despawn_age.is_some_and(|age| state.age >= age) {
+            commands.entity(entity).insert(Despawned);
+        }
+    }
+}
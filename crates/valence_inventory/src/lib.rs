@@ -25,6 +25,8 @@ use valence_server::protocol::{VarInt, WritePacket};
 use valence_server::text::IntoText;
 use valence_server::{GameMode, Hand, ItemKind, ItemStack, Text};
 
+pub mod block_breaking;
+pub mod item_entity;
 pub mod player_inventory;
 mod validate;
 
@@ -392,6 +394,15 @@ pub struct HeldItem {
     held_item_slot: u16,
 }
 
+impl Default for HeldItem {
+    fn default() -> Self {
+        Self {
+            // First slot of the hotbar.
+            held_item_slot: 36,
+        }
+    }
+}
+
 impl HeldItem {
     /// The slot ID of the currently held item, in the range 36-44 inclusive.
     /// This value is safe to use on the player's inventory directly.
@@ -580,8 +591,9 @@ impl<'a> InventoryWindowMut<'a> {
     }
 }
 
-/// Attach the necessary inventory components to new clients.
-fn init_new_client_inventories(clients: Query<Entity, Added<Client>>, mut commands: Commands) {
+/// Attach the necessary inventory components to new clients. Systems that
+/// initialize the inventories of new clients should run after this.
+pub fn init_new_client_inventories(clients: Query<Entity, Added<Client>>, mut commands: Commands) {
     for entity in &clients {
         commands.entity(entity).insert((
             Inventory::new(InventoryKind::Player),
@@ -592,10 +604,7 @@ fn init_new_client_inventories(clients: Query<Entity, Added<Client>>, mut comman
                 slots_changed: 0,
                 client_updated_cursor_item: None,
             },
-            HeldItem {
-                // First slot of the hotbar.
-                held_item_slot: 36,
-            },
+            HeldItem::default(),
         ));
     }
 }
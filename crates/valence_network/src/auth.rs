@@ -0,0 +1,142 @@
+//! Authentication of players in [online mode].
+//!
+//! [online mode]: crate::ConnectionMode::Online
+
+use std::net::SocketAddr;
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use num_bigint::BigInt;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use uuid::Uuid;
+use valence_protocol::profile::Property;
+
+use crate::SharedNetworkState;
+
+/// Verifies that a player owns the account they are logging in with. See
+/// [`NetworkSettings::authenticator`].
+///
+/// This is called after encryption is enabled, in place of the request to the
+/// session server that a vanilla server makes. Implementations can check
+/// players against a local Yggdrasil-compatible server, a database, or return
+/// fixed profiles in tests.
+///
+/// This trait uses [`mod@async_trait`].
+///
+/// [`NetworkSettings::authenticator`]: crate::NetworkSettings::authenticator
+#[async_trait]
+pub trait Authenticator: Send + Sync + 'static {
+    /// Returns the profile of the player, or `None` if the player could not be
+    /// verified. Players that could not be verified are disconnected with the
+    /// vanilla "Failed to verify username" message, while errors disconnect
+    /// them without a message.
+    ///
+    /// This function is called from within a tokio runtime.
+    async fn authenticate(
+        &self,
+        shared: &SharedNetworkState,
+        request: &AuthRequest<'_>,
+    ) -> anyhow::Result<Option<GameProfile>>;
+}
+
+/// The information about a login needed to authenticate the player.
+#[derive(Clone, Debug)]
+pub struct AuthRequest<'a> {
+    /// The username the client is logging in with.
+    pub username: &'a str,
+    /// The remote address of the client.
+    pub remote_addr: SocketAddr,
+    /// The secret the client chose to encrypt the connection with.
+    pub shared_secret: &'a [u8],
+    /// The public key of the server in DER format.
+    pub public_key_der: &'a [u8],
+}
+
+impl AuthRequest<'_> {
+    /// The server ID hash the client sent to the session server when joining.
+    pub fn server_hash(&self) -> String {
+        let hash = Sha1::new()
+            .chain_update(self.shared_secret)
+            .chain_update(self.public_key_der)
+            .finalize();
+
+        auth_digest(&hash)
+    }
+}
+
+/// The profile of an authenticated player.
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize)]
+pub struct GameProfile {
+    pub id: Uuid,
+    pub name: String,
+    pub properties: Vec<Property>,
+}
+
+/// Authenticates players with the session server at the URL returned by
+/// [`NetworkCallbacks::session_server`], which is Mojang's by default.
+///
+/// [`NetworkCallbacks::session_server`]: crate::NetworkCallbacks::session_server
+#[derive(Copy, Clone, Default, Debug)]
+pub struct SessionServerAuthenticator;
+
+#[async_trait]
+impl Authenticator for SessionServerAuthenticator {
+    async fn authenticate(
+        &self,
+        shared: &SharedNetworkState,
+        request: &AuthRequest<'_>,
+    ) -> anyhow::Result<Option<GameProfile>> {
+        let url = shared
+            .0
+            .callbacks
+            .inner
+            .session_server(
+                shared,
+                request.username,
+                &request.server_hash(),
+                &request.remote_addr.ip(),
+            )
+            .await;
+
+        let resp = shared.0.http_client.get(url).send().await?;
+
+        match resp.status() {
+            StatusCode::OK => {}
+            StatusCode::NO_CONTENT => return Ok(None),
+            status => {
+                bail!("session server GET request failed (status code {status})");
+            }
+        }
+
+        let profile = resp.json().await.context("parsing game profile")?;
+
+        Ok(Some(profile))
+    }
+}
+
+fn auth_digest(bytes: &[u8]) -> String {
+    BigInt::from_signed_bytes_be(bytes).to_str_radix(16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auth_digest_usernames() {
+        assert_eq!(
+            auth_digest(&Sha1::digest("Notch")),
+            "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48"
+        );
+        assert_eq!(
+            auth_digest(&Sha1::digest("jeb_")),
+            "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1"
+        );
+        assert_eq!(
+            auth_digest(&Sha1::digest("simon")),
+            "88e16a1019277b15d58faf0541e11910eb756f6"
+        );
+    }
+}
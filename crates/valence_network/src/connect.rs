@@ -2,25 +2,18 @@
 
 use std::io;
 use std::net::SocketAddr;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::{bail, ensure, Context};
 use base64::prelude::*;
-use hmac::digest::Update;
-use hmac::{Hmac, Mac};
-use num_bigint::BigInt;
-use reqwest::StatusCode;
 use rsa::Pkcs1v15Encrypt;
-use serde::Deserialize;
 use serde_json::{json, Value};
-use sha1::Sha1;
 use sha2::{Digest, Sha256};
-use tokio::net::{TcpListener, TcpStream};
-use tracing::{error, info, trace, warn};
+use tracing::{debug, error, info, trace, warn};
 use uuid::Uuid;
 use valence_lang::keys;
 use valence_protocol::profile::Property;
-use valence_protocol::Decode;
 use valence_server::client::Properties;
 use valence_server::protocol::packets::handshaking::handshake_c2s::HandshakeNextState;
 use valence_server::protocol::packets::handshaking::HandshakeC2s;
@@ -35,16 +28,23 @@ use valence_server::protocol::{PacketDecoder, PacketEncoder, RawBytes, VarInt};
 use valence_server::text::{Color, IntoText};
 use valence_server::{ident, Text, MINECRAFT_VERSION, PROTOCOL_VERSION};
 
+use crate::auth::AuthRequest;
+use crate::hub;
 use crate::legacy_ping::try_handle_legacy_ping;
+use crate::login_plugin::LoginPluginQueries;
 use crate::packet_io::PacketIo;
+use crate::rate_limit::RateLimitKind;
+use crate::replay;
+use crate::transport::{IncomingConnection, Transport, TransportStream};
+use crate::velocity::{self, PlayerInfo};
 use crate::{CleanupOnDrop, ConnectionMode, NewClientInfo, ServerListPing, SharedNetworkState};
 
-/// Accepts new connections to the server as they occur.
-pub(super) async fn do_accept_loop(shared: SharedNetworkState) {
-    let listener = match TcpListener::bind(shared.0.address).await {
+/// Accepts new connections from a transport as they occur.
+pub(super) async fn do_accept_loop(shared: SharedNetworkState, transport: Arc<dyn Transport>) {
+    let mut listener = match transport.bind().await {
         Ok(listener) => listener,
         Err(e) => {
-            error!("failed to start TCP listener: {e}");
+            error!("failed to start listener: {e}");
             return;
         }
     };
@@ -54,13 +54,23 @@ pub(super) async fn do_accept_loop(shared: SharedNetworkState) {
     loop {
         match shared.0.connection_sema.clone().acquire_owned().await {
             Ok(permit) => match listener.accept().await {
-                Ok((stream, remote_addr)) => {
+                Ok((remote_addr, incoming)) => {
+                    // The server may have stopped accepting while waiting.
+                    if shared.0.connection_sema.is_closed() {
+                        return;
+                    }
+
+                    if shared.is_ip_banned(remote_addr.ip()) {
+                        trace!("closing connection from banned address {remote_addr}");
+                        continue;
+                    }
+
                     let shared = shared.clone();
 
                     tokio::spawn(async move {
                         if let Err(e) = tokio::time::timeout(
                             timeout,
-                            handle_connection(shared, stream, remote_addr),
+                            handle_connection(shared, incoming, remote_addr),
                         )
                         .await
                         {
@@ -82,21 +92,42 @@ pub(super) async fn do_accept_loop(shared: SharedNetworkState) {
 
 async fn handle_connection(
     shared: SharedNetworkState,
-    mut stream: TcpStream,
+    incoming: IncomingConnection,
     remote_addr: SocketAddr,
 ) {
     trace!("handling connection");
 
-    if let Err(e) = stream.set_nodelay(true) {
-        error!("failed to set TCP_NODELAY: {e}");
+    if !shared
+        .0
+        .callbacks
+        .inner
+        .allow_connection(&shared, remote_addr)
+        .await
+    {
+        debug!("connection from {remote_addr} was not allowed");
+        return;
+    }
+
+    if !check_rate_limit(&shared, remote_addr, RateLimitKind::Handshake).await {
+        return;
     }
 
-    match try_handle_legacy_ping(&shared, &mut stream, remote_addr).await {
-        Ok(true) => return, // Legacy ping succeeded.
-        Ok(false) => {}     // No legacy ping.
-        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {}
+    let mut stream: Box<dyn TransportStream> = match incoming.await {
+        Ok(stream) => stream,
         Err(e) => {
-            warn!("legacy ping ended with error: {e:#}");
+            warn!("failed to set up connection: {e}");
+            return;
+        }
+    };
+
+    if let Some(tcp_stream) = stream.as_tcp_stream() {
+        match try_handle_legacy_ping(&shared, tcp_stream, remote_addr).await {
+            Ok(true) => return, // Legacy ping succeeded.
+            Ok(false) => {}     // No legacy ping.
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {}
+            Err(e) => {
+                warn!("legacy ping ended with error: {e:#}");
+            }
         }
     }
 
@@ -149,17 +180,62 @@ async fn handle_handshake(
     );
 
     match next_state {
-        HandshakeNextState::Status => handle_status(shared, io, remote_addr, handshake)
-            .await
-            .context("handling status"),
+        HandshakeNextState::Status => {
+            if !check_rate_limit(&shared, remote_addr, RateLimitKind::StatusRequest).await {
+                return Ok(());
+            }
+
+            handle_status(shared, io, remote_addr, handshake)
+                .await
+                .context("handling status")
+        }
         HandshakeNextState::Login => {
+            if !check_rate_limit(&shared, remote_addr, RateLimitKind::LoginAttempt).await {
+                io.send_packet(&LoginDisconnectS2c {
+                    reason: "Too many login attempts, please try again later"
+                        .color(Color::RED)
+                        .into(),
+                })
+                .await?;
+
+                return Ok(());
+            }
+
+            let protocol_version = handshake.protocol_version;
+            let translator = shared
+                .translators()
+                .get(handshake.protocol_version)
+                .cloned();
+
             match handle_login(&shared, &mut io, remote_addr, handshake)
                 .await
                 .context("handling login")?
             {
                 Some((info, cleanup)) => {
+                    if let Some(replay) = &shared.0.replay {
+                        // The recorded packets are already translated.
+                        tokio::spawn(replay::run_session(
+                            replay.clone(),
+                            io,
+                            protocol_version,
+                            cleanup,
+                        ));
+
+                        return Ok(());
+                    }
+
+                    if let Some(hub) = &shared.0.hub {
+                        io.set_translator(translator);
+
+                        // The session outlives the timeout of the initial connection.
+                        tokio::spawn(hub::run_session(hub.clone(), io, info, cleanup));
+
+                        return Ok(());
+                    }
+
                     let client = io.into_client_args(
                         info,
+                        translator,
                         shared.0.incoming_byte_limit,
                         shared.0.outgoing_byte_limit,
                         cleanup,
@@ -175,6 +251,33 @@ async fn handle_handshake(
     }
 }
 
+/// Records an event from `remote_addr` and returns whether it is within the
+/// configured rate limits.
+async fn check_rate_limit(
+    shared: &SharedNetworkState,
+    remote_addr: SocketAddr,
+    kind: RateLimitKind,
+) -> bool {
+    if shared
+        .0
+        .rate_limiter
+        .try_acquire(remote_addr.ip(), kind, Instant::now())
+    {
+        return true;
+    }
+
+    debug!("{remote_addr} exceeded the rate limit for {kind:?}");
+
+    shared
+        .0
+        .callbacks
+        .inner
+        .rate_limited(shared, remote_addr, kind)
+        .await;
+
+    false
+}
+
 async fn handle_status(
     shared: SharedNetworkState,
     mut io: PacketIo,
@@ -256,7 +359,12 @@ async fn handle_login(
     remote_addr: SocketAddr,
     handshake: HandshakeData,
 ) -> anyhow::Result<Option<(NewClientInfo, CleanupOnDrop)>> {
-    if handshake.protocol_version != PROTOCOL_VERSION {
+    if handshake.protocol_version != PROTOCOL_VERSION
+        && shared
+            .translators()
+            .get(handshake.protocol_version)
+            .is_none()
+    {
         io.send_packet(&LoginDisconnectS2c {
             // TODO: use correct translation key.
             reason: format!("Mismatched Minecraft version (server is on {MINECRAFT_VERSION})")
@@ -293,6 +401,21 @@ async fn handle_login(
         io.set_compression(shared.0.threshold);
     }
 
+    if let Err(reason) = shared
+        .0
+        .callbacks
+        .inner
+        .login_plugin_queries(shared, &info, &mut LoginPluginQueries::new(io))
+        .await
+    {
+        info!("disconnect during login plugin queries: \"{reason}\"");
+        io.send_packet(&LoginDisconnectS2c {
+            reason: reason.into(),
+        })
+        .await?;
+        return Ok(None);
+    }
+
     let cleanup = match shared.0.callbacks.inner.login(shared, &info).await {
         Ok(f) => CleanupOnDrop(Some(f)),
         Err(reason) => {
@@ -360,48 +483,26 @@ async fn login_online(
 
     io.enable_encryption(&crypt_key);
 
-    let hash = Sha1::new()
-        .chain(&shared_secret)
-        .chain(&shared.0.public_key_der)
-        .finalize();
+    let request = AuthRequest {
+        username: &username,
+        remote_addr,
+        shared_secret: &shared_secret,
+        public_key_der: &shared.0.public_key_der,
+    };
 
-    let url = shared
+    let Some(profile) = shared
         .0
-        .callbacks
-        .inner
-        .session_server(
-            shared,
-            username.as_str(),
-            &auth_digest(&hash),
-            &remote_addr.ip(),
-        )
-        .await;
-
-    let resp = shared.0.http_client.get(url).send().await?;
-
-    match resp.status() {
-        StatusCode::OK => {}
-        StatusCode::NO_CONTENT => {
-            let reason = Text::translate(keys::MULTIPLAYER_DISCONNECT_UNVERIFIED_USERNAME, []);
-            io.send_packet(&LoginDisconnectS2c {
-                reason: reason.into(),
-            })
-            .await?;
-            bail!("session server could not verify username");
-        }
-        status => {
-            bail!("session server GET request failed (status code {status})");
-        }
-    }
-
-    #[derive(Deserialize)]
-    struct GameProfile {
-        id: Uuid,
-        name: String,
-        properties: Vec<Property>,
-    }
-
-    let profile: GameProfile = resp.json().await.context("parsing game profile")?;
+        .authenticator
+        .authenticate(shared, &request)
+        .await?
+    else {
+        let reason = Text::translate(keys::MULTIPLAYER_DISCONNECT_UNVERIFIED_USERNAME, []);
+        io.send_packet(&LoginDisconnectS2c {
+            reason: reason.into(),
+        })
+        .await?;
+        bail!("could not verify username");
+    };
 
     ensure!(profile.name == username, "usernames do not match");
 
@@ -413,10 +514,6 @@ async fn login_online(
     })
 }
 
-fn auth_digest(bytes: &[u8]) -> String {
-    BigInt::from_signed_bytes_be(bytes).to_str_radix(16)
-}
-
 fn offline_uuid(username: &str) -> anyhow::Result<Uuid> {
     Uuid::from_slice(&Sha256::digest(username)[..16]).map_err(Into::into)
 }
@@ -476,16 +573,13 @@ async fn login_velocity(
     username: String,
     velocity_secret: &str,
 ) -> anyhow::Result<NewClientInfo> {
-    const VELOCITY_MIN_SUPPORTED_VERSION: u8 = 1;
-    const VELOCITY_MODERN_FORWARDING_WITH_KEY_V2: i32 = 3;
-
     let message_id: i32 = 0; // TODO: make this random?
 
     // Send Player Info Request into the Plugin Channel
     io.send_packet(&LoginQueryRequestS2c {
         message_id: VarInt(message_id),
         channel: ident!("velocity:player_info").into(),
-        data: RawBytes(&[VELOCITY_MIN_SUPPORTED_VERSION]).into(),
+        data: RawBytes(&[velocity::MIN_SUPPORTED_VERSION]).into(),
     })
     .await?;
 
@@ -503,66 +597,130 @@ async fn login_velocity(
         .context("missing plugin response data")?
         .0;
 
-    ensure!(data.len() >= 32, "invalid plugin response data length");
-    let (signature, mut data_without_signature) = data.split_at(32);
-
-    // Verify signature
-    let mut mac = Hmac::<Sha256>::new_from_slice(velocity_secret.as_bytes())?;
-    Mac::update(&mut mac, data_without_signature);
-    mac.verify_slice(signature)?;
-
-    // Check Velocity version
-    let version = VarInt::decode(&mut data_without_signature)
-        .context("failed to decode velocity version")?
-        .0;
-
-    // Get client address
-    let remote_addr = String::decode(&mut data_without_signature)?.parse()?;
-
-    // Get UUID
-    let uuid = Uuid::decode(&mut data_without_signature)?;
-
-    // Get username and validate
-    ensure!(
-        username == <&str>::decode(&mut data_without_signature)?,
-        "mismatched usernames"
-    );
+    let info = PlayerInfo::decode_signed(data.0, velocity_secret)?;
 
-    // Read game profile properties
-    let properties = Vec::<Property>::decode(&mut data_without_signature)
-        .context("decoding velocity game profile properties")?;
+    // Validate username
+    ensure!(username == info.username, "mismatched usernames");
 
-    if version >= VELOCITY_MODERN_FORWARDING_WITH_KEY_V2 {
+    if info.version >= velocity::MODERN_FORWARDING_WITH_KEY_V2 {
         // TODO
     }
 
     Ok(NewClientInfo {
-        uuid,
+        uuid: info.uuid,
         username,
-        properties: Properties(properties),
-        ip: remote_addr,
+        properties: Properties(info.properties),
+        ip: info.ip,
     })
 }
 
 #[cfg(test)]
 mod tests {
-    use sha1::Digest;
+    use std::net::Ipv4Addr;
+
+    use rand::rngs::OsRng;
+    use rsa::RsaPublicKey;
+    use tokio::io::DuplexStream;
+    use valence_server::protocol::decode::PacketFrame;
+    use valence_server::protocol::packets::login::LoginKeyC2s;
+    use valence_server::protocol::Packet;
 
     use super::*;
+    use crate::auth::{AuthRequest, Authenticator, GameProfile};
+    use crate::{async_trait, NetworkSettings};
+
+    const SHARED_SECRET: [u8; 16] = [7; 16];
+
+    /// Knows a single player.
+    struct MockAuthenticator;
+
+    #[async_trait]
+    impl Authenticator for MockAuthenticator {
+        async fn authenticate(
+            &self,
+            _shared: &SharedNetworkState,
+            request: &AuthRequest<'_>,
+        ) -> anyhow::Result<Option<GameProfile>> {
+            assert_eq!(request.shared_secret, SHARED_SECRET);
+
+            Ok((request.username == "Steve").then(|| GameProfile {
+                id: Uuid::from_u128(42),
+                name: "Steve".into(),
+                properties: vec![],
+            }))
+        }
+    }
+
+    /// Logs in as `username` in online mode and returns the packet ending the
+    /// login.
+    async fn client(stream: DuplexStream, username: String, key: RsaPublicKey) -> PacketFrame {
+        let mut io = PacketIo::new(Box::new(stream), PacketEncoder::new(), PacketDecoder::new());
+
+        io.send_packet(&LoginHelloC2s {
+            username: username.as_str().into(),
+            profile_id: None,
+        })
+        .await
+        .unwrap();
+
+        let hello = io.recv_packet::<LoginHelloS2c>().await.unwrap();
+        let verify_token = hello.verify_token.to_vec();
+
+        let shared_secret = key
+            .encrypt(&mut OsRng, Pkcs1v15Encrypt, &SHARED_SECRET)
+            .unwrap();
+        let verify_token = key
+            .encrypt(&mut OsRng, Pkcs1v15Encrypt, &verify_token)
+            .unwrap();
+
+        io.send_packet(&LoginKeyC2s {
+            shared_secret: &shared_secret,
+            verify_token: &verify_token,
+        })
+        .await
+        .unwrap();
+
+        io.enable_encryption(&SHARED_SECRET);
+
+        io.recv_frame().await.unwrap()
+    }
+
+    async fn login(shared: &SharedNetworkState, username: &str) -> PacketFrame {
+        let (server, client_stream) = tokio::io::duplex(4096);
+        let key = shared.0.rsa_key.to_public_key();
+        let client = tokio::spawn(client(client_stream, username.to_owned(), key));
+
+        let mut io = PacketIo::new(Box::new(server), PacketEncoder::new(), PacketDecoder::new());
+        let remote_addr = (Ipv4Addr::LOCALHOST, 1234).into();
+        let handshake = HandshakeData {
+            protocol_version: PROTOCOL_VERSION,
+            ..Default::default()
+        };
+
+        let _ = handle_login(shared, &mut io, remote_addr, handshake).await;
+
+        // Closes the connection so that the client fails instead of waiting
+        // forever if the login failed early.
+        drop(io);
+
+        client.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn custom_authenticator() {
+        let settings = NetworkSettings {
+            tokio_handle: Some(tokio::runtime::Handle::current()),
+            authenticator: Arc::new(MockAuthenticator),
+            ..Default::default()
+        };
+
+        let shared = SharedNetworkState::new(&settings, Default::default()).unwrap();
+
+        let frame = login(&shared, "Steve").await;
+        let success = frame.decode::<LoginSuccessS2c>().unwrap();
+        assert_eq!(success.uuid, Uuid::from_u128(42));
 
-    #[test]
-    fn auth_digest_usernames() {
-        assert_eq!(
-            auth_digest(&Sha1::digest("Notch")),
-            "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48"
-        );
-        assert_eq!(
-            auth_digest(&Sha1::digest("jeb_")),
-            "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1"
-        );
-        assert_eq!(
-            auth_digest(&Sha1::digest("simon")),
-            "88e16a1019277b15d58faf0541e11910eb756f6"
-        );
+        let frame = login(&shared, "Alex").await;
+        assert_eq!(frame.id, LoginDisconnectS2c::ID);
     }
 }
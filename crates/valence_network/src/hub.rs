@@ -0,0 +1,455 @@
+//! A hub mode which moves clients between backend Valence servers without a
+//! proxy.
+//!
+//! In hub mode, the server authenticates clients as usual but does not spawn
+//! them. Instead, it keeps the connection of every client and forwards it to a
+//! backend server over a local socket, logging in with Velocity's modern
+//! forwarding. Backends are regular Valence servers using
+//! [`ConnectionMode::Velocity`] with the same secret as
+//! [`HubSettings::forwarding_secret`].
+//!
+//! A backend moves a client to another backend by calling [`transfer`]. The hub
+//! then logs into the new backend and replays its [`GameJoinS2c`] to the client
+//! followed by a respawn, after removing the tab list entries and boss bars of
+//! the old backend. Scoreboard objectives and teams are not cleared when
+//! switching.
+//!
+//! [`ConnectionMode::Velocity`]: crate::ConnectionMode::Velocity
+
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use anyhow::{bail, ensure, Context};
+use tokio::net::TcpStream;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+use valence_server::protocol::decode::PacketFrame;
+use valence_server::protocol::packets::handshaking::handshake_c2s::HandshakeNextState;
+use valence_server::protocol::packets::handshaking::HandshakeC2s;
+use valence_server::protocol::packets::login::{
+    LoginCompressionS2c, LoginDisconnectS2c, LoginHelloC2s, LoginHelloS2c, LoginQueryRequestS2c,
+    LoginQueryResponseC2s, LoginSuccessS2c,
+};
+use valence_server::protocol::packets::play::boss_bar_s2c::BossBarAction;
+use valence_server::protocol::packets::play::{
+    BossBarS2c, ClientSettingsC2s, CustomPayloadS2c, DisconnectS2c, GameJoinS2c, GameMessageS2c,
+    PlayerListS2c, PlayerRemoveS2c, PlayerRespawnS2c,
+};
+use valence_server::protocol::{
+    Packet, PacketDecoder, PacketEncoder, RawBytes, VarInt, WritePacket,
+};
+use valence_server::text::{Color, IntoText};
+use valence_server::{ident, CompressionThreshold, Ident, PROTOCOL_VERSION};
+
+use crate::packet_io::PacketIo;
+use crate::velocity::{self, PlayerInfo};
+use crate::{CleanupOnDrop, NewClientInfo};
+
+/// The channel of the plugin message a backend sends to move a client to
+/// another backend. The data of the message is the name of the backend.
+pub const HUB_CHANNEL: Ident<&str> = ident!("valence:hub");
+
+/// Settings for hub mode. See [`NetworkSettings::hub`].
+///
+/// [`NetworkSettings::hub`]: crate::NetworkSettings::hub
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HubSettings {
+    /// The addresses of the backend servers by name.
+    pub backends: HashMap<String, SocketAddr>,
+    /// The name of the backend clients are sent to after logging in.
+    pub default_backend: String,
+    /// The secret shared with the backends for Velocity's modern forwarding.
+    pub forwarding_secret: String,
+}
+
+/// Asks the hub to move a client to the backend named `backend`. This is
+/// called on the backend server, usually with a [`Client`].
+///
+/// If the hub fails to connect to the backend, the client stays on the current
+/// backend and is sent an error message.
+///
+/// [`Client`]: valence_server::client::Client
+pub fn transfer<W: WritePacket>(mut client: W, backend: &str) {
+    client.write_packet(&CustomPayloadS2c {
+        channel: HUB_CHANNEL.into(),
+        data: RawBytes(backend.as_bytes()).into(),
+    });
+}
+
+/// Forwards a logged in client to the backends until either side disconnects.
+pub(crate) async fn run_session(
+    hub: Arc<HubSettings>,
+    client: PacketIo,
+    info: NewClientInfo,
+    cleanup: CleanupOnDrop,
+) {
+    let username = info.username.clone();
+
+    let mut session = Session {
+        hub,
+        info,
+        client,
+        client_settings: None,
+        player_list: HashSet::new(),
+        boss_bars: HashSet::new(),
+    };
+
+    if let Err(e) = session.run().await {
+        debug!("hub session of {username} ended: {e:#}");
+    }
+
+    drop(cleanup);
+}
+
+struct Session {
+    hub: Arc<HubSettings>,
+    info: NewClientInfo,
+    client: PacketIo,
+    /// The last client settings packet, which is sent to new backends.
+    client_settings: Option<PacketFrame>,
+    /// Tab list entries and boss bars added by the current backend.
+    player_list: HashSet<Uuid>,
+    boss_bars: HashSet<Uuid>,
+}
+
+impl Session {
+    async fn run(&mut self) -> anyhow::Result<()> {
+        let name = self.hub.default_backend.clone();
+
+        let mut backend = match self.connect(&name).await {
+            Ok((backend, join)) => {
+                self.client.send_frame(&join).await?;
+                backend
+            }
+            Err(e) => {
+                warn!(
+                    "failed to connect {} to backend {name}: {e:#}",
+                    self.info.username
+                );
+                return self.disconnect("Could not connect to the server").await;
+            }
+        };
+
+        info!("{} joined backend {name}", self.info.username);
+
+        loop {
+            tokio::select! {
+                frame = self.client.recv_frame() => {
+                    let frame = frame?;
+
+                    if frame.id == ClientSettingsC2s::ID {
+                        self.client_settings = Some(frame.clone());
+                    }
+
+                    if let Err(e) = backend.send_frame(&frame).await {
+                        debug!("error sending to backend: {e:#}");
+                        return self.disconnect("Lost connection to the server").await;
+                    }
+                }
+                frame = backend.recv_frame() => {
+                    let frame = match frame {
+                        Ok(frame) => frame,
+                        Err(e) => {
+                            debug!("error receiving from backend: {e:#}");
+                            return self.disconnect("Lost connection to the server").await;
+                        }
+                    };
+
+                    if frame.id == CustomPayloadS2c::ID {
+                        let payload = frame.decode::<CustomPayloadS2c>()?;
+
+                        if payload.channel == HUB_CHANNEL {
+                            let name = String::from_utf8(payload.data.0 .0.to_vec())
+                                .context("invalid backend name")?;
+
+                            if let Some(new_backend) = self.switch(&name).await? {
+                                backend = new_backend;
+                            }
+
+                            continue;
+                        }
+                    }
+
+                    self.track(&frame)?;
+                    self.client.send_frame(&frame).await?;
+                }
+            }
+        }
+    }
+
+    /// Logs into a backend and returns the connection along with the frame of
+    /// its [`GameJoinS2c`] packet.
+    async fn connect(&mut self, name: &str) -> anyhow::Result<(PacketIo, PacketFrame)> {
+        let addr = *self
+            .hub
+            .backends
+            .get(name)
+            .with_context(|| format!("unknown backend \"{name}\""))?;
+
+        let stream = TcpStream::connect(addr).await?;
+        stream.set_nodelay(true)?;
+
+        let mut io = PacketIo::new(Box::new(stream), PacketEncoder::new(), PacketDecoder::new());
+
+        io.send_packet(&HandshakeC2s {
+            protocol_version: VarInt(PROTOCOL_VERSION),
+            server_address: addr.ip().to_string().as_str().into(),
+            server_port: addr.port(),
+            next_state: HandshakeNextState::Login,
+        })
+        .await?;
+
+        io.send_packet(&LoginHelloC2s {
+            username: self.info.username.as_str().into(),
+            profile_id: Some(self.info.uuid),
+        })
+        .await?;
+
+        loop {
+            let frame = io.recv_frame().await?;
+
+            match frame.id {
+                LoginCompressionS2c::ID => {
+                    let pkt = frame.decode::<LoginCompressionS2c>()?;
+                    io.set_compression(CompressionThreshold(pkt.threshold.0));
+                }
+                LoginQueryRequestS2c::ID => {
+                    let pkt = frame.decode::<LoginQueryRequestS2c>()?;
+
+                    let data = if pkt.channel.as_str() == "velocity:player_info" {
+                        Some(
+                            PlayerInfo {
+                                version: velocity::MIN_SUPPORTED_VERSION.into(),
+                                ip: self.info.ip,
+                                uuid: self.info.uuid,
+                                username: self.info.username.clone(),
+                                properties: self.info.properties.0.clone(),
+                            }
+                            .encode_signed(&self.hub.forwarding_secret)?,
+                        )
+                    } else {
+                        None
+                    };
+
+                    io.send_packet(&LoginQueryResponseC2s {
+                        message_id: pkt.message_id,
+                        data: data.as_deref().map(|data| RawBytes(data).into()),
+                    })
+                    .await?;
+                }
+                LoginSuccessS2c::ID => break,
+                LoginDisconnectS2c::ID => {
+                    let pkt = frame.decode::<LoginDisconnectS2c>()?;
+                    bail!("disconnected by backend: {}", pkt.reason);
+                }
+                LoginHelloS2c::ID => bail!("backend is not using velocity forwarding"),
+                id => bail!("unexpected packet with ID {id} during login"),
+            }
+        }
+
+        let join = io.recv_frame().await?;
+
+        ensure!(
+            join.id == GameJoinS2c::ID,
+            "expected game join packet, got packet with ID {}",
+            join.id
+        );
+
+        if let Some(settings) = &self.client_settings {
+            io.send_frame(settings).await?;
+        }
+
+        Ok((io, join))
+    }
+
+    /// Moves the client to another backend. Returns `None` and keeps the
+    /// client on the current backend if the new one could not be joined.
+    async fn switch(&mut self, name: &str) -> anyhow::Result<Option<PacketIo>> {
+        let (backend, join) = match self.connect(name).await {
+            Ok(backend) => backend,
+            Err(e) => {
+                warn!(
+                    "failed to transfer {} to backend {name}: {e:#}",
+                    self.info.username
+                );
+
+                self.client
+                    .send_packet(&GameMessageS2c {
+                        chat: format!("Could not connect to \"{name}\"")
+                            .color(Color::RED)
+                            .into(),
+                        overlay: false,
+                    })
+                    .await?;
+
+                return Ok(None);
+            }
+        };
+
+        write_switch(
+            self.client.encoder(),
+            &join.decode()?,
+            &mut self.player_list,
+            &mut self.boss_bars,
+        );
+        self.client.flush().await?;
+
+        info!("transferred {} to backend {name}", self.info.username);
+
+        Ok(Some(backend))
+    }
+
+    /// Keeps track of what the current backend adds to the client that must be
+    /// removed when switching backends.
+    fn track(&mut self, frame: &PacketFrame) -> anyhow::Result<()> {
+        match frame.id {
+            PlayerListS2c::ID => {
+                let pkt = frame.decode::<PlayerListS2c>()?;
+
+                if pkt.actions.add_player() {
+                    self.player_list
+                        .extend(pkt.entries.iter().map(|entry| entry.player_uuid));
+                }
+            }
+            PlayerRemoveS2c::ID => {
+                for uuid in frame.decode::<PlayerRemoveS2c>()?.uuids.iter() {
+                    self.player_list.remove(uuid);
+                }
+            }
+            BossBarS2c::ID => {
+                let pkt = frame.decode::<BossBarS2c>()?;
+
+                match pkt.action {
+                    BossBarAction::Add { .. } => {
+                        self.boss_bars.insert(pkt.id);
+                    }
+                    BossBarAction::Remove => {
+                        self.boss_bars.remove(&pkt.id);
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    async fn disconnect(&mut self, reason: &'static str) -> anyhow::Result<()> {
+        self.client
+            .send_packet(&DisconnectS2c {
+                reason: reason.color(Color::RED).into(),
+            })
+            .await
+    }
+}
+
+/// Writes the packets that move a client from the world of the old backend
+/// into the world described by the game join packet of the new one.
+///
+/// Like Velocity, the game join packet of the new backend is sent to the client
+/// again, which resets its registries and entity ID, followed by a respawn.
+fn write_switch<W: WritePacket>(
+    mut w: W,
+    join: &GameJoinS2c,
+    player_list: &mut HashSet<Uuid>,
+    boss_bars: &mut HashSet<Uuid>,
+) {
+    if !player_list.is_empty() {
+        w.write_packet(&PlayerRemoveS2c {
+            uuids: player_list.drain().collect::<Vec<_>>().into(),
+        });
+    }
+
+    for id in boss_bars.drain() {
+        w.write_packet(&BossBarS2c {
+            id,
+            action: BossBarAction::Remove,
+        });
+    }
+
+    w.write_packet(join);
+
+    w.write_packet(&PlayerRespawnS2c {
+        dimension_type_name: join.dimension_type_name.clone(),
+        dimension_name: join.dimension_name.clone(),
+        hashed_seed: join.hashed_seed as u64,
+        game_mode: join.game_mode,
+        previous_game_mode: join.previous_game_mode,
+        is_debug: join.is_debug,
+        is_flat: join.is_flat,
+        copy_metadata: false,
+        last_death_location: join.last_death_location.clone(),
+        portal_cooldown: join.portal_cooldown,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::collections::BTreeSet;
+
+    use valence_server::nbt::Compound;
+    use valence_server::protocol::packets::play::game_join_s2c::GameJoinS2c;
+    use valence_server::protocol::GameMode;
+
+    use super::*;
+
+    #[test]
+    fn switch_packets() {
+        let join = GameJoinS2c {
+            entity_id: 0,
+            is_hardcore: false,
+            game_mode: GameMode::Creative,
+            previous_game_mode: Default::default(),
+            dimension_names: Cow::Owned(BTreeSet::new()),
+            registry_codec: Cow::Owned(Compound::new()),
+            dimension_type_name: ident!("overworld").into(),
+            dimension_name: ident!("lobby").into(),
+            hashed_seed: -1,
+            max_players: VarInt(20),
+            view_distance: VarInt(8),
+            simulation_distance: VarInt(6),
+            reduced_debug_info: false,
+            enable_respawn_screen: true,
+            is_debug: false,
+            is_flat: true,
+            last_death_location: None,
+            portal_cooldown: VarInt(0),
+        };
+
+        let mut player_list = HashSet::from([Uuid::from_u128(1), Uuid::from_u128(2)]);
+        let mut boss_bars = HashSet::from([Uuid::from_u128(3)]);
+
+        let mut enc = PacketEncoder::new();
+        write_switch(&mut enc, &join, &mut player_list, &mut boss_bars);
+
+        assert!(player_list.is_empty());
+        assert!(boss_bars.is_empty());
+
+        let mut dec = PacketDecoder::new();
+        dec.queue_bytes(enc.take());
+
+        let mut next = || dec.try_next_packet().unwrap().unwrap();
+
+        assert_eq!(next().decode::<PlayerRemoveS2c>().unwrap().uuids.len(), 2);
+
+        let frame = next();
+        let boss_bar = frame.decode::<BossBarS2c>().unwrap();
+        assert_eq!(boss_bar.id, Uuid::from_u128(3));
+        assert_eq!(boss_bar.action, BossBarAction::Remove);
+
+        let frame = next();
+        let join = frame.decode::<GameJoinS2c>().unwrap();
+        assert_eq!(join.dimension_name, ident!("lobby"));
+
+        let frame = next();
+        let respawn = frame.decode::<PlayerRespawnS2c>().unwrap();
+        assert_eq!(respawn.dimension_name, ident!("lobby"));
+        assert_eq!(respawn.game_mode, GameMode::Creative);
+        assert_eq!(respawn.hashed_seed, u64::MAX);
+
+        assert!(dec.try_next_packet().unwrap().is_none());
+    }
+}
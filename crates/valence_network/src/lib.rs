@@ -1,25 +1,40 @@
 #![doc = include_str!("../README.md")]
 
+pub mod auth;
 mod byte_channel;
 mod connect;
+pub mod hub;
 mod legacy_ping;
+mod login_plugin;
 mod packet_io;
+pub mod proxy;
+mod rate_limit;
+pub mod replay;
+pub mod transport;
+mod velocity;
 
 use std::borrow::Cow;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::Context;
 pub use async_trait::async_trait;
+use auth::{Authenticator, SessionServerAuthenticator};
 use bevy_app::prelude::*;
 use bevy_ecs::prelude::*;
 use connect::do_accept_loop;
 pub use connect::HandshakeData;
 use flume::{Receiver, Sender};
+use hub::HubSettings;
 pub use legacy_ping::{ServerListLegacyPingPayload, ServerListLegacyPingResponse};
+pub use login_plugin::{LoginPluginQueries, LoginPluginResponse};
 use rand::rngs::OsRng;
+use rate_limit::RateLimiter;
+pub use rate_limit::{Rate, RateLimit, RateLimitKind, RateLimits};
+use replay::Replay;
 use rsa::traits::PublicKeyParts;
 use rsa::RsaPrivateKey;
 use serde::Serialize;
@@ -28,9 +43,12 @@ use tokio::runtime::{Handle, Runtime};
 use tokio::sync::Semaphore;
 use tokio::time;
 use tracing::error;
+use transport::{TcpTransport, Transport};
 use uuid::Uuid;
 use valence_protocol::text::IntoText;
+use valence_protocol::translate::Translators;
 use valence_server::client::{ClientBundle, ClientBundleArgs, Properties, SpawnClientsSet};
+use valence_server::shutdown::ShuttingDown;
 use valence_server::{CompressionThreshold, Server, Text, MINECRAFT_VERSION, PROTOCOL_VERSION};
 
 pub struct NetworkPlugin;
@@ -54,55 +72,13 @@ fn build_plugin(app: &mut App) -> anyhow::Result<()> {
         .world_mut()
         .get_resource_or_insert_with(NetworkSettings::default);
 
-    let (new_clients_send, new_clients_recv) = flume::bounded(64);
-
-    let rsa_key = RsaPrivateKey::new(&mut OsRng, 1024)?;
-
-    let public_key_der =
-        rsa_der::public_key_to_der(&rsa_key.n().to_bytes_be(), &rsa_key.e().to_bytes_be())
-            .into_boxed_slice();
-
-    #[allow(clippy::if_then_some_else_none)]
-    let runtime = if settings.tokio_handle.is_none() {
-        Some(Runtime::new()?)
-    } else {
-        None
-    };
-
-    let tokio_handle = match &runtime {
-        Some(rt) => rt.handle().clone(),
-        None => settings.tokio_handle.clone().unwrap(),
-    };
-
-    let shared = SharedNetworkState(Arc::new(SharedNetworkStateInner {
-        callbacks: settings.callbacks.clone(),
-        address: settings.address,
-        incoming_byte_limit: settings.incoming_byte_limit,
-        outgoing_byte_limit: settings.outgoing_byte_limit,
-        connection_sema: Arc::new(Semaphore::new(
-            settings.max_connections.min(Semaphore::MAX_PERMITS),
-        )),
-        player_count: AtomicUsize::new(0),
-        max_players: settings.max_players,
-        connection_mode: settings.connection_mode.clone(),
-        threshold,
-        tokio_handle,
-        _tokio_runtime: runtime,
-        new_clients_send,
-        new_clients_recv,
-        rsa_key,
-        public_key_der,
-        http_client: reqwest::Client::new(),
-    }));
+    let shared = SharedNetworkState::new(&settings, threshold)?;
 
     app.insert_resource(shared.clone());
 
     // System for starting the accept loop.
     let start_accept_loop = move |shared: Res<SharedNetworkState>| {
-        let _guard = shared.0.tokio_handle.enter();
-
-        // Start accepting new connections.
-        tokio::spawn(do_accept_loop(shared.clone()));
+        shared.start_accept_loops();
     };
 
     let start_broadcast_to_lan_loop = move |shared: Res<SharedNetworkState>| {
@@ -131,6 +107,13 @@ fn build_plugin(app: &mut App) -> anyhow::Result<()> {
     // Spawn new clients before the event loop starts.
     app.add_systems(PreUpdate, spawn_new_clients.in_set(SpawnClientsSet));
 
+    // Stop accepting connections once the server is shutting down.
+    app.add_systems(
+        PostUpdate,
+        (|shared: Res<SharedNetworkState>| shared.stop_accepting())
+            .run_if(resource_added::<ShuttingDown>),
+    );
+
     Ok(())
 }
 
@@ -138,6 +121,90 @@ fn build_plugin(app: &mut App) -> anyhow::Result<()> {
 pub struct SharedNetworkState(Arc<SharedNetworkStateInner>);
 
 impl SharedNetworkState {
+    fn new(settings: &NetworkSettings, threshold: CompressionThreshold) -> anyhow::Result<Self> {
+        let (new_clients_send, new_clients_recv) = flume::bounded(64);
+
+        let rsa_key = RsaPrivateKey::new(&mut OsRng, 1024)?;
+
+        let public_key_der =
+            rsa_der::public_key_to_der(&rsa_key.n().to_bytes_be(), &rsa_key.e().to_bytes_be())
+                .into_boxed_slice();
+
+        #[allow(clippy::if_then_some_else_none)]
+        let runtime = if settings.tokio_handle.is_none() {
+            Some(Runtime::new()?)
+        } else {
+            None
+        };
+
+        let tokio_handle = match &runtime {
+            Some(rt) => rt.handle().clone(),
+            None => settings.tokio_handle.clone().unwrap(),
+        };
+
+        let replay = settings
+            .replay
+            .as_deref()
+            .map(Replay::load)
+            .transpose()?
+            .map(Arc::new);
+
+        // Recorded packets can only be replayed with the compression threshold
+        // they were written with.
+        let threshold = replay.as_ref().map_or(threshold, |r| r.header.threshold);
+
+        Ok(Self(Arc::new(SharedNetworkStateInner {
+            callbacks: settings.callbacks.clone(),
+            address: settings.address,
+            incoming_byte_limit: settings.incoming_byte_limit,
+            outgoing_byte_limit: settings.outgoing_byte_limit,
+            connection_sema: Arc::new(Semaphore::new(
+                settings.max_connections.min(Semaphore::MAX_PERMITS),
+            )),
+            player_count: AtomicUsize::new(0),
+            max_players: settings.max_players,
+            connection_mode: settings.connection_mode.clone(),
+            transports: settings.transports.clone(),
+            translators: settings.translators.clone(),
+            rate_limiter: RateLimiter::new(settings.rate_limits.clone()),
+            hub: settings.hub.clone().map(Arc::new),
+            replay,
+            authenticator: settings.authenticator.clone(),
+            threshold,
+            tokio_handle,
+            _tokio_runtime: runtime,
+            new_clients_send,
+            new_clients_recv,
+            rsa_key,
+            public_key_der,
+            http_client: reqwest::Client::new(),
+        })))
+    }
+
+    /// Starts accepting connections on every transport.
+    fn start_accept_loops(&self) {
+        let _guard = self.0.tokio_handle.enter();
+
+        tokio::spawn(do_accept_loop(
+            self.clone(),
+            Arc::new(TcpTransport::new(self.0.address)),
+        ));
+
+        for transport in &self.0.transports {
+            tokio::spawn(do_accept_loop(self.clone(), transport.clone()));
+        }
+    }
+
+    /// Stops accepting new connections on every transport. Connections that
+    /// were accepted before are not affected.
+    ///
+    /// This is done automatically when the server is [shutting down].
+    ///
+    /// [shutting down]: valence_server::shutdown::ShuttingDown
+    pub fn stop_accepting(&self) {
+        self.0.connection_sema.close();
+    }
+
     pub fn connection_mode(&self) -> &ConnectionMode {
         &self.0.connection_mode
     }
@@ -149,10 +216,31 @@ impl SharedNetworkState {
     pub fn max_players(&self) -> usize {
         self.0.max_players
     }
+
+    pub fn translators(&self) -> &Translators {
+        &self.0.translators
+    }
+
+    /// Temporarily bans an IP address. Connections from the address are closed
+    /// immediately until the ban expires. Clients that already joined are not
+    /// affected.
+    pub fn ban_ip(&self, ip: IpAddr, duration: Duration) {
+        self.0.rate_limiter.ban(ip, Instant::now() + duration);
+    }
+
+    /// Lifts the ban of an IP address. Returns whether the address was banned.
+    pub fn unban_ip(&self, ip: IpAddr) -> bool {
+        self.0.rate_limiter.unban(ip)
+    }
+
+    pub fn is_ip_banned(&self, ip: IpAddr) -> bool {
+        self.0.rate_limiter.is_banned(ip, Instant::now())
+    }
 }
 struct SharedNetworkStateInner {
     callbacks: ErasedNetworkCallbacks,
     address: SocketAddr,
+    transports: Vec<Arc<dyn Transport>>,
     incoming_byte_limit: usize,
     outgoing_byte_limit: usize,
     /// Limits the number of simultaneous connections to the server before the
@@ -162,6 +250,11 @@ struct SharedNetworkStateInner {
     player_count: AtomicUsize,
     max_players: usize,
     connection_mode: ConnectionMode,
+    translators: Translators,
+    rate_limiter: RateLimiter,
+    hub: Option<Arc<HubSettings>>,
+    replay: Option<Arc<Replay>>,
+    authenticator: Arc<dyn Authenticator>,
     threshold: CompressionThreshold,
     tokio_handle: Handle,
     // Holding a runtime handle is not enough to keep tokio working. We need
@@ -226,6 +319,13 @@ pub struct NetworkSettings {
     ///
     /// `0.0.0.0:25565`, which will listen on every available network interface.
     pub address: SocketAddr,
+    /// Transports to accept connections from in addition to the TCP listener
+    /// bound to [`address`](Self::address).
+    ///
+    /// # Default Value
+    ///
+    /// Empty.
+    pub transports: Vec<Arc<dyn Transport>>,
     /// The connection mode. This determines if client authentication and
     /// encryption should take place and if the server should get the player
     /// data from a proxy.
@@ -258,6 +358,47 @@ pub struct NetworkSettings {
     ///
     /// The default value is left unspecified and may change in future versions.
     pub outgoing_byte_limit: usize,
+    /// Translators for clients on protocol versions other than
+    /// [`PROTOCOL_VERSION`]. Clients on a version without a translator are
+    /// disconnected during login.
+    ///
+    /// # Default Value
+    ///
+    /// [`Translators::bundled`], so clients on 1.19.4 can join as well.
+    pub translators: Translators,
+    /// Limits on the rate of handshakes, status requests and login attempts,
+    /// and the temporary bans of IP addresses exceeding them.
+    ///
+    /// # Default Value
+    ///
+    /// No limits. [`RateLimits::recommended`] provides limits suitable for a
+    /// server exposed directly to the internet.
+    pub rate_limits: RateLimits,
+    /// Enables hub mode, where clients are forwarded to backend servers
+    /// instead of being spawned on this server. See the [`hub`] module.
+    ///
+    /// # Default Value
+    ///
+    /// `None`
+    pub hub: Option<HubSettings>,
+    /// Enables replay mode, where every client is sent the recording at this
+    /// path instead of being spawned on this server. The compression threshold
+    /// of the recording is used in place of the one from the server. See the
+    /// [`replay`] module.
+    ///
+    /// # Default Value
+    ///
+    /// `None`
+    pub replay: Option<PathBuf>,
+    /// Verifies the accounts of players in [online mode].
+    ///
+    /// # Default Value
+    ///
+    /// [`SessionServerAuthenticator`], which uses the session server returned
+    /// by [`NetworkCallbacks::session_server`].
+    ///
+    /// [online mode]: ConnectionMode::Online
+    pub authenticator: Arc<dyn Authenticator>,
 }
 
 impl Default for NetworkSettings {
@@ -268,11 +409,17 @@ impl Default for NetworkSettings {
             max_connections: 1024,
             max_players: 20,
             address: SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), 25565).into(),
+            transports: vec![],
             connection_mode: ConnectionMode::Online {
                 prevent_proxy_connections: false,
             },
             incoming_byte_limit: 2097152, // 2 MiB
             outgoing_byte_limit: 8388608, // 8 MiB
+            translators: Translators::bundled(),
+            rate_limits: RateLimits::default(),
+            hub: None,
+            replay: None,
+            authenticator: Arc::new(SessionServerAuthenticator),
         }
     }
 }
@@ -309,6 +456,38 @@ impl<T: NetworkCallbacks> From<T> for ErasedNetworkCallbacks {
 /// This trait uses [`mod@async_trait`].
 #[async_trait]
 pub trait NetworkCallbacks: Send + Sync + 'static {
+    /// Called for each new connection to determine if it is allowed. This is the
+    /// place to check custom blocklists. Connections from temporarily banned
+    /// IP addresses are closed before this is called.
+    ///
+    /// This function is called from within a tokio runtime.
+    ///
+    /// # Default Implementation
+    ///
+    /// All connections are allowed.
+    async fn allow_connection(&self, shared: &SharedNetworkState, remote_addr: SocketAddr) -> bool {
+        #![allow(unused_variables)]
+
+        true
+    }
+
+    /// Called when a connection is closed because it exceeded one of the
+    /// configured [`RateLimits`].
+    ///
+    /// This function is called from within a tokio runtime.
+    ///
+    /// # Default Implementation
+    ///
+    /// Does nothing.
+    async fn rate_limited(
+        &self,
+        shared: &SharedNetworkState,
+        remote_addr: SocketAddr,
+        kind: RateLimitKind,
+    ) {
+        #![allow(unused_variables)]
+    }
+
     /// Called when the server receives a Server List Ping query.
     /// Data for the response can be provided or the query can be ignored.
     ///
@@ -316,7 +495,8 @@ pub trait NetworkCallbacks: Send + Sync + 'static {
     ///
     /// # Default Implementation
     ///
-    /// A default placeholder response is returned.
+    /// A default placeholder response is returned. The version is the client's
+    /// if a translator is registered for it.
     async fn server_list_ping(
         &self,
         shared: &SharedNetworkState,
@@ -325,14 +505,23 @@ pub trait NetworkCallbacks: Send + Sync + 'static {
     ) -> ServerListPing {
         #![allow(unused_variables)]
 
+        let (version_name, protocol) =
+            match shared.translators().get(handshake_data.protocol_version) {
+                Some(translator) => (
+                    translator.minecraft_version().to_owned(),
+                    translator.protocol_version(),
+                ),
+                None => (MINECRAFT_VERSION.to_owned(), PROTOCOL_VERSION),
+            };
+
         ServerListPing::Respond {
             online_players: shared.player_count().load(Ordering::Relaxed) as i32,
             max_players: shared.max_players() as i32,
             player_sample: vec![],
             description: "A Valence Server".into_text(),
             favicon_png: &[],
-            version_name: MINECRAFT_VERSION.to_owned(),
-            protocol: PROTOCOL_VERSION,
+            version_name,
+            protocol,
         }
     }
 
@@ -400,6 +589,34 @@ pub trait NetworkCallbacks: Send + Sync + 'static {
         BroadcastToLan::Disabled
     }
 
+    /// Called for each client (after successful authentication if online mode
+    /// is enabled) to exchange login plugin messages with it before
+    /// [`login`](Self::login) is called. This can be used for mod handshakes or
+    /// custom authentication.
+    ///
+    /// Requests are sent and responses are received with `queries`. If
+    /// `Err(reason)` is returned, then the client is immediately disconnected
+    /// with `reason` as the displayed message.
+    ///
+    /// The whole login process, including these queries, must complete within
+    /// five seconds of the client connecting.
+    ///
+    /// This method is called from within a tokio runtime.
+    ///
+    /// # Default Implementation
+    ///
+    /// No requests are sent.
+    async fn login_plugin_queries(
+        &self,
+        shared: &SharedNetworkState,
+        info: &NewClientInfo,
+        queries: &mut LoginPluginQueries<'_>,
+    ) -> Result<(), Text> {
+        #![allow(unused_variables)]
+
+        Ok(())
+    }
+
     /// Called for each client (after successful authentication if online mode
     /// is enabled) to determine if they can join the server.
     /// - If `Err(reason)` is returned, then the client is immediately
@@ -453,7 +670,8 @@ pub trait NetworkCallbacks: Send + Sync + 'static {
 
     /// Called upon every client login to obtain the full URL to use for session
     /// server requests. This is done to authenticate player accounts. This
-    /// method is not called unless [online mode] is enabled.
+    /// method is not called unless [online mode] is enabled and
+    /// [`NetworkSettings::authenticator`] is a [`SessionServerAuthenticator`].
     ///
     /// It is assumed that upon successful request, a structure matching the
     /// description in the [wiki](https://wiki.vg/Protocol_Encryption#Server) was obtained.
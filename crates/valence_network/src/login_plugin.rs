@@ -0,0 +1,190 @@
+use std::borrow::Cow;
+use std::time::Duration;
+
+use anyhow::{ensure, Context};
+use valence_protocol::packets::login::{LoginQueryRequestS2c, LoginQueryResponseC2s};
+use valence_protocol::{Ident, RawBytes, VarInt};
+
+use crate::packet_io::PacketIo;
+
+/// Sends login plugin requests to a client and receives its responses. See
+/// [`NetworkCallbacks::login_plugin_queries`].
+///
+/// [`NetworkCallbacks::login_plugin_queries`]: crate::NetworkCallbacks::login_plugin_queries
+pub struct LoginPluginQueries<'a> {
+    io: &'a mut PacketIo,
+    next_message_id: i32,
+    timeout: Duration,
+}
+
+/// The response of a client to a login plugin request.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LoginPluginResponse {
+    /// The message ID of the request this is a response to.
+    pub message_id: i32,
+    /// The data sent by the client, or `None` if the client did not understand
+    /// the request.
+    pub data: Option<Vec<u8>>,
+}
+
+impl<'a> LoginPluginQueries<'a> {
+    /// The default time to wait for a response.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+
+    pub(crate) fn new(io: &'a mut PacketIo) -> Self {
+        Self {
+            io,
+            // Message ID 0 is used for Velocity's player info request.
+            next_message_id: 1,
+            timeout: Self::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// The time [`recv`](Self::recv) waits for a response before failing.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Sends a login plugin request without waiting for the response. Returns
+    /// the message ID of the request.
+    ///
+    /// Clients respond to requests in the order they were sent, so several
+    /// requests can be sent before receiving their responses.
+    pub async fn send<'b, C>(&mut self, channel: C, data: &[u8]) -> anyhow::Result<i32>
+    where
+        C: Into<Ident<Cow<'b, str>>>,
+    {
+        let message_id = self.next_message_id;
+        self.next_message_id = self.next_message_id.wrapping_add(1);
+
+        self.io
+            .send_packet(&LoginQueryRequestS2c {
+                message_id: VarInt(message_id),
+                channel: channel.into(),
+                data: RawBytes(data).into(),
+            })
+            .await?;
+
+        Ok(message_id)
+    }
+
+    /// Waits for the next login plugin response from the client.
+    pub async fn recv(&mut self) -> anyhow::Result<LoginPluginResponse> {
+        let response: LoginQueryResponseC2s =
+            tokio::time::timeout(self.timeout, self.io.recv_packet())
+                .await
+                .context("timed out waiting for login plugin response")??;
+
+        Ok(LoginPluginResponse {
+            message_id: response.message_id.0,
+            data: response.data.map(|data| data.0 .0.to_vec()),
+        })
+    }
+
+    /// Sends a login plugin request and waits for the response. Returns `None`
+    /// if the client did not understand the request.
+    pub async fn query<'b, C>(&mut self, channel: C, data: &[u8]) -> anyhow::Result<Option<Vec<u8>>>
+    where
+        C: Into<Ident<Cow<'b, str>>>,
+    {
+        let message_id = self.send(channel, data).await?;
+        let response = self.recv().await?;
+
+        ensure!(
+            response.message_id == message_id,
+            "mismatched plugin response ID (got {}, expected {message_id})",
+            response.message_id
+        );
+
+        Ok(response.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+    use valence_protocol::{ident, PacketDecoder, PacketEncoder};
+
+    use super::*;
+    use crate::transport::TransportStream;
+
+    impl TransportStream for DuplexStream {}
+
+    /// Answers every login plugin request on `channel` by reversing its data.
+    async fn client(mut stream: DuplexStream, channel: &str) {
+        let mut enc = PacketEncoder::new();
+        let mut dec = PacketDecoder::new();
+        let mut buf = [0; 1024];
+
+        loop {
+            while let Some(frame) = dec.try_next_packet().unwrap() {
+                let request = frame.decode::<LoginQueryRequestS2c>().unwrap();
+
+                let data = (request.channel.as_str() == channel).then(|| {
+                    let mut data = request.data.0 .0.to_vec();
+                    data.reverse();
+                    data
+                });
+
+                enc.append_packet(&LoginQueryResponseC2s {
+                    message_id: request.message_id,
+                    data: data.as_deref().map(|data| RawBytes(data).into()),
+                })
+                .unwrap();
+
+                stream.write_all(&enc.take()).await.unwrap();
+            }
+
+            match stream.read(&mut buf).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => dec.queue_slice(&buf[..n]),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn login_plugin_queries() {
+        let (server, client_stream) = tokio::io::duplex(1024);
+        tokio::spawn(client(client_stream, "test:reverse"));
+
+        let mut io = PacketIo::new(Box::new(server), PacketEncoder::new(), PacketDecoder::new());
+        let mut queries = LoginPluginQueries::new(&mut io);
+
+        assert_eq!(
+            queries.query(ident!("test:reverse"), b"abc").await.unwrap(),
+            Some(b"cba".to_vec())
+        );
+        assert_eq!(
+            queries.query(ident!("test:unknown"), b"abc").await.unwrap(),
+            None
+        );
+
+        // Several requests in flight.
+        let first = queries.send(ident!("test:reverse"), b"12").await.unwrap();
+        let second = queries.send(ident!("test:reverse"), b"34").await.unwrap();
+        assert_eq!(
+            queries.recv().await.unwrap(),
+            LoginPluginResponse {
+                message_id: first,
+                data: Some(b"21".to_vec())
+            }
+        );
+        assert_eq!(queries.recv().await.unwrap().message_id, second);
+    }
+
+    #[tokio::test]
+    async fn login_plugin_query_timeout() {
+        // The client never responds.
+        let (server, _client) = tokio::io::duplex(1024);
+
+        let mut io = PacketIo::new(Box::new(server), PacketEncoder::new(), PacketDecoder::new());
+        let mut queries = LoginPluginQueries::new(&mut io);
+        queries.set_timeout(Duration::from_millis(10));
+
+        assert!(queries.query(ident!("test:reverse"), b"").await.is_err());
+    }
+}
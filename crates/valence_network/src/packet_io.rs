@@ -1,4 +1,5 @@
 use std::io::ErrorKind;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 use std::{io, mem};
@@ -6,20 +7,21 @@ use std::{io, mem};
 use anyhow::bail;
 use bytes::BytesMut;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpStream;
 use tokio::sync::Semaphore;
 use tokio::task::JoinHandle;
 use tracing::{debug, warn};
+use valence_protocol::translate::PacketTranslator;
 use valence_protocol::CompressionThreshold;
 use valence_server::client::{ClientBundleArgs, ClientConnection, ReceivedPacket};
 use valence_server::protocol::decode::PacketFrame;
 use valence_server::protocol::{Decode, Encode, Packet, PacketDecoder, PacketEncoder};
 
 use crate::byte_channel::{byte_channel, ByteSender, TrySendError};
+use crate::transport::TransportStream;
 use crate::{CleanupOnDrop, NewClientInfo};
 
 pub(crate) struct PacketIo {
-    stream: TcpStream,
+    stream: Box<dyn TransportStream>,
     enc: PacketEncoder,
     dec: PacketDecoder,
     frame: PacketFrame,
@@ -28,7 +30,11 @@ pub(crate) struct PacketIo {
 const READ_BUF_SIZE: usize = 4096;
 
 impl PacketIo {
-    pub(crate) fn new(stream: TcpStream, enc: PacketEncoder, dec: PacketDecoder) -> Self {
+    pub(crate) fn new(
+        stream: Box<dyn TransportStream>,
+        enc: PacketEncoder,
+        dec: PacketDecoder,
+    ) -> Self {
         Self {
             stream,
             enc,
@@ -45,20 +51,43 @@ impl PacketIo {
         P: Packet + Encode,
     {
         self.enc.append_packet(pkt)?;
-        let bytes = self.enc.take();
-        self.stream.write_all(&bytes).await?;
-        Ok(())
+        self.flush().await
     }
 
     pub(crate) async fn recv_packet<'a, P>(&'a mut self) -> anyhow::Result<P>
     where
         P: Packet + Decode<'a>,
     {
+        self.frame = self.recv_frame().await?;
+
+        self.frame.decode()
+    }
+
+    /// Sends a packet frame without decoding it, such as one received from
+    /// another connection.
+    pub(crate) async fn send_frame(&mut self, frame: &PacketFrame) -> anyhow::Result<()> {
+        self.enc.append_frame(frame)?;
+        self.flush().await
+    }
+
+    /// The encoder for packets written before the next [`flush`](Self::flush).
+    pub(crate) fn encoder(&mut self) -> &mut PacketEncoder {
+        &mut self.enc
+    }
+
+    /// Sends the packets written to the encoder.
+    pub(crate) async fn flush(&mut self) -> anyhow::Result<()> {
+        let bytes = self.enc.take();
+        self.stream.write_all(&bytes).await?;
+        self.stream.flush().await?;
+        Ok(())
+    }
+
+    /// Receives the next packet frame. This is cancel safe.
+    pub(crate) async fn recv_frame(&mut self) -> anyhow::Result<PacketFrame> {
         loop {
             if let Some(frame) = self.dec.try_next_packet()? {
-                self.frame = frame;
-
-                return self.frame.decode();
+                return Ok(frame);
             }
 
             self.dec.reserve(READ_BUF_SIZE);
@@ -80,6 +109,11 @@ impl PacketIo {
         self.dec.set_compression(threshold);
     }
 
+    pub(crate) fn set_translator(&mut self, translator: Option<Arc<dyn PacketTranslator>>) {
+        self.enc.set_translator(translator.clone());
+        self.dec.set_translator(translator);
+    }
+
     pub(crate) fn enable_encryption(&mut self, key: &[u8; 16]) {
         self.enc.enable_encryption(key);
         self.dec.enable_encryption(key);
@@ -88,10 +122,14 @@ impl PacketIo {
     pub(crate) fn into_client_args(
         mut self,
         info: NewClientInfo,
+        translator: Option<Arc<dyn PacketTranslator>>,
         incoming_byte_limit: usize,
         outgoing_byte_limit: usize,
         cleanup: CleanupOnDrop,
     ) -> ClientBundleArgs {
+        // Only play state packets are translated.
+        self.set_translator(translator);
+
         let (incoming_sender, incoming_receiver) = flume::unbounded();
 
         let incoming_byte_limit = incoming_byte_limit.min(Semaphore::MAX_PERMITS);
@@ -99,7 +137,7 @@ impl PacketIo {
         let recv_sem = Arc::new(Semaphore::new(incoming_byte_limit));
         let recv_sem_clone = recv_sem.clone();
 
-        let (mut reader, mut writer) = self.stream.into_split();
+        let (mut reader, mut writer) = tokio::io::split(self.stream);
 
         let reader_task = tokio::spawn(async move {
             let mut buf = BytesMut::new();
@@ -170,6 +208,9 @@ impl PacketIo {
 
         let (outgoing_sender, mut outgoing_receiver) = byte_channel(outgoing_byte_limit);
 
+        let unsent = Arc::new(AtomicUsize::new(0));
+        let unsent_clone = unsent.clone();
+
         let writer_task = tokio::spawn(async move {
             loop {
                 let bytes = match outgoing_receiver.recv_async().await {
@@ -183,6 +224,13 @@ impl PacketIo {
                 if let Err(e) = writer.write_all(&bytes).await {
                     debug!("error writing data to stream: {e}");
                 }
+
+                // Transports like WebSockets buffer writes until flushed.
+                if let Err(e) = writer.flush().await {
+                    debug!("error flushing stream: {e}");
+                }
+
+                unsent_clone.fetch_sub(bytes.len(), Ordering::Relaxed);
             }
         });
 
@@ -193,6 +241,7 @@ impl PacketIo {
             properties: info.properties.0,
             conn: Box::new(RealClientConnection {
                 send: outgoing_sender,
+                unsent,
                 recv: incoming_receiver,
                 recv_sem: recv_sem_clone,
                 reader_task,
@@ -206,6 +255,9 @@ impl PacketIo {
 
 struct RealClientConnection {
     send: ByteSender,
+    /// The number of bytes sent to the writer task that have not been written
+    /// yet.
+    unsent: Arc<AtomicUsize>,
     recv: flume::Receiver<ReceivedPacket>,
     /// Limits the amount of data queued in the `recv` channel. Each permit
     /// represents one byte.
@@ -217,13 +269,22 @@ struct RealClientConnection {
 
 impl ClientConnection for RealClientConnection {
     fn try_send(&mut self, bytes: BytesMut) -> anyhow::Result<()> {
+        // Counted before sending so the writer task never subtracts first.
+        self.unsent.fetch_add(bytes.len(), Ordering::Relaxed);
+
         match self.send.try_send(bytes) {
             Ok(()) => Ok(()),
-            Err(TrySendError::Full(_)) => bail!(
-                "reached configured outgoing limit of {} bytes",
-                self.send.limit()
-            ),
-            Err(TrySendError::Disconnected(_)) => bail!("client disconnected"),
+            Err(TrySendError::Full(rest)) => {
+                self.unsent.fetch_sub(rest.len(), Ordering::Relaxed);
+                bail!(
+                    "reached configured outgoing limit of {} bytes",
+                    self.send.limit()
+                )
+            }
+            Err(TrySendError::Disconnected(rest)) => {
+                self.unsent.fetch_sub(rest.len(), Ordering::Relaxed);
+                bail!("client disconnected")
+            }
         }
     }
 
@@ -245,6 +306,10 @@ impl ClientConnection for RealClientConnection {
     fn len(&self) -> usize {
         self.recv.len()
     }
+
+    fn unsent_len(&self) -> usize {
+        self.unsent.load(Ordering::Relaxed)
+    }
 }
 
 impl Drop for RealClientConnection {
@@ -0,0 +1,191 @@
+//! Running hub mode as a standalone proxy, without a Bevy app.
+//!
+//! The proxy authenticates clients according to
+//! [`NetworkSettings::connection_mode`] and logs them into the backends of
+//! [`NetworkSettings::hub`] with Velocity's modern forwarding. Backends can be
+//! Valence servers using [`ConnectionMode::Velocity`] or any other server that
+//! supports modern forwarding, such as Paper or Fabric with
+//! [FabricProxy-Lite].
+//!
+//! [`ConnectionMode::Velocity`]: crate::ConnectionMode::Velocity
+//! [FabricProxy-Lite]: https://github.com/OKTW-Network/FabricProxy-Lite
+
+use anyhow::ensure;
+use valence_server::CompressionThreshold;
+
+use crate::{NetworkSettings, SharedNetworkState};
+
+/// Starts accepting connections as a proxy and returns the network state,
+/// which can be used to ban IP addresses and read the player count.
+///
+/// Connections are handled on the runtime of
+/// [`NetworkSettings::tokio_handle`], or on a new runtime owned by the returned
+/// state if it is `None`. The proxy stops once the runtime shuts down.
+///
+/// Returns an error if [`NetworkSettings::hub`] is `None`.
+pub fn start(
+    settings: &NetworkSettings,
+    threshold: CompressionThreshold,
+) -> anyhow::Result<SharedNetworkState> {
+    ensure!(settings.hub.is_some(), "proxy requires hub settings");
+
+    let shared = SharedNetworkState::new(settings, threshold)?;
+    shared.start_accept_loops();
+
+    Ok(shared)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::{Ipv4Addr, SocketAddr};
+    use std::time::Duration;
+
+    use bytes::BytesMut;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::runtime::Handle;
+    use valence_server::protocol::decode::PacketFrame;
+    use valence_server::protocol::packets::handshaking::handshake_c2s::HandshakeNextState;
+    use valence_server::protocol::packets::handshaking::HandshakeC2s;
+    use valence_server::protocol::packets::login::{
+        LoginCompressionS2c, LoginHelloC2s, LoginQueryRequestS2c, LoginQueryResponseC2s,
+        LoginSuccessS2c,
+    };
+    use valence_server::protocol::packets::play::GameJoinS2c;
+    use valence_server::protocol::{Packet, PacketDecoder, PacketEncoder, RawBytes, VarInt};
+    use valence_server::{ident, PROTOCOL_VERSION};
+
+    use super::*;
+    use crate::hub::HubSettings;
+    use crate::packet_io::PacketIo;
+    use crate::velocity::PlayerInfo;
+    use crate::ConnectionMode;
+
+    const SECRET: &str = "secret";
+
+    fn frame(id: i32, body: &[u8]) -> PacketFrame {
+        PacketFrame {
+            id,
+            body: BytesMut::from(body),
+        }
+    }
+
+    /// A backend that checks the forwarded player information, then exchanges
+    /// one packet with the client.
+    async fn backend(listener: TcpListener) {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut io = PacketIo::new(Box::new(stream), PacketEncoder::new(), PacketDecoder::new());
+
+        io.recv_packet::<HandshakeC2s>().await.unwrap();
+        io.recv_packet::<LoginHelloC2s>().await.unwrap();
+
+        io.send_packet(&LoginQueryRequestS2c {
+            message_id: VarInt(0),
+            channel: ident!("velocity:player_info").into(),
+            data: RawBytes(&[1]).into(),
+        })
+        .await
+        .unwrap();
+
+        let response = io.recv_packet::<LoginQueryResponseC2s>().await.unwrap();
+        let info = PlayerInfo::decode_signed(response.data.unwrap().0 .0, SECRET).unwrap();
+
+        assert_eq!(info.username, "Steve");
+        assert_eq!(info.ip, Ipv4Addr::LOCALHOST);
+
+        io.send_packet(&LoginCompressionS2c {
+            threshold: VarInt(16),
+        })
+        .await
+        .unwrap();
+        io.set_compression(CompressionThreshold(16));
+
+        io.send_packet(&LoginSuccessS2c {
+            uuid: info.uuid,
+            username: info.username.as_str().into(),
+            properties: Default::default(),
+        })
+        .await
+        .unwrap();
+
+        // The proxy forwards the join packet without decoding it.
+        io.send_frame(&frame(GameJoinS2c::ID, b"join"))
+            .await
+            .unwrap();
+
+        let received = io.recv_frame().await.unwrap();
+        assert_eq!(received.id, 0x42);
+        assert_eq!(&received.body[..], &[7; 100]);
+
+        io.send_frame(&frame(0x43, b"pong")).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn forwards_to_backend() {
+        let backend_listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let backend_addr = backend_listener.local_addr().unwrap();
+        let backend = tokio::spawn(backend(backend_listener));
+
+        let proxy_addr: SocketAddr = {
+            let listener = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+            listener.local_addr().unwrap()
+        };
+
+        let settings = NetworkSettings {
+            tokio_handle: Some(Handle::current()),
+            address: proxy_addr,
+            connection_mode: ConnectionMode::Offline,
+            hub: Some(HubSettings {
+                backends: HashMap::from([("lobby".into(), backend_addr)]),
+                default_backend: "lobby".into(),
+                forwarding_secret: SECRET.into(),
+            }),
+            ..Default::default()
+        };
+
+        let _shared = start(&settings, CompressionThreshold::DEFAULT).unwrap();
+
+        let stream = loop {
+            match TcpStream::connect(proxy_addr).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+
+        let mut client =
+            PacketIo::new(Box::new(stream), PacketEncoder::new(), PacketDecoder::new());
+
+        client
+            .send_packet(&HandshakeC2s {
+                protocol_version: VarInt(PROTOCOL_VERSION),
+                server_address: "localhost".into(),
+                server_port: proxy_addr.port(),
+                next_state: HandshakeNextState::Login,
+            })
+            .await
+            .unwrap();
+
+        client
+            .send_packet(&LoginHelloC2s {
+                username: "Steve".into(),
+                profile_id: None,
+            })
+            .await
+            .unwrap();
+
+        client.recv_packet::<LoginSuccessS2c>().await.unwrap();
+
+        let join = client.recv_frame().await.unwrap();
+        assert_eq!(join.id, GameJoinS2c::ID);
+        assert_eq!(&join.body[..], b"join");
+
+        // Large enough to be compressed between the proxy and the backend.
+        client.send_frame(&frame(0x42, &[7; 100])).await.unwrap();
+
+        let pong = client.recv_frame().await.unwrap();
+        assert_eq!(pong.id, 0x43);
+        assert_eq!(&pong.body[..], b"pong");
+
+        backend.await.unwrap();
+    }
+}
@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A number of events allowed within a period of time.
+///
+/// Limits are enforced with a token bucket, so up to `count` events are
+/// allowed in a burst, after which the allowance refills at a rate of `count`
+/// per `period`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Rate {
+    pub count: u32,
+    pub period: Duration,
+}
+
+impl Rate {
+    pub const fn new(count: u32, period: Duration) -> Self {
+        Self { count, period }
+    }
+}
+
+/// Per IP address and global limits for one kind of event. `None` disables
+/// the limit.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub struct RateLimit {
+    pub per_ip: Option<Rate>,
+    pub global: Option<Rate>,
+}
+
+/// The kinds of events that are rate limited.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum RateLimitKind {
+    /// A new connection, before the handshake is read. Legacy server list pings
+    /// count as handshakes.
+    Handshake,
+    /// A server list ping.
+    StatusRequest,
+    /// A login attempt.
+    LoginAttempt,
+}
+
+const KIND_COUNT: usize = 3;
+
+impl RateLimitKind {
+    fn index(self) -> usize {
+        match self {
+            RateLimitKind::Handshake => 0,
+            RateLimitKind::StatusRequest => 1,
+            RateLimitKind::LoginAttempt => 2,
+        }
+    }
+}
+
+/// Settings for connection rate limiting and temporary bans. See
+/// [`NetworkSettings::rate_limits`].
+///
+/// Per IP address limits should not be used behind a proxy, because every
+/// connection comes from the address of the proxy.
+///
+/// [`NetworkSettings::rate_limits`]: crate::NetworkSettings::rate_limits
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RateLimits {
+    pub handshakes: RateLimit,
+    pub status_requests: RateLimit,
+    pub login_attempts: RateLimit,
+    /// The number of times an IP address can exceed its limits before it is
+    /// temporarily banned. `None` disables automatic bans.
+    pub ban_threshold: Option<u32>,
+    /// How long automatic bans last.
+    pub ban_duration: Duration,
+}
+
+impl RateLimits {
+    /// Limits suitable for a server exposed directly to the internet.
+    pub fn recommended() -> Self {
+        Self {
+            handshakes: RateLimit {
+                per_ip: Some(Rate::new(10, Duration::from_secs(10))),
+                global: Some(Rate::new(200, Duration::from_secs(1))),
+            },
+            status_requests: RateLimit {
+                per_ip: Some(Rate::new(5, Duration::from_secs(10))),
+                global: Some(Rate::new(100, Duration::from_secs(1))),
+            },
+            login_attempts: RateLimit {
+                per_ip: Some(Rate::new(3, Duration::from_secs(10))),
+                global: Some(Rate::new(50, Duration::from_secs(1))),
+            },
+            ban_threshold: Some(20),
+            ban_duration: Duration::from_secs(5 * 60),
+        }
+    }
+
+    fn limit(&self, kind: RateLimitKind) -> &RateLimit {
+        match kind {
+            RateLimitKind::Handshake => &self.handshakes,
+            RateLimitKind::StatusRequest => &self.status_requests,
+            RateLimitKind::LoginAttempt => &self.login_attempts,
+        }
+    }
+}
+
+/// No limits.
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            handshakes: RateLimit::default(),
+            status_requests: RateLimit::default(),
+            login_attempts: RateLimit::default(),
+            ban_threshold: None,
+            ban_duration: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Bucket {
+    tokens: f64,
+    last_update: Instant,
+}
+
+impl Bucket {
+    fn full(rate: Rate, now: Instant) -> Self {
+        Self {
+            tokens: f64::from(rate.count),
+            last_update: now,
+        }
+    }
+
+    /// Adds the allowance accumulated since the last update and returns whether
+    /// a token is available.
+    fn refill(&mut self, rate: Rate, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_update);
+        let refill = f64::from(rate.count) * elapsed.as_secs_f64() / rate.period.as_secs_f64();
+
+        self.tokens = (self.tokens + refill).min(f64::from(rate.count));
+        self.last_update = now;
+
+        self.tokens >= 1.0
+    }
+}
+
+#[derive(Debug)]
+struct IpState {
+    buckets: [Option<Bucket>; KIND_COUNT],
+    strikes: u32,
+    last_seen: Instant,
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    ips: HashMap<IpAddr, IpState>,
+    global: [Option<Bucket>; KIND_COUNT],
+    /// Banned IP addresses and the time their ban expires.
+    bans: HashMap<IpAddr, Instant>,
+    /// The number of tracked IP addresses at which stale entries are removed.
+    prune_at: usize,
+}
+
+const MIN_PRUNE_AT: usize = 1024;
+
+/// Tracks the rate limits of every IP address and the temporary ban list.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    settings: RateLimits,
+    state: Mutex<LimiterState>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(settings: RateLimits) -> Self {
+        Self {
+            settings,
+            state: Mutex::new(LimiterState {
+                ips: HashMap::new(),
+                global: [None; KIND_COUNT],
+                bans: HashMap::new(),
+                prune_at: MIN_PRUNE_AT,
+            }),
+        }
+    }
+
+    /// Records an event from `ip` and returns whether it is within the limits.
+    pub(crate) fn try_acquire(&self, ip: IpAddr, kind: RateLimitKind, now: Instant) -> bool {
+        let limit = self.settings.limit(kind);
+        let mut state = self.state.lock().unwrap();
+
+        if state.ips.len() >= state.prune_at {
+            self.prune(&mut state, now);
+        }
+
+        let LimiterState {
+            ips, global, bans, ..
+        } = &mut *state;
+
+        // Both buckets are checked before either is taken from, so an event
+        // rejected by the global limit does not use up the address's allowance
+        // or count towards a ban.
+        let ip_bucket = match limit.per_ip {
+            Some(rate) => {
+                let ip_state = ips.entry(ip).or_insert_with(|| IpState {
+                    buckets: [None; KIND_COUNT],
+                    strikes: 0,
+                    last_seen: now,
+                });
+
+                ip_state.last_seen = now;
+
+                let bucket = ip_state.buckets[kind.index()].get_or_insert(Bucket::full(rate, now));
+
+                if !bucket.refill(rate, now) {
+                    ip_state.strikes += 1;
+
+                    if self
+                        .settings
+                        .ban_threshold
+                        .is_some_and(|threshold| ip_state.strikes >= threshold)
+                    {
+                        ip_state.strikes = 0;
+                        bans.insert(ip, now + self.settings.ban_duration);
+                    }
+
+                    return false;
+                }
+
+                Some(bucket)
+            }
+            None => None,
+        };
+
+        let global_bucket = match limit.global {
+            Some(rate) => {
+                let bucket = global[kind.index()].get_or_insert(Bucket::full(rate, now));
+
+                if !bucket.refill(rate, now) {
+                    return false;
+                }
+
+                Some(bucket)
+            }
+            None => None,
+        };
+
+        for bucket in [ip_bucket, global_bucket].into_iter().flatten() {
+            bucket.tokens -= 1.0;
+        }
+
+        true
+    }
+
+    pub(crate) fn is_banned(&self, ip: IpAddr, now: Instant) -> bool {
+        let mut state = self.state.lock().unwrap();
+
+        match state.bans.get(&ip) {
+            Some(&expires) if expires > now => true,
+            Some(_) => {
+                state.bans.remove(&ip);
+                false
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn ban(&self, ip: IpAddr, expires: Instant) {
+        self.state.lock().unwrap().bans.insert(ip, expires);
+    }
+
+    pub(crate) fn unban(&self, ip: IpAddr) -> bool {
+        self.state.lock().unwrap().bans.remove(&ip).is_some()
+    }
+
+    /// Removes IP addresses whose buckets would be full again.
+    fn prune(&self, state: &mut LimiterState, now: Instant) {
+        let max_period = [
+            &self.settings.handshakes,
+            &self.settings.status_requests,
+            &self.settings.login_attempts,
+        ]
+        .into_iter()
+        .filter_map(|limit| limit.per_ip.map(|rate| rate.period))
+        .max()
+        .unwrap_or_default();
+
+        state
+            .ips
+            .retain(|_, ip_state| now.saturating_duration_since(ip_state.last_seen) < max_period);
+        state.bans.retain(|_, &mut expires| expires > now);
+
+        state.prune_at = (state.ips.len() * 2).max(MIN_PRUNE_AT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    fn limits() -> RateLimits {
+        RateLimits {
+            handshakes: RateLimit {
+                per_ip: Some(Rate::new(2, Duration::from_secs(1))),
+                global: Some(Rate::new(3, Duration::from_secs(1))),
+            },
+            ban_threshold: Some(2),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn per_ip_and_global_limits() {
+        let limiter = RateLimiter::new(limits());
+        let now = Instant::now();
+        let a = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let b = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+        assert!(limiter.try_acquire(a, RateLimitKind::Handshake, now));
+        assert!(limiter.try_acquire(a, RateLimitKind::Handshake, now));
+        assert!(!limiter.try_acquire(a, RateLimitKind::Handshake, now));
+
+        // Other kinds have no limits.
+        assert!(limiter.try_acquire(a, RateLimitKind::LoginAttempt, now));
+
+        // The global limit is shared between addresses.
+        assert!(limiter.try_acquire(b, RateLimitKind::Handshake, now));
+        assert!(!limiter.try_acquire(b, RateLimitKind::Handshake, now));
+
+        // The allowance refills over time.
+        let later = now + Duration::from_millis(500);
+        assert!(limiter.try_acquire(a, RateLimitKind::Handshake, later));
+        assert!(!limiter.try_acquire(a, RateLimitKind::Handshake, later));
+    }
+
+    #[test]
+    fn global_rejection_keeps_ip_allowance() {
+        let limiter = RateLimiter::new(limits());
+        let now = Instant::now();
+        let a = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let b = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        let c = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3));
+
+        assert!(limiter.try_acquire(a, RateLimitKind::Handshake, now));
+        assert!(limiter.try_acquire(a, RateLimitKind::Handshake, now));
+        assert!(limiter.try_acquire(b, RateLimitKind::Handshake, now));
+
+        // The global limit is exhausted, which is not the fault of `c`.
+        for _ in 0..4 {
+            assert!(!limiter.try_acquire(c, RateLimitKind::Handshake, now));
+        }
+        assert!(!limiter.is_banned(c, now));
+
+        let state = limiter.state.lock().unwrap();
+        let bucket = state.ips[&c].buckets[RateLimitKind::Handshake.index()].unwrap();
+        assert_eq!(bucket.tokens, 2.0);
+    }
+
+    #[test]
+    fn temporary_bans() {
+        let limiter = RateLimiter::new(limits());
+        let now = Instant::now();
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+        for _ in 0..3 {
+            limiter.try_acquire(ip, RateLimitKind::Handshake, now);
+        }
+        assert!(!limiter.is_banned(ip, now));
+
+        // The second strike bans the address.
+        limiter.try_acquire(ip, RateLimitKind::Handshake, now);
+        assert!(limiter.is_banned(ip, now));
+        assert!(!limiter.is_banned(ip, now + limits().ban_duration));
+
+        limiter.ban(ip, now + Duration::from_secs(1));
+        assert!(limiter.is_banned(ip, now));
+        assert!(limiter.unban(ip));
+        assert!(!limiter.is_banned(ip, now));
+    }
+}
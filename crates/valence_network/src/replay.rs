@@ -0,0 +1,205 @@
+//! Replaying recorded sessions to clients.
+//!
+//! In replay mode, every client that logs in is sent the clientbound packets
+//! of a recording made with [`Client::start_recording`] at their original
+//! speed, instead of joining the server. The packets of the client are
+//! ignored. Clients must use the same protocol version as the recorded client.
+//!
+//! Enable replay mode with [`NetworkSettings::replay`], or run a standalone
+//! replay server with [`start`].
+//!
+//! [`Client::start_recording`]: valence_server::client::Client::start_recording
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{ensure, Context};
+use tokio::time::Instant;
+use tracing::debug;
+use valence_server::protocol::packets::play::DisconnectS2c;
+use valence_server::recorder::{RecordedPacket, RecordingHeader, RecordingReader};
+use valence_server::text::IntoText;
+use valence_server::CompressionThreshold;
+
+use crate::packet_io::PacketIo;
+use crate::{CleanupOnDrop, NetworkSettings, SharedNetworkState};
+
+/// A recording loaded into memory.
+pub(crate) struct Replay {
+    pub(crate) header: RecordingHeader,
+    data: Vec<u8>,
+}
+
+impl Replay {
+    pub(crate) fn load(path: &Path) -> anyhow::Result<Self> {
+        let data = std::fs::read(path)
+            .with_context(|| format!("reading recording at {}", path.display()))?;
+
+        let header = RecordingReader::new(&data)?.header();
+
+        Ok(Self { header, data })
+    }
+}
+
+/// Starts accepting connections as a replay server and returns the network
+/// state. Connections are handled like in [`proxy::start`].
+///
+/// Returns an error if [`NetworkSettings::replay`] is `None` or the recording
+/// could not be read.
+///
+/// [`proxy::start`]: crate::proxy::start
+pub fn start(settings: &NetworkSettings) -> anyhow::Result<SharedNetworkState> {
+    ensure!(
+        settings.replay.is_some(),
+        "replay server requires a recording"
+    );
+
+    // The compression threshold is taken from the recording.
+    let shared = SharedNetworkState::new(settings, CompressionThreshold::DEFAULT)?;
+    shared.start_accept_loops();
+
+    Ok(shared)
+}
+
+pub(crate) async fn run_session(
+    replay: Arc<Replay>,
+    mut client: PacketIo,
+    protocol_version: i32,
+    cleanup: CleanupOnDrop,
+) {
+    if let Err(e) = play(&replay, &mut client, protocol_version).await {
+        debug!("replay session ended: {e:#}");
+    }
+
+    drop(cleanup);
+}
+
+async fn play(replay: &Replay, client: &mut PacketIo, protocol_version: i32) -> anyhow::Result<()> {
+    if protocol_version != replay.header.protocol_version {
+        client
+            .send_packet(&DisconnectS2c {
+                reason: format!(
+                    "This replay requires protocol version {}",
+                    replay.header.protocol_version
+                )
+                .into_text()
+                .into(),
+            })
+            .await?;
+
+        return Ok(());
+    }
+
+    let mut reader = RecordingReader::new(&replay.data)?;
+    let start = Instant::now();
+
+    while let Some(record) = reader.next_record()? {
+        let RecordedPacket::Clientbound(bytes) = record.packet else {
+            continue;
+        };
+
+        wait_until(client, start + record.time).await?;
+
+        client.encoder().append_bytes(bytes);
+        client.flush().await?;
+    }
+
+    // Give the client a moment to process the last packets.
+    wait_until(client, Instant::now() + Duration::from_secs(1)).await?;
+
+    client
+        .send_packet(&DisconnectS2c {
+            reason: "End of replay".into_text().into(),
+        })
+        .await
+}
+
+/// Waits until `deadline` while discarding the packets sent by the client.
+async fn wait_until(client: &mut PacketIo, deadline: Instant) -> anyhow::Result<()> {
+    loop {
+        tokio::select! {
+            () = tokio::time::sleep_until(deadline) => return Ok(()),
+            frame = client.recv_frame() => {
+                frame?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use tokio::net::{TcpListener, TcpStream};
+    use valence_server::protocol::packets::play::ChunkLoadDistanceS2c;
+    use valence_server::protocol::{
+        Encode, PacketDecoder, PacketEncoder, VarInt, VarLong, WritePacket,
+    };
+    use valence_server::PROTOCOL_VERSION;
+
+    use super::*;
+
+    fn recording(view_distances: &[(i32, i64)]) -> Vec<u8> {
+        let mut data = b"VREC".to_vec();
+        VarInt(1).encode(&mut data).unwrap();
+        VarInt(PROTOCOL_VERSION).encode(&mut data).unwrap();
+        VarInt(-1).encode(&mut data).unwrap();
+
+        for &(view_distance, millis) in view_distances {
+            let mut enc = PacketEncoder::new();
+            enc.write_packet(&ChunkLoadDistanceS2c {
+                view_distance: VarInt(view_distance),
+            });
+
+            0_u8.encode(&mut data).unwrap();
+            VarLong(millis).encode(&mut data).unwrap();
+            enc.take()[..].encode(&mut data).unwrap();
+        }
+
+        data
+    }
+
+    #[tokio::test]
+    async fn replays_clientbound_packets() {
+        let data = recording(&[(4, 0), (8, 100)]);
+        let replay = Arc::new(Replay {
+            header: RecordingReader::new(&data).unwrap().header(),
+            data,
+        });
+
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server_stream, _) = listener.accept().await.unwrap();
+
+        let server = PacketIo::new(
+            Box::new(server_stream),
+            PacketEncoder::new(),
+            PacketDecoder::new(),
+        );
+        let session = tokio::spawn(run_session(
+            replay,
+            server,
+            PROTOCOL_VERSION,
+            CleanupOnDrop(None),
+        ));
+
+        let mut client =
+            PacketIo::new(Box::new(stream), PacketEncoder::new(), PacketDecoder::new());
+
+        let start = Instant::now();
+
+        let pkt = client.recv_packet::<ChunkLoadDistanceS2c>().await.unwrap();
+        assert_eq!(pkt.view_distance, VarInt(4));
+
+        let pkt = client.recv_packet::<ChunkLoadDistanceS2c>().await.unwrap();
+        assert_eq!(pkt.view_distance, VarInt(8));
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        client.recv_packet::<DisconnectS2c>().await.unwrap();
+
+        session.await.unwrap();
+    }
+}
@@ -0,0 +1,143 @@
+//! Pluggable transports that clients can connect over.
+//!
+//! Every connection goes through the same handshake and login process
+//! regardless of the transport it arrived on. A [`TcpTransport`] bound to
+//! [`NetworkSettings::address`] is always started. Additional transports,
+//! such as the WebSocket transport enabled by the `websocket` feature, can be
+//! added to [`NetworkSettings::transports`].
+//!
+//! [`NetworkSettings::address`]: crate::NetworkSettings::address
+//! [`NetworkSettings::transports`]: crate::NetworkSettings::transports
+
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+
+use async_trait::async_trait;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::error;
+#[cfg(feature = "websocket")]
+pub use websocket::WebSocketTransport;
+
+#[cfg(feature = "websocket")]
+mod websocket;
+
+/// A source of incoming connections. This trait uses [`mod@async_trait`].
+#[async_trait]
+pub trait Transport: Send + Sync + 'static {
+    /// Starts listening for connections. This is called once from within a
+    /// tokio runtime when the server starts.
+    async fn bind(&self) -> io::Result<Box<dyn TransportListener>>;
+}
+
+/// A bound [`Transport`]. This trait uses [`mod@async_trait`].
+#[async_trait]
+pub trait TransportListener: Send + 'static {
+    /// The local address the listener is bound to.
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+
+    /// Waits for the next connection and returns its remote address.
+    ///
+    /// The returned future completes the transport level setup of the
+    /// connection, such as a WebSocket handshake. It is awaited on its own task
+    /// so that slow clients do not hold up the listener.
+    async fn accept(&mut self) -> io::Result<(SocketAddr, IncomingConnection)>;
+}
+
+/// The setup of a connection accepted by a [`TransportListener`].
+pub type IncomingConnection =
+    Pin<Box<dyn Future<Output = io::Result<Box<dyn TransportStream>>> + Send>>;
+
+/// A bidirectional byte stream to a client.
+pub trait TransportStream: AsyncRead + AsyncWrite + Send + Unpin + 'static {
+    /// Returns the underlying TCP stream if the bytes of the stream are sent
+    /// over TCP unchanged. This is required to answer legacy server list
+    /// pings.
+    fn as_tcp_stream(&mut self) -> Option<&mut TcpStream> {
+        None
+    }
+}
+
+impl TransportStream for TcpStream {
+    fn as_tcp_stream(&mut self) -> Option<&mut TcpStream> {
+        Some(self)
+    }
+}
+
+/// The plain TCP transport used by the vanilla client.
+#[derive(Copy, Clone, Debug)]
+pub struct TcpTransport {
+    pub address: SocketAddr,
+}
+
+impl TcpTransport {
+    pub fn new(address: SocketAddr) -> Self {
+        Self { address }
+    }
+}
+
+#[async_trait]
+impl Transport for TcpTransport {
+    async fn bind(&self) -> io::Result<Box<dyn TransportListener>> {
+        Ok(Box::new(TcpListener::bind(self.address).await?))
+    }
+}
+
+#[async_trait]
+impl TransportListener for TcpListener {
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        TcpListener::local_addr(self)
+    }
+
+    async fn accept(&mut self) -> io::Result<(SocketAddr, IncomingConnection)> {
+        let (stream, remote_addr) = TcpListener::accept(self).await?;
+
+        if let Err(e) = stream.set_nodelay(true) {
+            error!("failed to set TCP_NODELAY: {e}");
+        }
+
+        Ok((
+            remote_addr,
+            Box::pin(async move { Ok(Box::new(stream) as Box<dyn TransportStream>) }),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn tcp_loopback() {
+        let mut listener = TcpTransport::new("127.0.0.1:0".parse().unwrap())
+            .bind()
+            .await
+            .unwrap();
+
+        let addr = listener.local_addr().unwrap();
+
+        let client = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(b"ping").await.unwrap();
+
+            let mut buf = [0; 4];
+            stream.read_exact(&mut buf).await.unwrap();
+            buf
+        });
+
+        let (_, incoming) = listener.accept().await.unwrap();
+        let mut stream = incoming.await.unwrap();
+        assert!(stream.as_tcp_stream().is_some());
+
+        let mut buf = [0; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+        stream.write_all(b"pong").await.unwrap();
+
+        assert_eq!(&client.await.unwrap(), b"pong");
+    }
+}
@@ -0,0 +1,223 @@
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use async_trait::async_trait;
+use futures_util::{Sink, Stream};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use tokio_tungstenite::WebSocketStream;
+use tracing::error;
+
+use super::{IncomingConnection, Transport, TransportListener, TransportStream};
+
+/// A transport accepting WebSocket connections, for browser based clients and
+/// web proxies.
+///
+/// Each binary message carries a part of the regular packet stream. Message
+/// boundaries do not need to line up with packet boundaries.
+#[derive(Copy, Clone, Debug)]
+pub struct WebSocketTransport {
+    pub address: SocketAddr,
+}
+
+impl WebSocketTransport {
+    pub fn new(address: SocketAddr) -> Self {
+        Self { address }
+    }
+}
+
+#[async_trait]
+impl Transport for WebSocketTransport {
+    async fn bind(&self) -> io::Result<Box<dyn TransportListener>> {
+        Ok(Box::new(WebSocketListener(
+            TcpListener::bind(self.address).await?,
+        )))
+    }
+}
+
+struct WebSocketListener(TcpListener);
+
+#[async_trait]
+impl TransportListener for WebSocketListener {
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.0.local_addr()
+    }
+
+    async fn accept(&mut self) -> io::Result<(SocketAddr, IncomingConnection)> {
+        let (stream, remote_addr) = self.0.accept().await?;
+
+        if let Err(e) = stream.set_nodelay(true) {
+            error!("failed to set TCP_NODELAY: {e}");
+        }
+
+        Ok((
+            remote_addr,
+            Box::pin(async move {
+                let ws = tokio_tungstenite::accept_async(stream)
+                    .await
+                    .map_err(ws_to_io_error)?;
+
+                Ok(Box::new(WebSocketConnection {
+                    ws,
+                    read_buf: vec![],
+                    read_pos: 0,
+                }) as Box<dyn TransportStream>)
+            }),
+        ))
+    }
+}
+
+/// Adapts the messages of a WebSocket to a byte stream.
+struct WebSocketConnection {
+    ws: WebSocketStream<TcpStream>,
+    /// The binary message currently being read.
+    read_buf: Vec<u8>,
+    read_pos: usize,
+}
+
+impl TransportStream for WebSocketConnection {}
+
+impl AsyncRead for WebSocketConnection {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        while self.read_pos == self.read_buf.len() {
+            match ready!(Pin::new(&mut self.ws).poll_next(cx)) {
+                Some(Ok(Message::Binary(data))) => {
+                    self.read_buf = data;
+                    self.read_pos = 0;
+                }
+                // Pings are answered by the WebSocket itself.
+                Some(Ok(Message::Ping(_) | Message::Pong(_) | Message::Frame(_))) => {}
+                Some(Ok(Message::Text(_))) => {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "unexpected text message",
+                    )))
+                }
+                Some(Ok(Message::Close(_))) | None => return Poll::Ready(Ok(())),
+                Some(Err(e)) => return Poll::Ready(Err(ws_to_io_error(e))),
+            }
+        }
+
+        let len = buf.remaining().min(self.read_buf.len() - self.read_pos);
+        let start = self.read_pos;
+        buf.put_slice(&self.read_buf[start..start + len]);
+        self.read_pos += len;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for WebSocketConnection {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut ws = Pin::new(&mut self.ws);
+
+        ready!(ws.as_mut().poll_ready(cx)).map_err(ws_to_io_error)?;
+
+        ws.start_send(Message::Binary(buf.to_vec()))
+            .map_err(ws_to_io_error)?;
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.ws)
+            .poll_flush(cx)
+            .map_err(ws_to_io_error)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.ws)
+            .poll_close(cx)
+            .map_err(ws_to_io_error)
+    }
+}
+
+fn ws_to_io_error(e: WsError) -> io::Error {
+    match e {
+        WsError::Io(e) => e,
+        WsError::ConnectionClosed | WsError::AlreadyClosed => {
+            io::Error::from(io::ErrorKind::BrokenPipe)
+        }
+        e => io::Error::other(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{SinkExt, StreamExt};
+    use valence_protocol::packets::handshaking::handshake_c2s::HandshakeNextState;
+    use valence_protocol::packets::handshaking::HandshakeC2s;
+    use valence_protocol::packets::status::{QueryPingC2s, QueryPongS2c};
+    use valence_protocol::{PacketDecoder, PacketEncoder, VarInt, PROTOCOL_VERSION};
+
+    use super::*;
+    use crate::packet_io::PacketIo;
+
+    #[tokio::test]
+    async fn websocket_loopback() {
+        let mut listener = WebSocketTransport::new("127.0.0.1:0".parse().unwrap())
+            .bind()
+            .await
+            .unwrap();
+
+        let addr = listener.local_addr().unwrap();
+
+        let client = tokio::spawn(async move {
+            let stream = TcpStream::connect(addr).await.unwrap();
+            let (mut ws, _) = tokio_tungstenite::client_async(format!("ws://{addr}/"), stream)
+                .await
+                .unwrap();
+
+            let mut enc = PacketEncoder::new();
+            enc.append_packet(&HandshakeC2s {
+                protocol_version: VarInt(PROTOCOL_VERSION),
+                server_address: "localhost".into(),
+                server_port: addr.port(),
+                next_state: HandshakeNextState::Status,
+            })
+            .unwrap();
+            enc.append_packet(&QueryPingC2s { payload: 42 }).unwrap();
+
+            // Split the packets across messages at an arbitrary point.
+            let bytes = enc.take();
+            let (first, second) = bytes.split_at(5);
+            ws.send(Message::Binary(first.to_vec())).await.unwrap();
+            ws.send(Message::Binary(second.to_vec())).await.unwrap();
+
+            let Some(Ok(Message::Binary(data))) = ws.next().await else {
+                panic!("expected a binary message");
+            };
+
+            let mut dec = PacketDecoder::new();
+            dec.queue_slice(&data);
+            let frame = dec.try_next_packet().unwrap().unwrap();
+            frame.decode::<QueryPongS2c>().unwrap().payload
+        });
+
+        let (_, incoming) = listener.accept().await.unwrap();
+        let mut stream = incoming.await.unwrap();
+        assert!(stream.as_tcp_stream().is_none());
+
+        let mut io = PacketIo::new(stream, PacketEncoder::new(), PacketDecoder::new());
+
+        let handshake = io.recv_packet::<HandshakeC2s>().await.unwrap();
+        assert_eq!(handshake.protocol_version.0, PROTOCOL_VERSION);
+        assert_eq!(handshake.server_address.0, "localhost");
+
+        let QueryPingC2s { payload } = io.recv_packet().await.unwrap();
+        io.send_packet(&QueryPongS2c { payload }).await.unwrap();
+
+        assert_eq!(client.await.unwrap(), 42);
+    }
+}
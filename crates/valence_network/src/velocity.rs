@@ -0,0 +1,120 @@
+//! Velocity's modern forwarding, which passes the player information of a
+//! client from a proxy to a backend server in a login plugin request.
+
+use std::net::IpAddr;
+
+use anyhow::{ensure, Context};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+use valence_protocol::profile::Property;
+use valence_protocol::{Decode, Encode, VarInt};
+
+/// The forwarding version requested from the proxy, which does not include a
+/// signed chat key.
+pub(crate) const MIN_SUPPORTED_VERSION: u8 = 1;
+pub(crate) const MODERN_FORWARDING_WITH_KEY_V2: i32 = 3;
+
+const SIGNATURE_LEN: usize = 32;
+
+/// The player information forwarded by the proxy.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct PlayerInfo {
+    pub(crate) version: i32,
+    pub(crate) ip: IpAddr,
+    pub(crate) uuid: Uuid,
+    pub(crate) username: String,
+    pub(crate) properties: Vec<Property>,
+}
+
+impl PlayerInfo {
+    /// Verifies the signature of the response data with the forwarding secret
+    /// and decodes the player information.
+    pub(crate) fn decode_signed(data: &[u8], secret: &str) -> anyhow::Result<Self> {
+        ensure!(
+            data.len() >= SIGNATURE_LEN,
+            "invalid plugin response data length"
+        );
+        let (signature, mut data) = data.split_at(SIGNATURE_LEN);
+
+        // Verify signature
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())?;
+        Mac::update(&mut mac, data);
+        mac.verify_slice(signature)?;
+
+        // Check Velocity version
+        let version = VarInt::decode(&mut data)
+            .context("failed to decode velocity version")?
+            .0;
+
+        // Get client address
+        let ip = String::decode(&mut data)?.parse()?;
+
+        // Get UUID
+        let uuid = Uuid::decode(&mut data)?;
+
+        let username = String::decode(&mut data)?;
+
+        // Read game profile properties
+        let properties = Vec::<Property>::decode(&mut data)
+            .context("decoding velocity game profile properties")?;
+
+        Ok(Self {
+            version,
+            ip,
+            uuid,
+            username,
+            properties,
+        })
+    }
+
+    /// Encodes the player information and signs it with the forwarding secret.
+    pub(crate) fn encode_signed(&self, secret: &str) -> anyhow::Result<Vec<u8>> {
+        let mut data = vec![];
+        VarInt(self.version).encode(&mut data)?;
+        self.ip.to_string().encode(&mut data)?;
+        self.uuid.encode(&mut data)?;
+        self.username.encode(&mut data)?;
+        self.properties.encode(&mut data)?;
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())?;
+        Mac::update(&mut mac, &data);
+
+        let mut signed = mac.finalize().into_bytes().to_vec();
+        signed.extend_from_slice(&data);
+
+        Ok(signed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[test]
+    fn player_info_round_trip() {
+        let info = PlayerInfo {
+            version: MIN_SUPPORTED_VERSION.into(),
+            ip: Ipv4Addr::new(192, 168, 0, 7).into(),
+            uuid: Uuid::from_u128(0x1234),
+            username: "Steve".into(),
+            properties: vec![Property {
+                name: "textures".into(),
+                value: "abc".into(),
+                signature: Some("def".into()),
+            }],
+        };
+
+        let data = info.encode_signed("secret").unwrap();
+        assert_eq!(PlayerInfo::decode_signed(&data, "secret").unwrap(), info);
+
+        // The signature is checked.
+        assert!(PlayerInfo::decode_signed(&data, "other secret").is_err());
+
+        let mut tampered = data.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(PlayerInfo::decode_signed(&tampered, "secret").is_err());
+    }
+}
@@ -1,8 +1,11 @@
 #[cfg(feature = "encryption")]
 use aes::cipher::{generic_array::GenericArray, BlockDecryptMut, BlockSizeUser, KeyIvInit};
+use std::sync::Arc;
+
 use anyhow::{bail, ensure, Context};
 use bytes::{Buf, BytesMut};
 
+use crate::translate::{PacketTranslator, Translation};
 use crate::var_int::{VarInt, VarIntDecodeError};
 #[cfg(feature = "compression")]
 use crate::CompressionThreshold;
@@ -22,6 +25,7 @@ pub struct PacketDecoder {
     threshold: CompressionThreshold,
     #[cfg(feature = "encryption")]
     cipher: Option<Cipher>,
+    translator: Option<Arc<dyn PacketTranslator>>,
 }
 
 impl PacketDecoder {
@@ -29,7 +33,25 @@ impl PacketDecoder {
         Self::default()
     }
 
+    /// Returns the next complete packet, translated by the installed
+    /// translator. Packets the translator drops are skipped.
     pub fn try_next_packet(&mut self) -> anyhow::Result<Option<PacketFrame>> {
+        loop {
+            let Some(mut frame) = self.try_next_frame()? else {
+                return Ok(None);
+            };
+
+            let Some(translator) = &self.translator else {
+                return Ok(Some(frame));
+            };
+
+            if translator.translate_serverbound(&mut frame)? == Translation::Forward {
+                return Ok(Some(frame));
+            }
+        }
+    }
+
+    fn try_next_frame(&mut self) -> anyhow::Result<Option<PacketFrame>> {
         let mut r = &self.buf[..];
 
         let packet_len = match VarInt::decode_partial(&mut r) {
@@ -193,6 +215,15 @@ impl PacketDecoder {
         }
     }
 
+    /// Sets the translator applied to all packets returned from now on.
+    pub fn set_translator(&mut self, translator: Option<Arc<dyn PacketTranslator>>) {
+        self.translator = translator;
+    }
+
+    pub fn translator(&self) -> Option<&Arc<dyn PacketTranslator>> {
+        self.translator.as_ref()
+    }
+
     pub fn take_capacity(&mut self) -> BytesMut {
         self.buf.split_off(self.buf.len())
     }
@@ -1,4 +1,5 @@
 use std::io::Write;
+use std::sync::Arc;
 
 #[cfg(feature = "encryption")]
 use aes::cipher::generic_array::GenericArray;
@@ -8,8 +9,10 @@ use anyhow::ensure;
 use bytes::{BufMut, BytesMut};
 use tracing::warn;
 
+use crate::decode::PacketFrame;
+use crate::translate::{PacketTranslator, Translation};
 use crate::var_int::VarInt;
-use crate::{CompressionThreshold, Encode, Packet, MAX_PACKET_SIZE};
+use crate::{CompressionThreshold, Decode, Encode, Packet, PacketDecoder, MAX_PACKET_SIZE};
 
 /// The AES block cipher with a 128 bit key, using the CFB-8 mode of
 /// operation.
@@ -25,6 +28,7 @@ pub struct PacketEncoder {
     threshold: CompressionThreshold,
     #[cfg(feature = "encryption")]
     cipher: Option<Cipher>,
+    translator: Option<Arc<dyn PacketTranslator>>,
 }
 
 impl PacketEncoder {
@@ -32,9 +36,39 @@ impl PacketEncoder {
         Self::default()
     }
 
+    /// Appends framed packet data. If a translator is installed, the packets
+    /// are unframed, translated and framed again.
     #[inline]
     pub fn append_bytes(&mut self, bytes: &[u8]) {
-        self.buf.extend_from_slice(bytes)
+        if self.translator.is_none() {
+            self.buf.extend_from_slice(bytes)
+        } else if let Err(e) = self.append_translated_bytes(bytes) {
+            warn!("failed to translate packet bytes: {e:#}");
+        }
+    }
+
+    fn append_translated_bytes(&mut self, bytes: &[u8]) -> anyhow::Result<()> {
+        let mut dec = PacketDecoder::new();
+        #[cfg(feature = "compression")]
+        dec.set_compression(self.threshold);
+        dec.queue_slice(bytes);
+
+        while let Some(frame) = dec.try_next_packet()? {
+            self.append_frame(&frame)?;
+        }
+
+        Ok(())
+    }
+
+    /// Appends a packet from its ID and body, such as a [`PacketFrame`] read
+    /// from a [`PacketDecoder`].
+    pub fn append_frame(&mut self, frame: &PacketFrame) -> anyhow::Result<()> {
+        let start_len = self.buf.len();
+
+        VarInt(frame.id).encode((&mut self.buf).writer())?;
+        self.buf.extend_from_slice(&frame.body);
+
+        self.finish_packet(start_len)
     }
 
     pub fn prepend_packet<P>(&mut self, pkt: &P) -> anyhow::Result<()>
@@ -67,6 +101,44 @@ impl PacketEncoder {
 
         pkt.encode_with_id((&mut self.buf).writer())?;
 
+        self.finish_packet(start_len)
+    }
+
+    /// Translates and frames the unframed packet starting at `start_len`. The
+    /// packet is removed from the buffer if this fails, so that it does not
+    /// corrupt the packets after it.
+    fn finish_packet(&mut self, start_len: usize) -> anyhow::Result<()> {
+        let res = self.translate_and_frame(start_len);
+
+        if res.is_err() {
+            self.buf.truncate(start_len);
+        }
+
+        res
+    }
+
+    fn translate_and_frame(&mut self, start_len: usize) -> anyhow::Result<()> {
+        if let Some(translator) = &self.translator {
+            let mut r = &self.buf[start_len..];
+            let id = VarInt::decode(&mut r)?.0;
+
+            let mut frame = PacketFrame {
+                id,
+                body: BytesMut::from(r),
+            };
+
+            let translation = translator.translate_clientbound(&mut frame)?;
+
+            self.buf.truncate(start_len);
+
+            if translation == Translation::Drop {
+                return Ok(());
+            }
+
+            VarInt(frame.id).encode((&mut self.buf).writer())?;
+            self.buf.extend_from_slice(&frame.body);
+        }
+
         let data_len = self.buf.len() - start_len;
 
         #[cfg(feature = "compression")]
@@ -163,6 +235,35 @@ impl PacketEncoder {
         self.buf.clear();
     }
 
+    /// The number of bytes written since the last [take](Self::take).
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// The packets written since the last [take](Self::take), before they are
+    /// encrypted.
+    pub fn bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// The compression threshold packets are written with. This is always
+    /// [`CompressionThreshold::DEFAULT`] without the `compression` feature.
+    #[cfg(feature = "compression")]
+    pub fn compression(&self) -> CompressionThreshold {
+        self.threshold
+    }
+
+    /// The compression threshold packets are written with. This is always
+    /// [`CompressionThreshold::DEFAULT`] without the `compression` feature.
+    #[cfg(not(feature = "compression"))]
+    pub fn compression(&self) -> CompressionThreshold {
+        CompressionThreshold::DEFAULT
+    }
+
     #[cfg(feature = "compression")]
     pub fn set_compression(&mut self, threshold: CompressionThreshold) {
         self.threshold = threshold;
@@ -181,6 +282,16 @@ impl PacketEncoder {
         assert!(self.cipher.is_none(), "encryption is already enabled");
         self.cipher = Some(Cipher::new_from_slices(key, key).expect("invalid key"));
     }
+
+    /// Sets the translator applied to all packets written from now on. Packets
+    /// that were already written are not translated.
+    pub fn set_translator(&mut self, translator: Option<Arc<dyn PacketTranslator>>) {
+        self.translator = translator;
+    }
+
+    pub fn translator(&self) -> Option<&Arc<dyn PacketTranslator>> {
+        self.translator.as_ref()
+    }
 }
 
 /// Types that can have packets written to them.
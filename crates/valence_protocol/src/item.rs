@@ -1,6 +1,6 @@
 use std::io::Write;
 
-pub use valence_generated::item::ItemKind;
+pub use valence_generated::item::{ItemKind, Tool, ToolKind};
 use valence_nbt::Compound;
 
 use crate::{Decode, Encode};
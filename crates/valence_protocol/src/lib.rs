@@ -33,6 +33,7 @@ pub mod packets;
 pub mod profile;
 mod raw;
 pub mod sound;
+pub mod translate;
 pub mod var_int;
 mod var_long;
 mod velocity;
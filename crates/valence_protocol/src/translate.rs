@@ -0,0 +1,1060 @@
+//! Translation of play state packets between [`PROTOCOL_VERSION`] and other
+//! protocol versions.
+//!
+//! Valence only understands the packets of the version it targets. To let
+//! clients on other versions join, a [`PacketTranslator`] can be installed on
+//! the [`PacketEncoder`] and [`PacketDecoder`] of a connection once it enters
+//! the play state. Every packet written to the encoder is translated from the
+//! native protocol to the client's protocol before it is framed, and every
+//! packet read from the decoder is translated from the client's protocol to
+//! the native protocol after it is unframed.
+//!
+//! [`VersionMapping`] is a table-driven translator covering the common
+//! differences between releases: remapped packet IDs, registry IDs and custom
+//! rewrites for packets whose layout changed. The only bundled mapping is for
+//! 1.19.4 (protocol 762), see [`VersionMapping::v1_19_4`] and
+//! [`Translators::bundled`]. Clients on other versions need a mapping built
+//! with [`VersionMapping::new`] or a custom [`PacketTranslator`].
+//!
+//! [`PROTOCOL_VERSION`]: crate::PROTOCOL_VERSION
+//! [`PacketEncoder`]: crate::PacketEncoder
+//! [`PacketDecoder`]: crate::PacketDecoder
+
+mod v1_19_4;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use anyhow::{bail, ensure, Context};
+use bytes::BytesMut;
+use uuid::Uuid;
+use valence_nbt::Compound;
+
+use crate::decode::PacketFrame;
+use crate::packets::play::{
+    BlockEntityUpdateS2c, BlockEventS2c, BlockUpdateS2c, ChunkDataS2c, ChunkDeltaUpdateS2c,
+    ClickSlotC2s, CooldownUpdateS2c, CreativeInventoryActionC2s, EntityEquipmentUpdateS2c,
+    EntitySpawnS2c, EntityTrackerUpdateS2c, InventoryS2c, ParticleS2c, PlaySoundFromEntityS2c,
+    PlaySoundS2c, ScreenHandlerSlotUpdateS2c, SetTradeOffersS2c, SynchronizeTagsS2c, WorldEventS2c,
+};
+use crate::{Decode, Encode, GlobalPos, Packet, Particle, VarInt, VarLong};
+
+/// What to do with a packet after it was translated.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Translation {
+    /// Send or handle the translated packet.
+    Forward,
+    /// Discard the packet, because it has no equivalent on the other side.
+    Drop,
+}
+
+/// Translates play state packets between [`PROTOCOL_VERSION`] and the protocol
+/// version of a client.
+///
+/// [`PROTOCOL_VERSION`]: crate::PROTOCOL_VERSION
+pub trait PacketTranslator: Send + Sync + 'static {
+    /// The protocol version of the clients this translator is for.
+    fn protocol_version(&self) -> i32;
+
+    /// The stringified name of the Minecraft version of the clients this
+    /// translator is for.
+    fn minecraft_version(&self) -> &str;
+
+    /// Translates a packet sent by the server from the native protocol to the
+    /// client's protocol. The ID and body of `frame` are modified in place.
+    fn translate_clientbound(&self, frame: &mut PacketFrame) -> anyhow::Result<Translation>;
+
+    /// Translates a packet sent by the client from the client's protocol to the
+    /// native protocol. The ID and body of `frame` are modified in place.
+    fn translate_serverbound(&self, frame: &mut PacketFrame) -> anyhow::Result<Translation>;
+}
+
+/// The set of translators available to clients, keyed by protocol version.
+#[derive(Clone, Default)]
+pub struct Translators {
+    map: HashMap<i32, Arc<dyn PacketTranslator>>,
+}
+
+impl Translators {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the translators for the versions with a mapping bundled with
+    /// this crate. This is currently only 1.19.4.
+    pub fn bundled() -> Self {
+        let mut translators = Self::new();
+        translators.insert(VersionMapping::v1_19_4());
+        translators
+    }
+
+    /// Adds a translator for the protocol version it reports. The previous
+    /// translator for the version is returned, if any.
+    pub fn insert<T: PacketTranslator>(
+        &mut self,
+        translator: T,
+    ) -> Option<Arc<dyn PacketTranslator>> {
+        self.map
+            .insert(translator.protocol_version(), Arc::new(translator))
+    }
+
+    /// Returns the translator for the given protocol version.
+    pub fn get(&self, protocol_version: i32) -> Option<&Arc<dyn PacketTranslator>> {
+        self.map.get(&protocol_version)
+    }
+
+    /// Returns the protocol versions translators are registered for.
+    pub fn protocol_versions(&self) -> impl Iterator<Item = i32> + '_ {
+        self.map.keys().copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl fmt::Debug for Translators {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.map.keys()).finish()
+    }
+}
+
+/// A function rewriting the body of a packet whose layout differs between
+/// versions.
+///
+/// Clientbound rewrites receive the packet in the native layout and must
+/// produce the client's layout. Serverbound rewrites receive the packet in the
+/// client's layout and must produce the native layout.
+pub type RewriteFn =
+    Arc<dyn Fn(&VersionMapping, &mut PacketFrame) -> anyhow::Result<Translation> + Send + Sync>;
+
+/// A [`PacketTranslator`] built from ID tables and per-packet rewrites.
+///
+/// Clientbound packets are first rewritten by the rewrites registered for
+/// their native ID and then get their ID remapped. Serverbound packets get
+/// their ID remapped to the native ID first and are then rewritten by the
+/// rewrites registered for the native ID. Packet and registry IDs without an
+/// entry in the tables are passed through unchanged.
+///
+/// Rewrites for the registry IDs in packets are registered by default. They
+/// cover block states and block entity kinds in chunk data, section and block
+/// updates, items in inventories, equipment, trades and entity metadata, as
+/// well as sounds, particles and the block and item tags. Registry IDs in
+/// recipes, advancements and statistics are not remapped.
+#[derive(Clone)]
+pub struct VersionMapping {
+    protocol_version: i32,
+    minecraft_version: String,
+    clientbound_ids: HashMap<i32, Option<i32>>,
+    serverbound_ids: HashMap<i32, Option<i32>>,
+    block_states: IdMap,
+    blocks: IdMap,
+    block_entity_kinds: IdMap,
+    items: IdMap,
+    sounds: IdMap,
+    particles: IdMap,
+    clientbound_rewrites: HashMap<i32, Vec<RewriteFn>>,
+    serverbound_rewrites: HashMap<i32, Vec<RewriteFn>>,
+}
+
+#[derive(Clone, Default, Debug)]
+struct IdMap {
+    to_client: HashMap<i32, i32>,
+    to_native: HashMap<i32, i32>,
+}
+
+impl IdMap {
+    /// Builds the map from a table of client IDs indexed by native ID. Client
+    /// IDs used by several native IDs are mapped back to the lowest of them.
+    fn from_table(to_client: &[u16]) -> Self {
+        let mut map = Self::default();
+
+        for (native, &client) in to_client.iter().enumerate() {
+            map.to_client.insert(native as i32, client.into());
+            map.to_native.entry(client.into()).or_insert(native as i32);
+        }
+
+        map
+    }
+
+    /// Builds the map from a table of client IDs indexed by native ID and a
+    /// table of native IDs indexed by client ID.
+    fn from_tables(to_client: &[u16], to_native: &[u16]) -> Self {
+        let mut map = Self::default();
+
+        for (native, &client) in to_client.iter().enumerate() {
+            map.to_client.insert(native as i32, client.into());
+        }
+
+        for (client, &native) in to_native.iter().enumerate() {
+            map.to_native.insert(client as i32, native.into());
+        }
+
+        map
+    }
+
+    fn insert(&mut self, native: i32, client: i32) {
+        self.to_client.insert(native, client);
+        self.to_native.insert(client, native);
+    }
+
+    fn to_client(&self, native: i32) -> i32 {
+        self.to_client.get(&native).copied().unwrap_or(native)
+    }
+
+    fn to_native(&self, client: i32) -> i32 {
+        self.to_native.get(&client).copied().unwrap_or(client)
+    }
+
+    /// Whether the native ID maps to an equivalent client ID rather than a
+    /// fallback.
+    fn is_equivalent(&self, native: i32) -> bool {
+        self.to_native(self.to_client(native)) == native
+    }
+}
+
+/// The ID of `minecraft:falling_block` in the entity type registry.
+const FALLING_BLOCK_ENTITY_KIND: i32 = 36;
+
+/// The world event of a block being broken, with the block state as data.
+const BLOCK_BROKEN_WORLD_EVENT: i32 = 2001;
+
+/// The world event of a jukebox starting to play, with the item of the disc
+/// as data.
+const JUKEBOX_PLAYING_WORLD_EVENT: i32 = 1010;
+
+impl VersionMapping {
+    pub fn new<V: Into<String>>(protocol_version: i32, minecraft_version: V) -> Self {
+        Self {
+            protocol_version,
+            minecraft_version: minecraft_version.into(),
+            clientbound_ids: HashMap::new(),
+            serverbound_ids: HashMap::new(),
+            block_states: IdMap::default(),
+            blocks: IdMap::default(),
+            block_entity_kinds: IdMap::default(),
+            items: IdMap::default(),
+            sounds: IdMap::default(),
+            particles: IdMap::default(),
+            clientbound_rewrites: HashMap::new(),
+            serverbound_rewrites: HashMap::new(),
+        }
+        .rewrite_clientbound(ChunkDataS2c::ID, |mapping, frame| {
+            rewrite_body(frame, |r, w| {
+                // Chunk position and heightmaps.
+                copy::<[i32; 2]>(r, w)?;
+                copy::<Compound>(r, w)?;
+
+                let sections = <&[u8]>::decode(r).context("decoding chunk sections")?;
+                let mut data = Vec::with_capacity(sections.len());
+                map_chunk_sections(sections, &mut data, |id| mapping.block_state_to_client(id))?;
+                VarInt(data.len() as i32).encode(&mut *w)?;
+                w.extend_from_slice(&data);
+
+                let block_entity_count = copy::<VarInt>(r, w)?.0;
+                for _ in 0..block_entity_count {
+                    // Packed XZ and Y.
+                    copy::<i8>(r, w)?;
+                    copy::<i16>(r, w)?;
+                    map_var_int(r, w, |id| mapping.block_entity_kind_to_client(id))?;
+                    copy::<Compound>(r, w)?;
+                }
+
+                Ok(())
+            })
+        })
+        .rewrite_clientbound(ChunkDeltaUpdateS2c::ID, |mapping, frame| {
+            rewrite_body(frame, |r, w| {
+                copy::<i64>(r, w)?;
+
+                let count = copy::<VarInt>(r, w)?.0;
+                for _ in 0..count {
+                    let entry = VarLong::decode(r)?.0;
+                    let state = mapping.block_state_to_client((entry >> 12) as i32);
+                    VarLong(i64::from(state) << 12 | entry & 0xfff).encode(&mut *w)?;
+                }
+
+                Ok(())
+            })
+        })
+        .rewrite_clientbound(BlockUpdateS2c::ID, |mapping, frame| {
+            rewrite_body(frame, |r, w| {
+                copy::<i64>(r, w)?;
+                map_var_int(r, w, |id| mapping.block_state_to_client(id)).map(drop)
+            })
+        })
+        .rewrite_clientbound(BlockEventS2c::ID, |mapping, frame| {
+            rewrite_body(frame, |r, w| {
+                // Position, action ID and action parameter.
+                copy::<(i64, u8, u8)>(r, w)?;
+                map_var_int(r, w, |id| mapping.block_to_client(id)).map(drop)
+            })
+        })
+        .rewrite_clientbound(BlockEntityUpdateS2c::ID, |mapping, frame| {
+            rewrite_body(frame, |r, w| {
+                copy::<i64>(r, w)?;
+                map_var_int(r, w, |id| mapping.block_entity_kind_to_client(id)).map(drop)
+            })
+        })
+        .rewrite_clientbound(WorldEventS2c::ID, |mapping, frame| {
+            rewrite_body(frame, |r, w| {
+                let event = copy::<i32>(r, w)?;
+                copy::<i64>(r, w)?;
+                let data = i32::decode(r)?;
+
+                match event {
+                    BLOCK_BROKEN_WORLD_EVENT => mapping.block_state_to_client(data),
+                    JUKEBOX_PLAYING_WORLD_EVENT => mapping.item_to_client(data),
+                    _ => data,
+                }
+                .encode(&mut *w)
+            })
+        })
+        .rewrite_clientbound(EntitySpawnS2c::ID, |mapping, frame| {
+            rewrite_body(frame, |r, w| {
+                copy::<VarInt>(r, w)?;
+                copy::<Uuid>(r, w)?;
+                let kind = copy::<VarInt>(r, w)?.0;
+                // Position, pitch, yaw and head yaw.
+                copy::<([f64; 3], [u8; 3])>(r, w)?;
+
+                if kind == FALLING_BLOCK_ENTITY_KIND {
+                    map_var_int(r, w, |id| mapping.block_state_to_client(id))?;
+                }
+
+                Ok(())
+            })
+        })
+        .rewrite_clientbound(InventoryS2c::ID, |mapping, frame| {
+            rewrite_body(frame, |r, w| {
+                // Window ID and state ID.
+                copy::<(u8, VarInt)>(r, w)?;
+
+                let count = copy::<VarInt>(r, w)?.0;
+                // The carried item follows the slots.
+                for _ in 0..=count {
+                    map_item_stack(r, w, |id| mapping.item_to_client(id))?;
+                }
+
+                Ok(())
+            })
+        })
+        .rewrite_clientbound(ScreenHandlerSlotUpdateS2c::ID, |mapping, frame| {
+            rewrite_body(frame, |r, w| {
+                // Window ID, state ID and slot index.
+                copy::<(i8, VarInt, i16)>(r, w)?;
+                map_item_stack(r, w, |id| mapping.item_to_client(id))
+            })
+        })
+        .rewrite_clientbound(EntityEquipmentUpdateS2c::ID, |mapping, frame| {
+            rewrite_body(frame, |r, w| {
+                copy::<VarInt>(r, w)?;
+
+                loop {
+                    let slot = copy::<i8>(r, w)?;
+                    map_item_stack(r, w, |id| mapping.item_to_client(id))?;
+
+                    // The top bit of the slot is set if another entry follows.
+                    if slot >= 0 {
+                        break Ok(());
+                    }
+                }
+            })
+        })
+        .rewrite_clientbound(EntityTrackerUpdateS2c::ID, |mapping, frame| {
+            rewrite_body(frame, |r, w| {
+                copy::<VarInt>(r, w)?;
+                map_tracked_values(mapping, r, w)
+            })
+        })
+        .rewrite_clientbound(SetTradeOffersS2c::ID, |mapping, frame| {
+            rewrite_body(frame, |r, w| {
+                copy::<VarInt>(r, w)?;
+
+                let count = copy::<VarInt>(r, w)?.0;
+                for _ in 0..count {
+                    // First input, output and second input.
+                    for _ in 0..3 {
+                        map_item_stack(r, w, |id| mapping.item_to_client(id))?;
+                    }
+                    // Whether the trade is disabled, the uses, maximum uses,
+                    // experience, special price, price multiplier and demand.
+                    copy::<(bool, [i32; 4], f32, i32)>(r, w)?;
+                }
+
+                Ok(())
+            })
+        })
+        .rewrite_clientbound(CooldownUpdateS2c::ID, |mapping, frame| {
+            rewrite_body(frame, |r, w| {
+                map_var_int(r, w, |id| mapping.item_to_client(id)).map(drop)
+            })
+        })
+        .rewrite_clientbound(ParticleS2c::ID, |mapping, frame| {
+            rewrite_body(frame, |r, w| {
+                let particle = map_var_int(r, w, |id| mapping.particle_to_client(id))?;
+                // Long distance, position, offset, maximum speed and count.
+                copy::<(bool, [f64; 3], [f32; 3], f32, i32)>(r, w)?;
+                map_particle_data(mapping, particle, r, w)
+            })
+        })
+        .rewrite_clientbound(PlaySoundS2c::ID, |mapping, frame| {
+            rewrite_body(frame, |r, w| map_sound(mapping, r, w))
+        })
+        .rewrite_clientbound(PlaySoundFromEntityS2c::ID, |mapping, frame| {
+            rewrite_body(frame, |r, w| map_sound(mapping, r, w))
+        })
+        .rewrite_clientbound(SynchronizeTagsS2c::ID, |mapping, frame| {
+            rewrite_body(frame, |r, w| {
+                let registry_count = copy::<VarInt>(r, w)?.0;
+                for _ in 0..registry_count {
+                    let ids = match copy::<&str>(r, w)? {
+                        "minecraft:block" => Some(&mapping.blocks),
+                        "minecraft:item" => Some(&mapping.items),
+                        _ => None,
+                    };
+
+                    let tag_count = copy::<VarInt>(r, w)?.0;
+                    for _ in 0..tag_count {
+                        copy::<&str>(r, w)?;
+                        let entries = Vec::<VarInt>::decode(r)?;
+
+                        match ids {
+                            // Entries without an equivalent are left out, so
+                            // their fallbacks are not tagged.
+                            Some(ids) => entries
+                                .into_iter()
+                                .filter(|id| ids.is_equivalent(id.0))
+                                .map(|id| VarInt(ids.to_client(id.0)))
+                                .collect::<Vec<_>>()
+                                .encode(&mut *w)?,
+                            None => entries.encode(&mut *w)?,
+                        }
+                    }
+                }
+
+                Ok(())
+            })
+        })
+        .rewrite_serverbound(ClickSlotC2s::ID, |mapping, frame| {
+            rewrite_body(frame, |r, w| {
+                // Window ID, state ID, slot index, button and mode.
+                copy::<(u8, VarInt, i16, i8, VarInt)>(r, w)?;
+
+                let count = copy::<VarInt>(r, w)?.0;
+                for _ in 0..count {
+                    copy::<i16>(r, w)?;
+                    map_item_stack(r, w, |id| mapping.item_to_native(id))?;
+                }
+
+                // The carried item.
+                map_item_stack(r, w, |id| mapping.item_to_native(id))
+            })
+        })
+        .rewrite_serverbound(CreativeInventoryActionC2s::ID, |mapping, frame| {
+            rewrite_body(frame, |r, w| {
+                copy::<i16>(r, w)?;
+                map_item_stack(r, w, |id| mapping.item_to_native(id))
+            })
+        })
+    }
+
+    /// Maps the native ID of a clientbound packet to the ID used by the
+    /// client. If `client_id` is `None`, the packet does not exist in the
+    /// client's version and is dropped.
+    pub fn clientbound_packet(mut self, native_id: i32, client_id: Option<i32>) -> Self {
+        self.clientbound_ids.insert(native_id, client_id);
+        self
+    }
+
+    /// Maps the ID of a serverbound packet used by the client to the native
+    /// ID. If `native_id` is `None`, the packet does not exist in the native
+    /// version and is dropped.
+    pub fn serverbound_packet(mut self, client_id: i32, native_id: Option<i32>) -> Self {
+        self.serverbound_ids.insert(client_id, native_id);
+        self
+    }
+
+    /// Maps a native block state ID to the block state ID used by the client.
+    pub fn block_state(mut self, native: i32, client: i32) -> Self {
+        self.block_states.insert(native, client);
+        self
+    }
+
+    /// Maps a native block ID to the block ID used by the client.
+    pub fn block(mut self, native: i32, client: i32) -> Self {
+        self.blocks.insert(native, client);
+        self
+    }
+
+    /// Maps a native block entity kind ID to the ID used by the client.
+    pub fn block_entity_kind(mut self, native: i32, client: i32) -> Self {
+        self.block_entity_kinds.insert(native, client);
+        self
+    }
+
+    /// Maps a native item ID to the item ID used by the client.
+    pub fn item(mut self, native: i32, client: i32) -> Self {
+        self.items.insert(native, client);
+        self
+    }
+
+    /// Maps a native sound ID to the sound ID used by the client.
+    pub fn sound(mut self, native: i32, client: i32) -> Self {
+        self.sounds.insert(native, client);
+        self
+    }
+
+    /// Maps a native particle ID to the particle ID used by the client.
+    pub fn particle(mut self, native: i32, client: i32) -> Self {
+        self.particles.insert(native, client);
+        self
+    }
+
+    /// Registers a rewrite for the clientbound packet with the given native ID.
+    /// The rewrites of a packet run in the order they were registered, after
+    /// the default rewrites.
+    pub fn rewrite_clientbound<F>(mut self, native_id: i32, f: F) -> Self
+    where
+        F: Fn(&VersionMapping, &mut PacketFrame) -> anyhow::Result<Translation>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.clientbound_rewrites
+            .entry(native_id)
+            .or_default()
+            .push(Arc::new(f));
+        self
+    }
+
+    /// Registers a rewrite for the serverbound packet with the given native ID.
+    /// The rewrites of a packet run in the order they were registered, after
+    /// the default rewrites.
+    pub fn rewrite_serverbound<F>(mut self, native_id: i32, f: F) -> Self
+    where
+        F: Fn(&VersionMapping, &mut PacketFrame) -> anyhow::Result<Translation>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.serverbound_rewrites
+            .entry(native_id)
+            .or_default()
+            .push(Arc::new(f));
+        self
+    }
+
+    pub fn block_state_to_client(&self, native: i32) -> i32 {
+        self.block_states.to_client(native)
+    }
+
+    pub fn block_state_to_native(&self, client: i32) -> i32 {
+        self.block_states.to_native(client)
+    }
+
+    pub fn block_to_client(&self, native: i32) -> i32 {
+        self.blocks.to_client(native)
+    }
+
+    pub fn block_entity_kind_to_client(&self, native: i32) -> i32 {
+        self.block_entity_kinds.to_client(native)
+    }
+
+    pub fn item_to_client(&self, native: i32) -> i32 {
+        self.items.to_client(native)
+    }
+
+    pub fn item_to_native(&self, client: i32) -> i32 {
+        self.items.to_native(client)
+    }
+
+    pub fn sound_to_client(&self, native: i32) -> i32 {
+        self.sounds.to_client(native)
+    }
+
+    pub fn particle_to_client(&self, native: i32) -> i32 {
+        self.particles.to_client(native)
+    }
+}
+
+impl PacketTranslator for VersionMapping {
+    fn protocol_version(&self) -> i32 {
+        self.protocol_version
+    }
+
+    fn minecraft_version(&self) -> &str {
+        &self.minecraft_version
+    }
+
+    fn translate_clientbound(&self, frame: &mut PacketFrame) -> anyhow::Result<Translation> {
+        for rewrite in self
+            .clientbound_rewrites
+            .get(&frame.id)
+            .into_iter()
+            .flatten()
+        {
+            if rewrite(self, frame)? == Translation::Drop {
+                return Ok(Translation::Drop);
+            }
+        }
+
+        match self.clientbound_ids.get(&frame.id) {
+            Some(Some(id)) => frame.id = *id,
+            Some(None) => return Ok(Translation::Drop),
+            None => {}
+        }
+
+        Ok(Translation::Forward)
+    }
+
+    fn translate_serverbound(&self, frame: &mut PacketFrame) -> anyhow::Result<Translation> {
+        match self.serverbound_ids.get(&frame.id) {
+            Some(Some(id)) => frame.id = *id,
+            Some(None) => return Ok(Translation::Drop),
+            None => {}
+        }
+
+        for rewrite in self
+            .serverbound_rewrites
+            .get(&frame.id)
+            .into_iter()
+            .flatten()
+        {
+            if rewrite(self, frame)? == Translation::Drop {
+                return Ok(Translation::Drop);
+            }
+        }
+
+        Ok(Translation::Forward)
+    }
+}
+
+impl fmt::Debug for VersionMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VersionMapping")
+            .field("protocol_version", &self.protocol_version)
+            .field("minecraft_version", &self.minecraft_version)
+            .field("clientbound_ids", &self.clientbound_ids)
+            .field("serverbound_ids", &self.serverbound_ids)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Rewrites the body of a packet from the front. `f` reads fields from the old
+/// body and writes their replacements. The fields it does not read are copied
+/// unchanged.
+fn rewrite_body<F>(frame: &mut PacketFrame, f: F) -> anyhow::Result<Translation>
+where
+    F: FnOnce(&mut &[u8], &mut Vec<u8>) -> anyhow::Result<()>,
+{
+    let mut r = &frame.body[..];
+    let mut w = Vec::with_capacity(frame.body.len() + 8);
+
+    f(&mut r, &mut w)?;
+    w.extend_from_slice(r);
+
+    frame.body = BytesMut::from(&w[..]);
+    Ok(Translation::Forward)
+}
+
+/// Decodes a value and copies its bytes unchanged.
+fn copy<'a, T: Decode<'a>>(r: &mut &'a [u8], w: &mut Vec<u8>) -> anyhow::Result<T> {
+    let before = *r;
+    let value = T::decode(r)?;
+    w.extend_from_slice(&before[..before.len() - r.len()]);
+    Ok(value)
+}
+
+/// Rewrites a registry ID encoded as a [`VarInt`]. The original ID is
+/// returned.
+fn map_var_int<F>(r: &mut &[u8], w: &mut Vec<u8>, map: F) -> anyhow::Result<i32>
+where
+    F: FnOnce(i32) -> i32,
+{
+    let id = VarInt::decode(r).context("decoding registry ID")?.0;
+    VarInt(map(id)).encode(w)?;
+    Ok(id)
+}
+
+/// Rewrites the item ID of an item stack.
+fn map_item_stack<F>(r: &mut &[u8], w: &mut Vec<u8>, map: F) -> anyhow::Result<()>
+where
+    F: FnOnce(i32) -> i32,
+{
+    if copy::<bool>(r, w).context("decoding item stack")? {
+        map_var_int(r, w, map)?;
+        // The count and NBT are unchanged.
+        copy::<(i8, Compound)>(r, w)?;
+    }
+
+    Ok(())
+}
+
+/// Rewrites the block states in the paletted containers of chunk sections.
+fn map_chunk_sections<F>(mut r: &[u8], w: &mut Vec<u8>, map: F) -> anyhow::Result<()>
+where
+    F: Fn(i32) -> i32,
+{
+    while !r.is_empty() {
+        // Non-air block count.
+        copy::<i16>(&mut r, w)?;
+        map_paletted_container(&mut r, w, 8, &map).context("decoding block states")?;
+        map_paletted_container(&mut r, w, 3, |id| id).context("decoding biomes")?;
+    }
+
+    Ok(())
+}
+
+/// Rewrites the values of a paletted container. Containers with more than
+/// `max_indirect_bits` bits per entry use the IDs directly, which are
+/// rewritten in place with the same number of bits.
+fn map_paletted_container<F>(
+    r: &mut &[u8],
+    w: &mut Vec<u8>,
+    max_indirect_bits: u8,
+    map: F,
+) -> anyhow::Result<()>
+where
+    F: Fn(i32) -> i32,
+{
+    let bits = copy::<u8>(r, w)?;
+
+    if bits == 0 {
+        map_var_int(r, w, &map)?;
+        // The data array of single valued containers is empty.
+        copy::<VarInt>(r, w)?;
+        return Ok(());
+    }
+
+    if bits <= max_indirect_bits {
+        let palette_len = copy::<VarInt>(r, w)?.0;
+        for _ in 0..palette_len {
+            map_var_int(r, w, &map)?;
+        }
+        copy::<Vec<u64>>(r, w)?;
+        return Ok(());
+    }
+
+    ensure!(bits <= 32, "invalid number of bits per entry ({bits})");
+
+    let mask = (1_u64 << bits) - 1;
+    let per_long = 64 / u32::from(bits);
+
+    let longs = copy::<VarInt>(r, w)?.0;
+    for _ in 0..longs {
+        let long = u64::decode(r)?;
+        let mut mapped = 0;
+
+        for i in 0..per_long {
+            let shift = i * u32::from(bits);
+            let id = ((long >> shift) & mask) as i32;
+            let new_id = map(id) as u64;
+
+            if new_id > mask {
+                bail!("ID {new_id} does not fit in {bits} bits");
+            }
+
+            mapped |= new_id << shift;
+        }
+
+        mapped.encode(&mut *w)?;
+    }
+
+    Ok(())
+}
+
+/// Rewrites the registry IDs in a native particle's data.
+fn map_particle_data(
+    mapping: &VersionMapping,
+    particle: i32,
+    r: &mut &[u8],
+    w: &mut Vec<u8>,
+) -> anyhow::Result<()> {
+    let mut data = *r;
+
+    match Particle::decode_with_id(particle, r)? {
+        Particle::Block(_) | Particle::BlockMarker(_) | Particle::FallingDust(_) => {
+            map_var_int(&mut data, w, |id| mapping.block_state_to_client(id))?;
+        }
+        Particle::Item(_) => map_item_stack(&mut data, w, |id| mapping.item_to_client(id))?,
+        _ => w.extend_from_slice(&data[..data.len() - r.len()]),
+    }
+
+    Ok(())
+}
+
+/// Rewrites a sound event, which is either a registry ID offset by one or
+/// zero followed by the sound's identifier.
+fn map_sound(mapping: &VersionMapping, r: &mut &[u8], w: &mut Vec<u8>) -> anyhow::Result<()> {
+    map_var_int(r, w, |id| match id {
+        0 => 0,
+        id => mapping.sound_to_client(id - 1) + 1,
+    })
+    .map(drop)
+}
+
+/// The IDs of the native tracked data types, as used in entity metadata.
+mod tracked_data {
+    pub(super) const BYTE: i32 = 0;
+    pub(super) const INTEGER: i32 = 1;
+    pub(super) const LONG: i32 = 2;
+    pub(super) const FLOAT: i32 = 3;
+    pub(super) const STRING: i32 = 4;
+    pub(super) const TEXT_COMPONENT: i32 = 5;
+    pub(super) const OPTIONAL_TEXT_COMPONENT: i32 = 6;
+    pub(super) const ITEM_STACK: i32 = 7;
+    pub(super) const BOOLEAN: i32 = 8;
+    pub(super) const ROTATION: i32 = 9;
+    pub(super) const BLOCK_POS: i32 = 10;
+    pub(super) const OPTIONAL_BLOCK_POS: i32 = 11;
+    pub(super) const FACING: i32 = 12;
+    pub(super) const OPTIONAL_UUID: i32 = 13;
+    pub(super) const BLOCK_STATE: i32 = 14;
+    pub(super) const OPTIONAL_BLOCK_STATE: i32 = 15;
+    pub(super) const NBT_COMPOUND: i32 = 16;
+    pub(super) const PARTICLE: i32 = 17;
+    pub(super) const VILLAGER_DATA: i32 = 18;
+    pub(super) const OPTIONAL_INT: i32 = 19;
+    pub(super) const ENTITY_POSE: i32 = 20;
+    pub(super) const CAT_VARIANT: i32 = 21;
+    pub(super) const FROG_VARIANT: i32 = 22;
+    pub(super) const OPTIONAL_GLOBAL_POS: i32 = 23;
+    pub(super) const PAINTING_VARIANT: i32 = 24;
+    pub(super) const SNIFFER_STATE: i32 = 25;
+    pub(super) const VECTOR3F: i32 = 26;
+    pub(super) const QUATERNIONF: i32 = 27;
+}
+
+/// Rewrites the registry IDs in the tracked values of entity metadata.
+fn map_tracked_values(
+    mapping: &VersionMapping,
+    r: &mut &[u8],
+    w: &mut Vec<u8>,
+) -> anyhow::Result<()> {
+    use tracked_data::*;
+
+    // The values are terminated by an index of 0xff.
+    while copy::<u8>(r, w)? != 0xff {
+        match copy::<VarInt>(r, w)?.0 {
+            ITEM_STACK => map_item_stack(r, w, |id| mapping.item_to_client(id))?,
+            BLOCK_STATE => {
+                map_var_int(r, w, |id| mapping.block_state_to_client(id))?;
+            }
+            // Zero for no block state, otherwise the ID offset by one.
+            OPTIONAL_BLOCK_STATE => {
+                map_var_int(r, w, |id| match id {
+                    0 => 0,
+                    id => mapping.block_state_to_client(id - 1) + 1,
+                })?;
+            }
+            PARTICLE => {
+                let particle = map_var_int(r, w, |id| mapping.particle_to_client(id))?;
+                map_particle_data(mapping, particle, r, w)?;
+            }
+            BYTE => copy::<u8>(r, w).map(drop)?,
+            INTEGER | FACING | OPTIONAL_INT | ENTITY_POSE | CAT_VARIANT | FROG_VARIANT
+            | PAINTING_VARIANT | SNIFFER_STATE => copy::<VarInt>(r, w).map(drop)?,
+            LONG => copy::<VarLong>(r, w).map(drop)?,
+            FLOAT => copy::<f32>(r, w).map(drop)?,
+            STRING | TEXT_COMPONENT => copy::<&str>(r, w).map(drop)?,
+            OPTIONAL_TEXT_COMPONENT => copy::<Option<&str>>(r, w).map(drop)?,
+            BOOLEAN => copy::<bool>(r, w).map(drop)?,
+            ROTATION | VECTOR3F => copy::<[f32; 3]>(r, w).map(drop)?,
+            BLOCK_POS => copy::<i64>(r, w).map(drop)?,
+            OPTIONAL_BLOCK_POS => copy::<Option<i64>>(r, w).map(drop)?,
+            OPTIONAL_UUID => copy::<Option<Uuid>>(r, w).map(drop)?,
+            NBT_COMPOUND => copy::<Compound>(r, w).map(drop)?,
+            VILLAGER_DATA => copy::<[VarInt; 3]>(r, w).map(drop)?,
+            OPTIONAL_GLOBAL_POS => copy::<Option<GlobalPos>>(r, w).map(drop)?,
+            QUATERNIONF => copy::<[f32; 4]>(r, w).map(drop)?,
+            kind => bail!("unknown tracked data type {kind}"),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::packets::play::{KeepAliveC2s, KeepAliveS2c};
+    use crate::{BlockPos, BlockState, ItemKind, ItemStack, PacketDecoder, PacketEncoder};
+
+    fn mapping() -> VersionMapping {
+        VersionMapping::new(762, "1.19.4")
+            .clientbound_packet(KeepAliveS2c::ID, Some(0x7f))
+            .serverbound_packet(0x7e, Some(KeepAliveC2s::ID))
+            .block_state(BlockState::STONE.to_raw().into(), 1234)
+            .item(ItemKind::Diamond.to_raw().into(), 4321)
+    }
+
+    /// Frames a packet with an uncompressed header.
+    fn raw_packet(id: i32, body: &[u8]) -> Vec<u8> {
+        let mut data = vec![];
+        VarInt(id).encode(&mut data).unwrap();
+        data.extend_from_slice(body);
+
+        let mut packet = vec![];
+        VarInt(data.len() as i32).encode(&mut packet).unwrap();
+        packet.extend_from_slice(&data);
+        packet
+    }
+
+    #[test]
+    fn clientbound_translation() {
+        let mapping = mapping();
+
+        let mut enc = PacketEncoder::new();
+        enc.set_translator(Some(Arc::new(mapping.clone())));
+
+        enc.append_packet(&KeepAliveS2c { id: 5 }).unwrap();
+        enc.append_packet(&BlockUpdateS2c {
+            position: BlockPos::new(1, 2, 3),
+            block_id: BlockState::STONE,
+        })
+        .unwrap();
+        enc.append_packet(&ScreenHandlerSlotUpdateS2c {
+            window_id: 0,
+            state_id: VarInt(1),
+            slot_idx: 36,
+            slot_data: Cow::Owned(ItemStack::new(ItemKind::Diamond, 3, None)),
+        })
+        .unwrap();
+
+        let mut dec = PacketDecoder::new();
+        dec.queue_bytes(enc.take());
+
+        let frame = dec.try_next_packet().unwrap().unwrap();
+        assert_eq!(frame.id, 0x7f);
+        assert_eq!(&frame.body[..], &5_u64.to_be_bytes());
+
+        let frame = dec.try_next_packet().unwrap().unwrap();
+        assert_eq!(frame.id, BlockUpdateS2c::ID);
+        let mut r = &frame.body[..];
+        assert_eq!(BlockPos::decode(&mut r).unwrap(), BlockPos::new(1, 2, 3));
+        assert_eq!(VarInt::decode(&mut r).unwrap().0, 1234);
+
+        let frame = dec.try_next_packet().unwrap().unwrap();
+        let mut r = &frame.body[..];
+        i8::decode(&mut r).unwrap();
+        VarInt::decode(&mut r).unwrap();
+        i16::decode(&mut r).unwrap();
+        assert!(bool::decode(&mut r).unwrap());
+        assert_eq!(VarInt::decode(&mut r).unwrap().0, 4321);
+        assert_eq!(i8::decode(&mut r).unwrap(), 3);
+
+        assert!(dec.try_next_packet().unwrap().is_none());
+    }
+
+    #[test]
+    fn clientbound_translation_of_raw_bytes() {
+        let mut raw = PacketEncoder::new();
+        raw.append_packet(&KeepAliveS2c { id: 5 }).unwrap();
+
+        let mut enc = PacketEncoder::new();
+        enc.set_translator(Some(Arc::new(
+            mapping().clientbound_packet(BlockUpdateS2c::ID, None),
+        )));
+        enc.append_bytes(&raw.take());
+        enc.append_packet(&BlockUpdateS2c {
+            position: BlockPos::new(0, 0, 0),
+            block_id: BlockState::AIR,
+        })
+        .unwrap();
+
+        let mut dec = PacketDecoder::new();
+        dec.queue_bytes(enc.take());
+
+        assert_eq!(dec.try_next_packet().unwrap().unwrap().id, 0x7f);
+        // The block update was dropped.
+        assert!(dec.try_next_packet().unwrap().is_none());
+    }
+
+    #[test]
+    fn failed_clientbound_translation() {
+        struct FailingTranslator;
+
+        impl PacketTranslator for FailingTranslator {
+            fn protocol_version(&self) -> i32 {
+                762
+            }
+
+            fn minecraft_version(&self) -> &str {
+                "1.19.4"
+            }
+
+            fn translate_clientbound(
+                &self,
+                frame: &mut PacketFrame,
+            ) -> anyhow::Result<Translation> {
+                ensure!(frame.id != KeepAliveS2c::ID, "cannot translate keepalive");
+                Ok(Translation::Forward)
+            }
+
+            fn translate_serverbound(&self, _: &mut PacketFrame) -> anyhow::Result<Translation> {
+                Ok(Translation::Forward)
+            }
+        }
+
+        let mut enc = PacketEncoder::new();
+        enc.set_translator(Some(Arc::new(FailingTranslator)));
+
+        enc.append_packet(&KeepAliveS2c { id: 5 }).unwrap_err();
+        assert!(enc.is_empty());
+
+        enc.append_packet(&BlockUpdateS2c {
+            position: BlockPos::new(1, 2, 3),
+            block_id: BlockState::STONE,
+        })
+        .unwrap();
+
+        // The failed packet left nothing behind in front of the next one.
+        let mut dec = PacketDecoder::new();
+        dec.queue_bytes(enc.take());
+
+        let frame = dec.try_next_packet().unwrap().unwrap();
+        assert_eq!(frame.id, BlockUpdateS2c::ID);
+        assert!(dec.try_next_packet().unwrap().is_none());
+    }
+
+    #[test]
+    fn serverbound_translation() {
+        let mapping = mapping();
+
+        // A creative inventory action and keepalive as a 1.19.4 client would send
+        // them.
+        let mut action = vec![];
+        36_i16.encode(&mut action).unwrap();
+        true.encode(&mut action).unwrap();
+        VarInt(4321).encode(&mut action).unwrap();
+        1_i8.encode(&mut action).unwrap();
+        0_u8.encode(&mut action).unwrap();
+
+        let mut bytes = raw_packet(CreativeInventoryActionC2s::ID, &action);
+        bytes.extend(raw_packet(0x7e, &9_u64.to_be_bytes()));
+
+        let mut dec = PacketDecoder::new();
+        dec.set_translator(Some(Arc::new(mapping.clone())));
+        dec.queue_slice(&bytes);
+
+        let frame = dec.try_next_packet().unwrap().unwrap();
+        let action = frame.decode::<CreativeInventoryActionC2s>().unwrap();
+        assert_eq!(action.clicked_item.item, ItemKind::Diamond);
+
+        let frame = dec.try_next_packet().unwrap().unwrap();
+        assert_eq!(frame.decode::<KeepAliveC2s>().unwrap().id, 9);
+
+        assert!(dec.try_next_packet().unwrap().is_none());
+
+        // Packets without a native equivalent are skipped.
+        let mut dec = PacketDecoder::new();
+        dec.set_translator(Some(Arc::new(mapping.serverbound_packet(0x7e, None))));
+        dec.queue_slice(&bytes);
+        assert!(dec.try_next_packet().unwrap().is_some());
+        assert!(dec.try_next_packet().unwrap().is_none());
+    }
+}
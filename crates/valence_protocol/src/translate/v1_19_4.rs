@@ -0,0 +1,424 @@
+use valence_generated::translation::v1_19_4 as tables;
+use valence_nbt::{Compound, List, Value};
+
+use super::{copy, rewrite_body, IdMap, Translation, VersionMapping};
+use crate::packets::play::{
+    ChunkDataS2c, ChunkDeltaUpdateS2c, DeathMessageS2c, EndCombatS2c, GameJoinS2c, LightUpdateS2c,
+    PlayerRespawnS2c, SignEditorOpenS2c, UpdateSignC2s,
+};
+use crate::{Encode, ItemKind, Packet, VarInt};
+
+impl VersionMapping {
+    /// Returns the mapping for clients on Minecraft 1.19.4 (protocol 762).
+    ///
+    /// The packet IDs of 1.19.4 are the same as the native ones. Blocks, items,
+    /// sounds and particles added in 1.20 are replaced by similar ones the
+    /// client knows, such as gravel for suspicious gravel. Armor trim patterns
+    /// using templates added in 1.20 are removed from the registry codec.
+    pub fn v1_19_4() -> Self {
+        let mut mapping = Self::new(tables::PROTOCOL_VERSION, tables::MINECRAFT_VERSION)
+            .rewrite_clientbound(GameJoinS2c::ID, |mapping, frame| {
+                let mut join = frame.decode::<GameJoinS2c>()?;
+
+                remove_unknown_trim_patterns(mapping, join.registry_codec.to_mut());
+
+                // The portal cooldown at the end was added in 1.20.
+                let mut body = vec![];
+                join.encode(&mut body)?;
+                body.truncate(body.len() - join.portal_cooldown.written_size());
+
+                frame.body = body[..].into();
+                Ok(Translation::Forward)
+            })
+            .rewrite_clientbound(PlayerRespawnS2c::ID, |_, frame| {
+                let respawn = frame.decode::<PlayerRespawnS2c>()?;
+                let len = frame.body.len() - respawn.portal_cooldown.written_size();
+
+                frame.body.truncate(len);
+                Ok(Translation::Forward)
+            })
+            .rewrite_clientbound(ChunkDataS2c::ID, |_, frame| {
+                rewrite_body(frame, |r, w| {
+                    // Chunk position, heightmaps and chunk sections.
+                    copy::<([i32; 2], Compound, &[u8])>(r, w)?;
+
+                    let block_entity_count = copy::<VarInt>(r, w)?.0;
+                    for _ in 0..block_entity_count {
+                        copy::<(i8, i16, VarInt, Compound)>(r, w)?;
+                    }
+
+                    // Light data starts with whether to trust the edges.
+                    true.encode(w)
+                })
+            })
+            .rewrite_clientbound(LightUpdateS2c::ID, |_, frame| {
+                rewrite_body(frame, |r, w| {
+                    copy::<[VarInt; 2]>(r, w)?;
+                    true.encode(w)
+                })
+            })
+            .rewrite_clientbound(ChunkDeltaUpdateS2c::ID, |_, frame| {
+                rewrite_body(frame, |r, w| {
+                    copy::<i64>(r, w)?;
+                    // Whether to suppress light updates, which 1.20 never does.
+                    false.encode(w)
+                })
+            })
+            .rewrite_clientbound(SignEditorOpenS2c::ID, |_, frame| {
+                // Only the front text can be edited, so just the position is sent.
+                frame.body.truncate(8);
+                Ok(Translation::Forward)
+            })
+            .rewrite_clientbound(EndCombatS2c::ID, |_, frame| {
+                // The entity ID of the killer.
+                frame.body.extend_from_slice(&(-1_i32).to_be_bytes());
+                Ok(Translation::Forward)
+            })
+            .rewrite_clientbound(DeathMessageS2c::ID, |_, frame| {
+                rewrite_body(frame, |r, w| {
+                    copy::<VarInt>(r, w)?;
+                    // The entity ID of the killer.
+                    (-1_i32).encode(w)
+                })
+            })
+            .rewrite_serverbound(UpdateSignC2s::ID, |_, frame| {
+                rewrite_body(frame, |r, w| {
+                    copy::<i64>(r, w)?;
+                    // Only the front text can be edited.
+                    true.encode(w)
+                })
+            });
+
+        mapping.block_states = IdMap::from_table(tables::BLOCK_STATES);
+        mapping.blocks = IdMap::from_tables(tables::BLOCKS, tables::BLOCKS_TO_NATIVE);
+        mapping.block_entity_kinds = IdMap::from_table(tables::BLOCK_ENTITY_KINDS);
+        mapping.items = IdMap::from_tables(tables::ITEMS, tables::ITEMS_TO_NATIVE);
+        mapping.sounds = IdMap::from_table(tables::SOUNDS);
+        mapping.particles = IdMap::from_table(tables::PARTICLES);
+
+        mapping
+    }
+}
+
+/// Removes the trim patterns whose template item has no equivalent on the
+/// client, which would fail to decode the registry codec otherwise.
+fn remove_unknown_trim_patterns(mapping: &VersionMapping, codec: &mut Compound) {
+    let Some(Value::Compound(registry)) = codec.get_mut("minecraft:trim_pattern") else {
+        return;
+    };
+
+    let Some(Value::List(List::Compound(patterns))) = registry.get_mut("value") else {
+        return;
+    };
+
+    patterns.retain(|pattern| {
+        let Some(Value::Compound(element)) = pattern.get("element") else {
+            return true;
+        };
+
+        let Some(Value::String(item)) = element.get("template_item") else {
+            return true;
+        };
+
+        let name = item.strip_prefix("minecraft:").unwrap_or(item);
+        ItemKind::from_str(name)
+            .is_none_or(|kind| mapping.items.is_equivalent(kind.to_raw().into()))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::block::BlockEntityKind;
+    use crate::decode::PacketFrame;
+    use crate::packets::play::chunk_data_s2c::ChunkDataBlockEntity;
+    use crate::packets::play::chunk_delta_update_s2c::ChunkDeltaUpdateEntry;
+    use crate::packets::play::{ClickSlotC2s, EntityTrackerUpdateS2c, InventoryS2c, PlaySoundS2c};
+    use crate::sound::{SoundCategory, SoundId};
+    use crate::{
+        BlockState, ChunkPos, ChunkSectionPos, Decode, ItemStack, PacketDecoder, PacketEncoder,
+        RawBytes, VarLong,
+    };
+
+    // IDs of 1.19.4.
+    const SCULK: i32 = 21022;
+    const GRAVEL: i32 = 118;
+    const REINFORCED_DEEPSLATE: i32 = 23716;
+    const SUSPICIOUS_SAND_BLOCK_ENTITY: i32 = 38;
+    const DIAMOND_ITEM: i32 = 760;
+    const GRAVEL_ITEM: i32 = 47;
+    const BRICK_ITEM: i32 = 877;
+    const POTTERY_SHARD_ARCHER_ITEM: i32 = 1224;
+    const GRAVEL_BREAK_SOUND: i32 = 565;
+
+    fn clientbound<P: Encode + Packet>(packet: &P) -> PacketFrame {
+        let mut enc = PacketEncoder::new();
+        enc.set_translator(Some(Arc::new(VersionMapping::v1_19_4())));
+        enc.append_packet(packet).unwrap();
+
+        let mut dec = PacketDecoder::new();
+        dec.queue_bytes(enc.take());
+
+        let frame = dec.try_next_packet().unwrap().unwrap();
+        assert!(dec.try_next_packet().unwrap().is_none());
+        frame
+    }
+
+    fn serverbound(id: i32, body: &[u8]) -> PacketFrame {
+        let mut data = vec![];
+        VarInt(id).encode(&mut data).unwrap();
+        data.extend_from_slice(body);
+
+        let mut packet = vec![];
+        VarInt(data.len() as i32).encode(&mut packet).unwrap();
+        packet.extend_from_slice(&data);
+
+        let mut dec = PacketDecoder::new();
+        dec.set_translator(Some(Arc::new(VersionMapping::v1_19_4())));
+        dec.queue_slice(&packet);
+        dec.try_next_packet().unwrap().unwrap()
+    }
+
+    fn item_stack(r: &mut &[u8]) -> (i32, i8) {
+        assert!(bool::decode(r).unwrap());
+        let item = VarInt::decode(r).unwrap().0;
+        let count = i8::decode(r).unwrap();
+        assert_eq!(u8::decode(r).unwrap(), 0);
+        (item, count)
+    }
+
+    #[test]
+    fn chunk_data() {
+        let mut sections = vec![];
+
+        // Single valued blocks and biomes.
+        4096_i16.encode(&mut sections).unwrap();
+        0_u8.encode(&mut sections).unwrap();
+        VarInt(BlockState::SCULK.to_raw().into())
+            .encode(&mut sections)
+            .unwrap();
+        VarInt(0).encode(&mut sections).unwrap();
+        [0_u8; 3].encode(&mut sections).unwrap();
+
+        // Blocks with an indirect palette.
+        1_i16.encode(&mut sections).unwrap();
+        4_u8.encode(&mut sections).unwrap();
+        vec![
+            VarInt(0),
+            VarInt(BlockState::SUSPICIOUS_GRAVEL.to_raw().into()),
+        ]
+        .encode(&mut sections)
+        .unwrap();
+        let mut data = vec![0_u64; 256];
+        data[0] = 1;
+        data.encode(&mut sections).unwrap();
+        [0_u8; 3].encode(&mut sections).unwrap();
+
+        // Blocks with a direct palette.
+        2_i16.encode(&mut sections).unwrap();
+        15_u8.encode(&mut sections).unwrap();
+        let mut data = vec![0_u64; 1024];
+        data[0] = u64::from(BlockState::REINFORCED_DEEPSLATE.to_raw())
+            | u64::from(BlockState::STONE.to_raw()) << 15;
+        data.encode(&mut sections).unwrap();
+        [0_u8; 3].encode(&mut sections).unwrap();
+
+        let frame = clientbound(&ChunkDataS2c {
+            pos: ChunkPos::new(1, 2),
+            heightmaps: Cow::Owned(Compound::new()),
+            blocks_and_biomes: &sections,
+            block_entities: Cow::Owned(vec![ChunkDataBlockEntity {
+                packed_xz: 0,
+                y: 0,
+                kind: BlockEntityKind::BrushableBlock,
+                data: Cow::Owned(Compound::new()),
+            }]),
+            sky_light_mask: Cow::Owned(vec![0b101]),
+            block_light_mask: Cow::Owned(vec![]),
+            empty_sky_light_mask: Cow::Owned(vec![]),
+            empty_block_light_mask: Cow::Owned(vec![]),
+            sky_light_arrays: Cow::Owned(vec![]),
+            block_light_arrays: Cow::Owned(vec![]),
+        });
+
+        let mut r = &frame.body[..];
+        assert_eq!(<[i32; 2]>::decode(&mut r).unwrap(), [1, 2]);
+        Compound::decode(&mut r).unwrap();
+
+        let mut sections = <&[u8]>::decode(&mut r).unwrap();
+
+        i16::decode(&mut sections).unwrap();
+        assert_eq!(u8::decode(&mut sections).unwrap(), 0);
+        assert_eq!(VarInt::decode(&mut sections).unwrap().0, SCULK);
+        <[u8; 4]>::decode(&mut sections).unwrap();
+
+        i16::decode(&mut sections).unwrap();
+        assert_eq!(u8::decode(&mut sections).unwrap(), 4);
+        assert_eq!(
+            Vec::<VarInt>::decode(&mut sections).unwrap(),
+            [VarInt(0), VarInt(GRAVEL)]
+        );
+        assert_eq!(Vec::<u64>::decode(&mut sections).unwrap()[0], 1);
+        <[u8; 3]>::decode(&mut sections).unwrap();
+
+        i16::decode(&mut sections).unwrap();
+        assert_eq!(u8::decode(&mut sections).unwrap(), 15);
+        let data = Vec::<u64>::decode(&mut sections).unwrap();
+        assert_eq!(data[0] & 0x7fff, REINFORCED_DEEPSLATE as u64);
+        assert_eq!(data[0] >> 15 & 0x7fff, 1);
+        assert!(data[1..].iter().all(|&long| long == 0));
+        <[u8; 3]>::decode(&mut sections).unwrap();
+        assert!(sections.is_empty());
+
+        assert_eq!(VarInt::decode(&mut r).unwrap().0, 1);
+        <(i8, i16)>::decode(&mut r).unwrap();
+        assert_eq!(
+            VarInt::decode(&mut r).unwrap().0,
+            SUSPICIOUS_SAND_BLOCK_ENTITY
+        );
+        Compound::decode(&mut r).unwrap();
+
+        // Trust edges, followed by the light data.
+        assert!(bool::decode(&mut r).unwrap());
+        assert_eq!(Vec::<u64>::decode(&mut r).unwrap(), [0b101]);
+    }
+
+    #[test]
+    fn chunk_delta_update() {
+        let frame = clientbound(&ChunkDeltaUpdateS2c {
+            chunk_sect_pos: ChunkSectionPos::new(0, 1, 0),
+            blocks: Cow::Owned(vec![ChunkDeltaUpdateEntry::new()
+                .with_off_x(3)
+                .with_block_state(BlockState::SCULK.to_raw().into())]),
+        });
+
+        let mut r = &frame.body[..];
+        i64::decode(&mut r).unwrap();
+        // Light updates are not suppressed.
+        assert!(!bool::decode(&mut r).unwrap());
+
+        let entries = Vec::<VarLong>::decode(&mut r).unwrap();
+        assert_eq!(entries, [VarLong(i64::from(SCULK) << 12 | 3 << 8)]);
+        assert!(r.is_empty());
+    }
+
+    #[test]
+    fn inventory() {
+        let frame = clientbound(&InventoryS2c {
+            window_id: 0,
+            state_id: VarInt(0),
+            slots: Cow::Owned(vec![
+                ItemStack::new(ItemKind::Diamond, 2, None),
+                ItemStack::EMPTY,
+                ItemStack::new(ItemKind::ArcherPotterySherd, 1, None),
+                ItemStack::new(ItemKind::AnglerPotterySherd, 1, None),
+            ]),
+            carried_item: Cow::Owned(ItemStack::new(ItemKind::SuspiciousGravel, 64, None)),
+        });
+
+        let mut r = &frame.body[..];
+        <(u8, VarInt, VarInt)>::decode(&mut r).unwrap();
+        assert_eq!(item_stack(&mut r), (DIAMOND_ITEM, 2));
+        assert!(!bool::decode(&mut r).unwrap());
+        assert_eq!(item_stack(&mut r), (POTTERY_SHARD_ARCHER_ITEM, 1));
+        // New items are replaced with similar ones.
+        assert_eq!(item_stack(&mut r), (BRICK_ITEM, 1));
+        assert_eq!(item_stack(&mut r), (GRAVEL_ITEM, 64));
+        assert!(r.is_empty());
+    }
+
+    #[test]
+    fn entity_metadata() {
+        let mut values = vec![];
+        // An item stack.
+        8_u8.encode(&mut values).unwrap();
+        VarInt(7).encode(&mut values).unwrap();
+        ItemStack::new(ItemKind::Diamond, 1, None)
+            .encode(&mut values)
+            .unwrap();
+        // A string.
+        2_u8.encode(&mut values).unwrap();
+        VarInt(4).encode(&mut values).unwrap();
+        "sculk".encode(&mut values).unwrap();
+        // An optional block state.
+        9_u8.encode(&mut values).unwrap();
+        VarInt(15).encode(&mut values).unwrap();
+        VarInt(i32::from(BlockState::SCULK.to_raw()) + 1)
+            .encode(&mut values)
+            .unwrap();
+        0xff_u8.encode(&mut values).unwrap();
+
+        let frame = clientbound(&EntityTrackerUpdateS2c {
+            entity_id: VarInt(1),
+            tracked_values: RawBytes(&values),
+        });
+
+        let mut r = &frame.body[..];
+        VarInt::decode(&mut r).unwrap();
+        assert_eq!(<(u8, VarInt)>::decode(&mut r).unwrap(), (8, VarInt(7)));
+        assert_eq!(item_stack(&mut r), (DIAMOND_ITEM, 1));
+        assert_eq!(<(u8, VarInt)>::decode(&mut r).unwrap(), (2, VarInt(4)));
+        assert_eq!(<&str>::decode(&mut r).unwrap(), "sculk");
+        assert_eq!(
+            <(u8, VarInt, VarInt)>::decode(&mut r).unwrap(),
+            (9, VarInt(15), VarInt(SCULK + 1))
+        );
+        assert_eq!(u8::decode(&mut r).unwrap(), 0xff);
+        assert!(r.is_empty());
+    }
+
+    #[test]
+    fn sound() {
+        let frame = clientbound(&PlaySoundS2c {
+            id: SoundId::Reference {
+                id: VarInt(crate::Sound::BlockSuspiciousGravelBreak.to_raw().into()),
+            },
+            category: SoundCategory::Block,
+            position: Default::default(),
+            volume: 1.0,
+            pitch: 1.0,
+            seed: 0,
+        });
+
+        let mut r = &frame.body[..];
+        assert_eq!(VarInt::decode(&mut r).unwrap().0, GRAVEL_BREAK_SOUND + 1);
+    }
+
+    #[test]
+    fn serverbound_packets() {
+        // A click as a 1.19.4 client would send it.
+        let mut body = vec![];
+        (0_u8, VarInt(1), 36_i16, 0_i8, VarInt(0))
+            .encode(&mut body)
+            .unwrap();
+        VarInt(1).encode(&mut body).unwrap();
+        36_i16.encode(&mut body).unwrap();
+        (true, VarInt(POTTERY_SHARD_ARCHER_ITEM), 1_i8, 0_u8)
+            .encode(&mut body)
+            .unwrap();
+        (true, VarInt(DIAMOND_ITEM), 3_i8, 0_u8)
+            .encode(&mut body)
+            .unwrap();
+
+        let frame = serverbound(ClickSlotC2s::ID, &body);
+        let click = frame.decode::<ClickSlotC2s>().unwrap();
+        assert_eq!(
+            click.slot_changes[0].stack.item,
+            ItemKind::ArcherPotterySherd
+        );
+        assert_eq!(click.carried_item.item, ItemKind::Diamond);
+
+        // A sign update without the side of the sign.
+        let mut body = vec![];
+        crate::BlockPos::new(1, 2, 3).encode(&mut body).unwrap();
+        ["a", "b", "c", "d"].encode(&mut body).unwrap();
+
+        let frame = serverbound(UpdateSignC2s::ID, &body);
+        let update = frame.decode::<UpdateSignC2s>().unwrap();
+        assert!(update.is_front_text);
+        assert_eq!(update.lines.map(|line| line.0), ["a", "b", "c", "d"]);
+    }
+}
@@ -61,7 +61,7 @@ fn handle_player_action(
             }
 
             // TODO: check that digging is happening within configurable distance to client.
-            // TODO: check that blocks are being broken at the appropriate speeds.
+            // Break speeds are checked by `BlockBreakingPlugin` in `valence_inventory`.
 
             match pkt.action {
                 PlayerAction::StartDestroyBlock => {
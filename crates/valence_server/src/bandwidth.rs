@@ -0,0 +1,260 @@
+//! Bandwidth accounting and the outgoing packet budget of clients.
+//!
+//! Packets written to a [`Client`] are sent at the end of the tick. Chunk data
+//! and the packets that must arrive in order with it are instead written to the
+//! client's [deferred queue](Client::deferred), which is sent as fast as the
+//! client's [`SendBudget`] allows. This way, loading a large number of chunks
+//! does not hold back time-critical packets such as entity movement.
+//!
+//! [`Client`]: crate::client::Client
+
+use bevy_ecs::prelude::*;
+use derive_more::{Deref, DerefMut};
+use flate2::{Decompress, FlushDecompress};
+use rustc_hash::FxHashMap;
+use valence_protocol::{CompressionThreshold, VarInt};
+
+/// The default [`SendBudget`] of clients.
+pub const DEFAULT_SEND_BUDGET: usize = 256 * 1024;
+
+/// The number of bytes that may be sent to a client per tick.
+///
+/// Packets written directly to the client are always sent in full. Packets in
+/// the [deferred queue](crate::client::Client::deferred) are only sent while
+/// the total number of bytes sent in the tick is within the budget. At least
+/// one deferred packet is sent per tick so that the queue is always drained
+/// eventually.
+///
+/// Use `SendBudget(usize::MAX)` to send all packets at the end of every tick.
+#[derive(Component, Copy, Clone, PartialEq, Eq, Debug, Deref, DerefMut)]
+pub struct SendBudget(pub usize);
+
+impl Default for SendBudget {
+    fn default() -> Self {
+        Self(DEFAULT_SEND_BUDGET)
+    }
+}
+
+/// The number of packets of some type and the total size of those packets in
+/// bytes.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub struct PacketCount {
+    pub packets: u64,
+    pub bytes: u64,
+}
+
+impl PacketCount {
+    fn add(&mut self, bytes: usize) {
+        self.packets += 1;
+        self.bytes += bytes as u64;
+    }
+}
+
+/// Counts the packets sent to and received from a client by packet ID. See
+/// [`Client::packet_stats`](crate::client::Client::packet_stats).
+///
+/// Sent packets are counted when they are written to the client, with their
+/// size on the wire including the length prefix and compression. Received
+/// packets are counted with the size of their ID and body after
+/// decompression.
+///
+/// Packets written as pre-encoded bytes, like those broadcast by layers, are
+/// not counted by ID unless [frame inspection](Self::set_inspect_frames) is
+/// enabled, because reading their IDs means decompressing every packet again
+/// for every client. Their size is counted in
+/// [`unidentified_sent_bytes`](Self::unidentified_sent_bytes) instead.
+#[derive(Clone, Default, Debug)]
+pub struct PacketStats {
+    sent: FxHashMap<i32, PacketCount>,
+    received: FxHashMap<i32, PacketCount>,
+    unidentified_sent_bytes: u64,
+    inspect_frames: bool,
+}
+
+impl PacketStats {
+    /// Returns the sent packets with the given ID.
+    pub fn sent(&self, id: i32) -> PacketCount {
+        self.sent.get(&id).copied().unwrap_or_default()
+    }
+
+    /// Returns the received packets with the given ID.
+    pub fn received(&self, id: i32) -> PacketCount {
+        self.received.get(&id).copied().unwrap_or_default()
+    }
+
+    /// Returns an iterator over the IDs of all sent packets and their counts
+    /// in no particular order.
+    pub fn iter_sent(&self) -> impl Iterator<Item = (i32, PacketCount)> + '_ {
+        self.sent.iter().map(|(&id, &count)| (id, count))
+    }
+
+    /// Returns an iterator over the IDs of all received packets and their
+    /// counts in no particular order.
+    pub fn iter_received(&self) -> impl Iterator<Item = (i32, PacketCount)> + '_ {
+        self.received.iter().map(|(&id, &count)| (id, count))
+    }
+
+    /// Returns the number of bytes sent as pre-encoded packets whose IDs were
+    /// not read.
+    pub fn unidentified_sent_bytes(&self) -> u64 {
+        self.unidentified_sent_bytes
+    }
+
+    /// Returns the sum of all sent packets. The number of bytes includes the
+    /// [unidentified](Self::unidentified_sent_bytes) bytes, but the number of
+    /// packets does not.
+    pub fn total_sent(&self) -> PacketCount {
+        let mut total = sum(self.sent.values());
+        total.bytes += self.unidentified_sent_bytes;
+        total
+    }
+
+    /// Returns the sum of all received packets.
+    pub fn total_received(&self) -> PacketCount {
+        sum(self.received.values())
+    }
+
+    /// Resets all counters to zero.
+    pub fn clear(&mut self) {
+        self.sent.clear();
+        self.received.clear();
+        self.unidentified_sent_bytes = 0;
+    }
+
+    /// Returns whether pre-encoded packets are inspected to count them by ID.
+    pub fn inspect_frames(&self) -> bool {
+        self.inspect_frames
+    }
+
+    /// Sets whether pre-encoded packets are inspected to count them by ID.
+    /// This is disabled by default because it is expensive with compression
+    /// enabled.
+    pub fn set_inspect_frames(&mut self, inspect_frames: bool) {
+        self.inspect_frames = inspect_frames;
+    }
+
+    pub(crate) fn record_sent(&mut self, id: i32, bytes: usize) {
+        self.sent.entry(id).or_default().add(bytes);
+    }
+
+    pub(crate) fn record_received(&mut self, id: i32, bytes: usize) {
+        self.received.entry(id).or_default().add(bytes);
+    }
+
+    /// Records pre-encoded packet frames as sent. The frames are only read if
+    /// frame inspection is enabled.
+    pub(crate) fn record_sent_frames(&mut self, mut bytes: &[u8], threshold: CompressionThreshold) {
+        if self.inspect_frames {
+            while let Some((len, id)) = next_frame(bytes, threshold) {
+                match id {
+                    Some(id) => self.record_sent(id, len),
+                    None => self.unidentified_sent_bytes += len as u64,
+                }
+
+                bytes = &bytes[len..];
+            }
+        }
+
+        self.unidentified_sent_bytes += bytes.len() as u64;
+    }
+}
+
+fn sum<'a>(counts: impl Iterator<Item = &'a PacketCount>) -> PacketCount {
+    counts.fold(PacketCount::default(), |acc, count| PacketCount {
+        packets: acc.packets + count.packets,
+        bytes: acc.bytes + count.bytes,
+    })
+}
+
+/// Returns the length of the first packet frame in `bytes` including its
+/// length prefix. Returns `None` if `bytes` does not begin with a complete
+/// frame.
+pub(crate) fn frame_len(bytes: &[u8]) -> Option<usize> {
+    let mut r = bytes;
+    let packet_len = usize::try_from(VarInt::decode_partial(&mut r).ok()?).ok()?;
+    let len = bytes.len() - r.len() + packet_len;
+    (len <= bytes.len()).then_some(len)
+}
+
+/// Returns the length of the first packet frame in `bytes` like [`frame_len`],
+/// along with the packet ID if it could be read.
+fn next_frame(bytes: &[u8], threshold: CompressionThreshold) -> Option<(usize, Option<i32>)> {
+    let len = frame_len(bytes)?;
+    let mut r = &bytes[..len];
+    VarInt::decode_partial(&mut r).ok()?;
+
+    if threshold.0 < 0 {
+        return Some((len, VarInt::decode_partial(r).ok()));
+    }
+
+    let data_len = VarInt::decode_partial(&mut r).ok()?;
+
+    if data_len == 0 {
+        return Some((len, VarInt::decode_partial(r).ok()));
+    }
+
+    // Only the beginning of the packet needs to be decompressed to read the ID.
+    let mut id_buf = [0; VarInt::MAX_SIZE];
+    let mut z = Decompress::new(true);
+    let id = match z.decompress(r, &mut id_buf, FlushDecompress::None) {
+        Ok(_) => VarInt::decode_partial(&id_buf[..z.total_out() as usize]).ok(),
+        Err(_) => None,
+    };
+
+    Some((len, id))
+}
+
+#[cfg(test)]
+mod tests {
+    use valence_protocol::encode::{PacketWriter, WritePacket};
+    use valence_protocol::packets::play::{ChunkLoadDistanceS2c, CustomPayloadS2c};
+    use valence_protocol::{ident, Packet, RawBytes};
+
+    use super::*;
+
+    #[test]
+    fn record_sent_frames() {
+        for threshold in [CompressionThreshold(-1), CompressionThreshold(64)] {
+            let mut buf = vec![];
+            let mut writer = PacketWriter::new(&mut buf, threshold);
+
+            writer.write_packet(&ChunkLoadDistanceS2c {
+                view_distance: VarInt(10),
+            });
+            let small_len = writer.buf.len();
+
+            // Large enough to be compressed.
+            writer.write_packet(&CustomPayloadS2c {
+                channel: ident!("test").into(),
+                data: RawBytes(&[0; 1000]).into(),
+            });
+            let large_len = writer.buf.len() - small_len;
+
+            let mut stats = PacketStats::default();
+            stats.record_sent_frames(&buf, threshold);
+
+            assert_eq!(stats.total_sent().packets, 0);
+            assert_eq!(stats.unidentified_sent_bytes(), buf.len() as u64);
+
+            stats.clear();
+            stats.set_inspect_frames(true);
+            stats.record_sent_frames(&buf, threshold);
+
+            assert_eq!(
+                stats.sent(ChunkLoadDistanceS2c::ID),
+                PacketCount {
+                    packets: 1,
+                    bytes: small_len as u64
+                }
+            );
+            assert_eq!(
+                stats.sent(CustomPayloadS2c::ID),
+                PacketCount {
+                    packets: 1,
+                    bytes: large_len as u64
+                }
+            );
+            assert_eq!(stats.total_sent().bytes, buf.len() as u64);
+        }
+    }
+}
@@ -22,7 +22,7 @@ use valence_entity::{
     ClearEntityChangesSet, EntityId, EntityStatus, OldPosition, Position, Velocity,
 };
 use valence_math::{DVec3, Vec3};
-use valence_protocol::encode::{PacketEncoder, WritePacket};
+use valence_protocol::encode::{PacketEncoder, PacketWriter, WritePacket};
 use valence_protocol::packets::play::chunk_biome_data_s2c::ChunkBiome;
 use valence_protocol::packets::play::game_state_change_s2c::GameEventKind;
 use valence_protocol::packets::play::particle_s2c::Particle;
@@ -36,11 +36,13 @@ use valence_protocol::profile::Property;
 use valence_protocol::sound::{Sound, SoundCategory, SoundId};
 use valence_protocol::text::{IntoText, Text};
 use valence_protocol::var_int::VarInt;
-use valence_protocol::{BlockPos, ChunkPos, Encode, GameMode, Packet};
+use valence_protocol::{BlockPos, ChunkPos, Encode, GameMode, Packet, PROTOCOL_VERSION};
 use valence_registry::RegistrySet;
 use valence_server_common::{Despawned, UniqueId};
 
+use crate::bandwidth::{PacketStats, SendBudget};
 use crate::layer::{ChunkLayer, EntityLayer, UpdateLayersPostClientSet, UpdateLayersPreClientSet};
+use crate::recorder::PacketRecorder;
 use crate::ChunkView;
 
 pub struct ClientPlugin;
@@ -142,6 +144,9 @@ pub struct ClientBundle {
     pub flying_speed: crate::abilities::FlyingSpeed,
     pub fov_modifier: crate::abilities::FovModifier,
     pub player_abilities_flags: crate::abilities::PlayerAbilitiesFlags,
+    pub experience: crate::experience::Experience,
+    pub send_budget: SendBudget,
+    pub movement_state: crate::movement::MovementState,
     pub player: PlayerEntityBundle,
 }
 
@@ -152,6 +157,10 @@ impl ClientBundle {
             client: Client {
                 conn: args.conn,
                 enc: args.enc,
+                deferred: BytesMut::new(),
+                stats: PacketStats::default(),
+                recorder: None,
+                sent_velocity: None,
             },
             settings: Default::default(),
             entity_remove_buf: Default::default(),
@@ -183,6 +192,9 @@ impl ClientBundle {
             flying_speed: Default::default(),
             fov_modifier: Default::default(),
             player_abilities_flags: Default::default(),
+            experience: Default::default(),
+            send_budget: Default::default(),
+            movement_state: Default::default(),
             player: PlayerEntityBundle {
                 uuid: UniqueId(args.uuid),
                 ..Default::default()
@@ -221,6 +233,13 @@ pub struct ClientMarker;
 pub struct Client {
     conn: Box<dyn ClientConnection>,
     pub(crate) enc: PacketEncoder,
+    /// Packet frames waiting to be sent within the [`SendBudget`].
+    deferred: BytesMut,
+    pub(crate) stats: PacketStats,
+    recorder: Option<PacketRecorder>,
+    /// The velocity last set with [`Client::set_velocity`], until it is taken
+    /// by the movement checks.
+    sent_velocity: Option<Vec3>,
 }
 
 /// Represents the bidirectional packet channel between the server and a client
@@ -239,6 +258,12 @@ pub trait ClientConnection: Send + Sync + 'static {
     fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// The number of bytes passed to [`Self::try_send`] that have not been
+    /// written to the underlying connection yet.
+    fn unsent_len(&self) -> usize {
+        0
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -265,14 +290,43 @@ impl WritePacket for Client {
     where
         P: Packet + Encode,
     {
-        self.enc.write_packet_fallible(packet)
+        let start_len = self.enc.len();
+        self.enc.write_packet_fallible(packet)?;
+        self.stats.record_sent(P::ID, self.enc.len() - start_len);
+        Ok(())
     }
 
     fn write_packet_bytes(&mut self, bytes: &[u8]) {
+        self.stats.record_sent_frames(bytes, self.enc.compression());
         self.enc.write_packet_bytes(bytes)
     }
 }
 
+/// Writes packets to the back of a client's deferred queue. See
+/// [`Client::deferred`].
+pub struct DeferredWriter<'a> {
+    client: &'a mut Client,
+}
+
+impl WritePacket for DeferredWriter<'_> {
+    fn write_packet_fallible<P>(&mut self, packet: &P) -> anyhow::Result<()>
+    where
+        P: Packet + Encode,
+    {
+        let mut buf = vec![];
+        PacketWriter::new(&mut buf, self.client.enc.compression()).write_packet_fallible(packet)?;
+        self.client.stats.record_sent(P::ID, buf.len());
+        self.client.deferred.extend_from_slice(&buf);
+        Ok(())
+    }
+
+    fn write_packet_bytes(&mut self, bytes: &[u8]) {
+        let threshold = self.client.enc.compression();
+        self.client.stats.record_sent_frames(bytes, threshold);
+        self.client.deferred.extend_from_slice(bytes)
+    }
+}
+
 impl Client {
     pub fn connection(&self) -> &dyn ClientConnection {
         self.conn.as_ref()
@@ -282,14 +336,84 @@ impl Client {
         self.conn.as_mut()
     }
 
-    /// Flushes the packet queue to the underlying connection.
+    /// Returns a writer for this client's deferred queue.
     ///
-    /// This is called automatically at the end of the tick and when the client
-    /// is dropped. Unless you're in a hurry, there's usually no reason to
-    /// call this method yourself.
+    /// Packets in the deferred queue are sent in the order they were written,
+    /// but only after the packets written directly to the client and within
+    /// the client's [`SendBudget`]. Chunk data and the packets that must be
+    /// ordered with it, like block updates and chunk unloads, are written here.
+    /// This is also a good place for other large packets which are not
+    /// time-critical.
+    pub fn deferred(&mut self) -> DeferredWriter<'_> {
+        DeferredWriter { client: self }
+    }
+
+    /// The number of bytes in the deferred queue that have not been sent yet.
+    pub fn deferred_len(&self) -> usize {
+        self.deferred.len()
+    }
+
+    /// Discards the packets in the deferred queue that have not been sent yet.
+    pub fn clear_deferred(&mut self) {
+        self.deferred.clear();
+    }
+
+    /// Returns the counts of packets sent to and received from this client.
+    pub fn packet_stats(&self) -> &PacketStats {
+        &self.stats
+    }
+
+    pub fn packet_stats_mut(&mut self) -> &mut PacketStats {
+        &mut self.stats
+    }
+
+    /// Flushes the packet queue and the entire deferred queue to the
+    /// underlying connection.
+    ///
+    /// This is called when the client is dropped. Unless you're in a hurry,
+    /// there's usually no reason to call this method yourself.
     ///
     /// Returns an error if flushing was unsuccessful.
     pub fn flush_packets(&mut self) -> anyhow::Result<()> {
+        self.flush_packets_within(usize::MAX)
+    }
+
+    /// Flushes the packet queue to the underlying connection, followed by as
+    /// much of the deferred queue as fits in `budget` bytes. See
+    /// [`SendBudget`].
+    ///
+    /// This is called automatically at the end of the tick with the client's
+    /// [`SendBudget`].
+    ///
+    /// Returns an error if flushing was unsuccessful.
+    pub fn flush_packets_within(&mut self, budget: usize) -> anyhow::Result<()> {
+        let mut sent = self.enc.len();
+        let mut deferred_len = 0;
+
+        while let Some(len) = crate::bandwidth::frame_len(&self.deferred[deferred_len..]) {
+            // Always send at least one deferred packet to make progress.
+            if deferred_len > 0 && sent.saturating_add(len) > budget {
+                break;
+            }
+
+            sent += len;
+            deferred_len += len;
+        }
+
+        if deferred_len > 0 {
+            let deferred = self.deferred.split_to(deferred_len);
+            self.enc.append_bytes(&deferred);
+        }
+
+        if let Some(recorder) = &mut self.recorder {
+            if !self.enc.is_empty() {
+                if let Err(e) = recorder.record_clientbound(self.enc.bytes(), Instant::now()) {
+                    warn!("failed to record packets, stopping recording: {e:#}");
+                    self.recorder = None;
+                }
+            }
+        }
+
         let bytes = self.enc.take();
         if !bytes.is_empty() {
             self.conn.try_send(bytes)
@@ -298,6 +422,54 @@ impl Client {
         }
     }
 
+    /// Records a packet received from the client in its [`PacketStats`] and
+    /// recording.
+    pub(crate) fn record_received(&mut self, pkt: &ReceivedPacket) {
+        self.stats
+            .record_received(pkt.id, VarInt(pkt.id).written_size() + pkt.body.len());
+
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(e) = recorder.record_serverbound(pkt.id, &pkt.body, pkt.timestamp) {
+                warn!("failed to record packets, stopping recording: {e:#}");
+                self.recorder = None;
+            }
+        }
+    }
+
+    /// Starts recording the packets sent to and received from this client with
+    /// `recorder`. Any previous recording is stopped. See
+    /// [`recorder`](crate::recorder).
+    ///
+    /// To record a session that can be replayed, start the recording on the
+    /// tick the client is added so that the recording includes the initial
+    /// packets of the game.
+    pub fn start_recording(&mut self, mut recorder: PacketRecorder) -> anyhow::Result<()> {
+        self.stop_recording()?;
+
+        let protocol_version = self
+            .enc
+            .translator()
+            .map_or(PROTOCOL_VERSION, |t| t.protocol_version());
+
+        recorder.write_header(protocol_version, self.enc.compression())?;
+        self.recorder = Some(recorder);
+
+        Ok(())
+    }
+
+    /// Stops the current recording, if any, and flushes it.
+    pub fn stop_recording(&mut self) -> anyhow::Result<()> {
+        if let Some(mut recorder) = self.recorder.take() {
+            recorder.flush()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
     /// Kills the client and shows `message` on the death screen. If an entity
     /// killed the player, you should supply it as `killer`.
     pub fn kill<'a, M: IntoText<'a>>(&mut self, message: M) {
@@ -364,10 +536,18 @@ impl Client {
 
     /// `velocity` is in m/s.
     pub fn set_velocity<V: Into<Vec3>>(&mut self, velocity: V) {
+        let velocity = velocity.into();
+
         self.write_packet(&EntityVelocityUpdateS2c {
             entity_id: VarInt(0),
-            velocity: Velocity(velocity.into()).to_packet_units(),
+            velocity: Velocity(velocity).to_packet_units(),
         });
+
+        self.sent_velocity = Some(velocity);
+    }
+
+    pub(crate) fn take_sent_velocity(&mut self) -> Option<Vec3> {
+        self.sent_velocity.take()
     }
 
     /// Triggers an [`EntityStatus`].
@@ -675,11 +855,11 @@ fn handle_layer_messages(
                 for (msg, range) in messages.iter_global() {
                     match msg {
                         crate::layer::chunk::GlobalMsg::Packet => {
-                            client.write_packet_bytes(&bytes[range]);
+                            client.deferred().write_packet_bytes(&bytes[range]);
                         }
                         crate::layer::chunk::GlobalMsg::PacketExcept { except } => {
                             if self_entity != except {
-                                client.write_packet_bytes(&bytes[range]);
+                                client.deferred().write_packet_bytes(&bytes[range]);
                             }
                         }
                     }
@@ -690,11 +870,11 @@ fn handle_layer_messages(
                 // Local messages
                 messages.query_local(old_view, |msg, range| match msg {
                     crate::layer::chunk::LocalMsg::PacketAt { .. } => {
-                        client.write_packet_bytes(&bytes[range]);
+                        client.deferred().write_packet_bytes(&bytes[range]);
                     }
                     crate::layer::chunk::LocalMsg::PacketAtExcept { except, .. } => {
                         if self_entity != except {
-                            client.write_packet_bytes(&bytes[range]);
+                            client.deferred().write_packet_bytes(&bytes[range]);
                         }
                     }
                     crate::layer::chunk::LocalMsg::RadiusAt {
@@ -702,7 +882,7 @@ fn handle_layer_messages(
                         radius_squared,
                     } => {
                         if in_radius(block_pos, center, radius_squared) {
-                            client.write_packet_bytes(&bytes[range]);
+                            client.deferred().write_packet_bytes(&bytes[range]);
                         }
                     }
                     crate::layer::chunk::LocalMsg::RadiusAtExcept {
@@ -711,7 +891,7 @@ fn handle_layer_messages(
                         except,
                     } => {
                         if self_entity != except && in_radius(block_pos, center, radius_squared) {
-                            client.write_packet_bytes(&bytes[range]);
+                            client.deferred().write_packet_bytes(&bytes[range]);
                         }
                     }
                     crate::layer::chunk::LocalMsg::ChangeBiome { pos } => {
@@ -730,12 +910,16 @@ fn handle_layer_messages(
                             [.., ChunkLayer::LOAD | ChunkLayer::OVERWRITE] => {
                                 // Load chunk.
                                 let chunk = chunk_layer.chunk(pos).expect("chunk must exist");
-                                chunk.write_init_packets(&mut *client, pos, chunk_layer.info());
+                                chunk.write_init_packets(
+                                    client.deferred(),
+                                    pos,
+                                    chunk_layer.info(),
+                                );
                                 chunk.inc_viewer_count();
                             }
                             [.., ChunkLayer::UNLOAD] => {
                                 // Unload chunk.
-                                client.write_packet(&UnloadChunkS2c { pos });
+                                client.deferred().write_packet(&UnloadChunkS2c { pos });
                                 debug_assert!(chunk_layer.chunk(pos).is_none());
                             }
                             _ => unreachable!("invalid message data while changing chunk state"),
@@ -744,7 +928,7 @@ fn handle_layer_messages(
                 });
 
                 if !chunk_biome_buf.is_empty() {
-                    client.write_packet(&ChunkBiomeDataS2c {
+                    client.deferred().write_packet(&ChunkBiomeDataS2c {
                         chunks: chunk_biome_buf.into(),
                     });
                 }
@@ -958,10 +1142,12 @@ pub(crate) fn update_view_and_layers(
             // Make sure the center chunk is set before loading chunks! Otherwise the client
             // may ignore the chunk.
             if old_view.pos != view.pos {
-                client.write_packet(&ChunkRenderDistanceCenterS2c {
-                    chunk_x: VarInt(view.pos.x),
-                    chunk_z: VarInt(view.pos.z),
-                });
+                client
+                    .deferred()
+                    .write_packet(&ChunkRenderDistanceCenterS2c {
+                        chunk_x: VarInt(view.pos.x),
+                        chunk_z: VarInt(view.pos.z),
+                    });
             }
 
             // Was the client's chunk layer changed?
@@ -971,7 +1157,7 @@ pub(crate) fn update_view_and_layers(
                 if let Ok(layer) = chunk_layers.get(old_chunk_layer.0) {
                     for pos in old_view.iter() {
                         if let Some(chunk) = layer.chunk(pos) {
-                            client.write_packet(&UnloadChunkS2c { pos });
+                            client.deferred().write_packet(&UnloadChunkS2c { pos });
                             chunk.dec_viewer_count();
                         }
                     }
@@ -981,7 +1167,7 @@ pub(crate) fn update_view_and_layers(
                 if let Ok(layer) = chunk_layers.get(chunk_layer.0) {
                     for pos in view.iter() {
                         if let Some(chunk) = layer.chunk(pos) {
-                            chunk.write_init_packets(&mut *client, pos, layer.info());
+                            chunk.write_init_packets(client.deferred(), pos, layer.info());
                             chunk.inc_viewer_count();
                         }
                     }
@@ -1103,7 +1289,7 @@ pub(crate) fn update_view_and_layers(
                     if let Ok(layer) = chunk_layers.get(chunk_layer.0) {
                         for pos in old_view.diff(view) {
                             if let Some(chunk) = layer.chunk(pos) {
-                                client.write_packet(&UnloadChunkS2c { pos });
+                                client.deferred().write_packet(&UnloadChunkS2c { pos });
                                 chunk.dec_viewer_count();
                             }
                         }
@@ -1113,7 +1299,7 @@ pub(crate) fn update_view_and_layers(
                     if let Ok(layer) = chunk_layers.get(chunk_layer.0) {
                         for pos in view.diff(old_view) {
                             if let Some(chunk) = layer.chunk(pos) {
-                                chunk.write_init_packets(&mut *client, pos, layer.info());
+                                chunk.write_init_packets(client.deferred(), pos, layer.info());
                                 chunk.inc_viewer_count();
                             }
                         }
@@ -1229,12 +1415,13 @@ fn update_old_view_dist(
     }
 }
 
-fn flush_packets(
-    mut clients: Query<(Entity, &mut Client), Changed<Client>>,
-    mut commands: Commands,
-) {
-    for (entity, mut client) in &mut clients {
-        if let Err(e) = client.flush_packets() {
+fn flush_packets(mut clients: Query<(Entity, &mut Client, &SendBudget)>, mut commands: Commands) {
+    for (entity, mut client, budget) in &mut clients {
+        if !client.is_changed() && client.deferred.is_empty() {
+            continue;
+        }
+
+        if let Err(e) = client.flush_packets_within(budget.0) {
             warn!("Failed to flush packet queue for client {entity:?}: {e:#}.");
             commands.entity(entity).remove::<Client>();
         }
@@ -117,6 +117,8 @@ fn run_event_loop(
     for (entity, mut client) in &mut clients {
         match client.connection_mut().try_recv() {
             Ok(Some(pkt)) => {
+                client.record_received(&pkt);
+
                 event_writer.send(PacketEvent {
                     client: entity,
                     timestamp: pkt.timestamp,
@@ -151,6 +153,8 @@ fn run_event_loop(
             if let Ok((_, mut client)) = clients.get_mut(*entity) {
                 match client.connection_mut().try_recv() {
                     Ok(Some(pkt)) => {
+                        client.record_received(&pkt);
+
                         event_writer.send(PacketEvent {
                             client: *entity,
                             timestamp: pkt.timestamp,
@@ -0,0 +1,36 @@
+use bevy_app::prelude::*;
+use bevy_ecs::prelude::*;
+use valence_protocol::packets::play::ExperienceBarUpdateS2c;
+use valence_protocol::{VarInt, WritePacket};
+
+use crate::client::{Client, UpdateClientsSet};
+
+pub struct ExperiencePlugin;
+
+impl Plugin for ExperiencePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, update_experience.in_set(UpdateClientsSet));
+    }
+}
+
+/// The experience of a client, shown in the experience bar.
+#[derive(Component, Copy, Clone, PartialEq, Default, Debug)]
+pub struct Experience {
+    /// The experience level displayed above the experience bar.
+    pub level: i32,
+    /// How full the experience bar is, in the range \[0, 1].
+    pub progress: f32,
+    /// The total number of experience points collected. This is shown on the
+    /// death screen.
+    pub total: i32,
+}
+
+fn update_experience(mut clients: Query<(&mut Client, &Experience), Changed<Experience>>) {
+    for (mut client, xp) in &mut clients {
+        client.write_packet(&ExperienceBarUpdateS2c {
+            bar: xp.progress,
+            level: VarInt(xp.level),
+            total_xp: VarInt(xp.total),
+        });
+    }
+}
@@ -1,5 +1,6 @@
 #[allow(clippy::module_inception)]
 mod chunk;
+pub mod light;
 pub mod loaded;
 mod paletted_container;
 pub mod unloaded;
@@ -11,6 +12,8 @@ use std::fmt;
 use bevy_app::prelude::*;
 use bevy_ecs::prelude::*;
 pub use chunk::{MAX_HEIGHT, *};
+pub use light::LightArray;
+use light::LightEngine;
 pub use loaded::LoadedChunk;
 use rustc_hash::FxHashMap;
 pub use unloaded::UnloadedChunk;
@@ -37,6 +40,8 @@ pub struct ChunkLayer {
     messages: ChunkLayerMessages,
     chunks: FxHashMap<ChunkPos, LoadedChunk>,
     info: ChunkLayerInfo,
+    /// The light engine of this layer, if enabled.
+    light_engine: Option<LightEngine>,
 }
 
 /// Chunk layer information.
@@ -46,6 +51,7 @@ pub(crate) struct ChunkLayerInfo {
     min_y: i32,
     biome_registry_len: usize,
     threshold: CompressionThreshold,
+    has_skylight: bool,
 }
 
 impl fmt::Debug for ChunkLayerInfo {
@@ -56,6 +62,7 @@ impl fmt::Debug for ChunkLayerInfo {
             .field("min_y", &self.min_y)
             .field("biome_registry_len", &self.biome_registry_len)
             .field("threshold", &self.threshold)
+            .field("has_skylight", &self.has_skylight)
             // Ignore sky light mask and array.
             .finish()
     }
@@ -151,7 +158,9 @@ impl ChunkLayer {
                 min_y: dim.min_y,
                 biome_registry_len: biomes.iter().len(),
                 threshold: server.compression_threshold(),
+                has_skylight: dim.has_skylight,
             },
+            light_engine: None,
         }
     }
 
@@ -170,6 +179,78 @@ impl ChunkLayer {
         self.info.min_y
     }
 
+    /// Returns whether the server-side light engine is enabled for this layer.
+    pub fn light_engine_enabled(&self) -> bool {
+        self.light_engine.is_some()
+    }
+
+    /// Enables or disables the server-side light engine for this layer. The
+    /// light engine is disabled by default.
+    ///
+    /// While enabled, sky light and block light are computed for every chunk
+    /// in the layer and updated whenever blocks change. Light changes are sent
+    /// to clients in view of the affected chunks at the end of the tick. Light
+    /// stored in an inserted [`UnloadedChunk`] is reused instead of being
+    /// recomputed if it is complete.
+    ///
+    /// While disabled, light stored in inserted chunks is still sent to
+    /// clients but is not updated when blocks change.
+    pub fn set_light_engine_enabled(&mut self, enabled: bool) {
+        if enabled == self.light_engine.is_some() {
+            return;
+        }
+
+        if enabled {
+            for chunk in self.chunks.values_mut() {
+                chunk.mark_needs_relight();
+            }
+
+            self.light_engine = Some(LightEngine::default());
+        } else {
+            self.light_engine = None;
+        }
+    }
+
+    /// Gets the sky light level at the given position, if the chunk is loaded
+    /// and has sky light.
+    pub fn sky_light<P: Into<BlockPos>>(&self, pos: P) -> Option<u8> {
+        let (chunk, x, y, z) = self.chunk_and_offsets(pos.into())?;
+
+        chunk
+            .sky_light_section(y / 16)
+            .map(|light| light.get(x, y % 16, z))
+    }
+
+    /// Gets the block light level at the given position, if the chunk is
+    /// loaded and has block light.
+    pub fn block_light<P: Into<BlockPos>>(&self, pos: P) -> Option<u8> {
+        let (chunk, x, y, z) = self.chunk_and_offsets(pos.into())?;
+
+        chunk
+            .block_light_section(y / 16)
+            .map(|light| light.get(x, y % 16, z))
+    }
+
+    fn chunk_and_offsets(&self, pos: BlockPos) -> Option<(&LoadedChunk, u32, u32, u32)> {
+        let y = pos
+            .y
+            .checked_sub(self.info.min_y)
+            .and_then(|y| y.try_into().ok())?;
+
+        if y >= self.info.height {
+            return None;
+        }
+
+        let chunk = self.chunk(pos)?;
+
+        Some((
+            chunk,
+            pos.x.rem_euclid(16) as u32,
+            y,
+            pos.z.rem_euclid(16) as u32,
+        ))
+    }
+
     /// Get a reference to the chunk at the given position, if it is loaded.
     pub fn chunk<P: Into<ChunkPos>>(&self, pos: P) -> Option<&LoadedChunk> {
         self.chunks.get(&pos.into())
@@ -785,6 +866,14 @@ fn update_chunk_layers_pre_client(mut layers: Query<&mut ChunkLayer>) {
     for layer in &mut layers {
         let layer = layer.into_inner();
 
+        if let Some(engine) = &mut layer.light_engine {
+            engine.update(&mut layer.chunks, &layer.info);
+        } else {
+            for chunk in layer.chunks.values_mut() {
+                chunk.discard_light_changes();
+            }
+        }
+
         for (&pos, chunk) in &mut layer.chunks {
             chunk.update_pre_client(pos, &layer.info, &mut layer.messages);
         }
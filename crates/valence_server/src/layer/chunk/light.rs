@@ -0,0 +1,790 @@
+//! Server-side sky and block light.
+//!
+//! Light is stored per chunk section as arrays of 4-bit light levels, matching
+//! the format used by the protocol and by Anvil region files. The
+//! [`LightEngine`] of a [`ChunkLayer`](super::ChunkLayer) keeps these arrays up
+//! to date as blocks are changed. Light is propagated across chunk borders for
+//! all chunks that are loaded in the layer.
+
+use std::collections::VecDeque;
+
+use rustc_hash::FxHashMap;
+use valence_generated::block::{PropName, PropValue};
+use valence_protocol::{BlockState, ChunkPos};
+
+use super::chunk::{Chunk, SECTION_BLOCK_COUNT};
+use super::loaded::LoadedChunk;
+use super::ChunkLayerInfo;
+
+/// The number of bytes in the light array of a single chunk section. Every
+/// block's light level is stored in a nibble.
+pub const LIGHT_ARRAY_LEN: usize = SECTION_BLOCK_COUNT / 2;
+
+/// The maximum light level.
+pub const MAX_LIGHT_LEVEL: u8 = 15;
+
+/// The light levels of the 16x16x16 blocks in a chunk section.
+///
+/// Sections where every block has the same light level (which is the case for
+/// most sections) are stored without allocating.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LightArray(Repr);
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Repr {
+    Uniform(u8),
+    Nibbles(Box<[u8; LIGHT_ARRAY_LEN]>),
+}
+
+impl LightArray {
+    /// Creates a light array where every block has the given light level.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `level` is greater than [`MAX_LIGHT_LEVEL`].
+    #[track_caller]
+    pub const fn uniform(level: u8) -> Self {
+        assert!(level <= MAX_LIGHT_LEVEL, "light level out of range");
+
+        Self(Repr::Uniform(level))
+    }
+
+    /// Creates a light array from packed nibbles in the vanilla format. The
+    /// light level of the block at `(x, y, z)` is in the nibble at index `x +
+    /// z * 16 + y * 16 * 16`, with the low nibble of each byte coming first.
+    ///
+    /// Returns `None` if the slice is not exactly [`LIGHT_ARRAY_LEN`] bytes
+    /// long.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let nibbles: [u8; LIGHT_ARRAY_LEN] = bytes.try_into().ok()?;
+
+        let mut res = Self(Repr::Nibbles(Box::new(nibbles)));
+        res.compact();
+        Some(res)
+    }
+
+    /// Returns the packed nibbles of this array in the vanilla format. See
+    /// [`Self::from_bytes`].
+    pub fn to_bytes(&self) -> [u8; LIGHT_ARRAY_LEN] {
+        match &self.0 {
+            Repr::Uniform(level) => [*level | (*level << 4); LIGHT_ARRAY_LEN],
+            Repr::Nibbles(nibbles) => **nibbles,
+        }
+    }
+
+    /// If every block in this array has the same light level, returns that
+    /// level.
+    pub fn uniform_level(&self) -> Option<u8> {
+        match &self.0 {
+            Repr::Uniform(level) => Some(*level),
+            Repr::Nibbles(_) => None,
+        }
+    }
+
+    /// Gets the light level at the given offsets. `x`, `y` and `z` are in the
+    /// range `0..16`.
+    #[track_caller]
+    pub fn get(&self, x: u32, y: u32, z: u32) -> u8 {
+        debug_assert!(x < 16 && y < 16 && z < 16);
+
+        match &self.0 {
+            Repr::Uniform(level) => *level,
+            Repr::Nibbles(nibbles) => {
+                let idx = (x + z * 16 + y * 16 * 16) as usize;
+                nibbles[idx / 2] >> (idx % 2 * 4) & 0xf
+            }
+        }
+    }
+
+    /// Sets the light level at the given offsets, returning the previous
+    /// level. `x`, `y` and `z` are in the range `0..16`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `level` is greater than [`MAX_LIGHT_LEVEL`].
+    #[track_caller]
+    pub fn set(&mut self, x: u32, y: u32, z: u32, level: u8) -> u8 {
+        debug_assert!(x < 16 && y < 16 && z < 16);
+        assert!(level <= MAX_LIGHT_LEVEL, "light level out of range");
+
+        let idx = (x + z * 16 + y * 16 * 16) as usize;
+
+        match &mut self.0 {
+            Repr::Uniform(old) => {
+                let old = *old;
+
+                if old != level {
+                    let mut nibbles = Box::new([old | (old << 4); LIGHT_ARRAY_LEN]);
+                    let shift = idx % 2 * 4;
+                    nibbles[idx / 2] = nibbles[idx / 2] & !(0xf << shift) | level << shift;
+                    self.0 = Repr::Nibbles(nibbles);
+                }
+
+                old
+            }
+            Repr::Nibbles(nibbles) => {
+                let shift = idx % 2 * 4;
+                let byte = &mut nibbles[idx / 2];
+                let old = *byte >> shift & 0xf;
+                *byte = *byte & !(0xf << shift) | level << shift;
+                old
+            }
+        }
+    }
+
+    /// Sets every light level in this array to `level`.
+    #[track_caller]
+    pub fn fill(&mut self, level: u8) {
+        *self = Self::uniform(level);
+    }
+
+    /// Switches to the uniform representation if possible.
+    pub(super) fn compact(&mut self) {
+        if let Repr::Nibbles(nibbles) = &self.0 {
+            let first = nibbles[0];
+
+            if first >> 4 == first & 0xf && nibbles.iter().all(|&b| b == first) {
+                self.0 = Repr::Uniform(first & 0xf);
+            }
+        }
+    }
+}
+
+impl Default for LightArray {
+    fn default() -> Self {
+        Self::uniform(0)
+    }
+}
+
+/// The two kinds of light tracked by the light engine.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(super) enum LightKind {
+    Sky,
+    Block,
+}
+
+/// Returns the amount by which the given block state reduces light passing
+/// through it, beyond the reduction of one level per block.
+pub(super) fn opacity(state: BlockState) -> u8 {
+    if state.is_opaque() {
+        MAX_LIGHT_LEVEL
+    } else {
+        // Water and leaves only dim light slightly.
+        u8::from(
+            state.is_liquid()
+                || state.get(PropName::Waterlogged) == Some(PropValue::True)
+                || state.to_kind().to_str().ends_with("_leaves"),
+        )
+    }
+}
+
+/// A position in a chunk layer. `x` and `z` are absolute block coordinates
+/// while `y` is relative to the bottom of the layer.
+#[derive(Copy, Clone, Debug)]
+struct LightNode {
+    x: i32,
+    y: u32,
+    z: i32,
+    level: u8,
+}
+
+impl LightNode {
+    fn chunk_pos(self) -> ChunkPos {
+        ChunkPos::new(self.x.div_euclid(16), self.z.div_euclid(16))
+    }
+
+    fn local_x(self) -> u32 {
+        self.x.rem_euclid(16) as u32
+    }
+
+    fn local_z(self) -> u32 {
+        self.z.rem_euclid(16) as u32
+    }
+
+    /// Returns the neighbors of this node which are inside the layer's height
+    /// bounds, along with a flag indicating if the neighbor is directly below.
+    fn neighbors(self, height: u32) -> impl Iterator<Item = (i32, u32, i32, bool)> {
+        let Self { x, y, z, .. } = self;
+
+        [
+            (x - 1, Some(y), z, false),
+            (x + 1, Some(y), z, false),
+            (x, Some(y), z - 1, false),
+            (x, Some(y), z + 1, false),
+            (x, y.checked_sub(1), z, true),
+            (x, Some(y + 1).filter(|&y| y < height), z, false),
+        ]
+        .into_iter()
+        .filter_map(|(x, y, z, down)| y.map(|y| (x, y, z, down)))
+    }
+}
+
+/// Computes the light level that flows from a block with light level `level`
+/// into a neighboring block with the given opacity.
+fn propagated_level(kind: LightKind, level: u8, opacity: u8, down: bool) -> u8 {
+    if kind == LightKind::Sky && down && level == MAX_LIGHT_LEVEL && opacity == 0 {
+        // Direct sky light travels downwards without decreasing.
+        MAX_LIGHT_LEVEL
+    } else {
+        level.saturating_sub(opacity.max(1))
+    }
+}
+
+/// Keeps the light of the chunks in a [`ChunkLayer`](super::ChunkLayer) up to
+/// date.
+#[derive(Default, Debug)]
+pub(super) struct LightEngine {
+    increase: VecDeque<LightNode>,
+    decrease: VecDeque<LightNode>,
+}
+
+impl LightEngine {
+    /// Relights all chunks that need it and propagates light changes caused
+    /// by block changes since the last update.
+    pub(super) fn update(
+        &mut self,
+        chunks: &mut FxHashMap<ChunkPos, LoadedChunk>,
+        info: &ChunkLayerInfo,
+    ) {
+        let mut relight = vec![];
+        let mut changes = vec![];
+
+        for (&pos, chunk) in chunks.iter_mut() {
+            let (needs_relight, pending) = chunk.take_light_changes();
+
+            if needs_relight {
+                relight.push(pos);
+            } else if !pending.is_empty() {
+                changes.push((pos, pending));
+            }
+        }
+
+        for pos in relight {
+            self.relight_chunk(chunks, info, pos);
+        }
+
+        for (pos, pending) in changes {
+            for idx in pending {
+                let x = pos.x * 16 + (idx % 16) as i32;
+                let z = pos.z * 16 + (idx / 16 % 16) as i32;
+                let y = idx / 16 / 16;
+
+                if info.has_skylight {
+                    self.update_block(chunks, info.height, LightKind::Sky, x, y, z);
+                }
+                self.update_block(chunks, info.height, LightKind::Block, x, y, z);
+            }
+        }
+    }
+
+    /// Recomputes the light of the chunk at `pos` from scratch and spreads it
+    /// to and from the neighboring chunks.
+    fn relight_chunk(
+        &mut self,
+        chunks: &mut FxHashMap<ChunkPos, LoadedChunk>,
+        info: &ChunkLayerInfo,
+        pos: ChunkPos,
+    ) {
+        let Some(chunk) = chunks.get(&pos) else {
+            return;
+        };
+
+        let height = chunk.height();
+
+        if info.has_skylight {
+            self.unlight_chunk(chunks, pos, height, LightKind::Sky);
+            self.seed_sky_light(chunks, pos, height);
+            self.pull_from_neighbors(chunks, pos, height, LightKind::Sky);
+            self.propagate_increase(chunks, height, LightKind::Sky);
+        }
+
+        self.unlight_chunk(chunks, pos, height, LightKind::Block);
+        self.seed_block_light(chunks, pos, height);
+        self.pull_from_neighbors(chunks, pos, height, LightKind::Block);
+        self.propagate_increase(chunks, height, LightKind::Block);
+    }
+
+    /// Sets the light of the chunk at `pos` to zero and removes the light it
+    /// spread to its neighbors.
+    fn unlight_chunk(
+        &mut self,
+        chunks: &mut FxHashMap<ChunkPos, LoadedChunk>,
+        pos: ChunkPos,
+        height: u32,
+        kind: LightKind,
+    ) {
+        let Some(chunk) = chunks.get_mut(&pos) else {
+            return;
+        };
+
+        for sect_y in 0..height / 16 {
+            let Some(light) = chunk.light_section(kind, sect_y) else {
+                continue;
+            };
+
+            if light.uniform_level() == Some(0) {
+                continue;
+            }
+
+            for y in 0..16 {
+                for z in 0..16 {
+                    for x in 0..16 {
+                        let level = light.get(x, y, z);
+
+                        if level > 0 {
+                            self.decrease.push_back(LightNode {
+                                x: pos.x * 16 + x as i32,
+                                y: sect_y * 16 + y,
+                                z: pos.z * 16 + z as i32,
+                                level,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        chunk.clear_light(kind);
+
+        self.propagate_decrease(chunks, height, kind);
+    }
+
+    /// Fills the blocks of the chunk at `pos` which can see the sky with
+    /// direct sky light and queues them for propagation.
+    fn seed_sky_light(
+        &mut self,
+        chunks: &mut FxHashMap<ChunkPos, LoadedChunk>,
+        pos: ChunkPos,
+        height: u32,
+    ) {
+        let Some(chunk) = chunks.get_mut(&pos) else {
+            return;
+        };
+
+        // The lowest offset in each column which receives direct sky light.
+        let mut sky_bottom = [height; 16 * 16];
+
+        for z in 0..16 {
+            for x in 0..16 {
+                let bottom = &mut sky_bottom[(x + z * 16) as usize];
+
+                while *bottom > 0 && opacity(chunk.block_state(x, *bottom - 1, z)) == 0 {
+                    *bottom -= 1;
+                }
+
+                for y in *bottom..height {
+                    chunk.set_light(LightKind::Sky, x, y, z, MAX_LIGHT_LEVEL);
+                }
+            }
+        }
+
+        for z in 0..16_u32 {
+            for x in 0..16_u32 {
+                let bottom = sky_bottom[(x + z * 16) as usize];
+
+                // Only blocks next to a darker block need to spread their light.
+                let top = if x == 0 || x == 15 || z == 0 || z == 15 {
+                    height
+                } else {
+                    [(x - 1, z), (x + 1, z), (x, z - 1), (x, z + 1)]
+                        .into_iter()
+                        .map(|(x, z)| sky_bottom[(x + z * 16) as usize])
+                        .max()
+                        .unwrap_or(bottom)
+                        .max(bottom + 1)
+                        .min(height)
+                };
+
+                for y in bottom..top {
+                    self.increase.push_back(LightNode {
+                        x: pos.x * 16 + x as i32,
+                        y,
+                        z: pos.z * 16 + z as i32,
+                        level: MAX_LIGHT_LEVEL,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Sets the block light of every light source in the chunk at `pos` and
+    /// queues them for propagation.
+    fn seed_block_light(
+        &mut self,
+        chunks: &mut FxHashMap<ChunkPos, LoadedChunk>,
+        pos: ChunkPos,
+        height: u32,
+    ) {
+        let Some(chunk) = chunks.get_mut(&pos) else {
+            return;
+        };
+
+        for y in 0..height {
+            for z in 0..16 {
+                for x in 0..16 {
+                    let luminance = chunk.block_state(x, y, z).luminance();
+
+                    if luminance > 0 {
+                        chunk.set_light(LightKind::Block, x, y, z, luminance);
+
+                        self.increase.push_back(LightNode {
+                            x: pos.x * 16 + x as i32,
+                            y,
+                            z: pos.z * 16 + z as i32,
+                            level: luminance,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Queues the blocks of the loaded neighbors of the chunk at `pos` which
+    /// border that chunk so that their light spreads into it.
+    fn pull_from_neighbors(
+        &mut self,
+        chunks: &FxHashMap<ChunkPos, LoadedChunk>,
+        pos: ChunkPos,
+        height: u32,
+        kind: LightKind,
+    ) {
+        // Neighbor chunk offset and the local (x, z) coordinates of its bordering
+        // column for each index.
+        let sides: [((i32, i32), fn(u32) -> (u32, u32)); 4] = [
+            ((-1, 0), |i| (15, i)),
+            ((1, 0), |i| (0, i)),
+            ((0, -1), |i| (i, 15)),
+            ((0, 1), |i| (i, 0)),
+        ];
+
+        for ((dx, dz), column) in sides {
+            let neighbor_pos = ChunkPos::new(pos.x + dx, pos.z + dz);
+
+            let Some(neighbor) = chunks.get(&neighbor_pos) else {
+                continue;
+            };
+
+            for i in 0..16 {
+                let (x, z) = column(i);
+
+                for y in 0..height.min(neighbor.height()) {
+                    let level = neighbor.light(kind, x, y, z);
+
+                    if level > 1 {
+                        self.increase.push_back(LightNode {
+                            x: neighbor_pos.x * 16 + x as i32,
+                            y,
+                            z: neighbor_pos.z * 16 + z as i32,
+                            level,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Updates the light around the block at the given position after it was
+    /// changed.
+    fn update_block(
+        &mut self,
+        chunks: &mut FxHashMap<ChunkPos, LoadedChunk>,
+        height: u32,
+        kind: LightKind,
+        x: i32,
+        y: u32,
+        z: i32,
+    ) {
+        let node = LightNode { x, y, z, level: 0 };
+
+        let Some(chunk) = chunks.get_mut(&node.chunk_pos()) else {
+            return;
+        };
+
+        let (local_x, local_z) = (node.local_x(), node.local_z());
+
+        // Remove the light that was previously at this block and everything that
+        // depended on it.
+        let old_level = chunk.light(kind, local_x, y, local_z);
+        if old_level > 0 {
+            chunk.set_light(kind, local_x, y, local_z, 0);
+            self.decrease.push_back(LightNode {
+                level: old_level,
+                ..node
+            });
+            self.propagate_decrease(chunks, height, kind);
+        }
+
+        let Some(chunk) = chunks.get_mut(&node.chunk_pos()) else {
+            return;
+        };
+
+        // Add the light emitted by the new block.
+        if kind == LightKind::Block {
+            let luminance = chunk.block_state(local_x, y, local_z).luminance();
+
+            if luminance > 0 {
+                chunk.set_light(kind, local_x, y, local_z, luminance);
+                self.increase.push_back(LightNode {
+                    level: luminance,
+                    ..node
+                });
+            }
+        } else if y + 1 == height
+            && opacity(chunk.block_state(local_x, y, local_z)) == 0
+            && chunk.light(kind, local_x, y, local_z) < MAX_LIGHT_LEVEL
+        {
+            // The top of the world is always exposed to the sky.
+            chunk.set_light(kind, local_x, y, local_z, MAX_LIGHT_LEVEL);
+            self.increase.push_back(LightNode {
+                level: MAX_LIGHT_LEVEL,
+                ..node
+            });
+        }
+
+        // Let the light of the neighbors flow back into the block.
+        for (nx, ny, nz, _) in node.neighbors(height) {
+            let neighbor = LightNode {
+                x: nx,
+                y: ny,
+                z: nz,
+                level: 0,
+            };
+
+            if let Some(chunk) = chunks.get(&neighbor.chunk_pos()) {
+                let level = chunk.light(kind, neighbor.local_x(), ny, neighbor.local_z());
+
+                if level > 0 {
+                    self.increase.push_back(LightNode { level, ..neighbor });
+                }
+            }
+        }
+
+        self.propagate_increase(chunks, height, kind);
+    }
+
+    /// Spreads light outwards from the nodes in the increase queue.
+    fn propagate_increase(
+        &mut self,
+        chunks: &mut FxHashMap<ChunkPos, LoadedChunk>,
+        height: u32,
+        kind: LightKind,
+    ) {
+        while let Some(node) = self.increase.pop_front() {
+            // Skip nodes whose light was changed after they were queued.
+            let current = chunks.get(&node.chunk_pos()).map_or(0, |chunk| {
+                chunk.light(kind, node.local_x(), node.y, node.local_z())
+            });
+
+            if current != node.level {
+                continue;
+            }
+
+            for (x, y, z, down) in node.neighbors(height) {
+                let neighbor = LightNode { x, y, z, level: 0 };
+
+                let Some(chunk) = chunks.get_mut(&neighbor.chunk_pos()) else {
+                    continue;
+                };
+
+                let (local_x, local_z) = (neighbor.local_x(), neighbor.local_z());
+
+                let opacity = opacity(chunk.block_state(local_x, y, local_z));
+                let level = propagated_level(kind, node.level, opacity, down);
+
+                if level > chunk.light(kind, local_x, y, local_z) {
+                    chunk.set_light(kind, local_x, y, local_z, level);
+
+                    if level > 1 {
+                        self.increase.push_back(LightNode { level, ..neighbor });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes the light that came from the nodes in the decrease queue. Blocks
+    /// at the border of the removed area are queued to spread their light back
+    /// in afterwards.
+    fn propagate_decrease(
+        &mut self,
+        chunks: &mut FxHashMap<ChunkPos, LoadedChunk>,
+        height: u32,
+        kind: LightKind,
+    ) {
+        while let Some(node) = self.decrease.pop_front() {
+            for (x, y, z, down) in node.neighbors(height) {
+                let neighbor = LightNode { x, y, z, level: 0 };
+
+                let Some(chunk) = chunks.get_mut(&neighbor.chunk_pos()) else {
+                    continue;
+                };
+
+                let (local_x, local_z) = (neighbor.local_x(), neighbor.local_z());
+
+                let level = chunk.light(kind, local_x, y, local_z);
+
+                if level == 0 {
+                    continue;
+                }
+
+                let depends_on_node = level < node.level
+                    || (kind == LightKind::Sky && down && node.level == MAX_LIGHT_LEVEL);
+
+                if depends_on_node {
+                    chunk.set_light(kind, local_x, y, local_z, 0);
+                    self.decrease.push_back(LightNode { level, ..neighbor });
+
+                    // The block may emit light of its own.
+                    if kind == LightKind::Block {
+                        let luminance = chunk.block_state(local_x, y, local_z).luminance();
+
+                        if luminance > 0 {
+                            chunk.set_light(kind, local_x, y, local_z, luminance);
+                            self.increase.push_back(LightNode {
+                                level: luminance,
+                                ..neighbor
+                            });
+                        }
+                    }
+                } else {
+                    self.increase.push_back(LightNode { level, ..neighbor });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use valence_protocol::{ident, CompressionThreshold};
+
+    use super::*;
+    use crate::layer::chunk::UnloadedChunk;
+
+    #[test]
+    fn light_array_get_set() {
+        let mut light = LightArray::uniform(15);
+
+        assert_eq!(light.set(1, 2, 3, 7), 15);
+        assert_eq!(light.get(1, 2, 3), 7);
+        assert_eq!(light.get(0, 2, 3), 15);
+        assert_eq!(light.uniform_level(), None);
+
+        let bytes = light.to_bytes();
+        assert_eq!(LightArray::from_bytes(&bytes), Some(light.clone()));
+
+        light.set(1, 2, 3, 15);
+        light.compact();
+        assert_eq!(light, LightArray::uniform(15));
+
+        assert_eq!(LightArray::from_bytes(&[0; 10]), None);
+    }
+
+    fn layer_info() -> ChunkLayerInfo {
+        ChunkLayerInfo {
+            dimension_type_name: ident!("whatever").into(),
+            height: 32,
+            min_y: 0,
+            biome_registry_len: 1,
+            threshold: CompressionThreshold(-1),
+            has_skylight: true,
+        }
+    }
+
+    fn loaded_chunk(chunk: UnloadedChunk) -> LoadedChunk {
+        let mut loaded = LoadedChunk::new(32);
+        loaded.insert(chunk);
+        loaded
+    }
+
+    #[test]
+    fn light_engine_sky_and_block_light() {
+        let info = layer_info();
+        let mut engine = LightEngine::default();
+        let mut chunks = FxHashMap::default();
+
+        // A stone floor at y = 10 with a roof covering part of the chunk.
+        let mut chunk = UnloadedChunk::with_height(32);
+        for z in 0..16 {
+            for x in 0..16 {
+                chunk.set_block_state(x, 10, z, BlockState::STONE);
+
+                if x < 8 {
+                    chunk.set_block_state(x, 14, z, BlockState::STONE);
+                }
+            }
+        }
+
+        chunks.insert(ChunkPos::new(0, 0), loaded_chunk(chunk));
+        engine.update(&mut chunks, &info);
+
+        let chunk = &chunks[&ChunkPos::new(0, 0)];
+        assert_eq!(chunk.light(LightKind::Sky, 12, 11, 5), 15);
+        assert_eq!(chunk.light(LightKind::Sky, 8, 11, 5), 15);
+        assert_eq!(chunk.light(LightKind::Sky, 7, 11, 5), 14);
+        assert_eq!(chunk.light(LightKind::Sky, 3, 11, 5), 10);
+        assert_eq!(chunk.light(LightKind::Sky, 3, 9, 5), 0);
+        assert_eq!(chunk.light(LightKind::Block, 3, 11, 5), 0);
+
+        // Place a light source under the roof.
+        let chunk = chunks.get_mut(&ChunkPos::new(0, 0)).unwrap();
+        chunk.set_block_state(2, 12, 2, BlockState::GLOWSTONE);
+        engine.update(&mut chunks, &info);
+
+        let chunk = &chunks[&ChunkPos::new(0, 0)];
+        assert_eq!(chunk.light(LightKind::Block, 2, 12, 2), 15);
+        assert_eq!(chunk.light(LightKind::Block, 3, 12, 2), 14);
+        assert_eq!(chunk.light(LightKind::Block, 2, 11, 4), 12);
+
+        // Remove it again.
+        let chunk = chunks.get_mut(&ChunkPos::new(0, 0)).unwrap();
+        chunk.set_block_state(2, 12, 2, BlockState::AIR);
+        engine.update(&mut chunks, &info);
+
+        let chunk = &chunks[&ChunkPos::new(0, 0)];
+        assert_eq!(chunk.light(LightKind::Block, 2, 12, 2), 0);
+        assert_eq!(chunk.light(LightKind::Block, 3, 12, 2), 0);
+
+        // Close the roof.
+        let chunk = chunks.get_mut(&ChunkPos::new(0, 0)).unwrap();
+        for z in 0..16 {
+            for x in 8..16 {
+                chunk.set_block_state(x, 14, z, BlockState::STONE);
+            }
+        }
+        engine.update(&mut chunks, &info);
+
+        let chunk = &chunks[&ChunkPos::new(0, 0)];
+        assert_eq!(chunk.light(LightKind::Sky, 12, 11, 5), 0);
+        assert_eq!(chunk.light(LightKind::Sky, 12, 15, 5), 15);
+    }
+
+    #[test]
+    fn light_engine_crosses_chunk_borders() {
+        let info = layer_info();
+        let mut engine = LightEngine::default();
+        let mut chunks = FxHashMap::default();
+
+        let mut chunk = UnloadedChunk::with_height(32);
+        chunk.fill_block_state_section(1, BlockState::STONE);
+        chunk.set_block_state(15, 20, 0, BlockState::GLOWSTONE);
+
+        chunks.insert(ChunkPos::new(0, 0), loaded_chunk(chunk.clone()));
+        engine.update(&mut chunks, &info);
+
+        chunk.set_block_state(15, 20, 0, BlockState::AIR);
+        chunk.set_block_state(0, 20, 0, BlockState::AIR);
+        chunk.set_block_state(1, 20, 0, BlockState::AIR);
+        chunks.insert(ChunkPos::new(1, 0), loaded_chunk(chunk));
+        engine.update(&mut chunks, &info);
+
+        assert_eq!(
+            chunks[&ChunkPos::new(1, 0)].light(LightKind::Block, 0, 20, 0),
+            14
+        );
+        assert_eq!(
+            chunks[&ChunkPos::new(1, 0)].light(LightKind::Block, 1, 20, 0),
+            13
+        );
+    }
+}
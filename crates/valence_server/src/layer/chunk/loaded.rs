@@ -10,9 +10,11 @@ use valence_protocol::encode::{PacketWriter, WritePacket};
 use valence_protocol::packets::play::chunk_data_s2c::ChunkDataBlockEntity;
 use valence_protocol::packets::play::chunk_delta_update_s2c::ChunkDeltaUpdateEntry;
 use valence_protocol::packets::play::{
-    BlockEntityUpdateS2c, BlockUpdateS2c, ChunkDataS2c, ChunkDeltaUpdateS2c,
+    BlockEntityUpdateS2c, BlockUpdateS2c, ChunkDataS2c, ChunkDeltaUpdateS2c, LightUpdateS2c,
+};
+use valence_protocol::{
+    BlockPos, BlockState, ChunkPos, ChunkSectionPos, Encode, FixedArray, VarInt,
 };
-use valence_protocol::{BlockPos, BlockState, ChunkPos, ChunkSectionPos, Encode};
 use valence_registry::biome::BiomeId;
 use valence_registry::RegistryIdx;
 
@@ -20,6 +22,7 @@ use super::chunk::{
     bit_width, check_biome_oob, check_block_oob, check_section_oob, BiomeContainer,
     BlockStateContainer, Chunk, SECTION_BLOCK_COUNT,
 };
+use super::light::{self, LightArray, LightKind, LIGHT_ARRAY_LEN};
 use super::paletted_container::PalettedContainer;
 use super::unloaded::{self, UnloadedChunk};
 use super::{ChunkLayerInfo, ChunkLayerMessages, LocalMsg};
@@ -38,6 +41,15 @@ pub struct LoadedChunk {
     changed_block_entities: BTreeSet<u32>,
     /// If any biomes in this chunk have been modified this tick.
     changed_biomes: bool,
+    /// Indices of the blocks whose changes may affect light and have not been
+    /// handled by the light engine yet.
+    pending_light_updates: Vec<u32>,
+    /// If the light of this chunk must be recomputed from scratch by the light
+    /// engine.
+    needs_relight: bool,
+    /// If the content of this chunk has changed since it was inserted or since
+    /// the last call to [`LoadedChunk::clear_modified`].
+    modified: bool,
     /// Cached bytes of the chunk initialization packet. The cache is considered
     /// invalidated if empty. This should be cleared whenever the chunk is
     /// modified in an observable way, even if the chunk is not viewed.
@@ -51,6 +63,10 @@ struct Section {
     /// Contains modifications for the update section packet. (Or the regular
     /// block update packet if len == 1).
     updates: Vec<ChunkDeltaUpdateEntry>,
+    sky_light: Option<LightArray>,
+    block_light: Option<LightArray>,
+    /// If the light in this section has been modified this tick.
+    changed_light: bool,
 }
 
 impl Section {
@@ -82,6 +98,17 @@ impl Section {
     }
 }
 
+/// Light data of a chunk in the format used by packets.
+#[derive(Default)]
+struct LightData {
+    sky_light_mask: Vec<u64>,
+    block_light_mask: Vec<u64>,
+    empty_sky_light_mask: Vec<u64>,
+    empty_block_light_mask: Vec<u64>,
+    sky_light_arrays: Vec<FixedArray<u8, LIGHT_ARRAY_LEN>>,
+    block_light_arrays: Vec<FixedArray<u8, LIGHT_ARRAY_LEN>>,
+}
+
 impl LoadedChunk {
     pub(crate) fn new(height: u32) -> Self {
         Self {
@@ -90,6 +117,9 @@ impl LoadedChunk {
             block_entities: BTreeMap::new(),
             changed_block_entities: BTreeSet::new(),
             changed_biomes: false,
+            pending_light_updates: vec![],
+            needs_relight: true,
+            modified: false,
             cached_init_packets: Mutex::new(vec![]),
         }
     }
@@ -110,51 +140,119 @@ impl LoadedChunk {
             .zip(chunk.sections)
             .map(|(sect, other_sect)| {
                 sect.updates.clear();
+                sect.changed_light = false;
 
                 unloaded::Section {
                     block_states: mem::replace(&mut sect.block_states, other_sect.block_states),
                     biomes: mem::replace(&mut sect.biomes, other_sect.biomes),
+                    sky_light: mem::replace(&mut sect.sky_light, other_sect.sky_light),
+                    block_light: mem::replace(&mut sect.block_light, other_sect.block_light),
                 }
             })
             .collect();
         let old_block_entities = mem::replace(&mut self.block_entities, chunk.block_entities);
         self.changed_block_entities.clear();
         self.changed_biomes = false;
+        self.modified = true;
         self.cached_init_packets.get_mut().clear();
         self.assert_no_changes();
 
-        UnloadedChunk {
+        let old_light_valid = self.light_is_valid();
+        self.pending_light_updates.clear();
+
+        // Reuse the light of the new chunk if it's complete.
+        self.needs_relight = self
+            .sections
+            .iter()
+            .any(|sect| sect.sky_light.is_none() || sect.block_light.is_none());
+
+        let mut old = UnloadedChunk {
             sections: old_sections,
             block_entities: old_block_entities,
+        };
+
+        if !old_light_valid {
+            old.clear_light();
         }
+
+        old
     }
 
     pub(crate) fn remove(&mut self) -> UnloadedChunk {
+        let old_light_valid = self.light_is_valid();
+        self.pending_light_updates.clear();
+
         let old_sections = self
             .sections
             .iter_mut()
             .map(|sect| {
                 sect.updates.clear();
+                sect.changed_light = false;
 
                 unloaded::Section {
                     block_states: mem::take(&mut sect.block_states),
                     biomes: mem::take(&mut sect.biomes),
+                    sky_light: sect.sky_light.take(),
+                    block_light: sect.block_light.take(),
                 }
             })
             .collect();
         let old_block_entities = mem::take(&mut self.block_entities);
         self.changed_block_entities.clear();
         self.changed_biomes = false;
+        self.needs_relight = true;
+        self.modified = false;
         self.cached_init_packets.get_mut().clear();
 
         self.assert_no_changes();
 
-        UnloadedChunk {
+        let mut old = UnloadedChunk {
             sections: old_sections,
             block_entities: old_block_entities,
+        };
+
+        if !old_light_valid {
+            old.clear_light();
+        }
+
+        old
+    }
+
+    /// Returns a copy of the content of this chunk as an [`UnloadedChunk`].
+    /// Light is only included if it is up to date.
+    pub fn to_unloaded(&self) -> UnloadedChunk {
+        let light_valid = self.light_is_valid();
+
+        UnloadedChunk {
+            sections: self
+                .sections
+                .iter()
+                .map(|sect| unloaded::Section {
+                    block_states: sect.block_states.clone(),
+                    biomes: sect.biomes.clone(),
+                    sky_light: sect.sky_light.clone().filter(|_| light_valid),
+                    block_light: sect.block_light.clone().filter(|_| light_valid),
+                })
+                .collect(),
+            block_entities: self.block_entities.clone(),
         }
     }
 
+    /// Returns `true` if the blocks, biomes or block entities of this chunk
+    /// have changed since the chunk was inserted into its layer or since the
+    /// last call to [`Self::clear_modified`]. Changes to light are not
+    /// considered.
+    ///
+    /// This is useful for knowing which chunks need to be saved.
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    /// Resets the flag returned by [`Self::is_modified`].
+    pub fn clear_modified(&mut self) {
+        self.modified = false;
+    }
+
     /// Returns the number of clients in view of this chunk.
     pub fn viewer_count(&self) -> u32 {
         self.viewer_count.load(Ordering::Relaxed)
@@ -177,6 +275,161 @@ impl LoadedChunk {
         debug_assert_ne!(old, 0, "viewer count underflow!");
     }
 
+    /// Gets the sky light of the section at `sect_y`, if the chunk has any.
+    /// Sky light is present if it was computed by the light engine of the
+    /// [`ChunkLayer`](super::ChunkLayer) or if it was included in the
+    /// [`UnloadedChunk`] inserted into the layer.
+    ///
+    /// # Panics
+    ///
+    /// May panic if the section offset is out of bounds.
+    #[track_caller]
+    pub fn sky_light_section(&self, sect_y: u32) -> Option<&LightArray> {
+        check_section_oob(self, sect_y);
+
+        self.sections[sect_y as usize].sky_light.as_ref()
+    }
+
+    /// Gets the block light of the section at `sect_y`, if the chunk has any.
+    /// See [`Self::sky_light_section`] for details.
+    ///
+    /// # Panics
+    ///
+    /// May panic if the section offset is out of bounds.
+    #[track_caller]
+    pub fn block_light_section(&self, sect_y: u32) -> Option<&LightArray> {
+        check_section_oob(self, sect_y);
+
+        self.sections[sect_y as usize].block_light.as_ref()
+    }
+
+    pub(super) fn light_section(&self, kind: LightKind, sect_y: u32) -> Option<&LightArray> {
+        match kind {
+            LightKind::Sky => self.sky_light_section(sect_y),
+            LightKind::Block => self.block_light_section(sect_y),
+        }
+    }
+
+    /// Gets the light level of the given kind at the position. Missing light
+    /// is treated as darkness.
+    pub(super) fn light(&self, kind: LightKind, x: u32, y: u32, z: u32) -> u8 {
+        self.light_section(kind, y / 16)
+            .map_or(0, |light| light.get(x, y % 16, z))
+    }
+
+    pub(super) fn set_light(&mut self, kind: LightKind, x: u32, y: u32, z: u32, level: u8) {
+        let sect = &mut self.sections[y as usize / 16];
+
+        let light = match kind {
+            LightKind::Sky => &mut sect.sky_light,
+            LightKind::Block => &mut sect.block_light,
+        };
+
+        if light
+            .get_or_insert_with(LightArray::default)
+            .set(x, y % 16, z, level)
+            != level
+        {
+            self.cached_init_packets.get_mut().clear();
+
+            if *self.viewer_count.get_mut() > 0 {
+                sect.changed_light = true;
+            }
+        }
+    }
+
+    /// Sets the light of the given kind to zero in the entire chunk.
+    pub(super) fn clear_light(&mut self, kind: LightKind) {
+        for sect in &mut self.sections {
+            let light = match kind {
+                LightKind::Sky => &mut sect.sky_light,
+                LightKind::Block => &mut sect.block_light,
+            };
+
+            if light.as_ref().and_then(LightArray::uniform_level) != Some(0) {
+                *light = Some(LightArray::default());
+
+                self.cached_init_packets.get_mut().clear();
+
+                if *self.viewer_count.get_mut() > 0 {
+                    sect.changed_light = true;
+                }
+            }
+        }
+    }
+
+    /// Returns if the chunk needs to be relit from scratch and the indices of
+    /// blocks whose light needs to be updated. The recorded changes are
+    /// cleared.
+    pub(super) fn take_light_changes(&mut self) -> (bool, Vec<u32>) {
+        (
+            mem::take(&mut self.needs_relight),
+            mem::take(&mut self.pending_light_updates),
+        )
+    }
+
+    /// Forgets about the changes recorded for the light engine. The light of
+    /// this chunk is considered out of date afterwards.
+    pub(super) fn discard_light_changes(&mut self) {
+        if !self.pending_light_updates.is_empty() {
+            self.pending_light_updates.clear();
+            self.needs_relight = true;
+        }
+    }
+
+    /// Marks the light of this chunk as out of date.
+    pub(super) fn mark_needs_relight(&mut self) {
+        self.needs_relight = true;
+    }
+
+    fn light_is_valid(&self) -> bool {
+        !self.needs_relight && self.pending_light_updates.is_empty()
+    }
+
+    /// Collects the light of the sections selected by `filter` in the format
+    /// used by [`ChunkDataS2c`] and [`LightUpdateS2c`]. Sections containing
+    /// only darkness are sent as empty instead.
+    fn light_data(&self, mut filter: impl FnMut(&Section) -> bool) -> LightData {
+        fn set_bit(mask: &mut Vec<u64>, bit: usize) {
+            if mask.len() <= bit / 64 {
+                mask.resize(bit / 64 + 1, 0);
+            }
+
+            mask[bit / 64] |= 1 << (bit % 64);
+        }
+
+        let mut data = LightData::default();
+
+        for (sect_y, sect) in self.sections.iter().enumerate() {
+            if !filter(sect) {
+                continue;
+            }
+
+            // Light sections start one section below the bottom of the chunk.
+            let bit = sect_y + 1;
+
+            if let Some(light) = &sect.sky_light {
+                if light.uniform_level() == Some(0) {
+                    set_bit(&mut data.empty_sky_light_mask, bit);
+                } else {
+                    set_bit(&mut data.sky_light_mask, bit);
+                    data.sky_light_arrays.push(FixedArray(light.to_bytes()));
+                }
+            }
+
+            if let Some(light) = &sect.block_light {
+                if light.uniform_level() == Some(0) {
+                    set_bit(&mut data.empty_block_light_mask, bit);
+                } else {
+                    set_bit(&mut data.block_light_mask, bit);
+                    data.block_light_arrays.push(FixedArray(light.to_bytes()));
+                }
+            }
+        }
+
+        data
+    }
+
     /// Performs the changes necessary to prepare this chunk for client updates.
     /// - Chunk change messages are written to the layer.
     /// - Recorded changes are cleared.
@@ -268,6 +521,30 @@ impl LoadedChunk {
 
         self.changed_block_entities.clear();
 
+        // Light
+        if self.sections.iter().any(|sect| sect.changed_light) {
+            let light = self.light_data(|sect| sect.changed_light);
+
+            messages.send_local_infallible(LocalMsg::PacketAt { pos }, |buf| {
+                let mut writer = PacketWriter::new(buf, info.threshold);
+
+                writer.write_packet(&LightUpdateS2c {
+                    chunk_x: VarInt(pos.x),
+                    chunk_z: VarInt(pos.z),
+                    sky_light_mask: Cow::Owned(light.sky_light_mask),
+                    block_light_mask: Cow::Owned(light.block_light_mask),
+                    empty_sky_light_mask: Cow::Owned(light.empty_sky_light_mask),
+                    empty_block_light_mask: Cow::Owned(light.empty_block_light_mask),
+                    sky_light_arrays: Cow::Owned(light.sky_light_arrays),
+                    block_light_arrays: Cow::Owned(light.block_light_arrays),
+                });
+            });
+
+            for sect in &mut self.sections {
+                sect.changed_light = false;
+            }
+        }
+
         // Biomes
         if self.changed_biomes {
             self.changed_biomes = false;
@@ -438,17 +715,19 @@ impl LoadedChunk {
                 })
                 .collect();
 
+            let light = self.light_data(|_| true);
+
             PacketWriter::new(&mut init_packets, info.threshold).write_packet(&ChunkDataS2c {
                 pos,
                 heightmaps: Cow::Owned(heightmaps),
                 blocks_and_biomes: &blocks_and_biomes,
                 block_entities: Cow::Owned(block_entities),
-                sky_light_mask: Cow::Borrowed(&[]),
-                block_light_mask: Cow::Borrowed(&[]),
-                empty_sky_light_mask: Cow::Borrowed(&[]),
-                empty_block_light_mask: Cow::Borrowed(&[]),
-                sky_light_arrays: Cow::Borrowed(&[]),
-                block_light_arrays: Cow::Borrowed(&[]),
+                sky_light_mask: Cow::Owned(light.sky_light_mask),
+                block_light_mask: Cow::Owned(light.block_light_mask),
+                empty_sky_light_mask: Cow::Owned(light.empty_sky_light_mask),
+                empty_block_light_mask: Cow::Owned(light.empty_block_light_mask),
+                sky_light_arrays: Cow::Owned(light.sky_light_arrays),
+                block_light_arrays: Cow::Owned(light.block_light_arrays),
             })
         }
 
@@ -465,6 +744,7 @@ impl LoadedChunk {
 
             for sect in &self.sections {
                 assert!(sect.updates.is_empty());
+                assert!(!sect.changed_light);
             }
         }
     }
@@ -494,8 +774,15 @@ impl Chunk for LoadedChunk {
         let old_block = sect.block_states.set(idx as usize, block);
 
         if block != old_block {
+            self.modified = true;
             self.cached_init_packets.get_mut().clear();
 
+            if light::opacity(block) != light::opacity(old_block)
+                || block.luminance() != old_block.luminance()
+            {
+                self.pending_light_updates.push(x + z * 16 + y * 16 * 16);
+            }
+
             if *self.viewer_count.get_mut() > 0 {
                 sect.updates.push(
                     ChunkDeltaUpdateEntry::new()
@@ -517,7 +804,9 @@ impl Chunk for LoadedChunk {
 
         if let PalettedContainer::Single(b) = &sect.block_states {
             if *b != block {
+                self.modified = true;
                 self.cached_init_packets.get_mut().clear();
+                self.needs_relight = true;
 
                 if *self.viewer_count.get_mut() > 0 {
                     // The whole section is being modified, so any previous modifications would
@@ -548,7 +837,9 @@ impl Chunk for LoadedChunk {
                         let idx = x + z * 16 + (sect_y * 16 + y) * (16 * 16);
 
                         if block != sect.block_states.get(idx as usize) {
+                            self.modified = true;
                             self.cached_init_packets.get_mut().clear();
+                            self.needs_relight = true;
 
                             if *self.viewer_count.get_mut() > 0 {
                                 sect.updates.push(
@@ -584,6 +875,7 @@ impl Chunk for LoadedChunk {
             if *self.viewer_count.get_mut() > 0 {
                 self.changed_block_entities.insert(idx);
             }
+            self.modified = true;
             self.cached_init_packets.get_mut().clear();
 
             Some(be)
@@ -608,6 +900,7 @@ impl Chunk for LoadedChunk {
                 if *self.viewer_count.get_mut() > 0 {
                     self.changed_block_entities.insert(idx);
                 }
+                self.modified = true;
                 self.cached_init_packets.get_mut().clear();
 
                 self.block_entities.insert(idx, nbt)
@@ -616,6 +909,7 @@ impl Chunk for LoadedChunk {
                 let res = self.block_entities.remove(&idx);
 
                 if res.is_some() {
+                    self.modified = true;
                     self.cached_init_packets.get_mut().clear();
                 }
 
@@ -629,6 +923,7 @@ impl Chunk for LoadedChunk {
             return;
         }
 
+        self.modified = true;
         self.cached_init_packets.get_mut().clear();
 
         if *self.viewer_count.get_mut() > 0 {
@@ -655,6 +950,7 @@ impl Chunk for LoadedChunk {
             .set(idx as usize, biome);
 
         if biome != old_biome {
+            self.modified = true;
             self.cached_init_packets.get_mut().clear();
 
             if *self.viewer_count.get_mut() > 0 {
@@ -672,10 +968,12 @@ impl Chunk for LoadedChunk {
 
         if let PalettedContainer::Single(b) = &sect.biomes {
             if *b != biome {
+                self.modified = true;
                 self.cached_init_packets.get_mut().clear();
                 self.changed_biomes = *self.viewer_count.get_mut() > 0;
             }
         } else {
+            self.modified = true;
             self.cached_init_packets.get_mut().clear();
             self.changed_biomes = *self.viewer_count.get_mut() > 0;
         }
@@ -690,7 +988,15 @@ impl Chunk for LoadedChunk {
             sect.block_states.shrink_to_fit();
             sect.biomes.shrink_to_fit();
             sect.updates.shrink_to_fit();
+
+            for light in [&mut sect.sky_light, &mut sect.block_light]
+                .into_iter()
+                .flatten()
+            {
+                light.compact();
+            }
         }
+        self.pending_light_updates.shrink_to_fit();
     }
 }
 
@@ -727,6 +1033,7 @@ mod tests {
                 min_y: -16,
                 biome_registry_len: 200,
                 threshold: CompressionThreshold(-1),
+                has_skylight: true,
             };
 
             let mut buf = vec![];
@@ -738,9 +1045,12 @@ mod tests {
             // Check that the cache is built.
             assert!(!chunk.cached_init_packets.get_mut().is_empty());
 
-            // Making a change should clear the cache.
+            chunk.clear_modified();
+
+            // Making a change should clear the cache and mark the chunk as modified.
             change(chunk);
             assert!(chunk.cached_init_packets.get_mut().is_empty());
+            assert!(chunk.is_modified());
 
             // Rebuild cache again.
             chunk.write_init_packets(&mut writer, ChunkPos::new(3, 4), &info);
@@ -763,13 +1073,16 @@ mod tests {
         });
         check(&mut chunk, |c| c.set_block_entity(3, 40, 5, None));
 
+        chunk.clear_modified();
+
         // Old block state is the same as new block state, so the cache should still be
-        // intact.
+        // intact and the chunk should not be modified.
         assert_eq!(
             chunk.set_block_state(0, 0, 0, BlockState::WET_SPONGE),
             BlockState::WET_SPONGE
         );
 
         assert!(!chunk.cached_init_packets.get_mut().is_empty());
+        assert!(!chunk.is_modified());
     }
 }
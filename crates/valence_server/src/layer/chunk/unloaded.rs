@@ -9,6 +9,7 @@ use super::chunk::{
     check_biome_oob, check_block_oob, check_section_oob, BiomeContainer, BlockStateContainer,
     Chunk, MAX_HEIGHT, SECTION_BLOCK_COUNT,
 };
+use super::light::LightArray;
 
 #[derive(Clone, Default, Debug)]
 pub struct UnloadedChunk {
@@ -20,6 +21,8 @@ pub struct UnloadedChunk {
 pub(super) struct Section {
     pub(super) block_states: BlockStateContainer,
     pub(super) biomes: BiomeContainer,
+    pub(super) sky_light: Option<LightArray>,
+    pub(super) block_light: Option<LightArray>,
 }
 
 impl UnloadedChunk {
@@ -61,6 +64,83 @@ impl UnloadedChunk {
             }
         }
     }
+
+    /// Returns an iterator over the block entities in this chunk, along with
+    /// their `[x, y, z]` offsets within the chunk. Block entities are yielded in
+    /// ascending order of their `y`, `z` and `x` offsets.
+    pub fn block_entities(&self) -> impl Iterator<Item = ([u32; 3], &Compound)> + '_ {
+        self.block_entities
+            .iter()
+            .map(|(&idx, nbt)| ([idx % 16, idx / (16 * 16), idx / 16 % 16], nbt))
+    }
+
+    /// Gets the sky light of the section at `sect_y`, if present.
+    ///
+    /// Light stored in an unloaded chunk is reused when the chunk is inserted
+    /// into a [`ChunkLayer`](super::ChunkLayer), as long as every section has
+    /// both sky light and block light. Note that the light is not updated when
+    /// blocks in an unloaded chunk are modified.
+    ///
+    /// # Panics
+    ///
+    /// May panic if the section offset is out of bounds.
+    #[track_caller]
+    pub fn sky_light(&self, sect_y: u32) -> Option<&LightArray> {
+        check_section_oob(self, sect_y);
+
+        self.sections[sect_y as usize].sky_light.as_ref()
+    }
+
+    /// Sets the sky light of the section at `sect_y`. The previous sky light
+    /// of the section is returned.
+    ///
+    /// # Panics
+    ///
+    /// May panic if the section offset is out of bounds.
+    #[track_caller]
+    pub fn set_sky_light(&mut self, sect_y: u32, light: Option<LightArray>) -> Option<LightArray> {
+        check_section_oob(self, sect_y);
+
+        std::mem::replace(&mut self.sections[sect_y as usize].sky_light, light)
+    }
+
+    /// Gets the block light of the section at `sect_y`, if present. See
+    /// [`Self::sky_light`] for details.
+    ///
+    /// # Panics
+    ///
+    /// May panic if the section offset is out of bounds.
+    #[track_caller]
+    pub fn block_light(&self, sect_y: u32) -> Option<&LightArray> {
+        check_section_oob(self, sect_y);
+
+        self.sections[sect_y as usize].block_light.as_ref()
+    }
+
+    /// Sets the block light of the section at `sect_y`. The previous block
+    /// light of the section is returned.
+    ///
+    /// # Panics
+    ///
+    /// May panic if the section offset is out of bounds.
+    #[track_caller]
+    pub fn set_block_light(
+        &mut self,
+        sect_y: u32,
+        light: Option<LightArray>,
+    ) -> Option<LightArray> {
+        check_section_oob(self, sect_y);
+
+        std::mem::replace(&mut self.sections[sect_y as usize].block_light, light)
+    }
+
+    /// Removes all sky light and block light from this chunk.
+    pub fn clear_light(&mut self) {
+        for sect in &mut self.sections {
+            sect.sky_light = None;
+            sect.block_light = None;
+        }
+    }
 }
 
 impl Chunk for UnloadedChunk {
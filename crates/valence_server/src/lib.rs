@@ -2,6 +2,7 @@
 
 pub mod abilities;
 pub mod action;
+pub mod bandwidth;
 pub mod brand;
 mod chunk_view;
 pub mod client;
@@ -9,6 +10,7 @@ pub mod client_command;
 pub mod client_settings;
 pub mod custom_payload;
 pub mod event_loop;
+pub mod experience;
 pub mod hand_swing;
 pub mod interact_block;
 pub mod interact_entity;
@@ -18,7 +20,11 @@ pub mod layer;
 pub mod message;
 pub mod movement;
 pub mod op_level;
+pub mod physics;
+pub mod raycast;
+pub mod recorder;
 pub mod resource_pack;
+pub mod shutdown;
 pub mod spawn;
 pub mod status;
 pub mod status_effect;
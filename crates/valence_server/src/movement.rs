@@ -1,12 +1,21 @@
 use bevy_app::prelude::*;
 use bevy_ecs::prelude::*;
-use valence_entity::{HeadYaw, Look, OnGround, Position};
-use valence_math::DVec3;
+use bevy_ecs::query::QueryData;
+use valence_entity::active_status_effects::ActiveStatusEffects;
+use valence_entity::entity;
+use valence_entity::hitbox::HitboxShape;
+use valence_entity::{HeadYaw, Look, OnGround, Pose, Position};
+use valence_math::{Aabb, DVec3};
 use valence_protocol::packets::play::{
     FullC2s, LookAndOnGroundC2s, OnGroundOnlyC2s, PositionAndOnGroundC2s, VehicleMoveC2s,
 };
+use valence_protocol::status_effects::StatusEffect;
+use valence_protocol::{BlockPos, GameMode};
 
+use crate::abilities::{FlyingSpeed, PlayerAbilitiesFlags};
+use crate::client::{Client, VisibleChunkLayer};
 use crate::event_loop::{EventLoopPreUpdate, PacketEvent};
+use crate::layer::ChunkLayer;
 use crate::teleport::TeleportState;
 
 pub struct MovementPlugin;
@@ -15,13 +24,62 @@ impl Plugin for MovementPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<MovementSettings>()
             .add_event::<MovementEvent>()
+            .add_event::<MovementRejectedEvent>()
             .add_systems(EventLoopPreUpdate, handle_client_movement);
     }
 }
 
 /// Configuration resource for client movement checks.
-#[derive(Resource, Default)]
-pub struct MovementSettings; // TODO
+///
+/// When a movement fails a check, it is not applied. Instead, the client is
+/// teleported back to its last accepted position and a
+/// [`MovementRejectedEvent`] is sent.
+///
+/// Checks are never applied to clients in spectator mode or to the movement of
+/// vehicles. All checks are disabled by default.
+/// [`MovementSettings::recommended`] enables them with limits that vanilla
+/// clients stay within.
+#[derive(Resource, Clone, PartialEq, Default, Debug)]
+pub struct MovementSettings {
+    /// The maximum horizontal distance in blocks a client may move in one
+    /// tick. The limit is raised by the Speed status effect, the
+    /// [`FlyingSpeed`] of clients that are allowed to fly and velocity set
+    /// with [`Client::set_velocity`]. Clients gliding with an elytra are not
+    /// checked.
+    ///
+    /// `None` disables the check.
+    pub max_horizontal_speed: Option<f64>,
+    /// The maximum distance in blocks a client may move upwards in one tick.
+    /// The limit is raised by the Jump Boost and Levitation status effects,
+    /// the [`FlyingSpeed`] of clients that are allowed to fly, velocity set
+    /// with [`Client::set_velocity`] and the speed of the previous fall, so
+    /// that clients can bounce on slime blocks and beds.
+    ///
+    /// `None` disables the check.
+    pub max_vertical_speed: Option<f64>,
+    /// Rejects movement that puts the client inside the collision shape of a
+    /// block. Clients that are already inside of a block may move out of it.
+    pub prevent_no_clip: bool,
+    /// The maximum number of consecutive moves a client that is not allowed to
+    /// fly may make in the air without ending up below the height it left the
+    /// ground at. A client counts as being in the air when no blocks other
+    /// than air are next to or below it.
+    ///
+    /// `None` disables the check.
+    pub max_hover_ticks: Option<u32>,
+}
+
+impl MovementSettings {
+    /// Settings with every check enabled.
+    pub fn recommended() -> Self {
+        Self {
+            max_horizontal_speed: Some(1.2),
+            max_vertical_speed: Some(0.8),
+            prevent_no_clip: true,
+            max_hover_ticks: Some(40),
+        }
+    }
+}
 
 /// Event sent when a client successfully moves.
 #[derive(Event, Clone, Debug)]
@@ -35,173 +93,340 @@ pub struct MovementEvent {
     pub old_on_ground: bool,
 }
 
+/// Event sent when the movement of a client fails a check in
+/// [`MovementSettings`]. The client is teleported back to `old_position`.
+#[derive(Event, Clone, Debug)]
+pub struct MovementRejectedEvent {
+    pub client: Entity,
+    /// The position the client tried to move to.
+    pub position: DVec3,
+    pub old_position: DVec3,
+    pub reason: MovementRejection,
+}
+
+/// The check a movement failed.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum MovementRejection {
+    /// The client moved further horizontally than allowed.
+    TooFast { distance: f64, max: f64 },
+    /// The client moved further upwards than allowed.
+    TooFastVertical { distance: f64, max: f64 },
+    /// The client moved into a block.
+    NoClip,
+    /// The client stayed in the air for too long.
+    Hovering,
+}
+
+/// [`Component`] that stores the state of the movement checks of a client.
+#[derive(Component, Default, Debug)]
+pub struct MovementState {
+    /// Horizontal distance per tick allowed by velocity set with
+    /// [`Client::set_velocity`].
+    knockback_horizontal: f64,
+    /// Upward distance per tick allowed by velocity set with
+    /// [`Client::set_velocity`].
+    knockback_vertical: f64,
+    /// The downward distance of the last movement.
+    fall_speed: f64,
+    /// The number of consecutive moves made in the air.
+    air_ticks: u32,
+    /// The height the client was at when it left the ground.
+    air_start_y: f64,
+}
+
+impl MovementState {
+    /// Returns the number of consecutive moves the client has made in the
+    /// air. This is only tracked while [`MovementSettings::max_hover_ticks`]
+    /// is enabled.
+    pub fn air_ticks(&self) -> u32 {
+        self.air_ticks
+    }
+}
+
+/// The amount knockback allowances shrink by on every move, which is the
+/// friction of air.
+const KNOCKBACK_DECAY: f64 = 0.91;
+
+/// The flying speed vanilla clients have by default.
+const DEFAULT_FLYING_SPEED: f64 = 0.05;
+
+/// Hitbox of a standing player, used for clients without a [`HitboxShape`].
+const PLAYER_SIZE: DVec3 = DVec3::new(0.6, 1.8, 0.6);
+
+#[derive(QueryData)]
+#[query_data(mutable)]
+struct MovementQuery {
+    client: &'static mut Client,
+    pos: &'static mut Position,
+    look: &'static mut Look,
+    head_yaw: &'static mut HeadYaw,
+    on_ground: &'static mut OnGround,
+    teleport_state: &'static mut TeleportState,
+    state: &'static mut MovementState,
+    game_mode: &'static GameMode,
+    abilities: &'static PlayerAbilitiesFlags,
+    flying_speed: &'static FlyingSpeed,
+    pose: &'static entity::Pose,
+    hitbox: Option<&'static HitboxShape>,
+    status_effects: Option<&'static ActiveStatusEffects>,
+    layer: &'static VisibleChunkLayer,
+}
+
 fn handle_client_movement(
     mut packets: EventReader<PacketEvent>,
-    mut clients: Query<(
-        &mut Position,
-        &mut Look,
-        &mut HeadYaw,
-        &mut OnGround,
-        &mut TeleportState,
-    )>,
+    mut clients: Query<MovementQuery>,
+    layers: Query<&ChunkLayer>,
+    settings: Res<MovementSettings>,
     mut movement_events: EventWriter<MovementEvent>,
+    mut rejected_events: EventWriter<MovementRejectedEvent>,
 ) {
     for packet in packets.read() {
-        if let Some(pkt) = packet.decode::<PositionAndOnGroundC2s>() {
-            if let Ok((pos, look, head_yaw, on_ground, teleport_state)) =
-                clients.get_mut(packet.client)
-            {
-                let mov = MovementEvent {
-                    client: packet.client,
-                    position: pkt.position,
-                    old_position: pos.0,
-                    look: *look,
-                    old_look: *look,
-                    on_ground: pkt.on_ground,
-                    old_on_ground: on_ground.0,
-                };
+        let Ok(client) = clients.get_mut(packet.client) else {
+            continue;
+        };
 
-                handle(
-                    mov,
-                    pos,
-                    look,
-                    head_yaw,
-                    on_ground,
-                    teleport_state,
-                    &mut movement_events,
-                );
-            }
-        } else if let Some(pkt) = packet.decode::<FullC2s>() {
-            if let Ok((pos, look, head_yaw, on_ground, teleport_state)) =
-                clients.get_mut(packet.client)
-            {
-                let mov = MovementEvent {
-                    client: packet.client,
-                    position: pkt.position,
-                    old_position: pos.0,
-                    look: Look {
-                        yaw: pkt.yaw,
-                        pitch: pkt.pitch,
-                    },
-                    old_look: *look,
-                    on_ground: pkt.on_ground,
-                    old_on_ground: on_ground.0,
-                };
+        let old_look = *client.look;
+        let old_on_ground = client.on_ground.0;
 
-                handle(
-                    mov,
-                    pos,
-                    look,
-                    head_yaw,
-                    on_ground,
-                    teleport_state,
-                    &mut movement_events,
-                );
-            }
-        } else if let Some(pkt) = packet.decode::<LookAndOnGroundC2s>() {
-            if let Ok((pos, look, head_yaw, on_ground, teleport_state)) =
-                clients.get_mut(packet.client)
-            {
-                let mov = MovementEvent {
-                    client: packet.client,
-                    position: pos.0,
-                    old_position: pos.0,
-                    look: Look {
-                        yaw: pkt.yaw,
-                        pitch: pkt.pitch,
-                    },
-                    old_look: *look,
-                    on_ground: pkt.on_ground,
-                    old_on_ground: on_ground.0,
+        // The new position, look and on-ground state, and whether the movement
+        // is checked.
+        let (position, look, on_ground, check) =
+            if let Some(pkt) = packet.decode::<PositionAndOnGroundC2s>() {
+                (Some(pkt.position), old_look, pkt.on_ground, true)
+            } else if let Some(pkt) = packet.decode::<FullC2s>() {
+                let look = Look {
+                    yaw: pkt.yaw,
+                    pitch: pkt.pitch,
                 };
-
-                handle(
-                    mov,
-                    pos,
-                    look,
-                    head_yaw,
-                    on_ground,
-                    teleport_state,
-                    &mut movement_events,
-                );
-            }
-        } else if let Some(pkt) = packet.decode::<OnGroundOnlyC2s>() {
-            if let Ok((pos, look, head_yaw, on_ground, teleport_state)) =
-                clients.get_mut(packet.client)
-            {
-                let mov = MovementEvent {
-                    client: packet.client,
-                    position: pos.0,
-                    old_position: pos.0,
-                    look: *look,
-                    old_look: *look,
-                    on_ground: pkt.on_ground,
-                    old_on_ground: on_ground.0,
+                (Some(pkt.position), look, pkt.on_ground, true)
+            } else if let Some(pkt) = packet.decode::<LookAndOnGroundC2s>() {
+                let look = Look {
+                    yaw: pkt.yaw,
+                    pitch: pkt.pitch,
                 };
-
-                handle(
-                    mov,
-                    pos,
-                    look,
-                    head_yaw,
-                    on_ground,
-                    teleport_state,
-                    &mut movement_events,
-                );
-            }
-        } else if let Some(pkt) = packet.decode::<VehicleMoveC2s>() {
-            if let Ok((pos, look, head_yaw, on_ground, teleport_state)) =
-                clients.get_mut(packet.client)
-            {
-                let mov = MovementEvent {
-                    client: packet.client,
-                    position: pkt.position,
-                    old_position: pos.0,
-                    look: Look {
-                        yaw: pkt.yaw,
-                        pitch: pkt.pitch,
-                    },
-                    old_look: *look,
-                    on_ground: on_ground.0,
-                    old_on_ground: on_ground.0,
+                (None, look, pkt.on_ground, false)
+            } else if let Some(pkt) = packet.decode::<OnGroundOnlyC2s>() {
+                (None, old_look, pkt.on_ground, false)
+            } else if let Some(pkt) = packet.decode::<VehicleMoveC2s>() {
+                let look = Look {
+                    yaw: pkt.yaw,
+                    pitch: pkt.pitch,
                 };
+                (Some(pkt.position), look, old_on_ground, false)
+            } else {
+                continue;
+            };
 
-                handle(
-                    mov,
-                    pos,
-                    look,
-                    head_yaw,
-                    on_ground,
-                    teleport_state,
-                    &mut movement_events,
-                );
-            }
-        }
+        let mov = MovementEvent {
+            client: packet.client,
+            position: position.unwrap_or(client.pos.0),
+            old_position: client.pos.0,
+            look,
+            old_look,
+            on_ground,
+            old_on_ground,
+        };
+
+        handle(
+            mov,
+            client,
+            check,
+            &layers,
+            &settings,
+            &mut movement_events,
+            &mut rejected_events,
+        );
     }
 }
 
 fn handle(
     mov: MovementEvent,
-    mut pos: Mut<Position>,
-    mut look: Mut<Look>,
-    mut head_yaw: Mut<HeadYaw>,
-    mut on_ground: Mut<OnGround>,
-    mut teleport_state: Mut<TeleportState>,
+    mut client: MovementQueryItem,
+    check: bool,
+    layers: &Query<&ChunkLayer>,
+    settings: &MovementSettings,
     movement_events: &mut EventWriter<MovementEvent>,
+    rejected_events: &mut EventWriter<MovementRejectedEvent>,
 ) {
-    if teleport_state.pending_teleports() != 0 {
+    if client.teleport_state.pending_teleports() != 0 {
         return;
     }
 
-    // TODO: check that the client isn't moving too fast / flying.
-    // TODO: check that the client isn't clipping through blocks.
+    if let Some(velocity) = client.client.take_sent_velocity() {
+        let velocity = velocity.as_dvec3() / 20.0;
+
+        client.state.knockback_horizontal = velocity.truncate().length();
+        client.state.knockback_vertical = velocity.y.max(0.0);
+        // Being launched upwards does not count as hovering.
+        client.state.air_ticks = 0;
+        client.state.air_start_y = mov.position.y.max(mov.old_position.y);
+    }
+
+    if check && *client.game_mode != GameMode::Spectator {
+        let layer = layers.get(client.layer.0).ok();
+
+        if let Err(reason) = check_movement(&mov, &mut client, layer, settings) {
+            // Teleport the client back to the last accepted position.
+            client.teleport_state.synced_pos = DVec3::NAN;
+            client.pos.set_changed();
+
+            rejected_events.send(MovementRejectedEvent {
+                client: mov.client,
+                position: mov.position,
+                old_position: mov.old_position,
+                reason,
+            });
+
+            return;
+        }
+    }
+
+    client.state.knockback_horizontal *= KNOCKBACK_DECAY;
+    client.state.knockback_vertical *= KNOCKBACK_DECAY;
 
-    pos.set_if_neq(Position(mov.position));
-    teleport_state.synced_pos = mov.position;
-    look.set_if_neq(mov.look);
-    teleport_state.synced_look = mov.look;
-    head_yaw.set_if_neq(HeadYaw(mov.look.yaw));
-    on_ground.set_if_neq(OnGround(mov.on_ground));
+    client.pos.set_if_neq(Position(mov.position));
+    client.teleport_state.synced_pos = mov.position;
+    client.look.set_if_neq(mov.look);
+    client.teleport_state.synced_look = mov.look;
+    client.head_yaw.set_if_neq(HeadYaw(mov.look.yaw));
+    client.on_ground.set_if_neq(OnGround(mov.on_ground));
 
     movement_events.send(mov);
 }
+
+fn check_movement(
+    mov: &MovementEvent,
+    client: &mut MovementQueryItem,
+    layer: Option<&ChunkLayer>,
+    settings: &MovementSettings,
+) -> Result<(), MovementRejection> {
+    let delta = mov.position - mov.old_position;
+
+    let effect_level = |effect| {
+        client
+            .status_effects
+            .and_then(|effects| effects.get_current_effect(effect))
+            .map_or(0.0, |effect| f64::from(effect.amplifier()) + 1.0)
+    };
+
+    let can_fly = client.abilities.allow_flying();
+    let gliding = client.pose.0 == Pose::FallFlying;
+    let flying_scale = if can_fly && client.abilities.flying() {
+        (f64::from(client.flying_speed.0) / DEFAULT_FLYING_SPEED).max(1.0)
+    } else {
+        1.0
+    };
+
+    if let Some(max_speed) = settings.max_horizontal_speed {
+        let distance = delta.truncate().length();
+        let max = max_speed * flying_scale * (1.0 + 0.2 * effect_level(StatusEffect::Speed))
+            + client.state.knockback_horizontal;
+
+        if !gliding && (distance.is_nan() || distance > max) {
+            return Err(MovementRejection::TooFast { distance, max });
+        }
+    }
+
+    if let Some(max_speed) = settings.max_vertical_speed {
+        let distance = delta.y;
+        let max = max_speed * flying_scale
+            + 0.1 * effect_level(StatusEffect::JumpBoost)
+            + 0.05 * effect_level(StatusEffect::Levitation)
+            + client.state.knockback_vertical
+            + client.state.fall_speed;
+
+        if !gliding && (distance.is_nan() || distance > max) {
+            return Err(MovementRejection::TooFastVertical { distance, max });
+        }
+    }
+
+    // The hitbox shape is empty until it is first updated.
+    let size = client
+        .hitbox
+        .map(|h| h.get())
+        .filter(|&h| h != Aabb::ZERO)
+        .unwrap_or(Aabb::from_bottom_size(DVec3::ZERO, PLAYER_SIZE));
+
+    if settings.prevent_no_clip {
+        // Clients come to rest exactly on the surface of blocks.
+        let shrink = DVec3::splat(1e-3);
+        let shrunk = Aabb::new(size.min() + shrink, size.max() - shrink);
+
+        if let Some(layer) = layer {
+            if collides(layer, shrunk + mov.position) && !collides(layer, shrunk + mov.old_position)
+            {
+                return Err(MovementRejection::NoClip);
+            }
+        }
+    }
+
+    if let Some(max_hover_ticks) = settings.max_hover_ticks {
+        let hovering_allowed = can_fly
+            || gliding
+            || effect_level(StatusEffect::Levitation) > 0.0
+            || effect_level(StatusEffect::SlowFalling) > 0.0;
+
+        // Blocks the client could be standing on, climbing or swimming in.
+        let grow = DVec3::new(0.1, 0.1, 0.1);
+        let surroundings = Aabb::new(size.min() - grow, size.max() + DVec3::new(0.1, 0.0, 0.1));
+
+        let supported = layer.is_none_or(|layer| touches_block(layer, surroundings + mov.position));
+
+        if hovering_allowed || supported {
+            client.state.air_ticks = 0;
+            client.state.air_start_y = mov.position.y;
+        } else {
+            client.state.air_ticks += 1;
+
+            if client.state.air_ticks > max_hover_ticks
+                && mov.position.y >= client.state.air_start_y
+            {
+                return Err(MovementRejection::Hovering);
+            }
+        }
+    }
+
+    client.state.fall_speed = (-delta.y).max(0.0);
+
+    Ok(())
+}
+
+/// Returns whether `f` returns `true` for the position of any block that
+/// `aabb` overlaps.
+fn blocks_in<F: FnMut(BlockPos) -> bool>(aabb: Aabb, mut f: F) -> bool {
+    let min = aabb.min().floor().as_ivec3();
+    let max = aabb.max().floor().as_ivec3();
+
+    for y in min.y..=max.y {
+        for z in min.z..=max.z {
+            for x in min.x..=max.x {
+                if f(BlockPos::new(x, y, z)) {
+                    return true;
+                }
+            }
+        }
+    }
+
+    false
+}
+
+/// Returns whether `aabb` intersects the collision shape of any block.
+fn collides(layer: &ChunkLayer, aabb: Aabb) -> bool {
+    blocks_in(aabb, |pos| {
+        layer.block(pos).is_some_and(|block| {
+            let offset = DVec3::new(f64::from(pos.x), f64::from(pos.y), f64::from(pos.z));
+
+            block
+                .state
+                .collision_shapes()
+                .any(|shape| (shape + offset).intersects(aabb))
+        })
+    })
+}
+
+/// Returns whether `aabb` overlaps any block that isn't air.
+fn touches_block(layer: &ChunkLayer, aabb: Aabb) -> bool {
+    blocks_in(aabb, |pos| {
+        layer.block(pos).is_some_and(|block| !block.state.is_air())
+    })
+}
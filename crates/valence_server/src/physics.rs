@@ -0,0 +1,287 @@
+//! Gravity, drag and block collisions for entities.
+//!
+//! [`PhysicsPlugin`] is not part of the default plugins. Once added, it gives
+//! newly spawned entities a [`Physics`] component with the vanilla properties
+//! of their [`EntityKind`]. Every tick, the [`Velocity`] of entities with a
+//! [`Physics`] component is integrated into their [`Position`], and their
+//! [`HitboxShape`] is moved through the blocks of the [`ChunkLayer`] in their
+//! [`EntityLayerId`], stopping at the collision shapes of blocks.
+//!
+//! Clients predict the motion of most entities themselves, so changes to the
+//! velocity made by the simulation are only sent to clients when an entity
+//! hits a block. Changes made to [`Velocity`] by game code are sent as usual.
+//!
+//! Clients are never simulated. Entities with [`NoGravity`] set do not fall.
+
+use bevy_app::prelude::*;
+use bevy_ecs::prelude::*;
+use valence_entity::entity::NoGravity;
+use valence_entity::hitbox::HitboxShape;
+use valence_entity::living::LivingEntity;
+use valence_entity::{EntityKind, EntityLayerId, OnGround, Position, Velocity};
+use valence_math::{Aabb, DVec3};
+use valence_protocol::BlockPos;
+
+use crate::client::ClientMarker;
+use crate::layer::{ChunkLayer, UpdateLayersPreClientSet};
+
+pub struct PhysicsPlugin;
+
+/// The system set the physics simulation runs in, in [`PostUpdate`]. Changes
+/// to [`Velocity`] and [`Position`] made before this set are part of the
+/// simulation of the current tick.
+#[derive(SystemSet, Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct PhysicsSet;
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.configure_sets(PostUpdate, PhysicsSet.before(UpdateLayersPreClientSet))
+            .add_systems(
+                PostUpdate,
+                (add_physics, simulate_physics).chain().in_set(PhysicsSet),
+            );
+    }
+}
+
+/// [`Component`] with the physical properties of an entity. Entities with this
+/// component are simulated by the [`PhysicsPlugin`].
+///
+/// Accelerations and velocities are in blocks per tick, like in vanilla.
+#[derive(Component, Copy, Clone, PartialEq, Debug)]
+pub struct Physics {
+    /// The downward acceleration of the entity.
+    pub gravity: f64,
+    /// The fraction of the vertical velocity lost every tick.
+    pub vertical_drag: f64,
+    /// The fraction of the horizontal velocity lost every tick.
+    pub horizontal_drag: f64,
+    /// The fraction of the horizontal velocity kept every tick while the
+    /// entity is on the ground, in addition to the drag.
+    pub ground_friction: f64,
+}
+
+impl Physics {
+    /// The friction of most blocks.
+    const GROUND_FRICTION: f64 = 0.6;
+
+    /// Items, falling blocks, TNT and experience orbs.
+    pub const ITEM: Self = Self {
+        gravity: 0.04,
+        vertical_drag: 0.02,
+        horizontal_drag: 0.02,
+        ground_friction: Self::GROUND_FRICTION,
+    };
+
+    /// Arrows and tridents.
+    pub const ARROW: Self = Self {
+        gravity: 0.05,
+        vertical_drag: 0.01,
+        horizontal_drag: 0.01,
+        ground_friction: Self::GROUND_FRICTION,
+    };
+
+    /// Snowballs, eggs, ender pearls, potions and other thrown items.
+    pub const THROWN: Self = Self {
+        gravity: 0.03,
+        vertical_drag: 0.01,
+        horizontal_drag: 0.01,
+        ground_friction: Self::GROUND_FRICTION,
+    };
+
+    /// Mobs and other living entities.
+    pub const LIVING: Self = Self {
+        gravity: 0.08,
+        vertical_drag: 0.02,
+        horizontal_drag: 0.09,
+        ground_friction: Self::GROUND_FRICTION,
+    };
+
+    /// Returns the physical properties of entities of the given kind, or
+    /// `None` if this kind is not simulated. Living entities are not
+    /// included, see [`Physics::LIVING`].
+    pub fn for_kind(kind: EntityKind) -> Option<Self> {
+        match kind {
+            EntityKind::ITEM
+            | EntityKind::FALLING_BLOCK
+            | EntityKind::TNT
+            | EntityKind::EXPERIENCE_ORB => Some(Self::ITEM),
+            EntityKind::ARROW | EntityKind::SPECTRAL_ARROW | EntityKind::TRIDENT => {
+                Some(Self::ARROW)
+            }
+            EntityKind::SNOWBALL
+            | EntityKind::EGG
+            | EntityKind::ENDER_PEARL
+            | EntityKind::POTION
+            | EntityKind::EXPERIENCE_BOTTLE => Some(Self::THROWN),
+            _ => None,
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn add_physics(
+    entities: Query<
+        (Entity, &EntityKind, Has<LivingEntity>),
+        (Added<EntityKind>, Without<Physics>, Without<ClientMarker>),
+    >,
+    mut commands: Commands,
+) {
+    for (entity, &kind, living) in &entities {
+        let physics = if living {
+            Some(Physics::LIVING)
+        } else {
+            Physics::for_kind(kind)
+        };
+
+        if let Some(physics) = physics {
+            commands.entity(entity).insert(physics);
+        }
+    }
+}
+
+/// Velocities below this are considered to be zero.
+const MIN_VELOCITY: f64 = 0.003;
+
+#[allow(clippy::type_complexity)]
+fn simulate_physics(
+    mut entities: Query<(
+        &Physics,
+        &mut Position,
+        &mut Velocity,
+        &mut OnGround,
+        &EntityLayerId,
+        Option<&HitboxShape>,
+        Option<&NoGravity>,
+    )>,
+    layers: Query<&ChunkLayer>,
+) {
+    for (physics, mut pos, mut velocity, mut on_ground, layer_id, shape, no_gravity) in
+        &mut entities
+    {
+        // Blocks per tick.
+        let old_velocity = velocity.0.as_dvec3() / 20.0;
+        let mut v = old_velocity;
+
+        if !no_gravity.is_some_and(|g| g.0) {
+            v.y -= physics.gravity;
+        }
+
+        let shape = shape.map_or(Aabb::ZERO, |s| s.get());
+
+        let motion = match layers.get(layer_id.0) {
+            Ok(layer) => collide(layer, shape + pos.0, v),
+            Err(_) => v,
+        };
+
+        let grounded = v.y < 0.0 && motion.y > v.y;
+        let mut hit = false;
+
+        for axis in 0..3 {
+            if motion[axis] != v[axis] {
+                v[axis] = 0.0;
+                // Only tell clients about collisions that stop the entity.
+                hit |= old_velocity[axis].abs() > MIN_VELOCITY;
+            }
+        }
+
+        if motion != DVec3::ZERO {
+            pos.0 += motion;
+        }
+
+        on_ground.set_if_neq(OnGround(grounded));
+
+        v.y *= 1.0 - physics.vertical_drag;
+
+        let mut horizontal = 1.0 - physics.horizontal_drag;
+        if grounded {
+            horizontal *= physics.ground_friction;
+        }
+        v.x *= horizontal;
+        v.z *= horizontal;
+
+        for axis in 0..3 {
+            if v[axis].abs() < MIN_VELOCITY {
+                v[axis] = 0.0;
+            }
+        }
+
+        let new_velocity = Velocity((v * 20.0).as_vec3());
+
+        if hit {
+            *velocity = new_velocity;
+        } else {
+            *velocity.bypass_change_detection() = new_velocity;
+        }
+    }
+}
+
+/// Returns how far `aabb` can move along `motion` before it hits the collision
+/// shape of a block. Like in vanilla, the motion is resolved one axis at a
+/// time, starting with the vertical axis.
+fn collide(layer: &ChunkLayer, aabb: Aabb, motion: DVec3) -> DVec3 {
+    if motion == DVec3::ZERO {
+        return motion;
+    }
+
+    let swept = aabb.union(aabb + motion);
+    let min = swept.min().floor().as_ivec3();
+    let max = swept.max().floor().as_ivec3();
+
+    let mut shapes = vec![];
+
+    // Blocks such as fences have collision shapes taller than a block.
+    for y in min.y - 1..=max.y {
+        for z in min.z..=max.z {
+            for x in min.x..=max.x {
+                if let Some(block) = layer.block(BlockPos::new(x, y, z)) {
+                    let offset = DVec3::new(f64::from(x), f64::from(y), f64::from(z));
+                    shapes.extend(block.state.collision_shapes().map(|s| s + offset));
+                }
+            }
+        }
+    }
+
+    let mut axes = [1, 0, 2];
+    if motion.x.abs() < motion.z.abs() {
+        axes = [1, 2, 0];
+    }
+
+    let mut aabb = aabb;
+    let mut result = DVec3::ZERO;
+
+    for axis in axes {
+        let offset = shapes.iter().fold(motion[axis], |offset, &shape| {
+            clip(shape, aabb, axis, offset)
+        });
+
+        let mut delta = DVec3::ZERO;
+        delta[axis] = offset;
+
+        aabb = aabb + delta;
+        result[axis] = offset;
+    }
+
+    result
+}
+
+/// Tolerance for touching surfaces.
+const EPSILON: f64 = 1e-7;
+
+/// Limits `offset` along `axis` so that `moving` does not move into `shape`.
+fn clip(shape: Aabb, moving: Aabb, axis: usize, offset: f64) -> f64 {
+    let overlaps = (0..3).filter(|&a| a != axis).all(|a| {
+        shape.max()[a] - EPSILON > moving.min()[a] && shape.min()[a] + EPSILON < moving.max()[a]
+    });
+
+    if !overlaps {
+        return offset;
+    }
+
+    if offset > 0.0 && shape.min()[axis] >= moving.max()[axis] - EPSILON {
+        offset.min(shape.min()[axis] - moving.max()[axis])
+    } else if offset < 0.0 && shape.max()[axis] <= moving.min()[axis] + EPSILON {
+        offset.max(shape.max()[axis] - moving.min()[axis])
+    } else {
+        offset
+    }
+}
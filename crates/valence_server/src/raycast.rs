@@ -0,0 +1,414 @@
+//! Casting rays against the blocks of a [`ChunkLayer`] and the [`Hitbox`]es of
+//! the entities in an [`EntityLayer`].
+//!
+//! Use the [`Raycast`] system parameter to find what a ray hits first:
+//!
+//! ```
+//! use valence_server::client::Client;
+//! use valence_server::ecs::prelude::*;
+//! use valence_server::entity::{EntityLayerId, Look, Position};
+//! use valence_server::math::DVec3;
+//! use valence_server::raycast::{Ray, Raycast, RaycastTarget};
+//!
+//! fn looking_at(
+//!     clients: Query<(Entity, &Position, &Look, &EntityLayerId), With<Client>>,
+//!     raycast: Raycast,
+//! ) {
+//!     for (entity, pos, look, layer) in &clients {
+//!         let ray = Ray::from_look(pos.0 + DVec3::new(0.0, 1.62, 0.0), *look);
+//!
+//!         let hit = raycast.cast(layer.0, ray, 5.0, |_, _| true, |e| e != entity);
+//!
+//!         if let Some(hit) = hit {
+//!             match hit.target {
+//!                 RaycastTarget::Block { pos, .. } => println!("looking at block {pos}"),
+//!                 RaycastTarget::Entity(target) => println!("looking at {target:?}"),
+//!             }
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Blocks are hit at their collision shapes. The outline shapes of blocks are
+//! not available, so blocks without a collision shape other than air and
+//! liquids, such as flowers and torches, are hit at their full cube. Pass a
+//! filter such as `|_, state| state.blocks_motion()` to let rays pass through
+//! them.
+//!
+//! [`Hitbox`]: valence_entity::hitbox::Hitbox
+
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::SystemParam;
+use rustc_hash::FxHashSet;
+use valence_entity::hitbox::Hitbox;
+use valence_entity::Look;
+use valence_math::{Aabb, DVec3, IVec3};
+use valence_protocol::{BlockPos, BlockState, ChunkPos, Direction};
+
+use crate::layer::{ChunkLayer, EntityLayer};
+
+/// A half-line starting at `origin`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Ray {
+    pub origin: DVec3,
+    /// The normalized direction of the ray.
+    pub direction: DVec3,
+}
+
+impl Ray {
+    /// Creates a ray from `origin` in the direction of `direction`, which does
+    /// not need to be normalized.
+    pub fn new<O: Into<DVec3>, D: Into<DVec3>>(origin: O, direction: D) -> Self {
+        Self {
+            origin: origin.into(),
+            direction: direction.into().normalize(),
+        }
+    }
+
+    /// Creates a ray from `origin` in the direction of `look`. For the view of
+    /// a player, `origin` is the position of its eyes.
+    pub fn from_look<O: Into<DVec3>>(origin: O, look: Look) -> Self {
+        Self::new(origin, look.vec().as_dvec3())
+    }
+
+    /// Returns the point at `distance` along the ray.
+    pub fn at(self, distance: f64) -> DVec3 {
+        self.origin + self.direction * distance
+    }
+
+    /// Returns the distance along the ray at which it enters `aabb`, and the
+    /// face it enters through.
+    fn intersect(self, aabb: Aabb) -> Option<(f64, Direction)> {
+        let [near, _] = aabb.ray_intersection(self.origin, self.direction)?;
+
+        // The ray enters through the face of the axis it crosses last.
+        let mut axis = 0;
+        let mut entry = f64::NEG_INFINITY;
+
+        for i in 0..3 {
+            let t0 = (aabb.min()[i] - self.origin[i]) / self.direction[i];
+            let t1 = (aabb.max()[i] - self.origin[i]) / self.direction[i];
+            let t = t0.min(t1);
+
+            if t > entry {
+                entry = t;
+                axis = i;
+            }
+        }
+
+        let positive = self.direction[axis] < 0.0;
+
+        let face = match (axis, positive) {
+            (0, false) => Direction::West,
+            (0, true) => Direction::East,
+            (1, false) => Direction::Down,
+            (1, true) => Direction::Up,
+            (_, false) => Direction::North,
+            (_, true) => Direction::South,
+        };
+
+        Some((near, face))
+    }
+}
+
+/// The first thing a ray hits.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct RaycastHit {
+    /// The point where the ray hits.
+    pub position: DVec3,
+    /// The distance from the origin of the ray to `position`.
+    pub distance: f64,
+    /// The face of the block or hitbox the ray enters through.
+    pub face: Direction,
+    pub target: RaycastTarget,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum RaycastTarget {
+    Block { pos: BlockPos, state: BlockState },
+    Entity(Entity),
+}
+
+/// Casts a ray against the blocks of `layer`. Only blocks for which `filter`
+/// returns `true` can be hit. Returns the first hit within `max_distance`,
+/// which must be finite.
+pub fn raycast_blocks<F>(
+    layer: &ChunkLayer,
+    ray: Ray,
+    max_distance: f64,
+    mut filter: F,
+) -> Option<RaycastHit>
+where
+    F: FnMut(BlockPos, BlockState) -> bool,
+{
+    let mut best: Option<RaycastHit> = None;
+
+    traverse(ray, max_distance, 1.0, |cell, entered| {
+        // Collision shapes can extend past their block, so keep going until no
+        // closer hit is possible.
+        if best.is_some_and(|hit| entered > hit.distance) {
+            return true;
+        }
+
+        let pos = BlockPos::new(cell.x, cell.y, cell.z);
+
+        let Some(block) = layer.block(pos) else {
+            return false;
+        };
+
+        let state = block.state;
+
+        if state.is_air() || state.is_liquid() || !filter(pos, state) {
+            return false;
+        }
+
+        let offset = DVec3::new(f64::from(pos.x), f64::from(pos.y), f64::from(pos.z));
+
+        let mut check = |shape: Aabb| {
+            if let Some((distance, face)) = ray.intersect(shape + offset) {
+                if distance <= max_distance && best.is_none_or(|hit| distance < hit.distance) {
+                    best = Some(RaycastHit {
+                        position: ray.at(distance),
+                        distance,
+                        face,
+                        target: RaycastTarget::Block { pos, state },
+                    });
+                }
+            }
+        };
+
+        if state.collision_shapes().len() == 0 {
+            check(Aabb::new(DVec3::ZERO, DVec3::ONE));
+        } else {
+            state.collision_shapes().for_each(check);
+        }
+
+        false
+    });
+
+    best
+}
+
+/// Casts a ray against the hitboxes of the entities in `layer`. Only entities
+/// for which `filter` returns `true` can be hit. Returns the first hit within
+/// `max_distance`, which must be finite.
+///
+/// `hitbox` returns the hitbox of an entity, or `None` if the entity has none.
+pub fn raycast_entities<H, F>(
+    layer: &EntityLayer,
+    ray: Ray,
+    max_distance: f64,
+    mut hitbox: H,
+    mut filter: F,
+) -> Option<RaycastHit>
+where
+    H: FnMut(Entity) -> Option<Aabb>,
+    F: FnMut(Entity) -> bool,
+{
+    let mut chunks = FxHashSet::default();
+
+    traverse(ray, max_distance, 16.0, |cell, _| {
+        // Hitboxes can extend into neighboring chunks.
+        for z in -1..=1 {
+            for x in -1..=1 {
+                chunks.insert(ChunkPos::new(cell.x + x, cell.z + z));
+            }
+        }
+
+        false
+    });
+
+    let mut best: Option<RaycastHit> = None;
+
+    for entity in chunks.into_iter().flat_map(|pos| layer.entities_at(pos)) {
+        let Some(aabb) = hitbox(entity) else {
+            continue;
+        };
+
+        if let Some((distance, face)) = ray.intersect(aabb) {
+            if distance <= max_distance
+                && best.is_none_or(|hit| distance < hit.distance)
+                && filter(entity)
+            {
+                best = Some(RaycastHit {
+                    position: ray.at(distance),
+                    distance,
+                    face,
+                    target: RaycastTarget::Entity(entity),
+                });
+            }
+        }
+    }
+
+    best
+}
+
+/// Visits the cells of a grid with cells of size `cell_size` that the ray
+/// passes through within `max_distance`, in order, along with the distance at
+/// which the ray enters them. Stops when `f` returns `true`. Nothing is visited
+/// if `max_distance` is not finite, because the traversal would never end.
+fn traverse<F>(ray: Ray, max_distance: f64, cell_size: f64, mut f: F)
+where
+    F: FnMut(IVec3, f64) -> bool,
+{
+    let origin = ray.origin / cell_size;
+    let dir = ray.direction;
+
+    if !origin.is_finite() || !dir.is_finite() || !max_distance.is_finite() || max_distance < 0.0 {
+        return;
+    }
+
+    let mut cell = origin.floor().as_ivec3();
+    let step = dir.signum().as_ivec3();
+
+    // The distance along the ray between cell boundaries on each axis.
+    let delta = (DVec3::splat(cell_size) / dir).abs();
+
+    // The distance along the ray to the next cell boundary on each axis.
+    let mut next = DVec3::ZERO;
+    for i in 0..3 {
+        next[i] = if dir[i] > 0.0 {
+            (f64::from(cell[i] + 1) - origin[i]) * cell_size / dir[i]
+        } else if dir[i] < 0.0 {
+            (f64::from(cell[i]) - origin[i]) * cell_size / dir[i]
+        } else {
+            f64::INFINITY
+        };
+    }
+
+    let mut entered = 0.0;
+
+    while entered <= max_distance {
+        if f(cell, entered) {
+            return;
+        }
+
+        let axis = if next.x < next.y {
+            if next.x < next.z {
+                0
+            } else {
+                2
+            }
+        } else if next.y < next.z {
+            1
+        } else {
+            2
+        };
+
+        let Some(next_cell) = cell[axis].checked_add(step[axis]) else {
+            return;
+        };
+
+        entered = next[axis];
+        next[axis] += delta[axis];
+        cell[axis] = next_cell;
+    }
+}
+
+/// A [`SystemParam`] for casting rays against blocks and entities.
+///
+/// The layer entities passed to the methods must have a [`ChunkLayer`] for
+/// blocks and an [`EntityLayer`] for entities. Nothing is hit in layers
+/// without them.
+#[derive(SystemParam)]
+pub struct Raycast<'w, 's> {
+    chunk_layers: Query<'w, 's, &'static ChunkLayer>,
+    entity_layers: Query<'w, 's, &'static EntityLayer>,
+    hitboxes: Query<'w, 's, &'static Hitbox>,
+}
+
+impl Raycast<'_, '_> {
+    /// Casts a ray against the blocks of `layer`. See [`raycast_blocks`].
+    pub fn blocks<F>(
+        &self,
+        layer: Entity,
+        ray: Ray,
+        max_distance: f64,
+        filter: F,
+    ) -> Option<RaycastHit>
+    where
+        F: FnMut(BlockPos, BlockState) -> bool,
+    {
+        let layer = self.chunk_layers.get(layer).ok()?;
+        raycast_blocks(layer, ray, max_distance, filter)
+    }
+
+    /// Casts a ray against the entities in `layer`. See
+    /// [`raycast_entities`].
+    pub fn entities<F>(
+        &self,
+        layer: Entity,
+        ray: Ray,
+        max_distance: f64,
+        filter: F,
+    ) -> Option<RaycastHit>
+    where
+        F: FnMut(Entity) -> bool,
+    {
+        let layer = self.entity_layers.get(layer).ok()?;
+        let hitbox = |entity| self.hitboxes.get(entity).ok().map(|h| h.get());
+
+        raycast_entities(layer, ray, max_distance, hitbox, filter)
+    }
+
+    /// Casts a ray against both the blocks and the entities of `layer` and
+    /// returns whichever is hit first.
+    pub fn cast<B, E>(
+        &self,
+        layer: Entity,
+        ray: Ray,
+        max_distance: f64,
+        block_filter: B,
+        entity_filter: E,
+    ) -> Option<RaycastHit>
+    where
+        B: FnMut(BlockPos, BlockState) -> bool,
+        E: FnMut(Entity) -> bool,
+    {
+        let block = self.blocks(layer, ray, max_distance, block_filter);
+        let max_distance = block.map_or(max_distance, |hit| hit.distance);
+
+        self.entities(layer, ray, max_distance, entity_filter)
+            .or(block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ray_enters_faces() {
+        let aabb = Aabb::new(DVec3::ZERO, DVec3::ONE);
+
+        let cases = [
+            ([0.5, 5.0, 0.5], [0.0, -1.0, 0.0], Direction::Up, 4.0),
+            ([0.5, -2.0, 0.5], [0.0, 1.0, 0.0], Direction::Down, 2.0),
+            ([3.0, 0.5, 0.5], [-1.0, 0.0, 0.0], Direction::East, 2.0),
+            ([-1.0, 0.5, 0.5], [1.0, 0.0, 0.0], Direction::West, 1.0),
+            ([0.5, 0.5, 2.0], [0.0, 0.0, -1.0], Direction::South, 1.0),
+            ([0.5, 0.5, -1.5], [0.0, 0.0, 1.0], Direction::North, 1.5),
+        ];
+
+        for (origin, direction, face, distance) in cases {
+            let ray = Ray::new(origin, direction);
+            assert_eq!(ray.intersect(aabb), Some((distance, face)));
+        }
+
+        let ray = Ray::new([2.0, 2.0, 2.0], [1.0, 0.0, 0.0]);
+        assert_eq!(ray.intersect(aabb), None);
+    }
+
+    #[test]
+    fn traverse_visits_cells_in_order() {
+        let ray = Ray::new([0.5, 0.25, 0.5], [1.0, 1.0, 0.0]);
+        let mut cells = vec![];
+
+        traverse(ray, 2.0, 1.0, |cell, _| {
+            cells.push(cell.to_array());
+            false
+        });
+
+        assert_eq!(cells, [[0, 0, 0], [1, 0, 0], [1, 1, 0]]);
+    }
+}
@@ -0,0 +1,257 @@
+//! Recording the packets of clients to a file.
+//!
+//! Start a recording with [`Client::start_recording`]. All packets sent to and
+//! received from the client are then written to the recording along with the
+//! time they were sent or received. Recordings can be read back with a
+//! [`RecordingReader`] and replayed to a connecting client with the replay mode
+//! of `valence_network`.
+//!
+//! # Format
+//!
+//! A recording begins with the magic bytes `VREC`, followed by the format
+//! version, the protocol version of the client and the compression threshold
+//! as `VarInt`s. The rest of the recording is a sequence of records. Each
+//! record is a byte for the direction of the record, followed by the time since
+//! the start of the recording in milliseconds as a `VarLong`.
+//!
+//! - Clientbound records (`0`) contain the framed packet data sent to the
+//!   client, prefixed with its length as a `VarInt`. Compressed packets are
+//!   stored compressed.
+//! - Serverbound records (`1`) contain the ID of a single packet as a `VarInt`
+//!   and its uncompressed body, prefixed with its length as a `VarInt`.
+//!
+//! [`Client::start_recording`]: crate::client::Client::start_recording
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, ensure};
+use valence_protocol::{CompressionThreshold, Decode, Encode, VarInt, VarLong};
+
+const MAGIC: &[u8; 4] = b"VREC";
+const FORMAT_VERSION: i32 = 1;
+
+const CLIENTBOUND: u8 = 0;
+const SERVERBOUND: u8 = 1;
+
+/// Writes the packets of a client to a recording. See the [module
+/// documentation](self).
+pub struct PacketRecorder {
+    out: Box<dyn Write + Send + Sync>,
+    start: Instant,
+}
+
+impl PacketRecorder {
+    /// Creates a recorder that writes to `out`.
+    pub fn new<W: Write + Send + Sync + 'static>(out: W) -> Self {
+        Self {
+            out: Box::new(out),
+            start: Instant::now(),
+        }
+    }
+
+    /// Creates a recorder that writes to a new file at `path`, replacing the
+    /// file if it exists.
+    pub fn create<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+
+    pub(crate) fn write_header(
+        &mut self,
+        protocol_version: i32,
+        threshold: CompressionThreshold,
+    ) -> anyhow::Result<()> {
+        self.start = Instant::now();
+
+        self.out.write_all(MAGIC)?;
+        VarInt(FORMAT_VERSION).encode(&mut self.out)?;
+        VarInt(protocol_version).encode(&mut self.out)?;
+        VarInt(threshold.0).encode(&mut self.out)?;
+
+        Ok(())
+    }
+
+    /// Records framed packet data sent to the client.
+    pub(crate) fn record_clientbound(&mut self, bytes: &[u8], at: Instant) -> anyhow::Result<()> {
+        self.write_record_start(CLIENTBOUND, at)?;
+        bytes.encode(&mut self.out)
+    }
+
+    /// Records a packet received from the client.
+    pub(crate) fn record_serverbound(
+        &mut self,
+        id: i32,
+        body: &[u8],
+        at: Instant,
+    ) -> anyhow::Result<()> {
+        self.write_record_start(SERVERBOUND, at)?;
+        VarInt(id).encode(&mut self.out)?;
+        body.encode(&mut self.out)
+    }
+
+    fn write_record_start(&mut self, kind: u8, at: Instant) -> anyhow::Result<()> {
+        let millis = at.saturating_duration_since(self.start).as_millis();
+
+        kind.encode(&mut self.out)?;
+        VarLong(millis as i64).encode(&mut self.out)
+    }
+
+    /// Writes any buffered data to the underlying writer.
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// The information at the start of a recording.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct RecordingHeader {
+    /// The protocol version of the recorded client.
+    pub protocol_version: i32,
+    /// The compression threshold the clientbound packets were written with.
+    pub threshold: CompressionThreshold,
+}
+
+/// A record in a recording.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Record<'a> {
+    /// The time since the start of the recording.
+    pub time: Duration,
+    pub packet: RecordedPacket<'a>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum RecordedPacket<'a> {
+    /// Framed packet data sent to the client. This may contain any number of
+    /// packets.
+    Clientbound(&'a [u8]),
+    /// A packet received from the client.
+    Serverbound { id: i32, body: &'a [u8] },
+}
+
+/// Reads the records of a recording from a byte slice.
+#[derive(Clone, Debug)]
+pub struct RecordingReader<'a> {
+    header: RecordingHeader,
+    data: &'a [u8],
+}
+
+impl<'a> RecordingReader<'a> {
+    /// Reads the header of the recording in `data`.
+    pub fn new(mut data: &'a [u8]) -> anyhow::Result<Self> {
+        ensure!(data.starts_with(MAGIC), "not a packet recording");
+        data = &data[MAGIC.len()..];
+
+        let version = VarInt::decode(&mut data)?.0;
+        ensure!(
+            version == FORMAT_VERSION,
+            "unsupported recording format version {version}"
+        );
+
+        let header = RecordingHeader {
+            protocol_version: VarInt::decode(&mut data)?.0,
+            threshold: CompressionThreshold(VarInt::decode(&mut data)?.0),
+        };
+
+        Ok(Self { header, data })
+    }
+
+    pub fn header(&self) -> RecordingHeader {
+        self.header
+    }
+
+    /// Reads the next record, or returns `None` at the end of the recording.
+    pub fn next_record(&mut self) -> anyhow::Result<Option<Record<'a>>> {
+        if self.data.is_empty() {
+            return Ok(None);
+        }
+
+        let kind = u8::decode(&mut self.data)?;
+        let millis = VarLong::decode(&mut self.data)?.0;
+        ensure!(millis >= 0, "negative record time");
+
+        let packet = match kind {
+            CLIENTBOUND => RecordedPacket::Clientbound(<&[u8]>::decode(&mut self.data)?),
+            SERVERBOUND => RecordedPacket::Serverbound {
+                id: VarInt::decode(&mut self.data)?.0,
+                body: <&[u8]>::decode(&mut self.data)?,
+            },
+            _ => bail!("invalid record kind {kind}"),
+        };
+
+        Ok(Some(Record {
+            time: Duration::from_millis(millis as u64),
+            packet,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    /// A writer that can be read from after the recorder is dropped.
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn recording_round_trip() {
+        let buf = SharedBuf::default();
+        let mut recorder = PacketRecorder::new(buf.clone());
+
+        recorder
+            .write_header(763, CompressionThreshold(256))
+            .unwrap();
+
+        let start = recorder.start;
+
+        recorder
+            .record_clientbound(&[1, 2, 3], start + Duration::from_millis(50))
+            .unwrap();
+        recorder
+            .record_serverbound(0x14, &[4, 5], start + Duration::from_millis(1200))
+            .unwrap();
+
+        let data = buf.0.lock().unwrap();
+        let mut reader = RecordingReader::new(&data).unwrap();
+
+        assert_eq!(
+            reader.header(),
+            RecordingHeader {
+                protocol_version: 763,
+                threshold: CompressionThreshold(256),
+            }
+        );
+        assert_eq!(
+            reader.next_record().unwrap(),
+            Some(Record {
+                time: Duration::from_millis(50),
+                packet: RecordedPacket::Clientbound(&[1, 2, 3]),
+            })
+        );
+        assert_eq!(
+            reader.next_record().unwrap(),
+            Some(Record {
+                time: Duration::from_millis(1200),
+                packet: RecordedPacket::Serverbound {
+                    id: 0x14,
+                    body: &[4, 5],
+                },
+            })
+        );
+        assert_eq!(reader.next_record().unwrap(), None);
+    }
+}
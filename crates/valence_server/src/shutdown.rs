@@ -0,0 +1,165 @@
+//! Graceful shutdown of the server.
+//!
+//! Send a [`ShutdownServer`] event to stop the server. On the tick the event is
+//! received, the [`ShuttingDown`] resource is inserted, every client is
+//! disconnected with [`ShutdownSettings::message`] and the [`Save`] schedule
+//! is run. The app exits with [`AppExit::Success`] once the remaining packets
+//! have been written to all client connections or
+//! [`ShutdownSettings::timeout`] has passed.
+
+use std::time::{Duration, Instant};
+
+use bevy_app::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_ecs::schedule::ScheduleLabel;
+use valence_protocol::packets::play::DisconnectS2c;
+use valence_protocol::text::{IntoText, Text};
+use valence_protocol::WritePacket;
+
+use crate::client::{Client, FlushPacketsSet};
+
+pub struct ShutdownPlugin;
+
+impl Plugin for ShutdownPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<SavePlugin>() {
+            app.add_plugins(SavePlugin);
+        }
+
+        app.init_resource::<ShutdownSettings>()
+            .add_event::<ShutdownServer>()
+            .add_systems(
+                PostUpdate,
+                (
+                    begin_shutdown,
+                    disconnect_clients
+                        .after(begin_shutdown)
+                        .run_if(resource_exists::<ShuttingDown>),
+                )
+                    .before(FlushPacketsSet),
+            )
+            .add_systems(
+                Last,
+                exit_when_flushed
+                    .after(run_save_schedule)
+                    .run_if(resource_exists::<ShuttingDown>),
+            );
+    }
+}
+
+/// Adds the [`Save`] schedule and runs it when [`AppExit`] is sent, or when
+/// the server is [shutting down] with [`ShutdownPlugin`].
+///
+/// Plugins that add systems to [`Save`] should add this plugin if it has not
+/// been added yet, so that their data is saved even without
+/// [`ShutdownPlugin`].
+///
+/// [shutting down]: ShuttingDown
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_schedule(Save).add_systems(
+            Last,
+            run_save_schedule.run_if(
+                resource_added::<ShuttingDown>
+                    .or_else(on_event::<AppExit>().and_then(not(resource_exists::<ShuttingDown>))),
+            ),
+        );
+    }
+}
+
+/// The schedule that saves the state of the server. Add systems here to
+/// persist data such as worlds and players. See [`SavePlugin`].
+///
+/// This schedule runs once while the server is [shutting down], while the
+/// clients are still present. It also runs when [`AppExit`] is sent without a
+/// [`ShutdownServer`] event.
+///
+/// [shutting down]: ShuttingDown
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Save;
+
+/// Send this event to shut down the server gracefully.
+#[derive(Event, Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub struct ShutdownServer;
+
+/// Resource present while the server is shutting down.
+#[derive(Resource, Copy, Clone, Debug)]
+pub struct ShuttingDown {
+    /// The moment the shutdown started.
+    pub since: Instant,
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct ShutdownSettings {
+    /// The message clients are disconnected with.
+    pub message: Text,
+    /// The maximum amount of time to wait for packets to be sent to clients
+    /// before exiting.
+    pub timeout: Duration,
+}
+
+impl Default for ShutdownSettings {
+    fn default() -> Self {
+        Self {
+            message: "Server closed".into_text(),
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+fn begin_shutdown(
+    mut events: EventReader<ShutdownServer>,
+    shutting_down: Option<Res<ShuttingDown>>,
+    mut commands: Commands,
+) {
+    if events.is_empty() {
+        return;
+    }
+
+    events.clear();
+
+    if shutting_down.is_none() {
+        commands.insert_resource(ShuttingDown {
+            since: Instant::now(),
+        });
+    }
+}
+
+/// Disconnects all clients when the shutdown begins and any clients that join
+/// afterwards.
+fn disconnect_clients(
+    mut clients: Query<&mut Client>,
+    shutting_down: Res<ShuttingDown>,
+    settings: Res<ShutdownSettings>,
+) {
+    for mut client in &mut clients {
+        if shutting_down.is_added() || client.is_added() {
+            // There's no point in sending chunks to a disconnected client.
+            client.clear_deferred();
+            client.write_packet(&DisconnectS2c {
+                reason: settings.message.clone().into(),
+            });
+        }
+    }
+}
+
+fn run_save_schedule(world: &mut World) {
+    world.run_schedule(Save);
+}
+
+fn exit_when_flushed(
+    clients: Query<&Client>,
+    shutting_down: Res<ShuttingDown>,
+    settings: Res<ShutdownSettings>,
+    mut exit_events: EventWriter<AppExit>,
+) {
+    let flushed = clients
+        .iter()
+        .all(|client| client.connection().unsent_len() == 0);
+
+    if flushed || shutting_down.since.elapsed() >= settings.timeout {
+        exit_events.send(AppExit::Success);
+    }
+}
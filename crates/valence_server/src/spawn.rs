@@ -8,7 +8,7 @@ use bevy_ecs::query::QueryData;
 use derive_more::{Deref, DerefMut};
 use valence_entity::EntityLayerId;
 use valence_protocol::packets::play::{GameJoinS2c, PlayerRespawnS2c, PlayerSpawnPositionS2c};
-use valence_protocol::{BlockPos, GameMode, GlobalPos, Ident, VarInt, WritePacket};
+use valence_protocol::{BlockPos, GameMode, GlobalPos, Ident, Packet, VarInt, WritePacket};
 use valence_registry::tags::TagsRegistry;
 use valence_registry::{BiomeRegistry, RegistryCodec};
 
@@ -109,6 +109,7 @@ pub(super) fn initial_join(
 
         // The login packet is prepended so that it's sent before all the other packets.
         // Some packets don't work correctly when sent before the game join packet.
+        let start_len = client.enc.len();
         _ = client.enc.prepend_packet(&GameJoinS2c {
             entity_id: 0, // We reserve ID 0 for clients.
             is_hardcore: spawn.is_hardcore.0,
@@ -129,6 +130,8 @@ pub(super) fn initial_join(
             last_death_location,
             portal_cooldown: VarInt(spawn.portal_cooldown.0),
         });
+        let join_len = client.enc.len() - start_len;
+        client.stats.record_sent(GameJoinS2c::ID, join_len);
 
         client.write_packet_bytes(tags.sync_tags_packet());
 
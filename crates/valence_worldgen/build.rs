@@ -0,0 +1,87 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::quote;
+use serde::Deserialize;
+use valence_build_utils::{ident, rerun_if_changed, write_generated_file};
+
+pub fn main() -> anyhow::Result<()> {
+    write_generated_file(build()?, "biome_parameters.rs")
+}
+
+fn build() -> anyhow::Result<TokenStream> {
+    rerun_if_changed(["extracted/biome_parameters.json"]);
+
+    let TopLevel { overworld, nether } =
+        serde_json::from_str(include_str!("extracted/biome_parameters.json"))?;
+
+    let overworld = parameter_list("OVERWORLD", &overworld);
+    let nether = parameter_list("NETHER", &nether);
+
+    Ok(quote! {
+        #overworld
+        #nether
+    })
+}
+
+fn parameter_list(name: &str, entries: &[Entry]) -> TokenStream {
+    let const_name = ident(name);
+    let doc = format!(
+        "The parameter points of the biomes in the vanilla {} along with the \
+         biome names.",
+        name.to_lowercase()
+    );
+
+    let entries = entries.iter().map(|entry| {
+        let range = |[min, max]: [i64; 2]| {
+            let min = Literal::i64_unsuffixed(min);
+            let max = Literal::i64_unsuffixed(max);
+            quote!(Parameter { min: #min, max: #max })
+        };
+
+        let temperature = range(entry.temperature);
+        let humidity = range(entry.humidity);
+        let continentalness = range(entry.continentalness);
+        let erosion = range(entry.erosion);
+        let depth = range(entry.depth);
+        let weirdness = range(entry.weirdness);
+        let offset = Literal::i64_unsuffixed(entry.offset);
+        let biome = &entry.biome;
+
+        quote! {
+            (
+                ParameterPoint {
+                    temperature: #temperature,
+                    humidity: #humidity,
+                    continentalness: #continentalness,
+                    erosion: #erosion,
+                    depth: #depth,
+                    weirdness: #weirdness,
+                    offset: #offset,
+                },
+                #biome,
+            )
+        }
+    });
+
+    quote! {
+        #[doc = #doc]
+        pub(super) const #const_name: &[(ParameterPoint, &str)] = &[#(#entries,)*];
+    }
+}
+
+#[derive(Deserialize)]
+struct TopLevel {
+    overworld: Vec<Entry>,
+    nether: Vec<Entry>,
+}
+
+#[derive(Deserialize)]
+struct Entry {
+    biome: String,
+    temperature: [i64; 2],
+    humidity: [i64; 2],
+    continentalness: [i64; 2],
+    erosion: [i64; 2],
+    depth: [i64; 2],
+    weirdness: [i64; 2],
+    offset: i64,
+}
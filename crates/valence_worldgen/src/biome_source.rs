@@ -0,0 +1,454 @@
+//! Biome placement using vanilla's multi-noise parameter lookup.
+//!
+//! Every biome in a [`MultiNoiseBiomeSource`] is assigned a region of the
+//! six-dimensional climate space (temperature, humidity, continentalness,
+//! erosion, depth and weirdness). The biome at a position is the biome whose
+//! region is closest to the climate at that position, which is sampled by a
+//! [`ClimateSampler`].
+
+use std::borrow::Cow;
+
+use valence_server::layer::chunk::{Chunk, UnloadedChunk};
+use valence_server::registry::biome::BiomeId;
+use valence_server::registry::BiomeRegistry;
+use valence_server::{ChunkPos, Ident};
+
+mod rtree;
+
+use rtree::RTree;
+
+mod vanilla {
+    use super::{Parameter, ParameterPoint};
+
+    include!(concat!(env!("OUT_DIR"), "/biome_parameters.rs"));
+}
+
+/// Converts a climate value to the fixed point representation used by the
+/// parameter lookup.
+pub fn quantize(value: f32) -> i64 {
+    (value * 10000.0) as i64
+}
+
+/// An inclusive range of quantized values for a single climate parameter.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Parameter {
+    pub min: i64,
+    pub max: i64,
+}
+
+impl Parameter {
+    /// The range containing only `value`.
+    pub fn point(value: f32) -> Self {
+        Self::span(value, value)
+    }
+
+    /// The range from `min` to `max`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min` is greater than `max`.
+    #[track_caller]
+    pub fn span(min: f32, max: f32) -> Self {
+        Self::new(quantize(min), quantize(max))
+    }
+
+    /// The range from the minimum of `from` to the maximum of `to`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the resulting range is empty.
+    #[track_caller]
+    pub fn between(from: Self, to: Self) -> Self {
+        Self::new(from.min, to.max)
+    }
+
+    #[track_caller]
+    fn new(min: i64, max: i64) -> Self {
+        assert!(
+            min <= max,
+            "min ({min}) must not be greater than max ({max})"
+        );
+
+        Self { min, max }
+    }
+
+    /// The distance from the range to a quantized value, or zero if the value
+    /// is inside the range.
+    pub fn distance(self, value: i64) -> i64 {
+        if value > self.max {
+            value - self.max
+        } else {
+            (self.min - value).max(0)
+        }
+    }
+}
+
+/// The region of the climate space assigned to a biome.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ParameterPoint {
+    pub temperature: Parameter,
+    pub humidity: Parameter,
+    pub continentalness: Parameter,
+    pub erosion: Parameter,
+    pub depth: Parameter,
+    pub weirdness: Parameter,
+    /// A quantized penalty added to the distance to this point. Larger offsets
+    /// make the biome rarer.
+    pub offset: i64,
+}
+
+impl ParameterPoint {
+    pub fn new(
+        temperature: Parameter,
+        humidity: Parameter,
+        continentalness: Parameter,
+        erosion: Parameter,
+        depth: Parameter,
+        weirdness: Parameter,
+        offset: f32,
+    ) -> Self {
+        Self {
+            temperature,
+            humidity,
+            continentalness,
+            erosion,
+            depth,
+            weirdness,
+            offset: quantize(offset),
+        }
+    }
+
+    /// The squared distance from this point to `target`.
+    pub fn fitness(&self, target: &TargetPoint) -> i64 {
+        self.parameter_space()
+            .iter()
+            .zip(target.to_array())
+            .map(|(param, t)| {
+                let d = param.distance(t);
+                d * d
+            })
+            .sum()
+    }
+
+    fn parameter_space(&self) -> [Parameter; 7] {
+        [
+            self.temperature,
+            self.humidity,
+            self.continentalness,
+            self.erosion,
+            self.depth,
+            self.weirdness,
+            Parameter::new(self.offset, self.offset),
+        ]
+    }
+}
+
+/// The quantized climate at a position.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Default, Debug)]
+pub struct TargetPoint {
+    pub temperature: i64,
+    pub humidity: i64,
+    pub continentalness: i64,
+    pub erosion: i64,
+    pub depth: i64,
+    pub weirdness: i64,
+}
+
+impl TargetPoint {
+    /// Creates a target point from unquantized climate values.
+    pub fn new(
+        temperature: f32,
+        humidity: f32,
+        continentalness: f32,
+        erosion: f32,
+        depth: f32,
+        weirdness: f32,
+    ) -> Self {
+        Self {
+            temperature: quantize(temperature),
+            humidity: quantize(humidity),
+            continentalness: quantize(continentalness),
+            erosion: quantize(erosion),
+            depth: quantize(depth),
+            weirdness: quantize(weirdness),
+        }
+    }
+
+    fn to_array(self) -> [i64; 7] {
+        [
+            self.temperature,
+            self.humidity,
+            self.continentalness,
+            self.erosion,
+            self.depth,
+            self.weirdness,
+            0,
+        ]
+    }
+}
+
+/// A list of values with parameter points, which finds the value closest to
+/// a target point.
+#[derive(Clone, Debug)]
+pub struct ParameterList<T> {
+    values: Vec<(ParameterPoint, T)>,
+    tree: RTree,
+}
+
+impl<T> ParameterList<T> {
+    /// # Panics
+    ///
+    /// Panics if `values` is empty.
+    pub fn new(values: Vec<(ParameterPoint, T)>) -> Self {
+        let tree = RTree::new(values.iter().map(|(point, _)| point));
+
+        Self { values, tree }
+    }
+
+    /// Returns the values in this list along with their parameter points.
+    pub fn values(&self) -> &[(ParameterPoint, T)] {
+        &self.values
+    }
+
+    /// Finds the value whose parameter point is closest to `target`.
+    ///
+    /// Ties between points are resolved in the same way as vanilla, except
+    /// that vanilla additionally prefers the result of the previous lookup
+    /// on the same thread.
+    pub fn find(&self, target: &TargetPoint) -> &T {
+        &self.values[self.tree.search(&target.to_array())].1
+    }
+}
+
+/// Samples the climate used to pick biomes.
+pub trait ClimateSampler {
+    /// Samples the climate at the given quart position. A quart is a 4x4x4
+    /// cell of blocks, which is the resolution of biomes.
+    fn sample(&self, quart_x: i32, quart_y: i32, quart_z: i32) -> TargetPoint;
+}
+
+impl<F> ClimateSampler for F
+where
+    F: Fn(i32, i32, i32) -> TargetPoint,
+{
+    fn sample(&self, quart_x: i32, quart_y: i32, quart_z: i32) -> TargetPoint {
+        self(quart_x, quart_y, quart_z)
+    }
+}
+
+/// Picks biomes by finding the closest biome in a [`ParameterList`] to the
+/// sampled climate.
+#[derive(Clone, Debug)]
+pub struct MultiNoiseBiomeSource {
+    parameters: ParameterList<BiomeId>,
+}
+
+impl MultiNoiseBiomeSource {
+    pub fn new(parameters: ParameterList<BiomeId>) -> Self {
+        Self { parameters }
+    }
+
+    /// Creates a biome source from a list of biome names. Biomes that are
+    /// missing from the registry are left out.
+    ///
+    /// # Panics
+    ///
+    /// Panics if none of the biomes are in the registry.
+    pub fn from_names<'a, I>(biomes: I, registry: &BiomeRegistry) -> Self
+    where
+        I: IntoIterator<Item = (ParameterPoint, Ident<Cow<'a, str>>)>,
+    {
+        let values = biomes
+            .into_iter()
+            .filter_map(|(point, name)| Some((point, registry.index_of(name.as_str_ident())?)))
+            .collect();
+
+        Self::new(ParameterList::new(values))
+    }
+
+    /// The biome source of the vanilla overworld.
+    pub fn overworld(registry: &BiomeRegistry) -> Self {
+        Self::from_names(overworld_parameters(), registry)
+    }
+
+    /// The biome source of the vanilla nether.
+    pub fn nether(registry: &BiomeRegistry) -> Self {
+        Self::from_names(nether_parameters(), registry)
+    }
+
+    pub fn parameters(&self) -> &ParameterList<BiomeId> {
+        &self.parameters
+    }
+
+    /// Finds the biome for the climate at `target`.
+    pub fn biome(&self, target: &TargetPoint) -> BiomeId {
+        *self.parameters.find(target)
+    }
+
+    /// Sets every biome in the chunk at `pos` from the climate sampled by
+    /// `sampler`. `min_y` is the Y coordinate of the bottom of the chunk.
+    pub fn fill_biomes<S: ClimateSampler>(
+        &self,
+        sampler: &S,
+        pos: ChunkPos,
+        min_y: i32,
+        chunk: &mut UnloadedChunk,
+    ) {
+        let min_quart_y = min_y.div_euclid(4);
+
+        for y in 0..chunk.height() / 4 {
+            for z in 0..4 {
+                for x in 0..4 {
+                    let target = sampler.sample(
+                        pos.x * 4 + x as i32,
+                        min_quart_y + y as i32,
+                        pos.z * 4 + z as i32,
+                    );
+
+                    chunk.set_biome(x, y, z, self.biome(&target));
+                }
+            }
+        }
+    }
+}
+
+/// The biome parameters of the vanilla overworld.
+pub fn overworld_parameters() -> Vec<(ParameterPoint, Ident<Cow<'static, str>>)> {
+    vanilla_parameters(vanilla::OVERWORLD)
+}
+
+/// The biome parameters of the vanilla nether.
+pub fn nether_parameters() -> Vec<(ParameterPoint, Ident<Cow<'static, str>>)> {
+    vanilla_parameters(vanilla::NETHER)
+}
+
+fn vanilla_parameters(
+    list: &'static [(ParameterPoint, &'static str)],
+) -> Vec<(ParameterPoint, Ident<Cow<'static, str>>)> {
+    list.iter()
+        .map(|&(point, name)| {
+            let name = Ident::new(name).expect("extracted biome names are valid identifiers");
+            (point, name)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nether_biome_lookup() {
+        let list = ParameterList::new(nether_parameters());
+        let find = |temperature, humidity| {
+            list.find(&TargetPoint::new(temperature, humidity, 0.0, 0.0, 0.0, 0.0))
+                .as_str()
+        };
+
+        assert_eq!(find(0.0, 0.0), "minecraft:nether_wastes");
+        assert_eq!(find(0.05, -0.6), "minecraft:soul_sand_valley");
+        assert_eq!(find(0.5, 0.1), "minecraft:crimson_forest");
+        // The offset makes warped forests less likely than nether wastes.
+        assert_eq!(find(0.0, 0.5), "minecraft:warped_forest");
+        assert_eq!(find(0.0, 0.3), "minecraft:nether_wastes");
+        assert_eq!(find(-0.6, 0.0), "minecraft:basalt_deltas");
+    }
+
+    #[test]
+    fn overworld_biome_lookup() {
+        let list = ParameterList::new(overworld_parameters());
+        let find = |t, h, c, e, d, w| list.find(&TargetPoint::new(t, h, c, e, d, w)).as_str();
+
+        assert_eq!(
+            find(0.0, 0.0, -1.1, 0.0, 0.0, 0.0),
+            "minecraft:mushroom_fields"
+        );
+        assert_eq!(
+            find(-0.8, 0.0, -0.8, 0.0, 0.0, 0.0),
+            "minecraft:deep_frozen_ocean"
+        );
+        assert_eq!(find(0.8, 0.0, -0.3, 0.0, 0.0, 0.0), "minecraft:warm_ocean");
+        assert_eq!(find(0.0, 0.0, 0.1, 0.0, 0.0, 0.0), "minecraft:river");
+        assert_eq!(find(0.8, 0.0, 0.5, 0.3, 0.0, -0.3), "minecraft:desert");
+        assert_eq!(
+            find(-0.8, 0.0, 0.5, 0.3, 0.0, -0.3),
+            "minecraft:snowy_plains"
+        );
+        assert_eq!(
+            find(-0.8, 0.0, 0.5, -0.9, 0.0, -0.6),
+            "minecraft:jagged_peaks"
+        );
+        assert_eq!(
+            find(0.0, 0.0, 0.9, 0.3, 0.5, 0.0),
+            "minecraft:dripstone_caves"
+        );
+        assert_eq!(find(0.0, 0.0, 0.5, -0.9, 1.1, 0.0), "minecraft:deep_dark");
+    }
+
+    #[test]
+    fn rtree_finds_closest_point() {
+        let points: Vec<_> = overworld_parameters()
+            .into_iter()
+            .enumerate()
+            .map(|(idx, (point, _))| (point, idx))
+            .collect();
+        let list = ParameterList::new(points);
+
+        // A simple xorshift generator keeps the test deterministic.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % 24001) as i64 - 12000
+        };
+
+        for _ in 0..2000 {
+            let target = TargetPoint {
+                temperature: next(),
+                humidity: next(),
+                continentalness: next(),
+                erosion: next(),
+                depth: next(),
+                weirdness: next(),
+            };
+
+            let best = list
+                .values()
+                .iter()
+                .map(|(point, _)| point.fitness(&target))
+                .min()
+                .unwrap();
+
+            let found = *list.find(&target);
+
+            assert_eq!(list.values()[found].0.fitness(&target), best);
+        }
+    }
+
+    #[test]
+    fn fill_chunk_biomes() {
+        let mut registry = BiomeRegistry::default();
+        for (_, name) in nether_parameters() {
+            registry.insert(name, Default::default());
+        }
+
+        let source = MultiNoiseBiomeSource::nether(&registry);
+        let crimson = registry
+            .index_of(Ident::new("crimson_forest").unwrap().as_str_ident())
+            .unwrap();
+
+        // Crimson forests in the upper half of the chunk.
+        let sampler = |_, quart_y: i32, _| {
+            let temperature = if quart_y >= 0 { 0.4 } else { 0.0 };
+            TargetPoint::new(temperature, 0.0, 0.0, 0.0, 0.0, 0.0)
+        };
+
+        let mut chunk = UnloadedChunk::with_height(64);
+        source.fill_biomes(&sampler, ChunkPos::new(3, -2), -32, &mut chunk);
+
+        assert_eq!(chunk.biome(0, 7, 0), BiomeId::default());
+        assert_eq!(chunk.biome(3, 8, 3), crimson);
+        assert_eq!(chunk.biome(1, 15, 2), crimson);
+    }
+}
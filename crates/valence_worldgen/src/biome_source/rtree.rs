@@ -0,0 +1,215 @@
+//! The R-tree vanilla uses to find the closest parameter point to a target.
+
+use super::{Parameter, ParameterPoint};
+
+/// The number of dimensions in the parameter space, including the offset.
+const DIMS: usize = 7;
+
+const CHILDREN_PER_NODE: usize = 6;
+
+#[derive(Clone, Debug)]
+pub(super) struct RTree {
+    root: Node,
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    space: [Parameter; DIMS],
+    kind: NodeKind,
+}
+
+#[derive(Clone, Debug)]
+enum NodeKind {
+    /// The index of a value in the parameter list.
+    Leaf(usize),
+    SubTree(Vec<Node>),
+}
+
+impl RTree {
+    /// Builds the tree in the same way as vanilla so that ties between points
+    /// are resolved identically.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `points` is empty.
+    pub(super) fn new<'a, I>(points: I) -> Self
+    where
+        I: IntoIterator<Item = &'a ParameterPoint>,
+    {
+        let leaves: Vec<_> = points
+            .into_iter()
+            .enumerate()
+            .map(|(idx, point)| Node {
+                space: point.parameter_space(),
+                kind: NodeKind::Leaf(idx),
+            })
+            .collect();
+
+        assert!(!leaves.is_empty(), "parameter list must not be empty");
+
+        Self {
+            root: build(leaves),
+        }
+    }
+
+    /// Returns the index of the value whose parameter point is closest to
+    /// `target`.
+    pub(super) fn search(&self, target: &[i64; DIMS]) -> usize {
+        match self.root.search(target, None) {
+            Some(Node {
+                kind: NodeKind::Leaf(idx),
+                ..
+            }) => *idx,
+            _ => unreachable!("search must find a leaf"),
+        }
+    }
+}
+
+impl Node {
+    fn new_subtree(children: Vec<Node>) -> Self {
+        let mut space = children[0].space;
+
+        for child in &children[1..] {
+            for (param, other) in space.iter_mut().zip(child.space) {
+                *param = Parameter {
+                    min: param.min.min(other.min),
+                    max: param.max.max(other.max),
+                };
+            }
+        }
+
+        Self {
+            space,
+            kind: NodeKind::SubTree(children),
+        }
+    }
+
+    fn distance(&self, target: &[i64; DIMS]) -> i64 {
+        self.space
+            .iter()
+            .zip(target)
+            .map(|(param, &t)| {
+                let d = param.distance(t);
+                d * d
+            })
+            .sum()
+    }
+
+    fn search<'a>(&'a self, target: &[i64; DIMS], best: Option<&'a Node>) -> Option<&'a Node> {
+        match &self.kind {
+            NodeKind::Leaf(_) => Some(self),
+            NodeKind::SubTree(children) => {
+                let mut best_dist = best.map_or(i64::MAX, |node| node.distance(target));
+                let mut best = best;
+
+                for child in children {
+                    let dist = child.distance(target);
+
+                    if best_dist > dist {
+                        let Some(leaf) = child.search(target, best) else {
+                            continue;
+                        };
+
+                        let leaf_dist = if std::ptr::eq(leaf, child) {
+                            dist
+                        } else {
+                            leaf.distance(target)
+                        };
+
+                        if best_dist > leaf_dist {
+                            best_dist = leaf_dist;
+                            best = Some(leaf);
+                        }
+                    }
+                }
+
+                best
+            }
+        }
+    }
+}
+
+fn build(mut children: Vec<Node>) -> Node {
+    if children.len() == 1 {
+        return children.pop().unwrap();
+    }
+
+    if children.len() <= CHILDREN_PER_NODE {
+        children.sort_by_key(|node| {
+            node.space
+                .iter()
+                .map(|param| ((param.min + param.max) / 2).abs())
+                .sum::<i64>()
+        });
+
+        return Node::new_subtree(children);
+    }
+
+    let mut best_cost = i64::MAX;
+    let mut best_dim = 0;
+    let mut best_buckets = vec![];
+
+    for dim in 0..DIMS {
+        sort(&mut children, dim, false);
+
+        let buckets = bucketize(&children);
+        let cost = buckets.iter().map(|bucket| cost(&bucket.space)).sum();
+
+        if best_cost > cost {
+            best_cost = cost;
+            best_dim = dim;
+            best_buckets = buckets;
+        }
+    }
+
+    sort(&mut best_buckets, best_dim, true);
+
+    Node::new_subtree(
+        best_buckets
+            .into_iter()
+            .map(|bucket| match bucket.kind {
+                NodeKind::SubTree(children) => build(children),
+                NodeKind::Leaf(_) => unreachable!(),
+            })
+            .collect(),
+    )
+}
+
+/// Sorts the nodes by the centers of their parameters, starting with `dim`.
+fn sort(nodes: &mut [Node], dim: usize, absolute: bool) {
+    let key = |node: &Node, dim: usize| {
+        let param = node.space[dim];
+        let center = (param.min + param.max) / 2;
+
+        if absolute {
+            center.abs()
+        } else {
+            center
+        }
+    };
+
+    nodes.sort_by(|a, b| {
+        (0..DIMS)
+            .map(|i| (dim + i) % DIMS)
+            .map(|d| key(a, d).cmp(&key(b, d)))
+            .find(|ord| ord.is_ne())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+}
+
+fn bucketize(nodes: &[Node]) -> Vec<Node> {
+    let exp = ((nodes.len() as f64 - 0.01).ln() / (CHILDREN_PER_NODE as f64).ln()).floor();
+    let bucket_size = CHILDREN_PER_NODE.pow(exp as u32);
+
+    nodes
+        .chunks(bucket_size)
+        .map(|chunk| Node::new_subtree(chunk.to_vec()))
+        .collect()
+}
+
+fn cost(space: &[Parameter; DIMS]) -> i64 {
+    space
+        .iter()
+        .map(|param| (param.max - param.min).abs())
+        .sum()
+}
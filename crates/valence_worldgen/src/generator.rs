@@ -0,0 +1,39 @@
+use valence_server::layer::chunk::UnloadedChunk;
+use valence_server::ChunkPos;
+
+use crate::region::ChunkRegion;
+
+/// A procedural generator of chunks, used by a
+/// [`GeneratedLevel`](crate::GeneratedLevel).
+///
+/// Chunks are generated in three stages. The noise and surface stages of a
+/// chunk only have access to the chunk itself, while the features stage has
+/// access to the neighbors of the chunk so that features such as trees can
+/// cross chunk borders. A chunk is only inserted into the layer after the
+/// features of the chunk and all of its neighbors have been placed.
+///
+/// The methods are called from a pool of worker threads, so they should not
+/// depend on the order in which chunks are generated.
+pub trait ChunkGenerator: Send + Sync + 'static {
+    /// Generates the base shape of the terrain in the chunk at `pos`.
+    ///
+    /// `chunk` is empty and has the height of the layer. `min_y` is the Y
+    /// coordinate of the bottom of the chunk.
+    fn generate_noise(&self, pos: ChunkPos, min_y: i32, chunk: &mut UnloadedChunk);
+
+    /// Replaces the surface of the terrain in the chunk at `pos` after
+    /// [`Self::generate_noise`], such as placing grass or sand on top of
+    /// stone.
+    ///
+    /// Does nothing by default.
+    fn build_surface(&self, _pos: ChunkPos, _min_y: i32, _chunk: &mut UnloadedChunk) {}
+
+    /// Places features such as trees and ores in the chunk at the center of
+    /// `region`. The surface of every chunk in the region has been built.
+    ///
+    /// Features may extend into the neighbors of the center chunk, but blocks
+    /// outside of the region cannot be modified.
+    ///
+    /// Does nothing by default.
+    fn place_features(&self, _region: &mut ChunkRegion) {}
+}
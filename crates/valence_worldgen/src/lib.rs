@@ -0,0 +1,443 @@
+#![doc = include_str!("../README.md")]
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::thread;
+
+use bevy_app::prelude::*;
+use bevy_ecs::prelude::*;
+use flume::{Receiver, Sender};
+use valence_server::client::{Client, OldView, View};
+use valence_server::entity::{EntityLayerId, OldEntityLayerId};
+use valence_server::layer::chunk::UnloadedChunk;
+use valence_server::layer::UpdateLayersPreClientSet;
+use valence_server::{ChunkLayer, ChunkPos};
+
+pub mod biome_source;
+mod generator;
+mod region;
+
+pub use generator::ChunkGenerator;
+pub use region::ChunkRegion;
+
+/// A task sent to the generator workers.
+enum Task {
+    /// Generate the noise and surface of the chunk at the position.
+    Terrain(ChunkPos),
+    /// Place the features of the chunk at the center of the region.
+    Features(ChunkRegion),
+}
+
+/// The outcome of a task handled by the generator workers.
+enum TaskResult {
+    Terrain(ChunkPos, UnloadedChunk),
+    Features(ChunkRegion),
+}
+
+/// The order in which chunks should be generated. Smaller values are generated
+/// first.
+type Priority = u64;
+
+/// A chunk that is still being generated.
+struct ProtoChunk {
+    /// The chunk, or `None` if a worker currently owns it.
+    chunk: Option<UnloadedChunk>,
+    /// Whether the features of the chunk have been placed.
+    decorated: bool,
+}
+
+/// Generates the chunks of the [`ChunkLayer`] on the same entity with a
+/// [`ChunkGenerator`].
+///
+/// Chunks in view of clients are generated, and chunks that are no longer in
+/// view of any client are removed. If the entity also has an `AnvilLevel`,
+/// chunks are loaded from the anvil level instead, and only the chunks that
+/// are absent from it are generated.
+#[derive(Component)]
+pub struct GeneratedLevel {
+    generator: Arc<dyn ChunkGenerator>,
+    /// The number of worker threads, or `None` if the workers have been
+    /// started.
+    thread_count: Option<NonZeroUsize>,
+    /// The set of chunk positions that should not be generated or unloaded by
+    /// the generator systems.
+    ///
+    /// This set is empty by default, but you can modify it at any time.
+    pub ignored_chunks: HashSet<ChunkPos>,
+    /// Chunks that need to be generated and inserted into the layer.
+    pending: HashMap<ChunkPos, Priority>,
+    /// Chunks that are being generated, including the neighbors of pending
+    /// chunks.
+    protos: HashMap<ChunkPos, ProtoChunk>,
+    /// Sender for the worker threads.
+    sender: Sender<Task>,
+    /// Receiver for the worker threads.
+    receiver: Receiver<TaskResult>,
+    /// Kept until the worker threads are started.
+    worker_channels: Option<(Receiver<Task>, Sender<TaskResult>)>,
+}
+
+impl GeneratedLevel {
+    /// Creates a level that generates chunks with `generator` on one worker
+    /// thread per available CPU.
+    pub fn new<G: ChunkGenerator>(generator: G) -> Self {
+        let thread_count = thread::available_parallelism().unwrap_or(NonZeroUsize::MIN);
+
+        let (task_sender, task_receiver) = flume::unbounded();
+        let (result_sender, result_receiver) = flume::unbounded();
+
+        Self {
+            generator: Arc::new(generator),
+            thread_count: Some(thread_count),
+            ignored_chunks: HashSet::new(),
+            pending: HashMap::new(),
+            protos: HashMap::new(),
+            sender: task_sender,
+            receiver: result_receiver,
+            worker_channels: Some((task_receiver, result_sender)),
+        }
+    }
+
+    /// Sets the number of worker threads used to generate chunks.
+    #[must_use]
+    pub fn with_thread_count(mut self, thread_count: NonZeroUsize) -> Self {
+        self.thread_count = Some(thread_count);
+        self
+    }
+
+    /// Returns the generator used by this level.
+    pub fn generator(&self) -> &Arc<dyn ChunkGenerator> {
+        &self.generator
+    }
+
+    /// Queues the chunk at `pos` to be generated with the highest priority.
+    /// Note that the chunk will be unloaded after it is generated unless it
+    /// has been added to [`GeneratedLevel::ignored_chunks`] or it is in view
+    /// of a client.
+    ///
+    /// This has no effect if a chunk at the position is already present.
+    pub fn force_chunk_generate(&mut self, pos: ChunkPos) {
+        self.pending.insert(pos, 0);
+    }
+
+    fn queue(&mut self, pos: ChunkPos, priority: Priority) {
+        match self.pending.entry(pos) {
+            Entry::Occupied(mut oe) => {
+                let pri = oe.get_mut();
+                *pri = (*pri).min(priority);
+            }
+            Entry::Vacant(ve) => {
+                ve.insert(priority);
+            }
+        }
+    }
+
+    /// Sends the tasks needed to generate the pending chunks and inserts the
+    /// chunks that are finished into the layer.
+    fn schedule(&mut self, layer: &mut ChunkLayer, finished: &mut Vec<ChunkPos>) {
+        let mut targets: Vec<_> = self.pending.iter().map(|(p, pri)| (*pri, *p)).collect();
+
+        // Chunks with higher priority are scheduled first.
+        targets.sort_unstable_by_key(|(pri, _)| *pri);
+
+        for (_, target) in targets {
+            if layer.chunk(target).is_some() {
+                self.pending.remove(&target);
+                continue;
+            }
+
+            let mut complete = true;
+
+            for pos in ChunkRegion::positions(target) {
+                // Chunks in the layer had their features placed before they were inserted.
+                if layer.chunk(pos).is_some()
+                    || self.protos.get(&pos).is_some_and(|proto| proto.decorated)
+                {
+                    continue;
+                }
+
+                complete = false;
+                self.queue_features(pos, layer.min_y());
+            }
+
+            if complete {
+                // Wait until the chunk is no longer used by the features of another chunk.
+                if let Some(chunk) = self
+                    .protos
+                    .get_mut(&target)
+                    .and_then(|proto| proto.chunk.take())
+                {
+                    self.protos.remove(&target);
+                    self.pending.remove(&target);
+
+                    layer.insert_chunk(target, chunk);
+                    finished.push(target);
+                }
+            }
+        }
+
+        if !finished.is_empty() || self.pending.is_empty() {
+            self.remove_unused_protos();
+        }
+    }
+
+    /// Sends a task to place the features of the chunk at `center` if its
+    /// neighbors are ready, or sends tasks to generate the terrain of the
+    /// neighbors otherwise.
+    fn queue_features(&mut self, center: ChunkPos, min_y: i32) {
+        let mut ready = true;
+
+        for pos in ChunkRegion::positions(center) {
+            match self.protos.entry(pos) {
+                Entry::Occupied(oe) => ready &= oe.get().chunk.is_some(),
+                Entry::Vacant(ve) => {
+                    ve.insert(ProtoChunk {
+                        chunk: None,
+                        decorated: false,
+                    });
+
+                    let _ = self.sender.send(Task::Terrain(pos));
+                    ready = false;
+                }
+            }
+        }
+
+        if ready {
+            let chunks = ChunkRegion::positions(center)
+                .filter_map(|pos| self.protos.get_mut(&pos)?.chunk.take())
+                .collect();
+
+            let _ = self
+                .sender
+                .send(Task::Features(ChunkRegion::new(center, min_y, chunks)));
+        }
+    }
+
+    /// Drops the chunks that are no longer needed to generate any pending
+    /// chunk.
+    fn remove_unused_protos(&mut self) {
+        let needed: HashSet<_> = self
+            .pending
+            .keys()
+            .flat_map(|pos| {
+                (-2..=2)
+                    .flat_map(move |z| (-2..=2).map(move |x| ChunkPos::new(pos.x + x, pos.z + z)))
+            })
+            .collect();
+
+        // Chunks owned by workers are kept so that their results can be returned.
+        self.protos
+            .retain(|pos, proto| proto.chunk.is_none() || needed.contains(pos));
+    }
+
+    /// Stores the results of the finished tasks.
+    fn recv_results(&mut self) {
+        for res in self.receiver.drain() {
+            match res {
+                TaskResult::Terrain(pos, chunk) => {
+                    if let Some(proto) = self.protos.get_mut(&pos) {
+                        proto.chunk = Some(chunk);
+                    }
+                }
+                TaskResult::Features(region) => {
+                    let center = region.center();
+
+                    for (pos, chunk) in region.into_chunks() {
+                        if let Some(proto) = self.protos.get_mut(&pos) {
+                            proto.chunk = Some(chunk);
+                            proto.decorated |= pos == center;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The state shared by the worker threads of a level.
+struct WorkerState {
+    generator: Arc<dyn ChunkGenerator>,
+    height: u32,
+    min_y: i32,
+    receiver: Receiver<Task>,
+    sender: Sender<TaskResult>,
+}
+
+pub struct WorldGenPlugin;
+
+impl Plugin for WorldGenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ChunkGenerateEvent>()
+            .add_systems(PreUpdate, remove_unviewed_chunks)
+            .add_systems(
+                PostUpdate,
+                (init_workers, update_client_views, generate_chunks)
+                    .chain()
+                    .before(UpdateLayersPreClientSet),
+            );
+
+        #[cfg(feature = "anvil")]
+        app.add_systems(
+            PostUpdate,
+            queue_missing_anvil_chunks
+                .before(generate_chunks)
+                .before(UpdateLayersPreClientSet),
+        );
+    }
+}
+
+/// Layers that load chunks around clients by themselves. Chunks in those
+/// layers are only generated when they are absent from the anvil level.
+#[cfg(feature = "anvil")]
+type ViewFilter = Without<valence_anvil::AnvilLevel>;
+#[cfg(not(feature = "anvil"))]
+type ViewFilter = ();
+
+fn init_workers(mut layers: Query<(&ChunkLayer, &mut GeneratedLevel), Added<GeneratedLevel>>) {
+    for (layer, mut level) in &mut layers {
+        let (Some(thread_count), Some((receiver, sender))) =
+            (level.thread_count.take(), level.worker_channels.take())
+        else {
+            continue;
+        };
+
+        let state = Arc::new(WorkerState {
+            generator: level.generator.clone(),
+            height: layer.height(),
+            min_y: layer.min_y(),
+            receiver,
+            sender,
+        });
+
+        for _ in 0..thread_count.get() {
+            let state = state.clone();
+            thread::spawn(move || generator_worker(&state));
+        }
+    }
+}
+
+/// Removes all chunks no longer viewed by clients.
+///
+/// This needs to run in `PreUpdate` where the chunk viewer counts have been
+/// updated from the previous tick.
+fn remove_unviewed_chunks(mut layers: Query<(&mut ChunkLayer, &GeneratedLevel), ViewFilter>) {
+    for (mut layer, level) in &mut layers {
+        layer.retain_chunks(|pos, chunk| {
+            chunk.viewer_count_mut() > 0 || level.ignored_chunks.contains(&pos)
+        });
+    }
+}
+
+fn update_client_views(
+    clients: Query<(&EntityLayerId, Ref<OldEntityLayerId>, View, OldView), With<Client>>,
+    mut layers: Query<(&ChunkLayer, &mut GeneratedLevel), ViewFilter>,
+) {
+    for (loc, old_loc, view, old_view) in &clients {
+        let view = view.get();
+        let old_view = old_view.get();
+
+        if loc != &*old_loc || view != old_view || old_loc.is_added() {
+            let Ok((layer, mut level)) = layers.get_mut(loc.0) else {
+                continue;
+            };
+
+            let mut queue_pos = |pos| {
+                if !level.ignored_chunks.contains(&pos) && layer.chunk(pos).is_none() {
+                    // Chunks closer to clients are prioritized.
+                    level.queue(pos, view.pos.distance_squared(pos));
+                }
+            };
+
+            // Queue all the new chunks in the view to be generated.
+            if old_loc.is_added() {
+                view.iter().for_each(&mut queue_pos);
+            } else {
+                view.diff(old_view).for_each(&mut queue_pos);
+            }
+        }
+    }
+}
+
+/// Queues the chunks that the anvil level of a layer does not have to be
+/// generated instead.
+#[cfg(feature = "anvil")]
+fn queue_missing_anvil_chunks(
+    mut events: EventReader<valence_anvil::ChunkLoadEvent>,
+    clients: Query<(&EntityLayerId, View), With<Client>>,
+    mut layers: Query<&mut GeneratedLevel>,
+) {
+    for event in events.read() {
+        // Chunks that failed to load are not generated, so that they are not
+        // overwritten when the level is saved.
+        if !matches!(event.status, valence_anvil::ChunkLoadStatus::Empty) {
+            continue;
+        }
+
+        let Ok(mut level) = layers.get_mut(event.chunk_layer) else {
+            continue;
+        };
+
+        let priority = clients
+            .iter()
+            .filter(|(loc, _)| loc.0 == event.chunk_layer)
+            .map(|(_, view)| view.get().pos.distance_squared(event.pos))
+            .min()
+            .unwrap_or(0);
+
+        level.queue(event.pos, priority);
+    }
+}
+
+fn generate_chunks(
+    mut layers: Query<(Entity, &mut ChunkLayer, &mut GeneratedLevel)>,
+    mut events: EventWriter<ChunkGenerateEvent>,
+    mut finished: Local<Vec<ChunkPos>>,
+) {
+    for (entity, mut layer, mut level) in &mut layers {
+        level.recv_results();
+        level.schedule(&mut layer, &mut finished);
+
+        for pos in finished.drain(..) {
+            events.send(ChunkGenerateEvent {
+                chunk_layer: entity,
+                pos,
+            });
+        }
+    }
+}
+
+fn generator_worker(state: &WorkerState) {
+    while let Ok(task) = state.receiver.recv() {
+        let res = match task {
+            Task::Terrain(pos) => {
+                let mut chunk = UnloadedChunk::with_height(state.height);
+
+                state.generator.generate_noise(pos, state.min_y, &mut chunk);
+                state.generator.build_surface(pos, state.min_y, &mut chunk);
+
+                TaskResult::Terrain(pos, chunk)
+            }
+            Task::Features(mut region) => {
+                state.generator.place_features(&mut region);
+
+                TaskResult::Features(region)
+            }
+        };
+
+        if state.sender.send(res).is_err() {
+            break;
+        }
+    }
+}
+
+/// An event sent by `valence_worldgen` after a chunk is generated and inserted
+/// into a layer.
+#[derive(Event, Debug)]
+pub struct ChunkGenerateEvent {
+    /// The [`ChunkLayer`] where the chunk was inserted.
+    pub chunk_layer: Entity,
+    /// The position of the chunk in the layer.
+    pub pos: ChunkPos,
+}
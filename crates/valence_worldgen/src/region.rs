@@ -0,0 +1,156 @@
+use valence_server::layer::chunk::{Block, BlockRef, Chunk, IntoBlock, UnloadedChunk};
+use valence_server::{BlockPos, ChunkPos};
+
+/// A 3x3 area of chunks being generated, passed to
+/// [`ChunkGenerator::place_features`](crate::ChunkGenerator::place_features).
+///
+/// Blocks are accessed with world coordinates. Accessing blocks outside of
+/// the region returns `None`.
+#[derive(Debug)]
+pub struct ChunkRegion {
+    center: ChunkPos,
+    min_y: i32,
+    height: u32,
+    /// The chunks in the region, ordered by ascending Z and then X.
+    chunks: Vec<UnloadedChunk>,
+}
+
+impl ChunkRegion {
+    /// Creates a region from the chunks around `center`, ordered by
+    /// ascending Z and then X.
+    pub(crate) fn new(center: ChunkPos, min_y: i32, chunks: Vec<UnloadedChunk>) -> Self {
+        debug_assert_eq!(chunks.len(), 9);
+
+        Self {
+            center,
+            min_y,
+            height: chunks[4].height(),
+            chunks,
+        }
+    }
+
+    /// Returns the positions of the chunks in a region around `center`, in
+    /// the same order as the chunks of the region.
+    pub(crate) fn positions(center: ChunkPos) -> impl Iterator<Item = ChunkPos> {
+        (-1..=1).flat_map(move |z| (-1..=1).map(move |x| ChunkPos::new(center.x + x, center.z + z)))
+    }
+
+    /// Consumes the region and returns its chunks along with their positions.
+    pub(crate) fn into_chunks(self) -> impl Iterator<Item = (ChunkPos, UnloadedChunk)> {
+        Self::positions(self.center).zip(self.chunks)
+    }
+
+    /// The position of the chunk whose features are being placed.
+    pub fn center(&self) -> ChunkPos {
+        self.center
+    }
+
+    /// The Y coordinate of the bottom of the chunks in the region.
+    pub fn min_y(&self) -> i32 {
+        self.min_y
+    }
+
+    /// The height of the chunks in the region in meters.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn index(&self, pos: ChunkPos) -> Option<usize> {
+        let x = pos.x.checked_sub(self.center.x)?.checked_add(1)?;
+        let z = pos.z.checked_sub(self.center.z)?.checked_add(1)?;
+
+        ((0..3).contains(&x) && (0..3).contains(&z)).then_some((x + z * 3) as usize)
+    }
+
+    /// Gets the chunk at `pos`, or `None` if it is outside of the region.
+    pub fn chunk(&self, pos: ChunkPos) -> Option<&UnloadedChunk> {
+        let idx = self.index(pos)?;
+        Some(&self.chunks[idx])
+    }
+
+    /// Gets the chunk at `pos` mutably, or `None` if it is outside of the
+    /// region.
+    pub fn chunk_mut(&mut self, pos: ChunkPos) -> Option<&mut UnloadedChunk> {
+        let idx = self.index(pos)?;
+        Some(&mut self.chunks[idx])
+    }
+
+    /// Converts a block position into the position of its chunk and the
+    /// offsets within the chunk.
+    fn locate(&self, pos: BlockPos) -> Option<(ChunkPos, [u32; 3])> {
+        let y = pos
+            .y
+            .checked_sub(self.min_y)
+            .and_then(|y| u32::try_from(y).ok())?;
+
+        if y >= self.height {
+            return None;
+        }
+
+        let x = pos.x.rem_euclid(16) as u32;
+        let z = pos.z.rem_euclid(16) as u32;
+
+        Some((ChunkPos::from(pos), [x, y, z]))
+    }
+
+    /// Gets the block at `pos`, or `None` if it is outside of the region.
+    pub fn block<P: Into<BlockPos>>(&self, pos: P) -> Option<BlockRef<'_>> {
+        let (chunk_pos, [x, y, z]) = self.locate(pos.into())?;
+
+        Some(self.chunk(chunk_pos)?.block(x, y, z))
+    }
+
+    /// Sets the block at `pos` and returns the previous block, or `None` if
+    /// the position is outside of the region.
+    pub fn set_block<P, B>(&mut self, pos: P, block: B) -> Option<Block>
+    where
+        P: Into<BlockPos>,
+        B: IntoBlock,
+    {
+        let (chunk_pos, [x, y, z]) = self.locate(pos.into())?;
+
+        Some(self.chunk_mut(chunk_pos)?.set_block(x, y, z, block))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use valence_server::BlockState;
+
+    use super::*;
+
+    #[test]
+    fn region_block_access() {
+        let center = ChunkPos::new(-1, 2);
+        let chunks = vec![UnloadedChunk::with_height(32); 9];
+        let mut region = ChunkRegion::new(center, -16, chunks);
+
+        // Inside the center chunk.
+        assert!(region
+            .set_block([-16, -16, 32], BlockState::STONE)
+            .is_some());
+        // Inside the north-west neighbor.
+        assert!(region.set_block([-17, 15, 31], BlockState::DIRT).is_some());
+        // Outside of the region horizontally and vertically.
+        assert!(region.set_block([-33, 0, 32], BlockState::DIRT).is_none());
+        assert!(region.set_block([-16, 16, 32], BlockState::DIRT).is_none());
+        assert!(region.set_block([-16, -17, 32], BlockState::DIRT).is_none());
+
+        assert_eq!(
+            region.chunk(center).unwrap().block_state(0, 0, 0),
+            BlockState::STONE
+        );
+        assert_eq!(
+            region
+                .chunk(ChunkPos::new(-2, 1))
+                .unwrap()
+                .block_state(15, 31, 15),
+            BlockState::DIRT
+        );
+
+        let positions: Vec<_> = region.into_chunks().map(|(pos, _)| pos).collect();
+        assert_eq!(positions[0], ChunkPos::new(-2, 1));
+        assert_eq!(positions[4], center);
+        assert_eq!(positions[8], ChunkPos::new(0, 3));
+    }
+}
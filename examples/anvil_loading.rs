@@ -6,6 +6,7 @@ use clap::Parser;
 use valence::abilities::{FlyingSpeed, FovModifier, PlayerAbilitiesFlags};
 use valence::message::SendMessage;
 use valence::prelude::*;
+use valence_anvil::playerdata::{PlayerData, PlayerDataFolder};
 use valence_anvil::{AnvilLevel, ChunkLoadEvent, ChunkLoadStatus};
 
 const SPAWN_POS: DVec3 = DVec3::new(0.0, 256.0, 0.0);
@@ -55,7 +56,10 @@ fn setup(
     server: Res<Server>,
     cli: Res<Cli>,
 ) {
-    let layer = LayerBundle::new(ident!("overworld"), &dimensions, &biomes, &server);
+    let mut layer = LayerBundle::new(ident!("overworld"), &dimensions, &biomes, &server);
+    // Send the light stored in the world to clients and keep it up to date.
+    layer.chunk.set_light_engine_enabled(true);
+
     let mut level = AnvilLevel::new(&cli.path, &biomes);
 
     // Force a 16x16 area of chunks around the origin to be loaded at all times.
@@ -70,7 +74,20 @@ fn setup(
         }
     }
 
-    commands.spawn((layer, level));
+    // Use the time, weather and world border stored in the world.
+    let level_data = level.read_level_data().unwrap_or_else(|e| {
+        eprintln!("Failed to read `level.dat`: {e}");
+        None
+    });
+
+    // Load and save players in the `playerdata` folder of the world.
+    commands.insert_resource(PlayerDataFolder::new(&cli.path));
+
+    let mut layer = commands.spawn((layer, level));
+
+    if let Some(level_data) = level_data {
+        level_data.apply(&mut layer);
+    }
 }
 
 fn init_clients(
@@ -84,6 +101,7 @@ fn init_clients(
             &mut PlayerAbilitiesFlags,
             &mut FlyingSpeed,
             &mut FovModifier,
+            Option<&PlayerData>,
         ),
         Added<Client>,
     >,
@@ -98,6 +116,7 @@ fn init_clients(
         mut abilities,
         mut flying_speed,
         mut fov_modifier,
+        player_data,
     ) in &mut clients
     {
         let layer = layers.single();
@@ -105,8 +124,14 @@ fn init_clients(
         layer_id.0 = layer;
         visible_chunk_layer.0 = layer;
         visible_entity_layers.0.insert(layer);
-        pos.set(SPAWN_POS);
-        *game_mode = GameMode::Adventure;
+
+        // Players that have been in the world before keep their saved position and
+        // game mode.
+        if player_data.is_none_or(PlayerData::is_new) {
+            pos.set(SPAWN_POS);
+            *game_mode = GameMode::Adventure;
+        }
+
         abilities.set_allow_flying(true);
         flying_speed.0 = 0.1;
         fov_modifier.0 = 0.05;
@@ -0,0 +1,147 @@
+#![allow(clippy::type_complexity)]
+
+//! Runs a proxy on port 25565 which authenticates players and forwards them
+//! with Velocity's modern forwarding to a backend.
+//!
+//! By default, the backend is a Valence server started by this example on port
+//! 25566. Pass `--backend <address>` to forward to another server instead, such
+//! as a Paper server with modern forwarding enabled and the same secret.
+
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+
+use clap::Parser;
+use valence::network::hub::HubSettings;
+use valence::network::{proxy, ConnectionMode};
+use valence::prelude::*;
+use valence::CompressionThreshold;
+
+const SPAWN_Y: i32 = 64;
+const BACKEND_PORT: u16 = 25566;
+
+#[derive(Parser)]
+#[clap(author, version, about)]
+struct Cli {
+    /// The address of the backend server. A backend is started on port 25566 if
+    /// this is not given.
+    #[arg(long)]
+    backend: Option<SocketAddr>,
+    /// The forwarding secret shared with the backend.
+    #[arg(long, default_value = "valence")]
+    secret: String,
+    /// Disables authentication of players.
+    #[arg(long)]
+    offline: bool,
+}
+
+pub fn main() {
+    let cli = Cli::parse();
+
+    let backend = cli
+        .backend
+        .unwrap_or_else(|| SocketAddrV4::new(Ipv4Addr::LOCALHOST, BACKEND_PORT).into());
+
+    let settings = NetworkSettings {
+        connection_mode: if cli.offline {
+            ConnectionMode::Offline
+        } else {
+            ConnectionMode::Online {
+                prevent_proxy_connections: false,
+            }
+        },
+        hub: Some(HubSettings {
+            backends: HashMap::from([("backend".into(), backend)]),
+            default_backend: "backend".into(),
+            forwarding_secret: cli.secret.clone(),
+        }),
+        ..Default::default()
+    };
+
+    // The proxy runs on its own runtime until it is dropped.
+    let _proxy = match proxy::start(&settings, CompressionThreshold(256)) {
+        Ok(proxy) => proxy,
+        Err(e) => {
+            eprintln!("failed to start proxy: {e:#}");
+            return;
+        }
+    };
+
+    if cli.backend.is_some() {
+        park_forever();
+    }
+
+    App::new()
+        .insert_resource(NetworkSettings {
+            address: backend,
+            connection_mode: ConnectionMode::Velocity {
+                secret: cli.secret.into(),
+            },
+            ..Default::default()
+        })
+        .add_plugins(DefaultPlugins)
+        .add_systems(Startup, setup)
+        .add_systems(Update, (init_clients, despawn_disconnected_clients))
+        .run();
+}
+
+fn park_forever() -> ! {
+    loop {
+        std::thread::park();
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    server: Res<Server>,
+    dimensions: Res<DimensionTypeRegistry>,
+    biomes: Res<BiomeRegistry>,
+) {
+    let mut layer = LayerBundle::new(ident!("overworld"), &dimensions, &biomes, &server);
+
+    for z in -5..5 {
+        for x in -5..5 {
+            layer.chunk.insert_chunk([x, z], UnloadedChunk::new());
+        }
+    }
+
+    for z in -25..25 {
+        for x in -25..25 {
+            layer
+                .chunk
+                .set_block([x, SPAWN_Y, z], BlockState::GRASS_BLOCK);
+        }
+    }
+
+    commands.spawn(layer);
+}
+
+fn init_clients(
+    mut clients: Query<
+        (
+            &mut EntityLayerId,
+            &mut VisibleChunkLayer,
+            &mut VisibleEntityLayers,
+            &mut Position,
+            &mut GameMode,
+        ),
+        Added<Client>,
+    >,
+    layers: Query<Entity, (With<ChunkLayer>, With<EntityLayer>)>,
+) {
+    for (
+        mut layer_id,
+        mut visible_chunk_layer,
+        mut visible_entity_layers,
+        mut pos,
+        mut game_mode,
+    ) in &mut clients
+    {
+        let layer = layers.single();
+
+        layer_id.0 = layer;
+        visible_chunk_layer.0 = layer;
+        visible_entity_layers.0.insert(layer);
+        pos.set([0.0, f64::from(SPAWN_Y) + 1.0, 0.0]);
+        *game_mode = GameMode::Creative;
+    }
+}
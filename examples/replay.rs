@@ -0,0 +1,136 @@
+#![allow(clippy::type_complexity)]
+
+//! Records the sessions of players and replays them.
+//!
+//! Without arguments, this runs a server where the session of every player is
+//! recorded to `<username>.vrec` in the current directory. Pass `--play <file>`
+//! to run a replay server instead, which sends the recorded session to every
+//! player that joins.
+
+use std::path::PathBuf;
+
+use clap::Parser;
+use valence::network::{replay, ConnectionMode};
+use valence::prelude::*;
+use valence::recorder::PacketRecorder;
+
+const SPAWN_Y: i32 = 64;
+
+#[derive(Parser)]
+#[clap(author, version, about)]
+struct Cli {
+    /// The recording to replay.
+    #[arg(long)]
+    play: Option<PathBuf>,
+}
+
+pub fn main() {
+    let cli = Cli::parse();
+
+    if let Some(path) = cli.play {
+        let settings = NetworkSettings {
+            connection_mode: ConnectionMode::Offline,
+            replay: Some(path),
+            ..Default::default()
+        };
+
+        // The replay server runs on its own runtime until it is dropped.
+        let _replay = match replay::start(&settings) {
+            Ok(replay) => replay,
+            Err(e) => {
+                eprintln!("failed to start replay server: {e:#}");
+                return;
+            }
+        };
+
+        park_forever();
+    }
+
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            (init_clients, start_recording, despawn_disconnected_clients),
+        )
+        .run();
+}
+
+fn park_forever() -> ! {
+    loop {
+        std::thread::park();
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    server: Res<Server>,
+    dimensions: Res<DimensionTypeRegistry>,
+    biomes: Res<BiomeRegistry>,
+) {
+    let mut layer = LayerBundle::new(ident!("overworld"), &dimensions, &biomes, &server);
+
+    for z in -5..5 {
+        for x in -5..5 {
+            layer.chunk.insert_chunk([x, z], UnloadedChunk::new());
+        }
+    }
+
+    for z in -25..25 {
+        for x in -25..25 {
+            layer
+                .chunk
+                .set_block([x, SPAWN_Y, z], BlockState::GRASS_BLOCK);
+        }
+    }
+
+    commands.spawn(layer);
+}
+
+fn init_clients(
+    mut clients: Query<
+        (
+            &mut EntityLayerId,
+            &mut VisibleChunkLayer,
+            &mut VisibleEntityLayers,
+            &mut Position,
+            &mut GameMode,
+        ),
+        Added<Client>,
+    >,
+    layers: Query<Entity, (With<ChunkLayer>, With<EntityLayer>)>,
+) {
+    for (
+        mut layer_id,
+        mut visible_chunk_layer,
+        mut visible_entity_layers,
+        mut pos,
+        mut game_mode,
+    ) in &mut clients
+    {
+        let layer = layers.single();
+
+        layer_id.0 = layer;
+        visible_chunk_layer.0 = layer;
+        visible_entity_layers.0.insert(layer);
+        pos.set([0.0, f64::from(SPAWN_Y) + 1.0, 0.0]);
+        *game_mode = GameMode::Creative;
+    }
+}
+
+fn start_recording(mut clients: Query<(&mut Client, &Username), Added<Client>>) {
+    for (mut client, username) in &mut clients {
+        let path = format!("{username}.vrec");
+
+        let result = PacketRecorder::create(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|recorder| client.start_recording(recorder));
+
+        match result {
+            Ok(()) => {
+                client.send_chat_message(format!("Recording your session to {path}"));
+            }
+            Err(e) => eprintln!("failed to record {username}: {e:#}"),
+        }
+    }
+}
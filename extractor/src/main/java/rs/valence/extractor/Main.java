@@ -46,6 +46,7 @@ public class Main implements ModInitializer {
                new Effects(),
                new Misc(),
                new Packets(),
+               new Particles(),
                new Sounds(),
                new TranslationKeys(),
         };
@@ -94,6 +95,7 @@ public class Main implements ModInitializer {
                 new Enchants(server),
                 new Entities(server),
                 new Items(server),
+                new Biomes(server),
             };
 
             for (var ext : startupExtractors) {
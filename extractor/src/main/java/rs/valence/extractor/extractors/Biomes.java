@@ -0,0 +1,69 @@
+package rs.valence.extractor.extractors;
+
+import com.google.gson.JsonArray;
+import com.google.gson.JsonElement;
+import com.google.gson.JsonObject;
+import net.minecraft.registry.DynamicRegistryManager;
+import net.minecraft.registry.RegistryKey;
+import net.minecraft.registry.RegistryKeys;
+import net.minecraft.server.MinecraftServer;
+import net.minecraft.world.biome.source.MultiNoiseBiomeSourceParameterList;
+import net.minecraft.world.biome.source.MultiNoiseBiomeSourceParameterLists;
+import net.minecraft.world.biome.source.util.MultiNoiseUtil;
+import rs.valence.extractor.Main;
+
+public class Biomes implements Main.Extractor {
+
+    private final DynamicRegistryManager.Immutable registryManager;
+
+    public Biomes(MinecraftServer server) {
+        this.registryManager = server.getRegistryManager();
+    }
+
+    private static JsonArray range(MultiNoiseUtil.ParameterRange range) {
+        var json = new JsonArray();
+        json.add(range.min());
+        json.add(range.max());
+        return json;
+    }
+
+    @Override
+    public String fileName() {
+        return "biome_parameters.json";
+    }
+
+    @Override
+    public JsonElement extract() {
+        var json = new JsonObject();
+
+        json.add("overworld", this.extractList(MultiNoiseBiomeSourceParameterLists.OVERWORLD));
+        json.add("nether", this.extractList(MultiNoiseBiomeSourceParameterLists.NETHER));
+
+        return json;
+    }
+
+    private JsonArray extractList(RegistryKey<MultiNoiseBiomeSourceParameterList> key) {
+        var list = this.registryManager.get(RegistryKeys.MULTI_NOISE_BIOME_SOURCE_PARAMETER_LIST).getOrThrow(key);
+
+        var json = new JsonArray();
+
+        // The parameter ranges are kept quantized, so they are the same as the ones vanilla uses for lookups.
+        for (var entry : list.getEntries().getEntries()) {
+            var point = entry.getFirst();
+            var entryJson = new JsonObject();
+
+            entryJson.addProperty("biome", entry.getSecond().getKey().orElseThrow().getValue().toString());
+            entryJson.add("temperature", range(point.temperature()));
+            entryJson.add("humidity", range(point.humidity()));
+            entryJson.add("continentalness", range(point.continentalness()));
+            entryJson.add("erosion", range(point.erosion()));
+            entryJson.add("depth", range(point.depth()));
+            entryJson.add("weirdness", range(point.weirdness()));
+            entryJson.addProperty("offset", point.offset());
+
+            json.add(entryJson);
+        }
+
+        return json;
+    }
+}
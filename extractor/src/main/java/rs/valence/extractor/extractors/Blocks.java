@@ -5,6 +5,7 @@ import com.google.gson.JsonElement;
 import com.google.gson.JsonObject;
 import net.minecraft.registry.Registries;
 import net.minecraft.item.VerticallyAttachableBlockItem;
+import net.minecraft.registry.tag.BlockTags;
 import net.minecraft.util.math.BlockPos;
 import net.minecraft.world.EmptyBlockView;
 
@@ -39,6 +40,31 @@ public class Blocks implements Main.Extractor {
             blockJson.addProperty("name", Registries.BLOCK.getId(block).getPath());
             blockJson.addProperty("translation_key", block.getTranslationKey());
             blockJson.addProperty("item_id", Registries.ITEM.getRawId(block.asItem()));
+            blockJson.addProperty("hardness", block.getHardness());
+            blockJson.addProperty("requires_tool", block.getDefaultState().isToolRequired());
+
+            var entry = block.getRegistryEntry();
+
+            if (entry.isIn(BlockTags.PICKAXE_MINEABLE)) {
+                blockJson.addProperty("tool", "pickaxe");
+            } else if (entry.isIn(BlockTags.AXE_MINEABLE)) {
+                blockJson.addProperty("tool", "axe");
+            } else if (entry.isIn(BlockTags.SHOVEL_MINEABLE)) {
+                blockJson.addProperty("tool", "shovel");
+            } else if (entry.isIn(BlockTags.HOE_MINEABLE)) {
+                blockJson.addProperty("tool", "hoe");
+            }
+
+            var toolTier = 0;
+            if (entry.isIn(BlockTags.NEEDS_DIAMOND_TOOL)) {
+                toolTier = 3;
+            } else if (entry.isIn(BlockTags.NEEDS_IRON_TOOL)) {
+                toolTier = 2;
+            } else if (entry.isIn(BlockTags.NEEDS_STONE_TOOL)) {
+                toolTier = 1;
+            }
+            blockJson.addProperty("tool_tier", toolTier);
+            blockJson.addProperty("sword_efficient", entry.isIn(BlockTags.SWORD_EFFICIENT));
 
             if (block.asItem() instanceof VerticallyAttachableBlockItem wsbItem) {
                 if (wsbItem.getBlock() == block) {
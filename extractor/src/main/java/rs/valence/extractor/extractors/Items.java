@@ -8,8 +8,16 @@ import net.minecraft.component.ComponentMap;
 import net.minecraft.component.DataComponentTypes;
 import net.minecraft.component.type.FoodComponent;
 import net.minecraft.enchantment.Enchantment;
+import net.minecraft.item.AxeItem;
+import net.minecraft.item.HoeItem;
 import net.minecraft.item.Item;
 import net.minecraft.item.ItemStack;
+import net.minecraft.item.PickaxeItem;
+import net.minecraft.item.ShearsItem;
+import net.minecraft.item.ShovelItem;
+import net.minecraft.item.SwordItem;
+import net.minecraft.item.ToolItem;
+import net.minecraft.item.ToolMaterials;
 import net.minecraft.registry.DynamicRegistryManager;
 import net.minecraft.registry.Registries;
 import net.minecraft.registry.RegistryKeys;
@@ -45,10 +53,53 @@ public class Items implements Main.Extractor {
             itemJson.addProperty("enchantability", realItem.getEnchantability());
             itemJson.addProperty("fireproof", realItem.getComponents().contains(DataComponentTypes.FIRE_RESISTANT));
 
+            if (realItem instanceof ToolItem toolItem) {
+                var toolJson = new JsonObject();
+                toolJson.addProperty("kind", toolKind(toolItem));
+                toolJson.addProperty("tier", toolTier(toolItem));
+                toolJson.addProperty("speed", toolItem.getMaterial().getMiningSpeedMultiplier());
+                itemJson.add("tool", toolJson);
+            } else if (realItem instanceof ShearsItem) {
+                var toolJson = new JsonObject();
+                toolJson.addProperty("kind", "shears");
+                toolJson.addProperty("tier", 0);
+                toolJson.addProperty("speed", 1.0f);
+                itemJson.add("tool", toolJson);
+            }
+
             itemJson.add("components", ComponentMap.CODEC.encodeStart(RegistryOps.of(JsonOps.INSTANCE, registryManager), realItem.getComponents()).getOrThrow());
 
             itemsJson.add(itemJson);
         }
         return itemsJson;
     }
+
+    private static String toolKind(ToolItem item) {
+        if (item instanceof PickaxeItem) {
+            return "pickaxe";
+        } else if (item instanceof AxeItem) {
+            return "axe";
+        } else if (item instanceof ShovelItem) {
+            return "shovel";
+        } else if (item instanceof HoeItem) {
+            return "hoe";
+        } else if (item instanceof SwordItem) {
+            return "sword";
+        }
+        throw new IllegalArgumentException("unknown tool item " + item);
+    }
+
+    // The mining level of the material, from before tool tiers were replaced by tags.
+    private static int toolTier(ToolItem item) {
+        if (item.getMaterial() instanceof ToolMaterials material) {
+            return switch (material) {
+                case WOOD, GOLD -> 0;
+                case STONE -> 1;
+                case IRON -> 2;
+                case DIAMOND -> 3;
+                case NETHERITE -> 4;
+            };
+        }
+        return 0;
+    }
 }
@@ -0,0 +1,31 @@
+package rs.valence.extractor.extractors;
+
+import com.google.gson.JsonArray;
+import com.google.gson.JsonElement;
+import com.google.gson.JsonObject;
+import net.minecraft.registry.Registries;
+import rs.valence.extractor.Main;
+
+public class Particles implements Main.Extractor {
+    public Particles() {
+    }
+
+    @Override
+    public String fileName() {
+        return "particles.json";
+    }
+
+    @Override
+    public JsonElement extract() throws Exception {
+        var particlesJson = new JsonArray();
+
+        for (var particle : Registries.PARTICLE_TYPE) {
+            var particleJson = new JsonObject();
+            particleJson.addProperty("id", Registries.PARTICLE_TYPE.getRawId(particle));
+            particleJson.addProperty("name", Registries.PARTICLE_TYPE.getId(particle).getPath());
+            particlesJson.add(particleJson);
+        }
+
+        return particlesJson;
+    }
+}
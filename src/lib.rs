@@ -68,6 +68,7 @@ use valence_server::custom_payload::CustomPayloadPlugin;
 use valence_server::entity::hitbox::HitboxPlugin;
 use valence_server::entity::EntityPlugin;
 use valence_server::event_loop::EventLoopPlugin;
+use valence_server::experience::ExperiencePlugin;
 use valence_server::hand_swing::HandSwingPlugin;
 use valence_server::interact_block::InteractBlockPlugin;
 use valence_server::interact_entity::InteractEntityPlugin;
@@ -79,6 +80,7 @@ use valence_server::movement::MovementPlugin;
 use valence_server::op_level::OpLevelPlugin;
 pub use valence_server::protocol::status_effects;
 use valence_server::resource_pack::ResourcePackPlugin;
+use valence_server::shutdown::ShutdownPlugin;
 use valence_server::status::StatusPlugin;
 use valence_server::status_effect::StatusEffectPlugin;
 use valence_server::teleport::TeleportPlugin;
@@ -87,6 +89,8 @@ pub use valence_server::*;
 pub use valence_weather as weather;
 #[cfg(feature = "world_border")]
 pub use valence_world_border as world_border;
+#[cfg(feature = "worldgen")]
+pub use valence_worldgen as worldgen;
 
 /// Contains the most frequently used items in Valence projects.
 ///
@@ -198,10 +202,12 @@ impl PluginGroup for DefaultPlugins {
             .add(InteractBlockPlugin)
             .add(InteractItemPlugin)
             .add(OpLevelPlugin)
+            .add(ExperiencePlugin)
             .add(ResourcePackPlugin)
             .add(StatusPlugin)
             .add(StatusEffectPlugin)
-            .add(AbilitiesPlugin);
+            .add(AbilitiesPlugin)
+            .add(ShutdownPlugin);
 
         #[cfg(feature = "log")]
         {
@@ -233,6 +239,16 @@ impl PluginGroup for DefaultPlugins {
             group = group.add(valence_anvil::AnvilPlugin)
         }
 
+        #[cfg(all(feature = "anvil", feature = "inventory"))]
+        {
+            group = group.add(valence_anvil::playerdata::PlayerDataPlugin)
+        }
+
+        #[cfg(feature = "worldgen")]
+        {
+            group = group.add(valence_worldgen::WorldGenPlugin)
+        }
+
         #[cfg(feature = "advancement")]
         {
             group = group.add(valence_advancement::AdvancementPlugin)
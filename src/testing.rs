@@ -12,10 +12,11 @@ use valence_network::NetworkPlugin;
 use valence_registry::{BiomeRegistry, DimensionTypeRegistry};
 use valence_server::client::{ClientBundle, ClientBundleArgs, ClientConnection, ReceivedPacket};
 use valence_server::keepalive::KeepaliveSettings;
+use valence_server::layer::chunk::UnloadedChunk;
 use valence_server::protocol::decode::PacketFrame;
 use valence_server::protocol::packets::play::{PlayerPositionLookS2c, TeleportConfirmC2s};
 use valence_server::protocol::{Decode, Encode, Packet, PacketDecoder, PacketEncoder, VarInt};
-use valence_server::{ChunkLayer, EntityLayer, Server, ServerSettings};
+use valence_server::{ChunkLayer, ChunkPos, EntityLayer, Server, ServerSettings};
 
 use crate::DefaultPlugins;
 pub struct ScenarioSingleClient {
@@ -70,6 +71,27 @@ impl ScenarioSingleClient {
             layer,
         }
     }
+
+    /// Like [`ScenarioSingleClient::new`], but the layer is also filled with
+    /// empty chunks in a square of `2 * radius` by `2 * radius` chunks around
+    /// the origin.
+    pub fn with_chunks(radius: i32) -> Self {
+        let mut scenario = Self::new();
+
+        let mut layer = scenario
+            .app
+            .world_mut()
+            .get_mut::<ChunkLayer>(scenario.layer)
+            .unwrap();
+
+        for z in -radius..radius {
+            for x in -radius..radius {
+                layer.insert_chunk(ChunkPos::new(x, z), UnloadedChunk::new());
+            }
+        }
+
+        scenario
+    }
 }
 
 impl Default for ScenarioSingleClient {
@@ -1,12 +1,18 @@
+mod block_breaking;
 mod boss_bar;
 mod client;
 mod equipment;
 mod example;
 mod hunger;
 mod inventory;
+mod item_entity;
 mod layer;
+mod movement;
+mod physics;
 mod player_list;
 mod potions;
+mod raycast;
 mod scoreboard;
 mod weather;
 mod world_border;
+mod worldgen;
@@ -0,0 +1,190 @@
+use bevy_ecs::prelude::*;
+
+use crate::entity::OnGround;
+use crate::inventory::block_breaking::{
+    BlockBreakingPlugin, BreakBlockEvent, BreakBlockRejectedEvent,
+};
+use crate::inventory::Inventory;
+use crate::protocol::packets::play::player_action_c2s::PlayerAction;
+use crate::protocol::packets::play::{BlockBreakingProgressS2c, BlockUpdateS2c, PlayerActionC2s};
+use crate::protocol::VarInt;
+use crate::testing::{create_mock_client, ScenarioSingleClient};
+use crate::{BlockPos, BlockState, ChunkLayer, Direction, GameMode, ItemKind, ItemStack};
+
+const STONE_POS: BlockPos = BlockPos::new(1, 0, 0);
+
+fn setup() -> ScenarioSingleClient {
+    let mut scenario = ScenarioSingleClient::with_chunks(2);
+
+    scenario.app.add_plugins(BlockBreakingPlugin);
+
+    scenario
+        .app
+        .world_mut()
+        .get_mut::<ChunkLayer>(scenario.layer)
+        .unwrap()
+        .set_block(STONE_POS, BlockState::STONE);
+
+    scenario
+        .app
+        .world_mut()
+        .entity_mut(scenario.client)
+        .insert(OnGround(true));
+
+    scenario.app.update();
+    scenario.helper.clear_received();
+
+    scenario
+}
+
+fn hold(scenario: &mut ScenarioSingleClient, item: ItemKind) {
+    scenario
+        .app
+        .world_mut()
+        .get_mut::<Inventory>(scenario.client)
+        .unwrap()
+        .set_slot(36, ItemStack::new(item, 1, None));
+}
+
+fn dig(scenario: &mut ScenarioSingleClient, action: PlayerAction) {
+    scenario.helper.send(&PlayerActionC2s {
+        action,
+        position: STONE_POS,
+        direction: Direction::Up,
+        sequence: VarInt(0),
+    });
+
+    scenario.app.update();
+}
+
+fn broken(scenario: &ScenarioSingleClient) -> usize {
+    scenario
+        .app
+        .world()
+        .resource::<Events<BreakBlockEvent>>()
+        .iter_current_update_events()
+        .count()
+}
+
+fn rejected(scenario: &ScenarioSingleClient) -> Vec<f32> {
+    scenario
+        .app
+        .world()
+        .resource::<Events<BreakBlockRejectedEvent>>()
+        .iter_current_update_events()
+        .map(|event| event.progress)
+        .collect()
+}
+
+#[test]
+fn block_breaking_too_fast_rejected() {
+    let mut scenario = setup();
+
+    hold(&mut scenario, ItemKind::IronPickaxe);
+
+    dig(&mut scenario, PlayerAction::StartDestroyBlock);
+    dig(&mut scenario, PlayerAction::StopDestroyBlock);
+
+    assert_eq!(broken(&scenario), 0);
+
+    let rejected = rejected(&scenario);
+    assert_eq!(rejected.len(), 1);
+    assert!(rejected[0] < 0.7);
+
+    // The client is told the block is still there.
+    scenario
+        .helper
+        .collect_received()
+        .assert_count::<BlockUpdateS2c>(1);
+}
+
+#[test]
+fn block_breaking_accepted_after_break_time() {
+    let mut scenario = setup();
+
+    hold(&mut scenario, ItemKind::IronPickaxe);
+
+    dig(&mut scenario, PlayerAction::StartDestroyBlock);
+
+    // Stone takes 8 ticks with an iron pickaxe.
+    for _ in 0..7 {
+        scenario.app.update();
+    }
+
+    dig(&mut scenario, PlayerAction::StopDestroyBlock);
+
+    assert_eq!(broken(&scenario), 1);
+    assert!(rejected(&scenario).is_empty());
+}
+
+#[test]
+fn block_breaking_by_hand_is_slower() {
+    let mut scenario = setup();
+
+    dig(&mut scenario, PlayerAction::StartDestroyBlock);
+
+    for _ in 0..7 {
+        scenario.app.update();
+    }
+
+    dig(&mut scenario, PlayerAction::StopDestroyBlock);
+
+    assert_eq!(broken(&scenario), 0);
+    assert_eq!(rejected(&scenario).len(), 1);
+}
+
+#[test]
+fn block_breaking_instant_in_creative() {
+    let mut scenario = setup();
+
+    *scenario
+        .app
+        .world_mut()
+        .get_mut::<GameMode>(scenario.client)
+        .unwrap() = GameMode::Creative;
+
+    dig(&mut scenario, PlayerAction::StartDestroyBlock);
+
+    assert_eq!(broken(&scenario), 1);
+}
+
+#[test]
+fn block_breaking_progress_shown_to_others() {
+    let mut scenario = setup();
+
+    let (mut bundle, mut helper_2) = create_mock_client("other");
+
+    bundle.player.layer.0 = scenario.layer;
+    bundle.visible_chunk_layer.0 = scenario.layer;
+    bundle.visible_entity_layers.0.insert(scenario.layer);
+
+    scenario.app.world_mut().spawn(bundle);
+    scenario.app.update();
+    helper_2.clear_received();
+
+    hold(&mut scenario, ItemKind::IronPickaxe);
+
+    dig(&mut scenario, PlayerAction::StartDestroyBlock);
+
+    for _ in 0..3 {
+        scenario.app.update();
+    }
+
+    helper_2
+        .collect_received()
+        .assert_count::<BlockBreakingProgressS2c>(3);
+
+    // The breaker sees their own progress client-side.
+    scenario
+        .helper
+        .collect_received()
+        .assert_count::<BlockBreakingProgressS2c>(0);
+
+    dig(&mut scenario, PlayerAction::AbortDestroyBlock);
+
+    // One more stage is shown before the abort is handled, then the
+    // animation is removed.
+    helper_2
+        .collect_received()
+        .assert_count::<BlockBreakingProgressS2c>(2);
+}
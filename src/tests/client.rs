@@ -1,12 +1,20 @@
+use bevy_app::AppExit;
+use bevy_ecs::prelude::*;
+
 use crate::abilities::PlayerAbilitiesFlags;
+use crate::bandwidth::SendBudget;
+use crate::client::Client;
 use crate::layer::chunk::UnloadedChunk;
 use crate::layer::ChunkLayer;
 use crate::math::DVec3;
 use crate::protocol::packets::play::{
-    FullC2s, MoveRelativeS2c, PlayerPositionLookS2c, TeleportConfirmC2s,
+    ChunkDataS2c, DisconnectS2c, EntityVelocityUpdateS2c, FullC2s, HandSwingC2s, MoveRelativeS2c,
+    PlayerPositionLookS2c, TeleportConfirmC2s,
 };
+use crate::protocol::{Packet, VarInt, Velocity, WritePacket};
+use crate::shutdown::{Save, ShutdownServer, ShuttingDown};
 use crate::testing::{create_mock_client, ScenarioSingleClient};
-use crate::{ChunkPos, GameMode};
+use crate::{ChunkPos, GameMode, Hand};
 
 #[test]
 fn client_teleport_and_move() {
@@ -101,3 +109,99 @@ fn client_gamemode_changed_ability() {
     assert!(!abilities.instant_break());
     assert!(!abilities.invulnerable());
 }
+
+#[test]
+fn client_send_budget_defers_chunks() {
+    let ScenarioSingleClient {
+        mut app,
+        client: client_ent,
+        mut helper,
+        layer: layer_ent,
+    } = ScenarioSingleClient::new();
+
+    // Only one deferred packet is sent per tick.
+    app.world_mut().get_mut::<SendBudget>(client_ent).unwrap().0 = 0;
+    app.world_mut()
+        .get_mut::<Client>(client_ent)
+        .unwrap()
+        .packet_stats_mut()
+        .set_inspect_frames(true);
+
+    app.update();
+    helper.clear_received();
+
+    let mut layer = app.world_mut().get_mut::<ChunkLayer>(layer_ent).unwrap();
+
+    for z in -1..=1 {
+        for x in -1..=1 {
+            layer.insert_chunk(ChunkPos::new(x, z), UnloadedChunk::new());
+        }
+    }
+
+    let mut client = app.world_mut().get_mut::<Client>(client_ent).unwrap();
+
+    client.write_packet(&EntityVelocityUpdateS2c {
+        entity_id: VarInt(0),
+        velocity: Velocity([0, 100, 0]),
+    });
+
+    helper.send(&HandSwingC2s { hand: Hand::Main });
+
+    app.update();
+
+    // Entity packets are not held back by chunk data.
+    let recvd = helper.collect_received();
+    recvd.assert_count::<EntityVelocityUpdateS2c>(1);
+    recvd.assert_count::<ChunkDataS2c>(1);
+
+    for _ in 0..8 {
+        app.update();
+        helper.collect_received().assert_count::<ChunkDataS2c>(1);
+    }
+
+    app.update();
+    helper.collect_received().assert_count::<ChunkDataS2c>(0);
+
+    let client = app.world().get::<Client>(client_ent).unwrap();
+    let stats = client.packet_stats();
+
+    assert_eq!(client.deferred_len(), 0);
+    assert_eq!(stats.sent(ChunkDataS2c::ID).packets, 9);
+    assert_eq!(stats.sent(EntityVelocityUpdateS2c::ID).packets, 1);
+    assert_eq!(stats.received(HandSwingC2s::ID).packets, 1);
+}
+
+#[test]
+fn client_disconnected_on_shutdown() {
+    #[derive(Resource, Default)]
+    struct SaveCount(usize);
+
+    let ScenarioSingleClient {
+        mut app,
+        mut helper,
+        layer: layer_ent,
+        ..
+    } = ScenarioSingleClient::new();
+
+    app.init_resource::<SaveCount>()
+        .add_systems(Save, |mut count: ResMut<SaveCount>| count.0 += 1);
+
+    let mut layer = app.world_mut().get_mut::<ChunkLayer>(layer_ent).unwrap();
+    layer.insert_chunk(ChunkPos::new(0, 0), UnloadedChunk::new());
+
+    app.update();
+    helper.clear_received();
+
+    app.world_mut().send_event(ShutdownServer);
+    app.update();
+
+    helper.collect_received().assert_count::<DisconnectS2c>(1);
+
+    assert!(app.world().contains_resource::<ShuttingDown>());
+    assert_eq!(app.world().resource::<SaveCount>().0, 1);
+    assert_eq!(app.should_exit(), Some(AppExit::Success));
+
+    // Saving only happens once.
+    app.update();
+    assert_eq!(app.world().resource::<SaveCount>().0, 1);
+}
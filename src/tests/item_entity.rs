@@ -0,0 +1,229 @@
+use bevy_ecs::prelude::*;
+
+use crate::entity::item::{ItemEntity, ItemEntityBundle, Stack};
+use crate::entity::{EntityLayerId, Position};
+use crate::inventory::item_entity::{ItemEntityPlugin, ItemEntitySettings, ItemEntityState};
+use crate::inventory::{DropItemStackEvent, Inventory};
+use crate::protocol::packets::play::ItemPickupAnimationS2c;
+use crate::testing::ScenarioSingleClient;
+use crate::{ItemKind, ItemStack};
+
+fn setup() -> ScenarioSingleClient {
+    let mut scenario = ScenarioSingleClient::new();
+
+    scenario.app.add_plugins(ItemEntityPlugin);
+    scenario.app.update();
+    scenario.helper.clear_received();
+
+    scenario
+}
+
+fn spawn_item(
+    scenario: &mut ScenarioSingleClient,
+    stack: ItemStack,
+    pos: [f64; 3],
+    pickup_delay: u32,
+) -> Entity {
+    scenario
+        .app
+        .world_mut()
+        .spawn((
+            ItemEntityBundle {
+                item_stack: Stack(stack),
+                layer: EntityLayerId(scenario.layer),
+                position: Position(pos.into()),
+                ..Default::default()
+            },
+            ItemEntityState {
+                age: 0,
+                pickup_delay,
+            },
+        ))
+        .id()
+}
+
+fn items(scenario: &mut ScenarioSingleClient) -> Vec<(Entity, ItemStack)> {
+    scenario
+        .app
+        .world_mut()
+        .query_filtered::<(Entity, &Stack), With<ItemEntity>>()
+        .iter(scenario.app.world())
+        .map(|(entity, stack)| (entity, stack.0.clone()))
+        .collect()
+}
+
+#[test]
+fn item_entity_spawned_on_drop() {
+    let mut scenario = setup();
+
+    scenario.app.world_mut().send_event(DropItemStackEvent {
+        client: scenario.client,
+        from_slot: Some(36),
+        stack: ItemStack::new(ItemKind::Diamond, 3, None),
+    });
+
+    scenario.app.update();
+
+    let items = items(&mut scenario);
+    assert_eq!(items.len(), 1);
+
+    let (item, stack) = &items[0];
+    assert_eq!(stack, &ItemStack::new(ItemKind::Diamond, 3, None));
+
+    let world = scenario.app.world();
+    let state = world.get::<ItemEntityState>(*item).unwrap();
+    let pos = world.get::<Position>(*item).unwrap().0;
+
+    assert_eq!(state.pickup_delay, 39);
+    assert!(pos.y > 1.0);
+}
+
+#[test]
+fn item_entity_picked_up_after_delay() {
+    let mut scenario = setup();
+
+    let item = spawn_item(
+        &mut scenario,
+        ItemStack::new(ItemKind::Diamond, 3, None),
+        [0.5, 0.0, 0.0],
+        5,
+    );
+
+    for _ in 0..5 {
+        scenario.app.update();
+    }
+
+    let inventory = scenario.app.world().get::<Inventory>(scenario.client);
+    assert!(inventory.unwrap().slot(36).is_empty());
+
+    scenario.app.update();
+
+    let world = scenario.app.world();
+    let inventory = world.get::<Inventory>(scenario.client).unwrap();
+
+    assert_eq!(
+        inventory.slot(36),
+        &ItemStack::new(ItemKind::Diamond, 3, None)
+    );
+    assert!(world.get_entity(item).is_none());
+
+    scenario
+        .helper
+        .collect_received()
+        .assert_count::<ItemPickupAnimationS2c>(1);
+}
+
+#[test]
+fn item_entity_partially_picked_up() {
+    let mut scenario = setup();
+
+    let mut inventory = scenario
+        .app
+        .world_mut()
+        .get_mut::<Inventory>(scenario.client)
+        .unwrap();
+
+    for slot in 9..=44 {
+        inventory.set_slot(slot, ItemStack::new(ItemKind::Stone, 64, None));
+    }
+
+    inventory.set_slot(40, ItemStack::new(ItemKind::EnderPearl, 10, None));
+
+    let item = spawn_item(
+        &mut scenario,
+        ItemStack::new(ItemKind::EnderPearl, 10, None),
+        [0.0, 0.0, 0.5],
+        0,
+    );
+
+    scenario.app.update();
+
+    let world = scenario.app.world();
+    let inventory = world.get::<Inventory>(scenario.client).unwrap();
+
+    // Ender pearls stack to 16.
+    assert_eq!(
+        inventory.slot(40),
+        &ItemStack::new(ItemKind::EnderPearl, 16, None)
+    );
+    assert_eq!(
+        world.get::<Stack>(item).unwrap().0,
+        ItemStack::new(ItemKind::EnderPearl, 4, None)
+    );
+}
+
+#[test]
+fn item_entity_nearby_stacks_merge() {
+    let mut scenario = setup();
+
+    let a = spawn_item(
+        &mut scenario,
+        ItemStack::new(ItemKind::Diamond, 3, None),
+        [10.0, 0.0, 0.0],
+        0,
+    );
+    spawn_item(
+        &mut scenario,
+        ItemStack::new(ItemKind::Diamond, 5, None),
+        [10.5, 0.0, 0.0],
+        0,
+    );
+    spawn_item(
+        &mut scenario,
+        ItemStack::new(ItemKind::Emerald, 5, None),
+        [10.0, 0.0, 0.5],
+        0,
+    );
+    spawn_item(
+        &mut scenario,
+        ItemStack::new(ItemKind::Diamond, 5, None),
+        [20.0, 0.0, 0.0],
+        0,
+    );
+
+    scenario.app.update();
+    scenario.app.update();
+
+    let mut items = items(&mut scenario);
+    items.sort_by_key(|(_, stack)| (stack.item.to_raw(), stack.count));
+
+    assert_eq!(
+        items
+            .iter()
+            .map(|(_, stack)| stack.clone())
+            .collect::<Vec<_>>(),
+        [
+            ItemStack::new(ItemKind::Diamond, 5, None),
+            ItemStack::new(ItemKind::Diamond, 8, None),
+            ItemStack::new(ItemKind::Emerald, 5, None),
+        ]
+    );
+    assert!(scenario.app.world().get_entity(a).is_none());
+}
+
+#[test]
+fn item_entity_despawned_when_old() {
+    let mut scenario = setup();
+
+    scenario.app.insert_resource(ItemEntitySettings {
+        despawn_age: Some(10),
+        ..Default::default()
+    });
+
+    let item = spawn_item(
+        &mut scenario,
+        ItemStack::new(ItemKind::Diamond, 1, None),
+        [10.0, 0.0, 0.0],
+        0,
+    );
+
+    for _ in 0..9 {
+        scenario.app.update();
+    }
+
+    assert!(scenario.app.world().get_entity(item).is_some());
+
+    scenario.app.update();
+
+    assert!(scenario.app.world().get_entity(item).is_none());
+}
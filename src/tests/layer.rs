@@ -9,7 +9,7 @@ use crate::layer::chunk::UnloadedChunk;
 use crate::layer::{ChunkLayer, EntityLayer};
 use crate::protocol::packets::play::{
     BlockEntityUpdateS2c, ChunkDataS2c, ChunkDeltaUpdateS2c, EntitiesDestroyS2c, EntitySpawnS2c,
-    MoveRelativeS2c, UnloadChunkS2c,
+    LightUpdateS2c, MoveRelativeS2c, UnloadChunkS2c,
 };
 use crate::protocol::Packet;
 use crate::testing::ScenarioSingleClient;
@@ -465,3 +465,51 @@ fn chunk_entity_spawn_despawn() {
         recvd.assert_count::<EntitiesDestroyS2c>(0)
     };
 }
+
+#[test]
+fn light_engine_sends_light_updates() {
+    let ScenarioSingleClient {
+        mut app,
+        mut helper,
+        layer: layer_ent,
+        ..
+    } = ScenarioSingleClient::new();
+
+    let mut layer = app.world_mut().get_mut::<ChunkLayer>(layer_ent).unwrap();
+
+    layer.set_light_engine_enabled(true);
+    layer.insert_chunk([0, 0], UnloadedChunk::new());
+
+    app.update();
+
+    {
+        let recvd = helper.collect_received();
+
+        let ChunkDataS2c {
+            sky_light_arrays, ..
+        } = recvd.first::<ChunkDataS2c>();
+        assert!(!sky_light_arrays.is_empty())
+    };
+
+    let mut layer = app.world_mut().get_mut::<ChunkLayer>(layer_ent).unwrap();
+
+    layer.set_block([1, 1, 1], BlockState::GLOWSTONE);
+
+    app.update();
+
+    helper.collect_received().assert_count::<LightUpdateS2c>(1);
+
+    let layer = app.world_mut().get::<ChunkLayer>(layer_ent).unwrap();
+
+    assert_eq!(layer.block_light([1, 1, 1]), Some(15));
+    assert_eq!(layer.block_light([3, 1, 1]), Some(13));
+
+    let mut layer = app.world_mut().get_mut::<ChunkLayer>(layer_ent).unwrap();
+
+    // Changes that don't affect light shouldn't send light updates.
+    layer.set_block([5, 1, 1], BlockState::OAK_SIGN);
+
+    app.update();
+
+    helper.collect_received().assert_count::<LightUpdateS2c>(0);
+}
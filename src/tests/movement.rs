@@ -0,0 +1,139 @@
+use bevy_ecs::prelude::*;
+
+use crate::client::Client;
+use crate::entity::Position;
+use crate::layer::ChunkLayer;
+use crate::math::DVec3;
+use crate::movement::{MovementRejectedEvent, MovementRejection, MovementSettings};
+use crate::protocol::packets::play::{PlayerPositionLookS2c, PositionAndOnGroundC2s};
+use crate::testing::ScenarioSingleClient;
+use crate::BlockState;
+
+fn setup(settings: MovementSettings) -> ScenarioSingleClient {
+    let mut scenario = ScenarioSingleClient::with_chunks(2);
+
+    scenario.app.insert_resource(settings);
+    scenario.app.update();
+
+    scenario.helper.confirm_initial_pending_teleports();
+    scenario.helper.clear_received();
+
+    scenario
+}
+
+fn move_to(scenario: &mut ScenarioSingleClient, x: f64, y: f64, z: f64) {
+    scenario.helper.send(&PositionAndOnGroundC2s {
+        position: DVec3::new(x, y, z),
+        on_ground: true,
+    });
+
+    scenario.app.update();
+}
+
+fn position(scenario: &ScenarioSingleClient) -> DVec3 {
+    scenario
+        .app
+        .world()
+        .get::<Position>(scenario.client)
+        .unwrap()
+        .0
+}
+
+fn rejections(scenario: &ScenarioSingleClient) -> Vec<MovementRejection> {
+    scenario
+        .app
+        .world()
+        .resource::<Events<MovementRejectedEvent>>()
+        .iter_current_update_events()
+        .map(|event| event.reason)
+        .collect()
+}
+
+#[test]
+fn movement_unchecked_by_default() {
+    let mut scenario = setup(MovementSettings::default());
+
+    move_to(&mut scenario, 100.0, 50.0, 0.0);
+
+    assert_eq!(position(&scenario), DVec3::new(100.0, 50.0, 0.0));
+    assert!(rejections(&scenario).is_empty());
+}
+
+#[test]
+fn movement_too_fast_rejected() {
+    let mut scenario = setup(MovementSettings::recommended());
+
+    move_to(&mut scenario, 0.5, 0.0, 0.0);
+    assert_eq!(position(&scenario), DVec3::new(0.5, 0.0, 0.0));
+
+    move_to(&mut scenario, 10.0, 0.0, 0.0);
+
+    // The client is teleported back.
+    assert_eq!(position(&scenario), DVec3::new(0.5, 0.0, 0.0));
+    assert!(matches!(
+        rejections(&scenario)[..],
+        [MovementRejection::TooFast { .. }]
+    ));
+    scenario
+        .helper
+        .collect_received()
+        .assert_count::<PlayerPositionLookS2c>(1);
+}
+
+#[test]
+fn movement_knockback_allowed() {
+    let mut scenario = setup(MovementSettings::recommended());
+
+    scenario
+        .app
+        .world_mut()
+        .get_mut::<Client>(scenario.client)
+        .unwrap()
+        .set_velocity([60.0, 20.0, 0.0]);
+
+    move_to(&mut scenario, 3.0, 1.0, 0.0);
+
+    assert_eq!(position(&scenario), DVec3::new(3.0, 1.0, 0.0));
+    assert!(rejections(&scenario).is_empty());
+}
+
+#[test]
+fn movement_into_block_rejected() {
+    let mut scenario = setup(MovementSettings {
+        prevent_no_clip: true,
+        ..Default::default()
+    });
+
+    scenario
+        .app
+        .world_mut()
+        .get_mut::<ChunkLayer>(scenario.layer)
+        .unwrap()
+        .set_block([1, 0, 0], BlockState::STONE);
+
+    // Touching the block is fine.
+    move_to(&mut scenario, 0.7, 0.0, 0.5);
+    assert_eq!(position(&scenario), DVec3::new(0.7, 0.0, 0.5));
+
+    move_to(&mut scenario, 1.2, 0.0, 0.5);
+
+    assert_eq!(position(&scenario), DVec3::new(0.7, 0.0, 0.5));
+    assert_eq!(rejections(&scenario), [MovementRejection::NoClip]);
+}
+
+#[test]
+fn movement_hovering_rejected() {
+    let mut scenario = setup(MovementSettings {
+        max_hover_ticks: Some(5),
+        ..Default::default()
+    });
+
+    for i in 1..=5 {
+        move_to(&mut scenario, f64::from(i) * 0.1, 0.0, 0.0);
+        assert!(rejections(&scenario).is_empty());
+    }
+
+    move_to(&mut scenario, 0.6, 0.0, 0.0);
+
+    assert_eq!(rejections(&scenario), [MovementRejection::Hovering]);
+}
@@ -0,0 +1,126 @@
+use crate::entity::item::ItemEntityBundle;
+use crate::entity::zombie::ZombieEntityBundle;
+use crate::entity::{EntityLayerId, OnGround, Position, Velocity};
+use crate::math::DVec3;
+use crate::physics::{Physics, PhysicsPlugin};
+use crate::protocol::packets::play::EntityVelocityUpdateS2c;
+use crate::testing::ScenarioSingleClient;
+use crate::{BlockState, ChunkLayer};
+
+fn setup() -> ScenarioSingleClient {
+    let mut scenario = ScenarioSingleClient::with_chunks(2);
+
+    scenario.app.add_plugins(PhysicsPlugin);
+
+    let mut layer = scenario
+        .app
+        .world_mut()
+        .get_mut::<ChunkLayer>(scenario.layer)
+        .unwrap();
+
+    for z in -5..5 {
+        for x in -5..5 {
+            layer.set_block([x, 0, z], BlockState::STONE);
+        }
+    }
+
+    scenario.app.update();
+    scenario.helper.clear_received();
+
+    scenario
+}
+
+#[test]
+fn physics_item_falls_and_lands() {
+    let mut scenario = setup();
+
+    let item = scenario
+        .app
+        .world_mut()
+        .spawn(ItemEntityBundle {
+            layer: EntityLayerId(scenario.layer),
+            position: Position(DVec3::new(0.5, 5.0, 0.5)),
+            ..Default::default()
+        })
+        .id();
+
+    scenario.app.update();
+    scenario.helper.clear_received();
+
+    for _ in 0..40 {
+        scenario.app.update();
+    }
+
+    let world = scenario.app.world();
+
+    assert_eq!(world.get::<Physics>(item), Some(&Physics::ITEM));
+    assert_eq!(world.get::<Position>(item).unwrap().0.y, 1.0);
+    assert!(world.get::<OnGround>(item).unwrap().0);
+    assert_eq!(world.get::<Velocity>(item).unwrap().0.y, 0.0);
+
+    // The client is only told about the landing.
+    scenario
+        .helper
+        .collect_received()
+        .assert_count::<EntityVelocityUpdateS2c>(1);
+}
+
+#[test]
+fn physics_slides_to_a_stop_on_ground() {
+    let mut scenario = setup();
+
+    let zombie = scenario
+        .app
+        .world_mut()
+        .spawn(ZombieEntityBundle {
+            layer: EntityLayerId(scenario.layer),
+            position: Position(DVec3::new(0.5, 1.0, 0.5)),
+            velocity: Velocity([10.0, 0.0, 0.0].into()),
+            ..Default::default()
+        })
+        .id();
+
+    for _ in 0..40 {
+        scenario.app.update();
+    }
+
+    let world = scenario.app.world();
+    let pos = world.get::<Position>(zombie).unwrap().0;
+
+    assert_eq!(world.get::<Physics>(zombie), Some(&Physics::LIVING));
+    assert_eq!(pos.y, 1.0);
+    assert!(pos.x > 0.5 && pos.x < 2.0);
+    assert_eq!(world.get::<Velocity>(zombie).unwrap().0, [0.0; 3].into());
+}
+
+#[test]
+fn physics_stops_at_walls() {
+    let mut scenario = setup();
+
+    scenario
+        .app
+        .world_mut()
+        .get_mut::<ChunkLayer>(scenario.layer)
+        .unwrap()
+        .set_block([3, 1, 0], BlockState::STONE);
+
+    let item = scenario
+        .app
+        .world_mut()
+        .spawn(ItemEntityBundle {
+            layer: EntityLayerId(scenario.layer),
+            position: Position(DVec3::new(0.5, 1.0, 0.5)),
+            velocity: Velocity([40.0, 0.0, 0.0].into()),
+            ..Default::default()
+        })
+        .id();
+
+    for _ in 0..20 {
+        scenario.app.update();
+    }
+
+    let pos = scenario.app.world().get::<Position>(item).unwrap().0;
+
+    // Items are 0.25 blocks wide.
+    assert_eq!(pos.x, 3.0 - 0.125);
+}
@@ -0,0 +1,105 @@
+use bevy_ecs::system::SystemState;
+
+use crate::entity::zombie::ZombieEntityBundle;
+use crate::entity::{EntityLayerId, Position};
+use crate::math::DVec3;
+use crate::raycast::{raycast_blocks, Ray, Raycast, RaycastTarget};
+use crate::testing::ScenarioSingleClient;
+use crate::{BlockState, ChunkLayer, Direction};
+
+fn setup() -> ScenarioSingleClient {
+    let mut scenario = ScenarioSingleClient::with_chunks(2);
+
+    let mut layer = scenario
+        .app
+        .world_mut()
+        .get_mut::<ChunkLayer>(scenario.layer)
+        .unwrap();
+
+    layer.set_block([5, 0, 0], BlockState::STONE);
+    layer.set_block([2, 0, 0], BlockState::OAK_SLAB);
+
+    scenario
+}
+
+#[test]
+fn raycast_hits_block_shapes() {
+    let scenario = setup();
+    let layer = scenario
+        .app
+        .world()
+        .get::<ChunkLayer>(scenario.layer)
+        .unwrap();
+
+    // The ray passes over the bottom slab and hits the stone.
+    let ray = Ray::new([0.5, 0.75, 0.5], [1.0, 0.0, 0.0]);
+    let hit = raycast_blocks(layer, ray, 10.0, |_, _| true).unwrap();
+
+    assert_eq!(hit.position, DVec3::new(5.0, 0.75, 0.5));
+    assert_eq!(hit.face, Direction::West);
+    assert_eq!(
+        hit.target,
+        RaycastTarget::Block {
+            pos: [5, 0, 0].into(),
+            state: BlockState::STONE
+        }
+    );
+
+    // The slab is hit from above.
+    let ray = Ray::new([2.5, 3.0, 0.5], [0.0, -1.0, 0.0]);
+    let hit = raycast_blocks(layer, ray, 10.0, |_, _| true).unwrap();
+
+    assert_eq!(hit.position, DVec3::new(2.5, 0.5, 0.5));
+    assert_eq!(hit.face, Direction::Up);
+
+    // Out of range and filtered blocks are not hit.
+    let ray = Ray::new([0.5, 0.25, 0.5], [1.0, 0.0, 0.0]);
+    assert!(raycast_blocks(layer, ray, 1.0, |_, _| true).is_none());
+    assert!(
+        raycast_blocks(layer, ray, 10.0, |_, state| state != BlockState::OAK_SLAB)
+            .is_some_and(|hit| hit.position.x == 5.0)
+    );
+
+    // An infinite ray that hits nothing would never end, so it is rejected.
+    let ray = Ray::new([0.5, 10.5, 0.5], [0.0, 1.0, 0.0]);
+    assert!(raycast_blocks(layer, ray, f64::INFINITY, |_, _| true).is_none());
+}
+
+#[test]
+fn raycast_hits_entities_before_blocks() {
+    let mut scenario = setup();
+
+    let zombie = scenario
+        .app
+        .world_mut()
+        .spawn(ZombieEntityBundle {
+            layer: EntityLayerId(scenario.layer),
+            position: Position(DVec3::new(4.0, 0.0, 0.5)),
+            ..Default::default()
+        })
+        .id();
+
+    // Update the hitbox and the entity layer.
+    scenario.app.update();
+    scenario.app.update();
+
+    let mut state = SystemState::<Raycast>::new(scenario.app.world_mut());
+    let raycast = state.get(scenario.app.world());
+
+    let ray = Ray::new([0.5, 0.75, 0.5], [1.0, 0.0, 0.0]);
+
+    let hit = raycast
+        .cast(scenario.layer, ray, 10.0, |_, _| true, |_| true)
+        .unwrap();
+
+    assert_eq!(hit.target, RaycastTarget::Entity(zombie));
+    assert_eq!(hit.face, Direction::West);
+    assert!((hit.position.x - 3.7).abs() < 1e-9);
+
+    // Filtered entities are passed through.
+    let hit = raycast
+        .cast(scenario.layer, ray, 10.0, |_, _| true, |e| e != zombie)
+        .unwrap();
+
+    assert_eq!(hit.position, DVec3::new(5.0, 0.75, 0.5));
+}
@@ -0,0 +1,64 @@
+use std::num::NonZeroUsize;
+use std::time::{Duration, Instant};
+
+use crate::layer::chunk::{Chunk, UnloadedChunk};
+use crate::testing::*;
+use crate::worldgen::{ChunkGenerator, ChunkRegion, GeneratedLevel};
+use crate::{BlockState, ChunkLayer, ChunkPos};
+
+struct TestGenerator;
+
+impl ChunkGenerator for TestGenerator {
+    fn generate_noise(&self, _pos: ChunkPos, _min_y: i32, chunk: &mut UnloadedChunk) {
+        chunk.fill_block_state_section(0, BlockState::STONE);
+    }
+
+    fn place_features(&self, region: &mut ChunkRegion) {
+        let center = region.center();
+        let y = region.min_y() + 16;
+
+        // Place a block in the neighbor to the west of the chunk.
+        region.set_block(
+            [center.x * 16 - 1, y, center.z * 16],
+            BlockState::GOLD_BLOCK,
+        );
+    }
+}
+
+#[test]
+fn generated_level_places_features_across_chunks() {
+    let ScenarioSingleClient { mut app, layer, .. } = ScenarioSingleClient::new();
+
+    app.world_mut().entity_mut(layer).insert(
+        GeneratedLevel::new(TestGenerator).with_thread_count(NonZeroUsize::new(2).unwrap()),
+    );
+
+    let start = Instant::now();
+
+    let chunk_layer = loop {
+        app.update();
+
+        let chunk_layer = app.world().get::<ChunkLayer>(layer).unwrap();
+
+        if chunk_layer.chunk(ChunkPos::new(0, 0)).is_some() {
+            break chunk_layer;
+        }
+
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "chunk was not generated"
+        );
+    };
+
+    let min_y = chunk_layer.min_y();
+
+    assert_eq!(
+        chunk_layer.block([0, min_y, 0]).unwrap().state,
+        BlockState::STONE
+    );
+    // Placed by the features of the chunk to the east.
+    assert_eq!(
+        chunk_layer.block([15, min_y + 16, 0]).unwrap().state,
+        BlockState::GOLD_BLOCK
+    );
+}